@@ -1,4 +1,7 @@
-use lipilekhika::{transliterate as transliterate_impl, Script};
+use lipilekhika::formats::subtitle::{
+  transliterate_subtitle as transliterate_subtitle_impl, SubtitleFormat,
+};
+use lipilekhika::{transliterate as transliterate_impl, CustomOptions, Script};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -11,11 +14,22 @@ struct Payload {
   options: Option<HashMap<String, bool>>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct SubtitlePayload {
+  text: String,
+  from: String,
+  to: String,
+  options: Option<HashMap<String, bool>>,
+  /// File extension (`srt` / `vtt`); detected from the contents when absent
+  format: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(tag = "code", content = "details", rename_all = "snake_case")]
 enum TransliterateError {
   InvalidScript { field: &'static str, value: String },
   InvalidCustomOptionKey,
+  UnsupportedSubtitleFormat { value: String },
 }
 
 fn parse_script(field: &'static str, value: &str) -> Result<Script, TransliterateError> {
@@ -25,26 +39,55 @@ fn parse_script(field: &'static str, value: &str) -> Result<Script, Transliterat
   })
 }
 
+fn parse_options(
+  options: Option<&HashMap<String, bool>>,
+) -> Result<Option<CustomOptions>, TransliterateError> {
+  options
+    .map(CustomOptions::try_from_map)
+    .transpose()
+    .map_err(|_| TransliterateError::InvalidCustomOptionKey)
+}
+
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command(rename_all = "snake_case")]
 fn transliterate(payload: Payload) -> Result<String, TransliterateError> {
   let from = parse_script("from", &payload.from)?;
   let to = parse_script("to", &payload.to)?;
-  let options = payload
-    .options
-    .as_ref()
-    .map(lipilekhika::CustomOptions::try_from_map)
-    .transpose()
-    .map_err(|_| TransliterateError::InvalidCustomOptionKey)?;
+  let options = parse_options(payload.options.as_ref())?;
   Ok(transliterate_impl(&payload.text, from, to, options.as_ref()).into_owned())
 }
 
+/// Transliterates only the cue text of an SRT/WebVTT file
+#[tauri::command(rename_all = "snake_case")]
+fn transliterate_subtitle(payload: SubtitlePayload) -> Result<String, TransliterateError> {
+  let from = parse_script("from", &payload.from)?;
+  let to = parse_script("to", &payload.to)?;
+  let options = parse_options(payload.options.as_ref())?;
+  let format = payload
+    .format
+    .map(|ext| {
+      SubtitleFormat::from_extension(&ext)
+        .ok_or(TransliterateError::UnsupportedSubtitleFormat { value: ext })
+    })
+    .transpose()?;
+  Ok(transliterate_subtitle_impl(
+    &payload.text,
+    format,
+    from,
+    to,
+    options.as_ref(),
+  ))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
     .plugin(tauri_plugin_opener::init())
     .plugin(tauri_plugin_store::Builder::default().build())
-    .invoke_handler(tauri::generate_handler![transliterate])
+    .invoke_handler(tauri::generate_handler![
+      transliterate,
+      transliterate_subtitle
+    ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
@@ -0,0 +1,312 @@
+//! Transliteration of the clipboard, or of the selection of the focused app, with a hotkey
+//! (or the tray menu). Configured in the `[clipboard]` table of `app_conf.toml`, with the
+//! custom transliteration `options` of the config.
+//!
+//! The work is done on the clipboard thread, which keeps the clipboard open for the life of
+//! the app: on Linux the converted text can only be pasted while it is.
+
+use crate::data::{get_ordered_script_list, normalize_script, script_list_to_typing_script};
+use crate::platform::{self, ClipboardKey};
+use crate::{AppState, ThreadMessage, ThreadMessageOrigin, ThreadMessageType};
+use crossbeam_channel::{Receiver, Sender};
+use lipilekhika::{CustomOptions, ScriptListEnum};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time the focused app gets to copy its selection.
+const COPY_TIMEOUT: Duration = Duration::from_millis(500);
+const COPY_POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Time the focused app gets to paste, before the clipboard gets its content back.
+const PASTE_TIMEOUT: Duration = Duration::from_millis(300);
+
+fn default_true() -> bool {
+  true
+}
+
+fn default_target() -> String {
+  "Devanagari".to_string()
+}
+
+fn default_targets() -> Vec<String> {
+  ["Devanagari", "Telugu", "Tamil", "Kannada", "Romanized"]
+    .map(String::from)
+    .to_vec()
+}
+
+/// The `[clipboard]` table of `app_conf.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardSettings {
+  /// Script of the text, empty to detect it. Text in no script (ASCII) is taken as Normal.
+  #[serde(default)]
+  pub source: String,
+
+  /// Script converted to, picked in the tray menu.
+  #[serde(default = "default_target")]
+  pub target: String,
+
+  /// Scripts offered in the tray menu.
+  #[serde(default = "default_targets")]
+  pub targets: Vec<String>,
+
+  /// The hotkey copies the selection of the focused app first, and pastes the result over
+  /// it; the clipboard then gets its content back. Without a selection (or when off) only
+  /// the clipboard is converted.
+  #[serde(default = "default_true")]
+  pub replace_selection: bool,
+}
+
+impl Default for ClipboardSettings {
+  fn default() -> Self {
+    Self {
+      source: String::new(),
+      target: default_target(),
+      targets: default_targets(),
+      replace_selection: default_true(),
+    }
+  }
+}
+
+impl ClipboardSettings {
+  /// Scripts of `targets` that exist, in order and without duplicates.
+  pub fn target_scripts(&self) -> Vec<ScriptListEnum> {
+    let mut scripts = Vec::new();
+    for script in self.targets.iter().filter_map(normalize_script) {
+      if !scripts.contains(&script) {
+        scripts.push(script);
+      }
+    }
+    scripts
+  }
+}
+
+/// Converts `text` as configured, returning the result and the script converted to.
+pub fn convert(
+  text: &str,
+  settings: &ClipboardSettings,
+  options: &CustomOptions,
+) -> Result<(String, ScriptListEnum), String> {
+  let target = normalize_script(&settings.target)
+    .ok_or_else(|| format!("Unknown target script `{}`", settings.target))?;
+  let source = if settings.source.trim().is_empty() {
+    lipilekhika::detect_script(text).unwrap_or(ScriptListEnum::Normal)
+  } else {
+    normalize_script(&settings.source)
+      .ok_or_else(|| format!("Unknown source script `{}`", settings.source))?
+  };
+  if source == target {
+    return Err(format!("The text is already in {target}"));
+  }
+  let converted = lipilekhika::transliterate(
+    text,
+    script_list_to_typing_script(source),
+    script_list_to_typing_script(target),
+    Some(options),
+  );
+  Ok((converted.into_owned(), target))
+}
+
+/// What to convert.
+#[derive(Debug, Clone, Copy)]
+pub enum ClipboardRequest {
+  /// From the hotkey: the selection when `replace_selection` is on, else the clipboard
+  Hotkey,
+  /// From the tray menu: the clipboard
+  Clipboard,
+}
+
+static REQUESTS: OnceLock<Sender<ClipboardRequest>> = OnceLock::new();
+
+/// Asks the clipboard thread to convert; ignored before it runs.
+pub fn transliterate(request: ClipboardRequest) {
+  if let Some(tx) = REQUESTS.get() {
+    let _ = tx.send(request);
+  }
+}
+
+/// Content of the clipboard, kept while the selection is copied and converted through it.
+/// Other formats (files, rich text) are not kept.
+enum ClipboardContent {
+  Text(String),
+  Image(arboard::ImageData<'static>),
+}
+
+impl ClipboardContent {
+  fn read(clipboard: &mut arboard::Clipboard) -> Option<Self> {
+    if let Ok(text) = clipboard.get_text() {
+      return Some(Self::Text(text));
+    }
+    clipboard.get_image().ok().map(Self::Image)
+  }
+
+  /// Puts `previous` back in the clipboard, or empties it when there was nothing.
+  fn restore(previous: Option<Self>, clipboard: &mut arboard::Clipboard) {
+    let _ = match previous {
+      Some(Self::Text(text)) => clipboard.set_text(text),
+      Some(Self::Image(image)) => clipboard.set_image(image),
+      None => clipboard.clear(),
+    };
+  }
+}
+
+/// Copies the selection of the focused app. The clipboard is cleared first to tell when the
+/// copy arrives; `None` without a selection.
+fn copy_selection(clipboard: &mut arboard::Clipboard) -> Option<String> {
+  let _ = clipboard.clear();
+  platform::send_clipboard_key(ClipboardKey::Copy);
+
+  let deadline = Instant::now() + COPY_TIMEOUT;
+  while Instant::now() < deadline {
+    thread::sleep(COPY_POLL_INTERVAL);
+    if let Ok(text) = clipboard.get_text()
+      && !text.is_empty()
+    {
+      return Some(text);
+    }
+  }
+  None
+}
+
+/// Message shown after converting to `target`.
+fn converted_message(target: ScriptListEnum) -> String {
+  let name = get_ordered_script_list()
+    .into_iter()
+    .find(|sd| sd.script_name == target)
+    .map(|sd| sd.to_string())
+    .unwrap_or_else(|| target.to_string());
+  format!("Converted to {name}")
+}
+
+/// Converts the text of the clipboard in place.
+fn convert_clipboard(
+  clipboard: &mut arboard::Clipboard,
+  settings: &ClipboardSettings,
+  options: &CustomOptions,
+) -> Result<ScriptListEnum, String> {
+  let text = clipboard.get_text().unwrap_or_default();
+  if text.trim().is_empty() {
+    return Err("Nothing to convert: the clipboard has no text".to_string());
+  }
+  let (converted, target) = convert(&text, settings, options)?;
+  clipboard
+    .set_text(converted)
+    .map_err(|err| format!("Could not write the clipboard: {err}"))?;
+  Ok(target)
+}
+
+/// Converts the selection of the focused app, pasting the result over it. The clipboard
+/// holds the selection and then the result, and gets its content back afterwards. Without
+/// a selection the clipboard is converted.
+fn convert_selection(
+  clipboard: &mut arboard::Clipboard,
+  settings: &ClipboardSettings,
+  options: &CustomOptions,
+) -> Result<ScriptListEnum, String> {
+  let previous = ClipboardContent::read(clipboard);
+  let Some(selection) = copy_selection(clipboard) else {
+    ClipboardContent::restore(previous, clipboard);
+    return convert_clipboard(clipboard, settings, options);
+  };
+  let result = convert(&selection, settings, options).and_then(|(converted, target)| {
+    clipboard
+      .set_text(converted)
+      .map_err(|err| format!("Could not write the clipboard: {err}"))?;
+    platform::send_clipboard_key(ClipboardKey::Paste);
+    thread::sleep(PASTE_TIMEOUT);
+    Ok(target)
+  });
+  ClipboardContent::restore(previous, clipboard);
+  result
+}
+
+/// Handles one request, returning the message to show.
+fn handle_request(
+  app_state: &AppState,
+  clipboard: &mut arboard::Clipboard,
+  request: ClipboardRequest,
+) -> Result<String, String> {
+  let (settings, options) = {
+    let state = app_state
+      .persitent_state
+      .lock()
+      .map_err(|_| "App state unavailable".to_string())?;
+    (state.clipboard.clone(), state.custom_options()?)
+  };
+
+  let target = match request {
+    ClipboardRequest::Hotkey if settings.replace_selection => {
+      convert_selection(clipboard, &settings, &options)?
+    }
+    _ => convert_clipboard(clipboard, &settings, &options)?,
+  };
+  Ok(converted_message(target))
+}
+
+/// Runs the clipboard thread, which handles [`transliterate`] requests and shows the result
+/// of each in the notification window.
+pub fn run_clipboard_thread(
+  app_state: Arc<AppState>,
+  tx_ui: Sender<ThreadMessage>,
+) -> thread::JoinHandle<()> {
+  let (tx, rx): (Sender<ClipboardRequest>, Receiver<ClipboardRequest>) =
+    crossbeam_channel::bounded(4);
+  let _ = REQUESTS.set(tx);
+
+  thread::spawn(move || {
+    let mut clipboard = arboard::Clipboard::new();
+    for request in rx {
+      let message = match clipboard.as_mut() {
+        Ok(clipboard) => handle_request(&app_state, clipboard, request),
+        Err(err) => Err(format!("Clipboard unavailable: {err}")),
+      };
+      let _ = tx_ui.send(ThreadMessage {
+        // on behalf of the hook (or tray) that asked for it
+        origin: ThreadMessageOrigin::KeyboardHook,
+        msg: ThreadMessageType::ShowNotification(message.unwrap_or_else(|err| err)),
+      });
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn converts_from_the_detected_or_configured_script() {
+    let options = CustomOptions::default();
+    let mut settings = ClipboardSettings {
+      target: "Telugu".to_string(),
+      ..Default::default()
+    };
+    assert_eq!(
+      convert("नमस्ते", &settings, &options),
+      Ok(("నమస్తే".to_string(), ScriptListEnum::Telugu))
+    );
+    // ASCII text is taken as Normal keys
+    assert_eq!(
+      convert("rAma", &settings, &options),
+      Ok(("రామ".to_string(), ScriptListEnum::Telugu))
+    );
+    assert!(convert("నమస్తే", &settings, &options).is_err());
+
+    settings.source = "Romanized".to_string();
+    settings.target = "Devanagari".to_string();
+    assert_eq!(convert("rāma", &settings, &options).unwrap().0, "राम");
+  }
+
+  #[test]
+  fn target_scripts_skip_unknown_and_repeated_names() {
+    let settings = ClipboardSettings {
+      targets: ["telugu", "Klingon", "Telugu", "hindi"]
+        .map(String::from)
+        .to_vec(),
+      ..Default::default()
+    };
+    assert_eq!(
+      settings.target_scripts(),
+      [ScriptListEnum::Telugu, ScriptListEnum::Devanagari]
+    );
+  }
+}
@@ -0,0 +1,178 @@
+//! Word completion for the keyboard hooks, off unless `word_completion` is on in the settings:
+//! finished words are learned, and Tab replaces the word being typed with its most used
+//! completion from the lexicon.
+//!
+//! The lexicon is built from an optional `words.txt` (one word per line, any script) and the
+//! learned frequencies, both in the app data directory. Nothing is learned in password
+//! fields (see [`platform::secure_input_focused`]).
+
+use crate::AppState;
+use crate::platform;
+use crossbeam_channel::Sender;
+use lipilekhika::completion::Lexicon;
+use lipilekhika::typing::TypingDiff;
+use std::fs;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+
+/// Learned words are written to disk in batches of this size (and on exit).
+const LEARNED_WORDS_PER_SAVE: usize = 20;
+
+fn data_file_path(file_name: &str) -> PathBuf {
+  let mut path = match dirs::data_dir() {
+    Some(p) => p,
+    None => return PathBuf::from(file_name), // fallback to current dir
+  };
+  path.push("lipilekhika");
+  fs::create_dir_all(&path).ok();
+  path.push(file_name);
+  path
+}
+
+fn word_list_path() -> PathBuf {
+  data_file_path("words.txt")
+}
+
+fn frequencies_path() -> PathBuf {
+  data_file_path("word_frequencies.tsv")
+}
+
+pub struct WordCompletion {
+  lexicon: Lexicon,
+  learned_since_save: usize,
+  /// Writes the learned frequencies off the keyboard hook thread, see [`spawn_writer`].
+  writer: Option<(Sender<String>, JoinHandle<()>)>,
+}
+
+/// Starts the thread writing the learned frequencies sent to it to `path`. Only the latest
+/// of the pending ones is written.
+fn spawn_writer(path: PathBuf) -> (Sender<String>, JoinHandle<()>) {
+  let (tx, rx) = crossbeam_channel::unbounded::<String>();
+  let handle = thread::spawn(move || {
+    while let Ok(text) = rx.recv() {
+      let text = rx.try_iter().last().unwrap_or(text);
+      if let Err(e) = fs::write(&path, text) {
+        eprintln!("Failed to save word frequencies: {e}");
+      }
+    }
+  });
+  (tx, handle)
+}
+
+impl WordCompletion {
+  /// Loads the user word list and learned frequencies; both are optional.
+  pub fn load() -> Self {
+    let mut lexicon = Lexicon::new();
+    let _ = lexicon.load_word_list_file(word_list_path());
+    let _ = lexicon.load_learned_frequencies_file(frequencies_path());
+    Self {
+      lexicon,
+      learned_since_save: 0,
+      writer: Some(spawn_writer(frequencies_path())),
+    }
+  }
+
+  /// An empty lexicon that is never written to disk, for the tests.
+  #[cfg(test)]
+  pub fn in_memory() -> Self {
+    Self {
+      lexicon: Lexicon::new(),
+      learned_since_save: 0,
+      writer: None,
+    }
+  }
+
+  pub fn learn(&mut self, text: &str) {
+    if text.trim().is_empty() {
+      return;
+    }
+    self.lexicon.learn(text);
+    self.learned_since_save += 1;
+    if self.learned_since_save >= LEARNED_WORDS_PER_SAVE {
+      self.save();
+    }
+  }
+
+  /// Hands the learned frequencies to the writer thread; the hooks must not wait on disk.
+  pub fn save(&mut self) {
+    if self.learned_since_save == 0 {
+      return;
+    }
+    if let Some((tx, _)) = &self.writer {
+      let _ = tx.send(self.lexicon.learned_frequencies());
+    }
+    self.learned_since_save = 0;
+  }
+
+  /// Saves what is left and waits for it to be written, on exit.
+  pub fn flush(&mut self) {
+    self.save();
+    if let Some((tx, handle)) = self.writer.take() {
+      drop(tx);
+      let _ = handle.join();
+    }
+  }
+}
+
+/// Returns `prev` with `diff` applied, i.e. the text the context shows after the key.
+fn apply_diff(prev: &str, diff: &TypingDiff) -> String {
+  let mut out = prev.to_string();
+  for _ in 0..diff.to_delete_chars_count {
+    out.pop();
+  }
+  out.push_str(&diff.diff_add_text);
+  out
+}
+
+/// Text that left the composition with `diff`: the output after `prev` minus what is still
+/// in `preedit`.
+pub fn finished_text(prev: &str, diff: &TypingDiff, preedit: &str) -> String {
+  let text = apply_diff(prev, diff);
+  text.strip_suffix(preedit).unwrap_or(&text).to_string()
+}
+
+/// Whether word completion is turned on in the settings.
+fn enabled(app_state: &AppState) -> bool {
+  app_state
+    .persitent_state
+    .lock()
+    .is_ok_and(|state| state.word_completion)
+}
+
+/// Learns a finished word, unless completion is off or it was typed in a password field.
+pub fn learn(app_state: &AppState, text: &str) {
+  if text.trim().is_empty() || !enabled(app_state) || platform::secure_input_focused() {
+    return;
+  }
+  if let Ok(mut completion) = app_state.completion.lock() {
+    completion.learn(text);
+  }
+}
+
+/// Ends the current composition (the text is already in the app) and learns it.
+pub fn commit_context(app_state: &AppState) {
+  let text = match app_state.typing_context.lock() {
+    Ok(mut guard) => guard.commit(),
+    Err(_) => return,
+  };
+  learn(app_state, &text);
+}
+
+/// Completes the word being typed. Returns how many chars to delete and the text to type,
+/// or `None` when there is nothing to complete or completion is off, so that Tab goes to
+/// the app.
+pub fn take_completion(app_state: &AppState) -> Option<(usize, String)> {
+  if !enabled(app_state) {
+    return None;
+  }
+  let (to_delete, text) = {
+    let mut ctx = app_state.typing_context.lock().ok()?;
+    let completion = app_state.completion.lock().ok()?;
+    let best = completion.lexicon.complete(&ctx, 1).into_iter().next()?;
+    let to_delete = ctx.preedit().chars().count();
+    ctx.clear_context();
+    (to_delete, best.text)
+  };
+  learn(app_state, &text);
+  Some((to_delete, text))
+}
@@ -1,3 +1,4 @@
+use lipilekhika::layout::TypingLayout;
 use lipilekhika::{Script, ScriptListEnum, get_script_list_data};
 use std::fmt;
 use std::str::FromStr;
@@ -13,6 +14,18 @@ pub fn script_list_to_typing_script(script: ScriptListEnum) -> Script {
   Script::from_str(script.as_ref()).expect("ScriptListEnum canonical name is a valid Script")
 }
 
+/// Keyboard layouts that can type `script`, phonetic first.
+pub fn layouts_for_script(script: Script) -> Vec<TypingLayout> {
+  [
+    TypingLayout::Phonetic,
+    TypingLayout::InScript,
+    TypingLayout::Tamil99,
+  ]
+  .into_iter()
+  .filter(|layout| layout.is_available_for(script))
+  .collect()
+}
+
 pub fn script_from_normalized_name(name: &str) -> Option<ScriptListEnum> {
   ScriptListEnum::from_str(name).ok()
 }
@@ -0,0 +1,257 @@
+//! Typing history of the keyboard hooks: the `TypingDiff`s they applied since the caret last
+//! moved, used to undo the conversion of the last word.
+//!
+//! The undo hotkey replaces the last word with the keys typed for it, and an opt-in
+//! heuristic does so by itself when `.` follows a word, for domains and emails. The rest of
+//! a reverted word is typed as is, until the next whitespace.
+
+use crate::AppState;
+use lipilekhika::typing::TypingDiff;
+use std::collections::VecDeque;
+
+/// Diffs kept; a word longer than this can no longer be undone.
+const MAX_HISTORY_ENTRIES: usize = 128;
+
+/// One diff sent to the focused app.
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+  /// Keys typed, `None` for a Backspace that stepped the typing context back
+  keys: Option<String>,
+  diff: TypingDiff,
+}
+
+impl HistoryEntry {
+  fn ends_word(&self) -> bool {
+    self
+      .keys
+      .as_ref()
+      .is_some_and(|keys| keys.chars().any(char::is_whitespace))
+  }
+}
+
+/// The last word of the history: the text it shows and the keys typed for it.
+#[derive(Debug, PartialEq, Eq)]
+struct Word {
+  text: String,
+  keys: String,
+  /// The whitespace after the word was typed
+  finished: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct TypingHistory {
+  /// Oldest first; whitespace typed in an entry separates words.
+  entries: VecDeque<HistoryEntry>,
+  /// Entries were dropped from the front, so the first word may be incomplete.
+  truncated: bool,
+  /// Latin text was started by a revert, to end with the word.
+  reverted_word: bool,
+}
+
+impl TypingHistory {
+  fn push(&mut self, keys: Option<&str>, diff: &TypingDiff) {
+    if self.entries.len() == MAX_HISTORY_ENTRIES {
+      self.entries.pop_front();
+      self.truncated = true;
+    }
+    self.entries.push_back(HistoryEntry {
+      keys: keys.map(str::to_string),
+      diff: diff.clone(),
+    });
+  }
+
+  fn clear(&mut self) {
+    self.entries.clear();
+    self.truncated = false;
+  }
+
+  /// The word being typed, or the one just finished when nothing followed its whitespace.
+  fn last_word(&self) -> Option<Word> {
+    let finished = self.entries.back()?.ends_word();
+    let end = self.entries.len() - usize::from(finished);
+    let start = match self.entries.range(..end).rposition(HistoryEntry::ends_word) {
+      Some(idx) => idx + 1,
+      None if self.truncated => return None,
+      None => 0,
+    };
+
+    let mut text = String::new();
+    let mut keys = String::new();
+    for entry in self.entries.range(start..) {
+      let len = text.chars().count();
+      if entry.diff.to_delete_chars_count > len {
+        // reaches into text from before the word
+        return None;
+      }
+      let keep = text
+        .char_indices()
+        .nth(len - entry.diff.to_delete_chars_count)
+        .map_or(text.len(), |(idx, _)| idx);
+      text.truncate(keep);
+      text.push_str(&entry.diff.diff_add_text);
+      match &entry.keys {
+        Some(typed) => keys.push_str(typed),
+        None => {
+          keys.pop();
+        }
+      }
+    }
+    // nothing was converted
+    if text == keys {
+      return None;
+    }
+    Some(Word {
+      text,
+      keys,
+      finished,
+    })
+  }
+}
+
+/// Records a diff sent to the focused app for `keys` (`None` for a Backspace), and ends the
+/// Latin text of a reverted word at whitespace.
+pub fn record(app_state: &AppState, keys: Option<&str>, diff: &TypingDiff) {
+  let end_latin = {
+    let Ok(mut history) = app_state.history.lock() else {
+      return;
+    };
+    history.push(keys, diff);
+    let end_latin =
+      history.reverted_word && history.entries.back().is_some_and(HistoryEntry::ends_word);
+    if end_latin {
+      history.reverted_word = false;
+    }
+    end_latin
+  };
+  if end_latin {
+    end_latin_text(app_state);
+  }
+}
+
+/// Forgets the history after the caret may have moved (navigation keys, clicks, other
+/// windows) or text was typed without it.
+pub fn forget(app_state: &AppState) {
+  let end_latin = {
+    let Ok(mut history) = app_state.history.lock() else {
+      return;
+    };
+    history.clear();
+    std::mem::take(&mut history.reverted_word)
+  };
+  if end_latin {
+    end_latin_text(app_state);
+  }
+}
+
+fn end_latin_text(app_state: &AppState) {
+  if let Ok(mut ctx) = app_state.typing_context.lock()
+    && ctx.is_latin_passthrough()
+  {
+    ctx.set_latin_passthrough(false);
+  }
+}
+
+/// Replaces the last word of the history with its keys, followed by `suffix`. Returns how
+/// many chars to delete and the text to type.
+fn revert_last_word(app_state: &AppState, suffix: &str) -> Option<(usize, String)> {
+  let mut history = app_state.history.lock().ok()?;
+  let word = history.last_word()?;
+  if !suffix.is_empty() && word.finished {
+    return None;
+  }
+  // the reverted text is not a conversion to undo again
+  history.clear();
+  history.reverted_word = !word.finished;
+  drop(history);
+
+  let mut ctx = app_state.typing_context.lock().ok()?;
+  ctx.clear_context();
+  if !word.finished {
+    ctx.set_latin_passthrough(true);
+  }
+  Some((word.text.chars().count(), word.keys + suffix))
+}
+
+/// Whether there is a converted word for [`take_undo`] to undo.
+pub fn can_undo(app_state: &AppState) -> bool {
+  app_state
+    .history
+    .lock()
+    .is_ok_and(|history| history.last_word().is_some())
+}
+
+/// Undoes the conversion of the last word, for the undo hotkey.
+pub fn take_undo(app_state: &AppState) -> Option<(usize, String)> {
+  revert_last_word(app_state, "")
+}
+
+/// With `auto_revert_before_dot` on, a `.` typed right after a converted word reverts the
+/// word (eg. the `example` of `example.com`). Returns the replacement like [`take_undo`],
+/// `None` to type `text` as usual.
+pub fn take_auto_revert(app_state: &AppState, text: &str) -> Option<(usize, String)> {
+  if text != "." {
+    return None;
+  }
+  let enabled = app_state
+    .persitent_state
+    .lock()
+    .ok()?
+    .auto_revert_before_dot;
+  if !enabled || app_state.typing_context.lock().ok()?.is_latin_passthrough() {
+    return None;
+  }
+  revert_last_word(app_state, text)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lipilekhika::Script;
+  use lipilekhika::typing::TypingContext;
+
+  /// Types `keys` one at a time, recording the diffs in `history`.
+  fn type_keys(ctx: &mut TypingContext, history: &mut TypingHistory, keys: &str) {
+    for ch in keys.chars() {
+      let diff = ctx.take_key_input(ch.to_string());
+      history.push(Some(&ch.to_string()), &diff);
+    }
+  }
+
+  #[test]
+  fn last_word_is_rebuilt_from_the_diffs() {
+    let mut ctx = TypingContext::new(Script::Devanagari, None);
+    let mut history = TypingHistory::default();
+    type_keys(&mut ctx, &mut history, "rAma namas");
+    let word = history.last_word().unwrap();
+    assert_eq!((word.text.as_str(), word.keys.as_str()), ("नमस्", "namas"));
+    assert!(!word.finished);
+
+    // a Backspace steps back a key
+    let diff = ctx.take_backspace();
+    history.push(None, &diff);
+    assert_eq!(history.last_word().unwrap().keys, "nama");
+
+    // the word just finished, with its space
+    type_keys(&mut ctx, &mut history, "ste ");
+    let word = history.last_word().unwrap();
+    assert_eq!(
+      (word.text.as_str(), word.keys.as_str()),
+      ("नमस्ते ", "namaste ")
+    );
+    assert!(word.finished);
+  }
+
+  #[test]
+  fn words_beyond_the_history_or_not_converted_are_not_undone() {
+    let mut ctx = TypingContext::new(Script::Devanagari, None);
+    let mut history = TypingHistory::default();
+    ctx.set_latin_passthrough(true);
+    type_keys(&mut ctx, &mut history, "example");
+    assert_eq!(history.last_word(), None);
+    ctx.set_latin_passthrough(false);
+
+    history.clear();
+    type_keys(&mut ctx, &mut history, &"k".repeat(MAX_HISTORY_ENTRIES + 1));
+    assert_eq!(history.last_word(), None);
+  }
+}
@@ -0,0 +1,592 @@
+//! Global shortcuts, configured in the `[hotkeys]` table of `app_conf.toml` as strings like
+//! `Alt+X` or `Ctrl+Shift+1` (empty to turn one off).
+//!
+//! The platform hooks build a [`Hotkey`] from every key pressed with Ctrl, Alt or Super held
+//! and call [`run`]; keys that run an action are kept from the apps.
+
+use crate::clipboard::{self, ClipboardRequest};
+use crate::completion;
+use crate::data::{get_ordered_script_list, normalize_script, script_list_to_typing_script};
+use crate::history;
+use crate::keymap::new_typing_context;
+use crate::{AppState, ThreadMessage, ThreadMessageOrigin, ThreadMessageType};
+use crossbeam_channel::Sender;
+use lipilekhika::ScriptListEnum;
+use lipilekhika::typing::TypingContextOptions;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+
+/// Name of the Super modifier (Windows or Command key) on this platform.
+#[cfg(target_os = "windows")]
+const SUPER_NAME: &str = "Win";
+#[cfg(target_os = "macos")]
+const SUPER_NAME: &str = "Cmd";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SUPER_NAME: &str = "Super";
+
+/// Name of the Alt modifier on this platform.
+#[cfg(target_os = "macos")]
+const ALT_NAME: &str = "Option";
+#[cfg(not(target_os = "macos"))]
+const ALT_NAME: &str = "Alt";
+
+/// Well-known shortcuts of the system and of most apps, reported as conflicts when recording.
+/// This is a fixed list, not read from the platform: shortcuts the user or the desktop
+/// environment changed, and those of other apps, are not known.
+#[cfg(target_os = "windows")]
+const SYSTEM_SHORTCUTS: &[(&str, &str)] = &[
+  ("Alt+F4", "closing windows"),
+  ("Alt+Space", "the window menu"),
+  ("Win+L", "locking the screen"),
+  ("Win+D", "showing the desktop"),
+  ("Win+E", "File Explorer"),
+  ("Win+R", "the Run dialog"),
+  ("Win+S", "search"),
+  ("Win+V", "clipboard history"),
+  ("Win+Space", "switching keyboard layouts"),
+  ("Ctrl+A", "select all"),
+  ("Ctrl+C", "copy"),
+  ("Ctrl+V", "paste"),
+  ("Ctrl+X", "cut"),
+  ("Ctrl+Z", "undo"),
+  ("Ctrl+Y", "redo"),
+  ("Ctrl+S", "saving"),
+  ("Ctrl+F", "find"),
+  ("Ctrl+P", "printing"),
+  ("Ctrl+N", "new windows"),
+  ("Ctrl+T", "new tabs"),
+  ("Ctrl+W", "closing tabs"),
+];
+#[cfg(target_os = "macos")]
+const SYSTEM_SHORTCUTS: &[(&str, &str)] = &[
+  ("Cmd+Space", "Spotlight"),
+  ("Ctrl+Space", "switching input sources"),
+  ("Cmd+Q", "quitting apps"),
+  ("Cmd+W", "closing windows"),
+  ("Cmd+H", "hiding apps"),
+  ("Cmd+M", "minimizing windows"),
+  ("Cmd+A", "select all"),
+  ("Cmd+C", "copy"),
+  ("Cmd+V", "paste"),
+  ("Cmd+X", "cut"),
+  ("Cmd+Z", "undo"),
+  ("Cmd+S", "saving"),
+  ("Cmd+F", "find"),
+  ("Cmd+P", "printing"),
+  ("Cmd+N", "new windows"),
+  ("Cmd+T", "new tabs"),
+];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SYSTEM_SHORTCUTS: &[(&str, &str)] = &[
+  ("Alt+F2", "the run dialog"),
+  ("Alt+F4", "closing windows"),
+  ("Alt+Space", "the window menu"),
+  ("Super+L", "locking the screen"),
+  ("Super+Space", "switching input sources"),
+  ("Ctrl+Alt+T", "opening a terminal"),
+  ("Ctrl+A", "select all"),
+  ("Ctrl+C", "copy"),
+  ("Ctrl+V", "paste"),
+  ("Ctrl+X", "cut"),
+  ("Ctrl+Z", "undo"),
+  ("Ctrl+S", "saving"),
+  ("Ctrl+F", "find"),
+  ("Ctrl+P", "printing"),
+  ("Ctrl+N", "new windows"),
+  ("Ctrl+T", "new tabs"),
+  ("Ctrl+W", "closing tabs"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HotkeyModifiers {
+  pub ctrl: bool,
+  pub alt: bool,
+  pub shift: bool,
+  /// Windows key, Command key on macOS
+  pub super_key: bool,
+}
+
+impl HotkeyModifiers {
+  /// Hotkeys need one of these, so that they never replace typed text.
+  pub fn has_command(&self) -> bool {
+    self.ctrl || self.alt || self.super_key
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HotkeyKey {
+  /// Letter (uppercase) or digit
+  Char(char),
+  /// Function keys F1 to F24
+  F(u8),
+  Escape,
+  Space,
+}
+
+impl HotkeyKey {
+  /// Key typing `ch` on a US layout, for letters and digits.
+  pub fn from_char(ch: char) -> Option<Self> {
+    ch.is_ascii_alphanumeric()
+      .then(|| HotkeyKey::Char(ch.to_ascii_uppercase()))
+  }
+
+  /// Key named like `X`, `F5`, `Esc` or `Space` (any case).
+  pub fn from_name(name: &str) -> Option<Self> {
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+      "esc" | "escape" => Some(HotkeyKey::Escape),
+      "space" => Some(HotkeyKey::Space),
+      _ => {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+          (Some(ch), None) => HotkeyKey::from_char(ch),
+          (Some('f'), Some(_)) => name[1..]
+            .parse()
+            .ok()
+            .filter(|n| (1..=24).contains(n))
+            .map(HotkeyKey::F),
+          _ => None,
+        }
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+  pub modifiers: HotkeyModifiers,
+  pub key: HotkeyKey,
+}
+
+impl fmt::Display for Hotkey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let modifiers = [
+      (self.modifiers.ctrl, "Ctrl"),
+      (self.modifiers.alt, ALT_NAME),
+      (self.modifiers.shift, "Shift"),
+      (self.modifiers.super_key, SUPER_NAME),
+    ];
+    for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+      write!(f, "{name}+")?;
+    }
+    match self.key {
+      HotkeyKey::Char(ch) => write!(f, "{ch}"),
+      HotkeyKey::F(n) => write!(f, "F{n}"),
+      HotkeyKey::Escape => write!(f, "Esc"),
+      HotkeyKey::Space => write!(f, "Space"),
+    }
+  }
+}
+
+impl FromStr for Hotkey {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+    let key = parts.pop().unwrap_or_default();
+    let mut modifiers = HotkeyModifiers::default();
+    for part in parts {
+      match part.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => modifiers.ctrl = true,
+        "alt" | "option" | "opt" => modifiers.alt = true,
+        "shift" => modifiers.shift = true,
+        "super" | "win" | "cmd" | "command" | "meta" => modifiers.super_key = true,
+        _ => return Err(format!("Unknown modifier `{part}` in `{s}`")),
+      }
+    }
+    let key = HotkeyKey::from_name(key).ok_or_else(|| format!("Unknown key `{key}` in `{s}`"))?;
+    if !modifiers.has_command() {
+      return Err(format!("`{s}` needs Ctrl, {ALT_NAME} or {SUPER_NAME}"));
+    }
+    Ok(Hotkey { modifiers, key })
+  }
+}
+
+/// Script switched to with its own hotkey.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FavouriteScript {
+  pub script: String,
+  #[serde(default)]
+  pub hotkey: String,
+}
+
+fn default_toggle_typing() -> Vec<String> {
+  vec!["Alt+X".to_string(), "Alt+C".to_string()]
+}
+
+/// Ctrl+Esc closed the app before the hotkeys could be configured.
+fn default_close_app() -> String {
+  "Ctrl+Esc".to_string()
+}
+
+fn default_undo_conversion() -> String {
+  "Alt+Z".to_string()
+}
+
+/// The `[hotkeys]` table of `app_conf.toml`. Invalid entries are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotkeySettings {
+  /// Turn typing on or off
+  #[serde(default = "default_toggle_typing")]
+  pub toggle_typing: Vec<String>,
+
+  /// Switch to the next favourite script (the next script without favourites)
+  #[serde(default)]
+  pub next_script: String,
+
+  #[serde(default = "default_close_app")]
+  pub close_app: String,
+
+  /// Convert the selection (or the clipboard), see `crate::clipboard`. Off by default, as
+  /// the hotkey is kept from the apps.
+  #[serde(default)]
+  pub transliterate_clipboard: String,
+
+  /// Turn the last word back into the keys typed for it, see `crate::history`
+  #[serde(default = "default_undo_conversion")]
+  pub undo_conversion: String,
+
+  #[serde(default)]
+  pub favourite_scripts: Vec<FavouriteScript>,
+}
+
+impl Default for HotkeySettings {
+  fn default() -> Self {
+    Self {
+      toggle_typing: default_toggle_typing(),
+      next_script: String::new(),
+      close_app: default_close_app(),
+      transliterate_clipboard: String::new(),
+      undo_conversion: default_undo_conversion(),
+      favourite_scripts: Vec::new(),
+    }
+  }
+}
+
+/// One configurable hotkey of [`HotkeySettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeySlot {
+  ToggleTyping(usize),
+  NextScript,
+  CloseApp,
+  TransliterateClipboard,
+  UndoConversion,
+  FavouriteScript(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+  ToggleTyping,
+  NextScript,
+  SwitchScript(ScriptListEnum),
+  CloseApp,
+  TransliterateClipboard,
+  UndoConversion,
+}
+
+impl HotkeySettings {
+  /// Every slot, in the order shown in the UI.
+  pub fn slots(&self) -> Vec<HotkeySlot> {
+    let toggles = (0..self.toggle_typing.len()).map(HotkeySlot::ToggleTyping);
+    let favourites = (0..self.favourite_scripts.len()).map(HotkeySlot::FavouriteScript);
+    toggles
+      .chain([
+        HotkeySlot::NextScript,
+        HotkeySlot::UndoConversion,
+        HotkeySlot::TransliterateClipboard,
+        HotkeySlot::CloseApp,
+      ])
+      .chain(favourites)
+      .collect()
+  }
+
+  pub fn get(&self, slot: HotkeySlot) -> Option<&str> {
+    let hotkey = match slot {
+      HotkeySlot::ToggleTyping(idx) => self.toggle_typing.get(idx)?,
+      HotkeySlot::NextScript => &self.next_script,
+      HotkeySlot::CloseApp => &self.close_app,
+      HotkeySlot::TransliterateClipboard => &self.transliterate_clipboard,
+      HotkeySlot::UndoConversion => &self.undo_conversion,
+      HotkeySlot::FavouriteScript(idx) => &self.favourite_scripts.get(idx)?.hotkey,
+    };
+    Some(hotkey)
+  }
+
+  /// Sets the hotkey of a slot; `None` turns it off.
+  pub fn set(&mut self, slot: HotkeySlot, hotkey: Option<Hotkey>) {
+    let value = hotkey.map(|h| h.to_string()).unwrap_or_default();
+    let target = match slot {
+      HotkeySlot::ToggleTyping(idx) => self.toggle_typing.get_mut(idx),
+      HotkeySlot::NextScript => Some(&mut self.next_script),
+      HotkeySlot::CloseApp => Some(&mut self.close_app),
+      HotkeySlot::TransliterateClipboard => Some(&mut self.transliterate_clipboard),
+      HotkeySlot::UndoConversion => Some(&mut self.undo_conversion),
+      HotkeySlot::FavouriteScript(idx) => self
+        .favourite_scripts
+        .get_mut(idx)
+        .map(|favourite| &mut favourite.hotkey),
+    };
+    if let Some(target) = target {
+      *target = value;
+    }
+  }
+
+  fn action(&self, slot: HotkeySlot) -> Option<HotkeyAction> {
+    Some(match slot {
+      HotkeySlot::ToggleTyping(_) => HotkeyAction::ToggleTyping,
+      HotkeySlot::NextScript => HotkeyAction::NextScript,
+      HotkeySlot::CloseApp => HotkeyAction::CloseApp,
+      HotkeySlot::TransliterateClipboard => HotkeyAction::TransliterateClipboard,
+      HotkeySlot::UndoConversion => HotkeyAction::UndoConversion,
+      HotkeySlot::FavouriteScript(idx) => {
+        HotkeyAction::SwitchScript(normalize_script(&self.favourite_scripts.get(idx)?.script)?)
+      }
+    })
+  }
+
+  /// Valid hotkeys with their slots.
+  fn bindings(&self) -> impl Iterator<Item = (HotkeySlot, Hotkey)> + '_ {
+    self.slots().into_iter().filter_map(|slot| {
+      let hotkey = self.get(slot)?.parse().ok()?;
+      Some((slot, hotkey))
+    })
+  }
+
+  pub fn action_for(&self, pressed: &Hotkey) -> Option<HotkeyAction> {
+    self
+      .bindings()
+      .find(|(_, hotkey)| hotkey == pressed)
+      .and_then(|(slot, _)| self.action(slot))
+  }
+
+  /// Why `hotkey` should not be used for `slot`: a system shortcut or another slot.
+  pub fn conflict(&self, slot: HotkeySlot, hotkey: &Hotkey) -> Option<String> {
+    if let Some((_, what)) = SYSTEM_SHORTCUTS
+      .iter()
+      .find(|(shortcut, _)| shortcut.parse::<Hotkey>().as_ref() == Ok(hotkey))
+    {
+      return Some(format!("{hotkey} is used for {what}"));
+    }
+    self
+      .bindings()
+      .find(|(other, bound)| *other != slot && bound == hotkey)
+      .map(|(other, _)| format!("{hotkey} is already used to {}", self.describe(other)))
+  }
+
+  /// What a slot does, eg. "switch to Tamil".
+  pub fn describe(&self, slot: HotkeySlot) -> String {
+    match slot {
+      HotkeySlot::ToggleTyping(_) => "turn typing on or off".to_string(),
+      HotkeySlot::NextScript => "switch to the next script".to_string(),
+      HotkeySlot::CloseApp => "close the app".to_string(),
+      HotkeySlot::TransliterateClipboard => "convert the selection or clipboard".to_string(),
+      HotkeySlot::UndoConversion => "undo the last conversion".to_string(),
+      HotkeySlot::FavouriteScript(idx) => match self.favourite_scripts.get(idx) {
+        Some(favourite) => format!("switch to {}", favourite.script),
+        None => "switch script".to_string(),
+      },
+    }
+  }
+}
+
+/// Script after the current one: among the favourites if any, else among all scripts.
+fn next_script(app_state: &AppState) -> Option<ScriptListEnum> {
+  let favourites: Vec<ScriptListEnum> = match app_state.persitent_state.lock() {
+    Ok(state) => state
+      .hotkeys
+      .favourite_scripts
+      .iter()
+      .filter_map(|favourite| normalize_script(&favourite.script))
+      .collect(),
+    Err(_) => return None,
+  };
+  let scripts = if favourites.is_empty() {
+    get_ordered_script_list()
+      .into_iter()
+      .map(|display| display.script_name)
+      .filter(|script| *script != ScriptListEnum::Normal)
+      .collect()
+  } else {
+    favourites
+  };
+  let current = normalize_script(
+    app_state
+      .typing_context
+      .lock()
+      .ok()?
+      .get_normalized_script(),
+  );
+  let next = match scripts.iter().position(|script| Some(*script) == current) {
+    Some(idx) => (idx + 1) % scripts.len(),
+    None => 0,
+  };
+  scripts.get(next).copied()
+}
+
+/// Types in `script` from now on, keeping the typing options.
+pub fn switch_script(app_state: &AppState, script: ScriptListEnum) {
+  completion::commit_context(app_state);
+  let Ok(mut state) = app_state.persitent_state.lock() else {
+    return;
+  };
+  state.script = script.to_string();
+  let (layout, auto_clear_time_ms) = (state.typing_layout(), state.auto_clear_time_ms);
+  drop(state);
+
+  let Ok(mut ctx) = app_state.typing_context.lock() else {
+    return;
+  };
+  let options = TypingContextOptions {
+    auto_context_clear_time_ms: auto_clear_time_ms,
+    use_native_numerals: ctx.get_use_native_numerals(),
+    include_inherent_vowel: ctx.get_include_inherent_vowel(),
+    layout,
+    latin_escape: ctx.get_latin_escape(),
+  };
+  *ctx = new_typing_context(script_list_to_typing_script(script), Some(options));
+}
+
+/// Runs the action of a pressed hotkey (none while the UI records one). `CloseApp` and
+/// `UndoConversion` (which types in the app) are left to the caller. Returns the action, if
+/// the key is a hotkey. The undo hotkey is only one while typing is on and there is a word
+/// to undo, so that the apps get it otherwise.
+pub fn run(app_state: &AppState, pressed: &Hotkey) -> Option<HotkeyAction> {
+  if app_state.recording_hotkey.load(Ordering::SeqCst) {
+    return None;
+  }
+  let action = app_state
+    .persitent_state
+    .lock()
+    .ok()?
+    .hotkeys
+    .action_for(pressed)?;
+  if action == HotkeyAction::UndoConversion
+    && !(app_state.typing_enabled.load(Ordering::SeqCst) && history::can_undo(app_state))
+  {
+    return None;
+  }
+  match action {
+    HotkeyAction::ToggleTyping => {
+      let was_enabled = app_state.typing_enabled.fetch_xor(true, Ordering::SeqCst);
+      // ^ a xor 1 = !a
+      if was_enabled {
+        completion::commit_context(app_state);
+        history::forget(app_state);
+      }
+    }
+    HotkeyAction::NextScript => switch_script(app_state, next_script(app_state)?),
+    HotkeyAction::SwitchScript(script) => switch_script(app_state, script),
+    HotkeyAction::TransliterateClipboard => clipboard::transliterate(ClipboardRequest::Hotkey),
+    HotkeyAction::CloseApp | HotkeyAction::UndoConversion => {}
+  }
+  Some(action)
+}
+
+/// Tells the UI and tray about an action run from the keyboard hook.
+pub fn notify(
+  tx_ui: &Sender<ThreadMessage>,
+  tx_tray: &Sender<ThreadMessage>,
+  action: HotkeyAction,
+) {
+  let send = |tx: &Sender<ThreadMessage>, msg| {
+    let _ = tx.send(ThreadMessage {
+      origin: ThreadMessageOrigin::KeyboardHook,
+      msg,
+    });
+  };
+  match action {
+    HotkeyAction::ToggleTyping => {
+      send(tx_ui, ThreadMessageType::TriggerTypingNotification);
+      send(tx_ui, ThreadMessageType::RerenderUI);
+      send(tx_tray, ThreadMessageType::RerenderTray);
+    }
+    HotkeyAction::NextScript | HotkeyAction::SwitchScript(_) => {
+      send(tx_ui, ThreadMessageType::ScriptChanged);
+      send(tx_tray, ThreadMessageType::RerenderTray);
+    }
+    HotkeyAction::CloseApp => send(tx_ui, ThreadMessageType::CloseApp),
+    // the clipboard thread shows the result
+    HotkeyAction::TransliterateClipboard => {}
+    HotkeyAction::UndoConversion => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn hotkey(s: &str) -> Hotkey {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn hotkeys_parse_and_display() {
+    let alt_x = hotkey("alt + x");
+    assert!(alt_x.modifiers.alt && !alt_x.modifiers.ctrl);
+    assert_eq!(alt_x.key, HotkeyKey::Char('X'));
+    assert_eq!(hotkey(&alt_x.to_string()), alt_x);
+    assert_eq!(hotkey("Win+Esc"), hotkey("Cmd+Escape"));
+    assert_eq!(hotkey("Ctrl+Shift+F12").key, HotkeyKey::F(12));
+
+    assert!("Shift+A".parse::<Hotkey>().is_err());
+    assert!("Alt+F25".parse::<Hotkey>().is_err());
+    assert!("Hyper+A".parse::<Hotkey>().is_err());
+    assert!("".parse::<Hotkey>().is_err());
+  }
+
+  #[test]
+  fn settings_map_hotkeys_to_actions() {
+    let mut settings = HotkeySettings::default();
+    settings.favourite_scripts.push(FavouriteScript {
+      script: "tamil".to_string(),
+      hotkey: "Alt+Shift+2".to_string(),
+    });
+    assert_eq!(
+      settings.action_for(&hotkey("Alt+C")),
+      Some(HotkeyAction::ToggleTyping)
+    );
+    assert_eq!(
+      settings.action_for(&hotkey("Alt+Shift+2")),
+      Some(HotkeyAction::SwitchScript(ScriptListEnum::Tamil))
+    );
+    assert_eq!(settings.action_for(&hotkey("Alt+S")), None);
+
+    settings.set(HotkeySlot::ToggleTyping(1), None);
+    settings.set(HotkeySlot::NextScript, Some(hotkey("Alt+S")));
+    assert_eq!(settings.action_for(&hotkey("Alt+C")), None);
+    assert_eq!(
+      settings.action_for(&hotkey("Alt+S")),
+      Some(HotkeyAction::NextScript)
+    );
+  }
+
+  #[test]
+  fn conflicts_with_system_shortcuts_and_other_hotkeys() {
+    let settings = HotkeySettings::default();
+    let slot = HotkeySlot::NextScript;
+    assert!(settings.conflict(slot, &hotkey("Ctrl+C")).is_some());
+    assert_eq!(
+      settings.conflict(slot, &hotkey("Alt+X")),
+      Some(format!(
+        "{} is already used to turn typing on or off",
+        hotkey("Alt+X")
+      ))
+    );
+    assert_eq!(
+      settings.conflict(HotkeySlot::ToggleTyping(0), &hotkey("Alt+X")),
+      None
+    );
+    assert_eq!(settings.conflict(slot, &hotkey("Alt+S")), None);
+
+    // The defaults are not reported
+    assert_eq!(
+      settings.action_for(&hotkey("Ctrl+Esc")),
+      Some(HotkeyAction::CloseApp)
+    );
+    for (slot, bound) in settings.bindings() {
+      assert_eq!(settings.conflict(slot, &bound), None);
+    }
+  }
+}
@@ -0,0 +1,119 @@
+//! User keymap overrides, one file per script in `<data dir>/lipilekhika/keymaps/`
+//! named after the script (`Devanagari.toml` or `Devanagari.json`), and the `[keymaps]`
+//! table of `app_conf.toml`, whose entries win over the ones of the files.
+//!
+//! The keymap of each script is read once and kept until the settings are applied again
+//! (at start, or when a profile or an import replaces them), so that switching between
+//! windows with different app profiles does not read the files each time.
+//!
+//! See `lipilekhika::keymap` for the file format.
+
+use crate::data::normalize_script;
+use lipilekhika::keymap::Keymap;
+use lipilekhika::typing::{TypingContext, TypingContextOptions};
+use lipilekhika::{Script, ScriptListEnum};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// Keymaps of the `[keymaps]` table of the config, by normalized script name.
+static CONFIG_KEYMAPS: RwLock<BTreeMap<String, Keymap>> = RwLock::new(BTreeMap::new());
+
+/// Keymaps returned by [`load_keymap`], by normalized script name (`None` for scripts
+/// without one).
+static LOADED_KEYMAPS: RwLock<BTreeMap<String, Option<Keymap>>> = RwLock::new(BTreeMap::new());
+
+/// Sets the keymaps of the config (keyed by script name), used by the typing contexts
+/// created from now on. Unknown script names are reported and ignored. The keymap files
+/// are read again as well.
+pub fn set_config_keymaps(keymaps: &BTreeMap<String, Keymap>) {
+  let mut by_script = BTreeMap::new();
+  for (name, keymap) in keymaps {
+    match normalize_script(name) {
+      Some(script) => {
+        by_script.insert(script.as_ref().to_string(), keymap.clone());
+      }
+      None => eprintln!("Ignoring keymap for unknown script `{name}` in app_conf.toml"),
+    }
+  }
+  if let Ok(mut config_keymaps) = CONFIG_KEYMAPS.write() {
+    *config_keymaps = by_script;
+  }
+  if let Ok(mut loaded) = LOADED_KEYMAPS.write() {
+    loaded.clear();
+  }
+}
+
+fn keymaps_dir() -> Option<PathBuf> {
+  let mut path = dirs::data_dir()?;
+  path.push("lipilekhika");
+  path.push("keymaps");
+  Some(path)
+}
+
+fn load_keymap_file(name: ScriptListEnum) -> Option<Keymap> {
+  let dir = keymaps_dir()?;
+  let path = ["toml", "json"]
+    .iter()
+    .map(|ext| dir.join(format!("{}.{ext}", name.as_ref())))
+    .find(|path| path.is_file())?;
+  match Keymap::from_file(&path) {
+    Ok(keymap) => Some(keymap),
+    Err(e) => {
+      eprintln!("Ignoring keymap {}: {e}", path.display());
+      None
+    }
+  }
+}
+
+/// The user's keymap for a script, if there is one. Unreadable files are reported and
+/// ignored so typing keeps working with the built-in keys.
+pub fn load_keymap(script: Script) -> Option<Keymap> {
+  let name: ScriptListEnum = script.into();
+  if let Some(keymap) = LOADED_KEYMAPS
+    .read()
+    .ok()
+    .and_then(|loaded| loaded.get(name.as_ref()).cloned())
+  {
+    return keymap;
+  }
+  let keymap = read_keymap(name);
+  if let Ok(mut loaded) = LOADED_KEYMAPS.write() {
+    loaded.insert(name.as_ref().to_string(), keymap.clone());
+  }
+  keymap
+}
+
+/// The keymap file of a script with the entries of the config applied over it.
+fn read_keymap(name: ScriptListEnum) -> Option<Keymap> {
+  let file = load_keymap_file(name);
+  let config = CONFIG_KEYMAPS
+    .read()
+    .ok()
+    .and_then(|keymaps| keymaps.get(name.as_ref()).cloned());
+  match (file, config) {
+    (Some(mut keymap), Some(config)) => {
+      for (input, target) in config.iter() {
+        keymap.insert(input, target.clone());
+      }
+      Some(keymap)
+    }
+    (file, config) => file.or(config),
+  }
+}
+
+/// Creates a typing context with the user's keymap for the script applied.
+pub fn new_typing_context(script: Script, options: Option<TypingContextOptions>) -> TypingContext {
+  let mut ctx = TypingContext::new(script, options);
+  if let Some(keymap) = load_keymap(script) {
+    match ctx.set_keymap(Some(&keymap)) {
+      Ok(conflicts) => {
+        for conflict in conflicts {
+          eprintln!("Keymap for {}: {conflict}", ctx.get_normalized_script());
+        }
+      }
+      Err(e) => eprintln!("Ignoring keymap for {}: {e}", ctx.get_normalized_script()),
+    }
+  }
+  ctx
+}
@@ -0,0 +1,99 @@
+use evdev::KeyCode;
+
+// ---- Input device constants ----
+
+/// Name of the uinput keyboard that passes keys on to the apps; skipped when looking for
+/// keyboards so that the backend does not read its own output.
+pub const VIRTUAL_KEYBOARD_NAME: &str = "lipilekhika virtual keyboard";
+
+// X keycodes are evdev key codes offset by 8
+pub const X_KEYCODE_OFFSET: u16 = 8;
+
+// Unused X keycodes remapped to type Unicode text through XTest (like xdotool). Several are
+// cycled so that apps reading the mapping late still see the right character.
+pub const MAX_SPARE_KEYCODES: usize = 8;
+
+// Keysyms
+pub const XK_BACKSPACE: u32 = 0xff08;
+/// AltGr in most layouts
+pub const XK_ISO_LEVEL3_SHIFT: u32 = 0xfe03;
+pub const XK_CONTROL_L: u32 = 0xffe3;
+pub const XK_LOWER_C: u32 = 0x0063;
+pub const XK_LOWER_V: u32 = 0x0076;
+/// Keysyms of Unicode characters outside Latin-1 are the code point plus this.
+pub const XK_UNICODE_OFFSET: u32 = 0x0100_0000;
+
+// Longest wait for the X server to take a key passed on through uinput before typing text
+pub const FORWARDED_KEY_SYNC_TIMEOUT_MS: u64 = 50;
+
+// Longest wait for the modifiers of a hotkey to be released before a clipboard shortcut
+pub const MODIFIER_RELEASE_TIMEOUT_MS: u64 = 1000;
+
+// Levels of child windows searched for the app's `WM_CLASS` under a top-level (frame) window
+pub const WINDOW_CLASS_SEARCH_DEPTH: u8 = 2;
+
+// Keys that can be part of a hotkey, with the character or function key number they stand for
+pub const HOTKEY_CHAR_KEYS: [(KeyCode, char); 36] = [
+  (KeyCode::KEY_A, 'A'),
+  (KeyCode::KEY_B, 'B'),
+  (KeyCode::KEY_C, 'C'),
+  (KeyCode::KEY_D, 'D'),
+  (KeyCode::KEY_E, 'E'),
+  (KeyCode::KEY_F, 'F'),
+  (KeyCode::KEY_G, 'G'),
+  (KeyCode::KEY_H, 'H'),
+  (KeyCode::KEY_I, 'I'),
+  (KeyCode::KEY_J, 'J'),
+  (KeyCode::KEY_K, 'K'),
+  (KeyCode::KEY_L, 'L'),
+  (KeyCode::KEY_M, 'M'),
+  (KeyCode::KEY_N, 'N'),
+  (KeyCode::KEY_O, 'O'),
+  (KeyCode::KEY_P, 'P'),
+  (KeyCode::KEY_Q, 'Q'),
+  (KeyCode::KEY_R, 'R'),
+  (KeyCode::KEY_S, 'S'),
+  (KeyCode::KEY_T, 'T'),
+  (KeyCode::KEY_U, 'U'),
+  (KeyCode::KEY_V, 'V'),
+  (KeyCode::KEY_W, 'W'),
+  (KeyCode::KEY_X, 'X'),
+  (KeyCode::KEY_Y, 'Y'),
+  (KeyCode::KEY_Z, 'Z'),
+  (KeyCode::KEY_0, '0'),
+  (KeyCode::KEY_1, '1'),
+  (KeyCode::KEY_2, '2'),
+  (KeyCode::KEY_3, '3'),
+  (KeyCode::KEY_4, '4'),
+  (KeyCode::KEY_5, '5'),
+  (KeyCode::KEY_6, '6'),
+  (KeyCode::KEY_7, '7'),
+  (KeyCode::KEY_8, '8'),
+  (KeyCode::KEY_9, '9'),
+];
+pub const HOTKEY_FUNCTION_KEYS: [(KeyCode, u8); 24] = [
+  (KeyCode::KEY_F1, 1),
+  (KeyCode::KEY_F2, 2),
+  (KeyCode::KEY_F3, 3),
+  (KeyCode::KEY_F4, 4),
+  (KeyCode::KEY_F5, 5),
+  (KeyCode::KEY_F6, 6),
+  (KeyCode::KEY_F7, 7),
+  (KeyCode::KEY_F8, 8),
+  (KeyCode::KEY_F9, 9),
+  (KeyCode::KEY_F10, 10),
+  (KeyCode::KEY_F11, 11),
+  (KeyCode::KEY_F12, 12),
+  (KeyCode::KEY_F13, 13),
+  (KeyCode::KEY_F14, 14),
+  (KeyCode::KEY_F15, 15),
+  (KeyCode::KEY_F16, 16),
+  (KeyCode::KEY_F17, 17),
+  (KeyCode::KEY_F18, 18),
+  (KeyCode::KEY_F19, 19),
+  (KeyCode::KEY_F20, 20),
+  (KeyCode::KEY_F21, 21),
+  (KeyCode::KEY_F22, 22),
+  (KeyCode::KEY_F23, 23),
+  (KeyCode::KEY_F24, 24),
+];
@@ -0,0 +1,647 @@
+use crate::completion;
+use crate::history;
+use crate::hotkeys::{self, Hotkey, HotkeyAction, HotkeyKey, HotkeyModifiers};
+use crate::window_context::{self, WindowId};
+
+use super::LinuxAppState;
+use super::constants::{HOTKEY_CHAR_KEYS, HOTKEY_FUNCTION_KEYS};
+use evdev::KeyCode;
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+
+/// Output of the hooks. The keyboards are grabbed, so every key that is not handled must be
+/// passed on here; `runtime::XKeyboard` writes to the uinput and XTest devices.
+pub trait VirtualKeyboard {
+  /// Pass a key event (`value`: 0 release, 1 press, 2 autorepeat) on to the apps.
+  fn forward(&mut self, key: KeyCode, value: i32);
+  /// Send backspace key n times
+  fn send_backspaces(&mut self, n: usize);
+  /// Type a Unicode string in the focused window
+  fn send_unicode_text(&mut self, text: &str);
+  /// Text typed by `key` with the current modifiers in the active keyboard layout
+  fn key_text(&mut self, key: KeyCode, modifiers: &Modifiers) -> Option<String>;
+  /// Whether `key` is AltGr (it selects the third level of the keys) in the active layout
+  fn is_alt_gr(&mut self, key: KeyCode) -> bool;
+  /// Top-level window receiving the keyboard input
+  fn focused_window(&mut self) -> Option<WindowId>;
+  /// Application of a window (its window class), for the app profiles
+  fn app_name(&mut self, window: WindowId) -> Option<String>;
+}
+
+/// Modifier state of the grabbed keyboards. It is tracked from their own events, as the
+/// display server only sees the keys passed on.
+#[derive(Default)]
+pub struct Modifiers {
+  pressed: HashSet<KeyCode>,
+  pub caps_lock: bool,
+  /// Right Alt is AltGr in the layout, checked when it is pressed.
+  right_alt_is_alt_gr: bool,
+}
+
+impl Modifiers {
+  fn update(&mut self, key: KeyCode, value: i32, keyboard: &mut impl VirtualKeyboard) {
+    match value {
+      0 => {
+        self.pressed.remove(&key);
+      }
+      1 => {
+        self.pressed.insert(key);
+        match key {
+          KeyCode::KEY_CAPSLOCK => self.caps_lock = !self.caps_lock,
+          KeyCode::KEY_RIGHTALT => self.right_alt_is_alt_gr = keyboard.is_alt_gr(key),
+          _ => {}
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn is_pressed(&self, left: KeyCode, right: KeyCode) -> bool {
+    self.pressed.contains(&left) || self.pressed.contains(&right)
+  }
+
+  pub fn shift(&self) -> bool {
+    self.is_pressed(KeyCode::KEY_LEFTSHIFT, KeyCode::KEY_RIGHTSHIFT)
+  }
+
+  pub fn ctrl_or_meta(&self) -> bool {
+    self.is_pressed(KeyCode::KEY_LEFTCTRL, KeyCode::KEY_RIGHTCTRL)
+      || self.is_pressed(KeyCode::KEY_LEFTMETA, KeyCode::KEY_RIGHTMETA)
+  }
+
+  /// Alt is held; AltGr is not Alt, it types characters.
+  pub fn alt(&self) -> bool {
+    self.pressed.contains(&KeyCode::KEY_LEFTALT)
+      || (self.pressed.contains(&KeyCode::KEY_RIGHTALT) && !self.right_alt_is_alt_gr)
+  }
+
+  pub fn alt_gr(&self) -> bool {
+    self.pressed.contains(&KeyCode::KEY_RIGHTALT) && self.right_alt_is_alt_gr
+  }
+
+  /// The hotkey of a key press, when Ctrl, Alt or Super is held.
+  fn hotkey(&self, key: KeyCode) -> Option<Hotkey> {
+    let key = match key {
+      KeyCode::KEY_ESC => HotkeyKey::Escape,
+      KeyCode::KEY_SPACE => HotkeyKey::Space,
+      _ => {
+        if let Some((_, ch)) = HOTKEY_CHAR_KEYS.iter().find(|(code, _)| *code == key) {
+          HotkeyKey::from_char(*ch)?
+        } else {
+          let (_, n) = HOTKEY_FUNCTION_KEYS.iter().find(|(code, _)| *code == key)?;
+          HotkeyKey::F(*n)
+        }
+      }
+    };
+    let modifiers = HotkeyModifiers {
+      ctrl: self.is_pressed(KeyCode::KEY_LEFTCTRL, KeyCode::KEY_RIGHTCTRL),
+      alt: self.alt(),
+      shift: self.shift(),
+      super_key: self.is_pressed(KeyCode::KEY_LEFTMETA, KeyCode::KEY_RIGHTMETA),
+    };
+    modifiers.has_command().then_some(Hotkey { modifiers, key })
+  }
+}
+
+/// State kept across the events of the grabbed keyboards.
+#[derive(Default)]
+pub struct HookState {
+  pub modifiers: Modifiers,
+  /// Keys whose press was passed on, so that only their release is passed on too.
+  forwarded: HashSet<KeyCode>,
+}
+
+/// Ends the composition; what was typed stays in the app and is learned for completions.
+fn clear_context(state: &LinuxAppState) {
+  completion::commit_context(&state.app_state);
+  history::forget(&state.app_state);
+}
+
+/// Types the keys of the last converted word over it. The modifiers held for the hotkey are
+/// released for the apps first, so that they do not turn the keys into shortcuts, and pressed
+/// again afterwards, so that the apps see their physical release as usual.
+fn undo_conversion(
+  state: &LinuxAppState,
+  keyboard: &mut impl VirtualKeyboard,
+  modifiers: &Modifiers,
+) {
+  let Some((to_delete, text)) = history::take_undo(&state.app_state) else {
+    return;
+  };
+  // a lock key pressed again would toggle its lock
+  let held: Vec<KeyCode> = modifiers
+    .pressed
+    .iter()
+    .copied()
+    .filter(|key| {
+      !matches!(
+        *key,
+        KeyCode::KEY_CAPSLOCK | KeyCode::KEY_NUMLOCK | KeyCode::KEY_SCROLLLOCK
+      )
+    })
+    .collect();
+  for key in &held {
+    keyboard.forward(*key, 0);
+  }
+  keyboard.send_backspaces(to_delete);
+  keyboard.send_unicode_text(&text);
+  for key in &held {
+    keyboard.forward(*key, 1);
+  }
+}
+
+/// Check if a key is a modifier key (Shift, Ctrl, Alt, Super, Caps Lock, etc.)
+fn is_modifier_key(key: KeyCode) -> bool {
+  matches!(
+    key,
+    KeyCode::KEY_LEFTSHIFT
+      | KeyCode::KEY_RIGHTSHIFT
+      | KeyCode::KEY_LEFTCTRL
+      | KeyCode::KEY_RIGHTCTRL
+      | KeyCode::KEY_LEFTALT
+      | KeyCode::KEY_RIGHTALT
+      | KeyCode::KEY_LEFTMETA
+      | KeyCode::KEY_RIGHTMETA
+      | KeyCode::KEY_CAPSLOCK
+      | KeyCode::KEY_NUMLOCK
+      | KeyCode::KEY_SCROLLLOCK
+  )
+}
+
+/// Check if a key is a context-clearing key (navigation, deletion, etc.)
+fn is_context_clear_key(key: KeyCode) -> bool {
+  matches!(
+    key,
+    KeyCode::KEY_BACKSPACE
+      | KeyCode::KEY_DELETE
+      | KeyCode::KEY_ENTER
+      | KeyCode::KEY_KPENTER
+      | KeyCode::KEY_TAB
+      | KeyCode::KEY_ESC
+      | KeyCode::KEY_LEFT
+      | KeyCode::KEY_RIGHT
+      | KeyCode::KEY_UP
+      | KeyCode::KEY_DOWN
+      | KeyCode::KEY_HOME
+      | KeyCode::KEY_END
+      | KeyCode::KEY_PAGEUP
+      | KeyCode::KEY_PAGEDOWN
+  )
+}
+
+/// Handles a mouse button press; `window` is the top-level window under the pointer.
+pub fn handle_click(state: &LinuxAppState, window: Option<WindowId>) {
+  // A click ends the composition of the clicked window (like onblur in web)
+  if state.app_state.typing_enabled.load(Ordering::SeqCst) {
+    window_context::click(&state.app_state, window);
+  }
+}
+
+/// Handles a key event of the grabbed keyboards, passing it on unless typing handled it.
+pub fn handle_key(
+  state: &LinuxAppState,
+  keyboard: &mut impl VirtualKeyboard,
+  hook: &mut HookState,
+  key: KeyCode,
+  value: i32,
+) {
+  // Modifier keys are always passed on, they are needed for capitalization and shortcuts
+  if is_modifier_key(key) {
+    hook.modifiers.update(key, value, keyboard);
+    keyboard.forward(key, value);
+    return;
+  }
+
+  if value == 0 {
+    if hook.forwarded.remove(&key) {
+      keyboard.forward(key, value);
+    }
+    return;
+  }
+
+  if take_key_down(state, keyboard, &hook.modifiers, key) {
+    return;
+  }
+  hook.forwarded.insert(key);
+  keyboard.forward(key, value);
+}
+
+/// Returns whether the key press (or autorepeat) was handled and must not reach the apps.
+fn take_key_down(
+  state: &LinuxAppState,
+  keyboard: &mut impl VirtualKeyboard,
+  modifiers: &Modifiers,
+  key: KeyCode,
+) -> bool {
+  // ---- Hotkeys: typing toggle, script switching, close app (regardless of typing mode) ----
+  if let Some(hotkey) = modifiers.hotkey(key)
+    && let Some(action) = hotkeys::run(&state.app_state, &hotkey)
+  {
+    if action == HotkeyAction::UndoConversion {
+      undo_conversion(state, keyboard, modifiers);
+    }
+    // Notify UI and tray to rerender based on latest app state
+    hotkeys::notify(&state.tx_ui, &state.tx_tray, action);
+    return true;
+  }
+
+  // If typing mode is disabled, pass everything through
+  if !state.app_state.typing_enabled.load(Ordering::SeqCst) {
+    return false;
+  }
+
+  // Switch to the composition and settings of the window being typed in
+  if let Some(window) = keyboard.focused_window()
+    && !window_context::focus(&state.app_state, window, || keyboard.app_name(window))
+  {
+    return false;
+  }
+
+  // Alt+Tab switches windows; the composition is kept for when typing resumes here
+  if key == KeyCode::KEY_TAB && modifiers.alt() {
+    return false;
+  }
+
+  // Plain Backspace steps the typing context back one key. The app's own Backspace is
+  // replaced by the diff, unless the context is empty.
+  if key == KeyCode::KEY_BACKSPACE && !modifiers.ctrl_or_meta() && !modifiers.alt() {
+    let diff = match state.app_state.typing_context.lock() {
+      Ok(mut guard) => guard.take_backspace(),
+      Err(_) => return false,
+    };
+    if diff.to_delete_chars_count == 0 && diff.diff_add_text.is_empty() {
+      history::forget(&state.app_state);
+      return false;
+    }
+    keyboard.send_backspaces(diff.to_delete_chars_count);
+    if !diff.diff_add_text.is_empty() {
+      keyboard.send_unicode_text(&diff.diff_add_text);
+    }
+    history::record(&state.app_state, None, &diff);
+    return true;
+  }
+
+  // Plain Tab completes the word being typed; without a completion it passes through.
+  if key == KeyCode::KEY_TAB
+    && !modifiers.ctrl_or_meta()
+    && !modifiers.alt()
+    && let Some((to_delete, text)) = completion::take_completion(&state.app_state)
+  {
+    keyboard.send_backspaces(to_delete);
+    keyboard.send_unicode_text(&text);
+    history::forget(&state.app_state);
+    return true;
+  }
+
+  // Clear context and pass through for navigation/editing keys
+  if is_context_clear_key(key) {
+    clear_context(state);
+    return false;
+  }
+
+  // Pass through shortcuts (Ctrl+anything, Super+anything, Alt+anything that is not a hotkey)
+  if modifiers.ctrl_or_meta() || modifiers.alt() {
+    clear_context(state);
+    return false;
+  }
+
+  // Do NOT lowercase here, uppercase characters are meaningful for some typing schemes.
+  let Some(text) = keyboard.key_text(key, modifiers) else {
+    return false;
+  };
+  if text.chars().any(char::is_control) {
+    return false;
+  }
+
+  // A `.` after a word may turn it back into its keys (domains, emails)
+  if let Some((to_delete, text)) = history::take_auto_revert(&state.app_state, &text) {
+    keyboard.send_backspaces(to_delete);
+    keyboard.send_unicode_text(&text);
+    return true;
+  }
+
+  // All characters are typed in a single locked section, as one net diff.
+  let (diff, finished) = {
+    let mut guard = match state.app_state.typing_context.lock() {
+      Ok(g) => g,
+      Err(_) => return false,
+    };
+    let prev = guard.preedit().to_string();
+    let diff = guard.take_text_input(&text);
+    let finished = completion::finished_text(&prev, &diff, guard.preedit());
+    (diff, finished)
+  };
+
+  // Now inject, with the lock released.
+  if diff.to_delete_chars_count > 0 {
+    keyboard.send_backspaces(diff.to_delete_chars_count);
+  }
+  if !diff.diff_add_text.is_empty() {
+    keyboard.send_unicode_text(&diff.diff_add_text);
+  }
+  completion::learn(&state.app_state, &finished);
+  history::record(&state.app_state, Some(&text), &diff);
+  true
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::AppState;
+  use crate::completion::WordCompletion;
+  use crate::history::TypingHistory;
+  use crate::persistent_state::{AppProfile, PersitentState};
+  use crate::window_context::WindowContexts;
+  use lipilekhika::Script;
+  use lipilekhika::typing::TypingContext;
+  use std::sync::atomic::AtomicBool;
+  use std::sync::{Arc, Mutex};
+
+  /// Records what the hooks send to the apps, and applies the text to a fake text field.
+  #[derive(Default)]
+  struct FakeKeyboard {
+    forwarded: Vec<(KeyCode, i32)>,
+    screen: String,
+    window: WindowId,
+    /// Right Alt is AltGr, typing `ā` with A
+    alt_gr: bool,
+  }
+
+  impl VirtualKeyboard for FakeKeyboard {
+    fn forward(&mut self, key: KeyCode, value: i32) {
+      self.forwarded.push((key, value));
+    }
+
+    fn send_backspaces(&mut self, n: usize) {
+      for _ in 0..n {
+        self.screen.pop();
+      }
+    }
+
+    fn send_unicode_text(&mut self, text: &str) {
+      self.screen.push_str(text);
+    }
+
+    fn key_text(&mut self, key: KeyCode, modifiers: &Modifiers) -> Option<String> {
+      if modifiers.alt_gr() {
+        return (key == KeyCode::KEY_A).then(|| "ā".to_string());
+      }
+      let ch = match key {
+        KeyCode::KEY_A => 'a',
+        KeyCode::KEY_K => 'k',
+        KeyCode::KEY_R => 'r',
+        KeyCode::KEY_SPACE => ' ',
+        KeyCode::KEY_DOT => '.',
+        _ => return None,
+      };
+      let upper = modifiers.shift() != modifiers.caps_lock;
+      Some(if upper { ch.to_ascii_uppercase() } else { ch }.to_string())
+    }
+
+    fn is_alt_gr(&mut self, key: KeyCode) -> bool {
+      self.alt_gr && key == KeyCode::KEY_RIGHTALT
+    }
+
+    fn focused_window(&mut self) -> Option<WindowId> {
+      Some(self.window)
+    }
+
+    fn app_name(&mut self, window: WindowId) -> Option<String> {
+      Some(if window == EDITOR { "Code" } else { "firefox" }.to_string())
+    }
+  }
+
+  const EDITOR: WindowId = 2;
+
+  fn linux_state(typing_enabled: bool) -> LinuxAppState {
+    let (tx_ui, _) = crossbeam_channel::unbounded();
+    let (tx_tray, _) = crossbeam_channel::unbounded();
+    LinuxAppState {
+      app_state: Arc::new(AppState {
+        typing_enabled: AtomicBool::new(typing_enabled),
+        typing_context: Mutex::new(TypingContext::new(Script::Devanagari, None)),
+        persitent_state: Mutex::new(PersitentState::default()),
+        completion: Mutex::new(WordCompletion::in_memory()),
+        window_contexts: Mutex::new(WindowContexts::default()),
+        history: Mutex::new(TypingHistory::default()),
+        recording_hotkey: AtomicBool::new(false),
+      }),
+      tx_ui,
+      tx_tray,
+    }
+  }
+
+  fn tap(state: &LinuxAppState, kb: &mut FakeKeyboard, hook: &mut HookState, key: KeyCode) {
+    handle_key(state, kb, hook, key, 1);
+    handle_key(state, kb, hook, key, 0);
+  }
+
+  #[test]
+  fn typed_keys_are_replaced_by_the_transliteration() {
+    let state = linux_state(true);
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_K);
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTSHIFT, 1);
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_A);
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTSHIFT, 0);
+
+    assert_eq!(kb.screen, "का");
+    assert_eq!(
+      kb.forwarded,
+      [(KeyCode::KEY_LEFTSHIFT, 1), (KeyCode::KEY_LEFTSHIFT, 0)]
+    );
+
+    // Backspace steps back one key instead of deleting the character
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_BACKSPACE);
+    assert_eq!(kb.screen, "क्");
+  }
+
+  #[test]
+  fn undo_hotkey_types_the_keys_of_the_last_word() {
+    let state = linux_state(true);
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    for key in [
+      KeyCode::KEY_R,
+      KeyCode::KEY_A,
+      KeyCode::KEY_K,
+      KeyCode::KEY_A,
+    ] {
+      tap(&state, &mut kb, &mut hook, key);
+    }
+    assert_eq!(kb.screen, "रक");
+
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTALT, 1);
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_Z);
+    assert_eq!(kb.screen, "raka");
+    // Alt is released before the keys are typed and pressed again after them
+    assert_eq!(
+      kb.forwarded,
+      [
+        (KeyCode::KEY_LEFTALT, 1),
+        (KeyCode::KEY_LEFTALT, 0),
+        (KeyCode::KEY_LEFTALT, 1)
+      ]
+    );
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTALT, 0);
+    // every forwarded press has its release
+    let balance = |key| {
+      kb.forwarded
+        .iter()
+        .filter(|(forwarded, _)| *forwarded == key)
+        .map(|(_, value)| if *value == 1 { 1 } else { -1 })
+        .sum::<i32>()
+    };
+    assert_eq!(balance(KeyCode::KEY_LEFTALT), 0);
+    assert_eq!(kb.forwarded.last(), Some(&(KeyCode::KEY_LEFTALT, 0)));
+
+    // the rest of the word is typed as is, the next one is converted
+    for key in [KeyCode::KEY_K, KeyCode::KEY_SPACE, KeyCode::KEY_K] {
+      tap(&state, &mut kb, &mut hook, key);
+    }
+    assert_eq!(kb.screen, "rakak क्");
+  }
+
+  #[test]
+  fn undo_hotkey_passes_through_without_a_word_to_undo() {
+    let state = linux_state(true);
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTALT, 1);
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_Z);
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTALT, 0);
+
+    assert_eq!(
+      kb.forwarded,
+      [
+        (KeyCode::KEY_LEFTALT, 1),
+        (KeyCode::KEY_Z, 1),
+        (KeyCode::KEY_Z, 0),
+        (KeyCode::KEY_LEFTALT, 0)
+      ]
+    );
+  }
+
+  #[test]
+  fn a_dot_after_a_word_reverts_it_when_enabled() {
+    let state = linux_state(true);
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    for key in [KeyCode::KEY_K, KeyCode::KEY_A, KeyCode::KEY_DOT] {
+      tap(&state, &mut kb, &mut hook, key);
+    }
+    assert_eq!(kb.screen, "क।");
+
+    state
+      .app_state
+      .persitent_state
+      .lock()
+      .unwrap()
+      .auto_revert_before_dot = true;
+    for key in [
+      KeyCode::KEY_SPACE,
+      KeyCode::KEY_K,
+      KeyCode::KEY_A,
+      KeyCode::KEY_DOT,
+      KeyCode::KEY_K,
+    ] {
+      tap(&state, &mut kb, &mut hook, key);
+    }
+    assert_eq!(kb.screen, "क। ka.k");
+  }
+
+  #[test]
+  fn keys_pass_through_while_typing_is_disabled() {
+    let state = linux_state(false);
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_K);
+
+    assert_eq!(kb.screen, "");
+    assert_eq!(kb.forwarded, [(KeyCode::KEY_K, 1), (KeyCode::KEY_K, 0)]);
+  }
+
+  #[test]
+  fn shortcuts_clear_the_context_and_pass_through() {
+    let state = linux_state(true);
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_R);
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTCTRL, 1);
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_A);
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTCTRL, 0);
+
+    assert!(
+      state
+        .app_state
+        .typing_context
+        .lock()
+        .unwrap()
+        .preedit()
+        .is_empty()
+    );
+    assert_eq!(
+      kb.forwarded[1..3],
+      [(KeyCode::KEY_A, 1), (KeyCode::KEY_A, 0)]
+    );
+  }
+
+  #[test]
+  fn app_profiles_apply_in_their_windows() {
+    let state = linux_state(true);
+    state.app_state.persitent_state.lock().unwrap().profiles = vec![AppProfile {
+      app: "code".into(),
+      typing_enabled: false,
+      ..PersitentState::default().profile_for(None)
+    }];
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_K);
+    assert_eq!(kb.screen, "क्");
+
+    // The editor is kept in English; the composition is kept for the browser
+    kb.window = EDITOR;
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_K);
+    assert_eq!(kb.forwarded, [(KeyCode::KEY_K, 1), (KeyCode::KEY_K, 0)]);
+
+    kb.window = 0;
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_A);
+    assert_eq!(kb.screen, "क");
+  }
+
+  #[test]
+  fn alt_x_toggles_typing_without_reaching_the_apps() {
+    let state = linux_state(false);
+    let (mut kb, mut hook) = (FakeKeyboard::default(), HookState::default());
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTALT, 1);
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_X);
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_LEFTALT, 0);
+
+    assert!(state.app_state.typing_enabled.load(Ordering::SeqCst));
+    assert_eq!(
+      kb.forwarded,
+      [(KeyCode::KEY_LEFTALT, 1), (KeyCode::KEY_LEFTALT, 0)]
+    );
+  }
+
+  #[test]
+  fn alt_gr_types_characters_instead_of_running_hotkeys() {
+    let state = linux_state(true);
+    let mut kb = FakeKeyboard {
+      alt_gr: true,
+      ..Default::default()
+    };
+    let mut hook = HookState::default();
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_RIGHTALT, 1);
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_X);
+    tap(&state, &mut kb, &mut hook, KeyCode::KEY_A);
+    handle_key(&state, &mut kb, &mut hook, KeyCode::KEY_RIGHTALT, 0);
+
+    // AltGr+X is not the Alt+X hotkey, and AltGr+A is typed
+    assert!(state.app_state.typing_enabled.load(Ordering::SeqCst));
+    assert_eq!(
+      kb.forwarded,
+      [
+        (KeyCode::KEY_RIGHTALT, 1),
+        (KeyCode::KEY_X, 1),
+        (KeyCode::KEY_X, 0),
+        (KeyCode::KEY_RIGHTALT, 0)
+      ]
+    );
+    assert!(kb.screen.ends_with('ā'));
+  }
+}
@@ -0,0 +1,507 @@
+use crossbeam_channel::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use evdev::uinput::VirtualDevice;
+use evdev::{AttributeSet, Device, EventSummary, KeyCode, KeyEvent};
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{
+  AtomEnum, ConnectionExt as _, KEY_PRESS_EVENT, KEY_RELEASE_EVENT, KeyButMask, Keycode, Keysym,
+  Mapping, Window,
+};
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+use super::LinuxAppState;
+use super::constants::*;
+use super::hooks::{self, HookState, Modifiers, VirtualKeyboard};
+use crate::platform::ClipboardKey;
+use crate::window_context::WindowId;
+
+/// Events of the input devices, read on one thread per device.
+enum DeviceEvent {
+  Key(KeyCode, i32),
+  Click,
+}
+
+pub fn run(
+  app_state: Arc<crate::AppState>,
+  tx_ui: Sender<crate::ThreadMessage>,
+  tx_tray: Sender<crate::ThreadMessage>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let linux_state = LinuxAppState {
+    app_state,
+    tx_ui,
+    tx_tray,
+  };
+
+  let (keyboards, mice) = input_devices();
+  if keyboards.is_empty() {
+    return Err(
+      std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        "No keyboard could be opened in /dev/input. \
+         Please add your user to the `input` group (and log in again), \
+         and make sure /dev/uinput is writable for it",
+      )
+      .into(),
+    );
+  }
+  let mut output = XKeyboard::open(&keyboards)?;
+
+  let (tx, rx) = crossbeam_channel::unbounded();
+  for mut keyboard in keyboards {
+    // Keys held while grabbing would stay pressed for the apps
+    while keyboard
+      .get_key_state()
+      .is_ok_and(|keys| keys.iter().next().is_some())
+    {
+      thread::sleep(Duration::from_millis(20));
+    }
+    keyboard.grab()?;
+    let tx = tx.clone();
+    thread::spawn(move || read_events(keyboard, tx));
+  }
+  // Mice are only read, not grabbed
+  for mouse in mice {
+    let tx = tx.clone();
+    thread::spawn(move || read_events(mouse, tx));
+  }
+  drop(tx);
+
+  let mut hook_state = HookState::default();
+  for event in rx {
+    match event {
+      DeviceEvent::Key(key, value) => {
+        hooks::handle_key(&linux_state, &mut output, &mut hook_state, key, value)
+      }
+      DeviceEvent::Click => hooks::handle_click(&linux_state, output.pointer_window()),
+    }
+  }
+  Ok(())
+}
+
+/// Keyboards and mice in /dev/input that can be opened. Devices with both keys and mouse
+/// buttons (some wireless receivers) are treated as mice, so they are never grabbed.
+fn input_devices() -> (Vec<Device>, Vec<Device>) {
+  let mut keyboards = Vec::new();
+  let mut mice = Vec::new();
+  for (_, device) in evdev::enumerate() {
+    if device.name() == Some(VIRTUAL_KEYBOARD_NAME) {
+      continue;
+    }
+    let Some(keys) = device.supported_keys() else {
+      continue;
+    };
+    if keys.contains(KeyCode::BTN_LEFT) {
+      mice.push(device);
+    } else if keys.contains(KeyCode::KEY_A) && keys.contains(KeyCode::KEY_ENTER) {
+      keyboards.push(device);
+    }
+  }
+  (keyboards, mice)
+}
+
+fn read_events(mut device: Device, tx: crossbeam_channel::Sender<DeviceEvent>) {
+  // Ends when the device is unplugged or the event loop has stopped
+  while let Ok(events) = device.fetch_events() {
+    for event in events {
+      let event = match event.destructure() {
+        EventSummary::Key(_, KeyCode::BTN_LEFT | KeyCode::BTN_RIGHT | KeyCode::BTN_MIDDLE, 1) => {
+          DeviceEvent::Click
+        }
+        EventSummary::Key(_, key, value) if key.code() < KeyCode::BTN_0.code() => {
+          DeviceEvent::Key(key, value)
+        }
+        _ => continue,
+      };
+      if tx.send(event).is_err() {
+        return;
+      }
+    }
+  }
+}
+
+/// Character typed by a keysym, if any.
+fn keysym_to_char(keysym: Keysym) -> Option<char> {
+  match keysym {
+    0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+    _ if keysym & 0xff00_0000 == XK_UNICODE_OFFSET => char::from_u32(keysym - XK_UNICODE_OFFSET),
+    _ => None,
+  }
+}
+
+fn char_to_keysym(ch: char) -> Keysym {
+  match ch as u32 {
+    cp @ (0x20..=0x7e | 0xa0..=0xff) => cp,
+    cp => cp + XK_UNICODE_OFFSET,
+  }
+}
+
+/// Sends Ctrl+C or Ctrl+V through XTest once the modifiers of the hotkey are released. It
+/// has its own X connection, as it runs on the clipboard thread.
+pub fn send_clipboard_key(key: ClipboardKey) -> Result<(), Box<dyn std::error::Error>> {
+  let (conn, screen) = x11rb::connect(None)?;
+  let setup = conn.setup();
+  let root = setup.roots[screen].root;
+  let min_keycode = setup.min_keycode;
+  let count = setup.max_keycode - min_keycode + 1;
+  let mapping = conn.get_keyboard_mapping(min_keycode, count)?.reply()?;
+  let per = mapping.keysyms_per_keycode.max(1) as usize;
+  let keycode_of = |keysym: Keysym| {
+    mapping
+      .keysyms
+      .chunks(per)
+      .position(|syms| syms.contains(&keysym))
+      .map(|idx| min_keycode + idx as u8)
+      .ok_or("key missing in the keyboard mapping")
+  };
+  let control = keycode_of(XK_CONTROL_L)?;
+  let letter = keycode_of(match key {
+    ClipboardKey::Copy => XK_LOWER_C,
+    ClipboardKey::Paste => XK_LOWER_V,
+  })?;
+
+  let held = KeyButMask::SHIFT | KeyButMask::CONTROL | KeyButMask::MOD1 | KeyButMask::MOD4;
+  let deadline = Instant::now() + Duration::from_millis(MODIFIER_RELEASE_TIMEOUT_MS);
+  while conn.query_pointer(root)?.reply()?.mask.intersects(held) && Instant::now() < deadline {
+    thread::sleep(Duration::from_millis(10));
+  }
+
+  for (event, keycode) in [
+    (KEY_PRESS_EVENT, control),
+    (KEY_PRESS_EVENT, letter),
+    (KEY_RELEASE_EVENT, letter),
+    (KEY_RELEASE_EVENT, control),
+  ] {
+    conn.xtest_fake_input(event, keycode, 0, root, 0, 0, 0)?;
+  }
+  conn.sync()?;
+  Ok(())
+}
+
+/// Core keyboard mapping of the X server.
+struct KeyboardMapping {
+  min_keycode: Keycode,
+  keysyms_per_keycode: usize,
+  /// Keysyms of each keycode from `min_keycode` on: the two levels of the first group, of
+  /// the second group, then the third and fourth (AltGr) levels of the first group.
+  keysyms: Vec<Keysym>,
+}
+
+impl KeyboardMapping {
+  fn load(conn: &RustConnection) -> Result<Self, Box<dyn std::error::Error>> {
+    let setup = conn.setup();
+    let min_keycode = setup.min_keycode;
+    let count = setup.max_keycode - min_keycode + 1;
+    let mapping = conn.get_keyboard_mapping(min_keycode, count)?.reply()?;
+    Ok(Self {
+      min_keycode,
+      keysyms_per_keycode: mapping.keysyms_per_keycode.max(1) as usize,
+      keysyms: mapping.keysyms,
+    })
+  }
+
+  fn keycodes(&self) -> impl Iterator<Item = (Keycode, &[Keysym])> {
+    let min_keycode = self.min_keycode;
+    self
+      .keysyms
+      .chunks(self.keysyms_per_keycode)
+      .enumerate()
+      .map(move |(idx, syms)| (min_keycode + idx as u8, syms))
+  }
+
+  fn keysyms_of(&self, keycode: u16) -> Option<&[Keysym]> {
+    let idx = usize::from(keycode.checked_sub(u16::from(self.min_keycode))?);
+    let per = self.keysyms_per_keycode;
+    self.keysyms.get(idx * per..(idx + 1) * per)
+  }
+
+  fn backspace(&self) -> Option<Keycode> {
+    self
+      .keycodes()
+      .find(|(_, syms)| syms.contains(&XK_BACKSPACE))
+      .map(|(keycode, _)| keycode)
+  }
+}
+
+/// Passes the grabbed keys on through a uinput keyboard, so they reach every app (also
+/// outside X), and types text with XTest. Backspaces go through XTest as well, as only the
+/// requests of one X connection are processed in order. The X server reads the uinput
+/// keyboard on its own, so before typing it is waited for to take the last key passed on.
+pub struct XKeyboard {
+  uinput: VirtualDevice,
+  conn: RustConnection,
+  root: Window,
+  /// Reloaded when the layout changes (eg. `setxkbmap`)
+  mapping: KeyboardMapping,
+  backspace: Option<Keycode>,
+  /// Keycodes without keysyms, remapped to the characters typed (like xdotool)
+  spare_keycodes: Vec<Keycode>,
+  next_spare: usize,
+  /// X keycode of the last key passed on, and whether it was pressed
+  last_forwarded: Option<(u16, bool)>,
+}
+
+impl XKeyboard {
+  pub fn open(keyboards: &[Device]) -> Result<Self, Box<dyn std::error::Error>> {
+    let mut keys = AttributeSet::<KeyCode>::new();
+    for key in keyboards
+      .iter()
+      .filter_map(Device::supported_keys)
+      .flat_map(|k| k.iter())
+    {
+      keys.insert(key);
+    }
+    let uinput = VirtualDevice::builder()?
+      .name(VIRTUAL_KEYBOARD_NAME)
+      .with_keys(&keys)?
+      .build()?;
+
+    let (conn, screen) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen].root;
+    let mapping = KeyboardMapping::load(&conn)?;
+    let spare_keycodes = mapping
+      .keycodes()
+      .filter(|(_, syms)| syms.iter().all(|&s| s == 0))
+      .map(|(keycode, _)| keycode)
+      .take(MAX_SPARE_KEYCODES)
+      .collect();
+
+    Ok(Self {
+      uinput,
+      conn,
+      root,
+      backspace: mapping.backspace(),
+      mapping,
+      spare_keycodes,
+      next_spare: 0,
+      last_forwarded: None,
+    })
+  }
+
+  /// Reloads the keyboard mapping if it was changed by another client. The changes made
+  /// to the spare keycodes to type text are skipped.
+  fn refresh_mapping(&mut self) {
+    let mut changed = false;
+    while let Ok(Some(event)) = self.conn.poll_for_event() {
+      if let Event::MappingNotify(notify) = event
+        && notify.request == Mapping::KEYBOARD
+        && !(notify.count == 1 && self.spare_keycodes.contains(&notify.first_keycode))
+      {
+        changed = true;
+      }
+    }
+    if changed && let Ok(mapping) = KeyboardMapping::load(&self.conn) {
+      self.backspace = mapping.backspace();
+      self.mapping = mapping;
+    }
+  }
+
+  /// Waits (briefly) for the X server to take the last key passed on through uinput, so
+  /// that it comes before the keys typed with XTest next.
+  fn sync_forwarded(&mut self) {
+    let Some((keycode, pressed)) = self.last_forwarded.take() else {
+      return;
+    };
+    let keycode = usize::from(keycode);
+    let deadline = Instant::now() + Duration::from_millis(FORWARDED_KEY_SYNC_TIMEOUT_MS);
+    while let Ok(Ok(reply)) = self.conn.query_keymap().map(|cookie| cookie.reply()) {
+      let is_down = reply
+        .keys
+        .get(keycode / 8)
+        .is_some_and(|byte| byte & (1 << (keycode % 8)) != 0);
+      if is_down == pressed || Instant::now() >= deadline {
+        return;
+      }
+      thread::sleep(Duration::from_millis(1));
+    }
+  }
+
+  fn tap_keycode(&self, keycode: Keycode) {
+    let _ = self
+      .conn
+      .xtest_fake_input(KEY_PRESS_EVENT, keycode, 0, self.root, 0, 0, 0);
+    let _ = self
+      .conn
+      .xtest_fake_input(KEY_RELEASE_EVENT, keycode, 0, self.root, 0, 0, 0);
+  }
+
+  /// Class of `window` or of the first of its descendants with one: window managers put
+  /// the app's window inside a frame window.
+  fn window_class(&self, window: Window, depth: u8) -> Option<String> {
+    let property = self
+      .conn
+      .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+      .ok()?
+      .reply()
+      .ok()?;
+    // Instance and class name, each ending with a NUL
+    if let Some(class) = property.value.split(|&b| b == 0).nth(1)
+      && !class.is_empty()
+    {
+      return Some(String::from_utf8_lossy(class).into_owned());
+    }
+    if depth == 0 {
+      return None;
+    }
+    let tree = self.conn.query_tree(window).ok()?.reply().ok()?;
+    tree
+      .children
+      .iter()
+      .find_map(|&child| self.window_class(child, depth - 1))
+  }
+
+  /// Top-level window under the pointer.
+  pub fn pointer_window(&self) -> Option<WindowId> {
+    let reply = self.conn.query_pointer(self.root).ok()?.reply().ok()?;
+    (reply.child != x11rb::NONE).then_some(reply.child as WindowId)
+  }
+}
+
+impl VirtualKeyboard for XKeyboard {
+  fn forward(&mut self, key: KeyCode, value: i32) {
+    let _ = self.uinput.emit(&[*KeyEvent::new(key, value)]);
+    if let Some(keycode) = key.code().checked_add(X_KEYCODE_OFFSET)
+      && keycode <= u16::from(u8::MAX)
+    {
+      self.last_forwarded = Some((keycode, value != 0));
+    }
+  }
+
+  fn send_backspaces(&mut self, n: usize) {
+    let Some(backspace) = self.backspace else {
+      return;
+    };
+    self.sync_forwarded();
+    for _ in 0..n {
+      self.tap_keycode(backspace);
+    }
+    let _ = self.conn.flush();
+  }
+
+  fn send_unicode_text(&mut self, s: &str) {
+    if self.spare_keycodes.is_empty() {
+      return;
+    }
+    self.sync_forwarded();
+    for ch in s.chars() {
+      let keycode = self.spare_keycodes[self.next_spare];
+      self.next_spare = (self.next_spare + 1) % self.spare_keycodes.len();
+      // The same keysym on both levels, so that a held Shift does not matter
+      let keysym = char_to_keysym(ch);
+      let _ = self
+        .conn
+        .change_keyboard_mapping(1, keycode, 2, &[keysym, keysym]);
+      // The mapping must be applied before the key is typed
+      let _ = self.conn.sync();
+      self.tap_keycode(keycode);
+    }
+    let _ = self.conn.sync();
+  }
+
+  fn key_text(&mut self, key: KeyCode, modifiers: &Modifiers) -> Option<String> {
+    self.refresh_mapping();
+    let keycode = key.code().checked_add(X_KEYCODE_OFFSET)?;
+    let syms = self.mapping.keysyms_of(keycode)?;
+    // AltGr selects the third and fourth levels
+    let syms = if modifiers.alt_gr() {
+      syms.get(4..)?
+    } else {
+      syms
+    };
+
+    let lower = syms.first().copied().unwrap_or(0);
+    let upper = syms.get(1).copied().filter(|&s| s != 0).unwrap_or(lower);
+    // Caps Lock only affects letters
+    let caps = modifiers.caps_lock && keysym_to_char(lower).is_some_and(char::is_alphabetic);
+    let keysym = if modifiers.shift() != caps {
+      upper
+    } else {
+      lower
+    };
+    keysym_to_char(keysym).map(String::from)
+  }
+
+  fn is_alt_gr(&mut self, key: KeyCode) -> bool {
+    self.refresh_mapping();
+    key
+      .code()
+      .checked_add(X_KEYCODE_OFFSET)
+      .and_then(|keycode| self.mapping.keysyms_of(keycode))
+      .is_some_and(|syms| syms.contains(&XK_ISO_LEVEL3_SHIFT))
+  }
+
+  fn focused_window(&mut self) -> Option<WindowId> {
+    let mut window = self.conn.get_input_focus().ok()?.reply().ok()?.focus;
+    // PointerRoot and None
+    if window <= 1 || window == self.root {
+      return None;
+    }
+    loop {
+      let tree = self.conn.query_tree(window).ok()?.reply().ok()?;
+      if tree.parent == self.root || tree.parent == x11rb::NONE {
+        return Some(window as WindowId);
+      }
+      window = tree.parent;
+    }
+  }
+
+  fn app_name(&mut self, window: WindowId) -> Option<String> {
+    self.window_class(window as Window, WINDOW_CLASS_SEARCH_DEPTH)
+  }
+}
+
+impl Drop for XKeyboard {
+  fn drop(&mut self) {
+    for &keycode in &self.spare_keycodes {
+      let _ = self.conn.change_keyboard_mapping(1, keycode, 1, &[0]);
+    }
+    let _ = self.conn.sync();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keysyms_round_trip_characters() {
+    for ch in ['a', 'Z', ' ', 'é', 'क', 'ா'] {
+      assert_eq!(keysym_to_char(char_to_keysym(ch)), Some(ch));
+    }
+    assert_eq!(char_to_keysym('a'), 0x61);
+    assert_eq!(char_to_keysym('क'), 0x0100_0915);
+    assert_eq!(keysym_to_char(XK_BACKSPACE), None);
+  }
+
+  /// Types through the real devices: needs an X server (eg. `xvfb-run`) and a writable
+  /// /dev/uinput, so it only runs with `cargo test -- --ignored` (under Xvfb in the CI).
+  #[test]
+  #[ignore]
+  fn types_unicode_text_under_x() {
+    let mut output = XKeyboard::open(&[]).expect("X display and /dev/uinput");
+    let modifiers = Modifiers::default();
+    assert_eq!(
+      output.key_text(KeyCode::KEY_A, &modifiers),
+      Some("a".into())
+    );
+    assert!(!output.spare_keycodes.is_empty());
+
+    // The spare keycodes type the text, and are free again afterwards
+    output.send_unicode_text("कि");
+    output.send_backspaces(2);
+    let spare = output.spare_keycodes[0];
+    drop(output);
+    let (conn, _) = x11rb::connect(None).unwrap();
+    let mapping = conn
+      .get_keyboard_mapping(spare, 1)
+      .unwrap()
+      .reply()
+      .unwrap();
+    assert!(mapping.keysyms.iter().all(|&s| s == 0));
+  }
+}
@@ -3,9 +3,68 @@
 
 use core_graphics::event::KeyCode;
 
-// Letters (toggle shortcut)
-pub const VK_X: u16 = KeyCode::ANSI_X;
-pub const VK_C: u16 = KeyCode::ANSI_C;
+// Keys that can be part of a hotkey, with the character or function key number they stand for
+pub const HOTKEY_CHAR_KEYS: [(u16, char); 36] = [
+  (KeyCode::ANSI_A, 'A'),
+  (KeyCode::ANSI_B, 'B'),
+  (KeyCode::ANSI_C, 'C'),
+  (KeyCode::ANSI_D, 'D'),
+  (KeyCode::ANSI_E, 'E'),
+  (KeyCode::ANSI_F, 'F'),
+  (KeyCode::ANSI_G, 'G'),
+  (KeyCode::ANSI_H, 'H'),
+  (KeyCode::ANSI_I, 'I'),
+  (KeyCode::ANSI_J, 'J'),
+  (KeyCode::ANSI_K, 'K'),
+  (KeyCode::ANSI_L, 'L'),
+  (KeyCode::ANSI_M, 'M'),
+  (KeyCode::ANSI_N, 'N'),
+  (KeyCode::ANSI_O, 'O'),
+  (KeyCode::ANSI_P, 'P'),
+  (KeyCode::ANSI_Q, 'Q'),
+  (KeyCode::ANSI_R, 'R'),
+  (KeyCode::ANSI_S, 'S'),
+  (KeyCode::ANSI_T, 'T'),
+  (KeyCode::ANSI_U, 'U'),
+  (KeyCode::ANSI_V, 'V'),
+  (KeyCode::ANSI_W, 'W'),
+  (KeyCode::ANSI_X, 'X'),
+  (KeyCode::ANSI_Y, 'Y'),
+  (KeyCode::ANSI_Z, 'Z'),
+  (KeyCode::ANSI_0, '0'),
+  (KeyCode::ANSI_1, '1'),
+  (KeyCode::ANSI_2, '2'),
+  (KeyCode::ANSI_3, '3'),
+  (KeyCode::ANSI_4, '4'),
+  (KeyCode::ANSI_5, '5'),
+  (KeyCode::ANSI_6, '6'),
+  (KeyCode::ANSI_7, '7'),
+  (KeyCode::ANSI_8, '8'),
+  (KeyCode::ANSI_9, '9'),
+];
+pub const HOTKEY_FUNCTION_KEYS: [(u16, u8); 20] = [
+  (KeyCode::F1, 1),
+  (KeyCode::F2, 2),
+  (KeyCode::F3, 3),
+  (KeyCode::F4, 4),
+  (KeyCode::F5, 5),
+  (KeyCode::F6, 6),
+  (KeyCode::F7, 7),
+  (KeyCode::F8, 8),
+  (KeyCode::F9, 9),
+  (KeyCode::F10, 10),
+  (KeyCode::F11, 11),
+  (KeyCode::F12, 12),
+  (KeyCode::F13, 13),
+  (KeyCode::F14, 14),
+  (KeyCode::F15, 15),
+  (KeyCode::F16, 16),
+  (KeyCode::F17, 17),
+  (KeyCode::F18, 18),
+  (KeyCode::F19, 19),
+  (KeyCode::F20, 20),
+];
+pub const VK_SPACE: u16 = KeyCode::SPACE;
 
 // Navigation & editing keys that clear context
 pub const VK_LEFT: u16 = KeyCode::LEFT_ARROW;
@@ -33,3 +92,6 @@ pub const VK_COMMAND: u16 = KeyCode::COMMAND;
 pub const VK_RIGHT_COMMAND: u16 = KeyCode::RIGHT_COMMAND;
 pub const VK_CAPS_LOCK: u16 = KeyCode::CAPS_LOCK;
 pub const VK_FUNCTION: u16 = KeyCode::FUNCTION;
+
+// Size of the buffer for `proc_name` (2 * MAXCOMLEN + 1, as in libproc.h)
+pub const PROC_NAME_BUFFER_SIZE: usize = 2 * 16 + 1;
@@ -1,9 +1,14 @@
-use crate::{ThreadMessage, ThreadMessageOrigin, ThreadMessageType};
+use crate::completion;
+use crate::history;
+use crate::hotkeys::{self, Hotkey, HotkeyAction, HotkeyKey, HotkeyModifiers};
+use crate::platform::ClipboardKey;
+use crate::window_context::{self, WindowId};
 
 use super::MacAppState;
 use super::constants::*;
 use core_foundation::base::TCFType;
 use core_foundation::mach_port::{CFMachPort, CFMachPortRef};
+use std::ffi::{c_char, c_int};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::Ordering;
@@ -19,10 +24,61 @@ use foreign_types::ForeignType;
 /// so the tap callback can skip them (analogous to LLKHF_INJECTED_FLAG on Windows).
 const INJECTED_EVENT_SENTINEL: i64 = 0x4C49_5049; // "LIPI"
 
+/// Ends the composition; what was typed stays in the app and is learned for completions.
 fn clear_context(state: &MacAppState) {
-  if let Ok(mut guard) = state.app_state.typing_context.lock() {
-    guard.clear_context();
-  }
+  completion::commit_context(&state.app_state);
+  history::forget(&state.app_state);
+}
+
+/// Process id of the app receiving an event.
+fn target_app(event: &CGEvent) -> Option<WindowId> {
+  let pid = event.get_integer_value_field(EventField::EVENT_TARGET_UNIX_PROCESS_ID);
+  (pid > 0).then_some(pid as WindowId)
+}
+
+/// The hotkey of a key press, when Control, Option or Command is held.
+fn pressed_hotkey(keycode: u16, flags: CGEventFlags) -> Option<Hotkey> {
+  let key = if keycode == VK_ESCAPE {
+    HotkeyKey::Escape
+  } else if keycode == VK_SPACE {
+    HotkeyKey::Space
+  } else if let Some((_, ch)) = HOTKEY_CHAR_KEYS.iter().find(|(code, _)| *code == keycode) {
+    HotkeyKey::from_char(*ch)?
+  } else {
+    let (_, n) = HOTKEY_FUNCTION_KEYS
+      .iter()
+      .find(|(code, _)| *code == keycode)?;
+    HotkeyKey::F(*n)
+  };
+  let modifiers = HotkeyModifiers {
+    ctrl: flags.contains(CGEventFlags::CGEventFlagControl),
+    alt: flags.contains(CGEventFlags::CGEventFlagAlternate),
+    shift: flags.contains(CGEventFlags::CGEventFlagShift),
+    super_key: flags.contains(CGEventFlags::CGEventFlagCommand),
+  };
+  modifiers.has_command().then_some(Hotkey { modifiers, key })
+}
+
+unsafe extern "C" {
+  // libproc, part of libSystem
+  fn proc_name(pid: c_int, buffer: *mut c_char, buffersize: u32) -> c_int;
+}
+
+#[link(name = "Carbon", kind = "framework")]
+unsafe extern "C" {
+  fn IsSecureEventInputEnabled() -> u8;
+}
+
+/// Whether secure input is on, as password fields turn it on.
+pub fn secure_input_focused() -> bool {
+  unsafe { IsSecureEventInputEnabled() != 0 }
+}
+
+/// Process name of an app (its process id), for the app profiles.
+fn app_name(app: WindowId) -> Option<String> {
+  let mut buf = [0u8; PROC_NAME_BUFFER_SIZE];
+  let len = unsafe { proc_name(app as c_int, buf.as_mut_ptr().cast(), buf.len() as u32) };
+  (len > 0).then(|| String::from_utf8_lossy(&buf[..len as usize]).into_owned())
 }
 
 fn is_modifier_key(keycode: u16) -> bool {
@@ -111,6 +167,25 @@ fn send_backspaces(n: usize) -> bool {
   true
 }
 
+/// Send Cmd+C or Cmd+V. The flags of the events replace the modifiers held for the hotkey.
+pub fn send_clipboard_key(key: ClipboardKey) {
+  let Ok(source) = CGEventSource::new(CGEventSourceStateID::Private) else {
+    return;
+  };
+  let keycode = match key {
+    ClipboardKey::Copy => KeyCode::ANSI_C,
+    ClipboardKey::Paste => KeyCode::ANSI_V,
+  };
+  for keydown in [true, false] {
+    let Ok(event) = CGEvent::new_keyboard_event(source.clone(), keycode, keydown) else {
+      return;
+    };
+    event.set_flags(CGEventFlags::CGEventFlagCommand);
+    tag_injected(&event);
+    event.post(CGEventTapLocation::HID);
+  }
+}
+
 /// Read the Unicode string from a keyboard event via CGEventKeyboardGetUnicodeString.
 fn get_event_string(event: &CGEvent) -> Option<String> {
   unsafe {
@@ -172,13 +247,13 @@ pub fn build_event_tap_callback(
       return CallbackResult::Keep;
     }
 
-    // Mouse clicks → clear context
+    // Mouse clicks → end the composition of the clicked app
     if matches!(
       event_type,
       CGEventType::LeftMouseDown | CGEventType::RightMouseDown | CGEventType::OtherMouseDown
     ) {
       if state.app_state.typing_enabled.load(Ordering::SeqCst) {
-        clear_context(&state);
+        window_context::click(&state.app_state, target_app(event));
       }
       return CallbackResult::Keep;
     }
@@ -195,48 +270,19 @@ pub fn build_event_tap_callback(
     let flags = event.get_flags();
     let is_keydown = matches!(event_type, CGEventType::KeyDown);
 
-    // ---- Option+X / Option+C toggle (works regardless of typing mode) ----
+    // ---- Hotkeys: typing toggle, script switching, close app (regardless of typing mode) ----
     if is_keydown
-      && (keycode == VK_X || keycode == VK_C)
-      && flags.contains(CGEventFlags::CGEventFlagAlternate)
-      && !flags.contains(CGEventFlags::CGEventFlagCommand)
-      && !flags.contains(CGEventFlags::CGEventFlagControl)
+      && let Some(hotkey) = pressed_hotkey(keycode, flags)
+      && let Some(action) = hotkeys::run(&state.app_state, &hotkey)
     {
-      // a xor 1 = !a
-      let now_enabled = !state
-        .app_state
-        .typing_enabled
-        .fetch_xor(true, Ordering::SeqCst);
-
-      if !now_enabled {
-        clear_context(&state);
+      // The text is typed with its own event, so the held modifiers do not change it
+      if action == HotkeyAction::UndoConversion
+        && let Some((to_delete, text)) = history::take_undo(&state.app_state)
+      {
+        send_backspaces(to_delete);
+        send_unicode_text(&text);
       }
-
-      let _ = state.tx_ui.send(ThreadMessage {
-        origin: ThreadMessageOrigin::KeyboardHook,
-        msg: ThreadMessageType::TriggerTypingNotification,
-      });
-      let _ = state.tx_ui.send(ThreadMessage {
-        origin: ThreadMessageOrigin::KeyboardHook,
-        msg: ThreadMessageType::RerenderUI,
-      });
-      let _ = state.tx_tray.send(ThreadMessage {
-        origin: ThreadMessageOrigin::KeyboardHook,
-        msg: ThreadMessageType::RerenderTray,
-      });
-
-      return CallbackResult::Drop;
-    }
-
-    // ---- Cmd+Esc / Ctrl+Esc → close app ----
-    if is_keydown
-      && keycode == VK_ESCAPE
-      && flags.intersects(CGEventFlags::CGEventFlagCommand | CGEventFlags::CGEventFlagControl)
-    {
-      let _ = state.tx_ui.send(ThreadMessage {
-        origin: ThreadMessageOrigin::KeyboardHook,
-        msg: ThreadMessageType::CloseApp,
-      });
+      hotkeys::notify(&state.tx_ui, &state.tx_tray, action);
       return CallbackResult::Drop;
     }
 
@@ -257,6 +303,63 @@ pub fn build_event_tap_callback(
       return CallbackResult::Keep;
     }
 
+    // Switch to the composition and settings of the app being typed in
+    if let Some(app) = target_app(event)
+      && !window_context::focus(&state.app_state, app, || app_name(app))
+    {
+      return CallbackResult::Keep;
+    }
+
+    // Cmd+Tab switches apps; the composition is kept for when typing resumes here
+    if keycode == VK_TAB && flags.contains(CGEventFlags::CGEventFlagCommand) {
+      return CallbackResult::Keep;
+    }
+
+    // Plain Backspace → step the typing context back one key (empty context: let it through)
+    if keycode == VK_DELETE
+      && !flags.intersects(
+        CGEventFlags::CGEventFlagCommand
+          | CGEventFlags::CGEventFlagControl
+          | CGEventFlags::CGEventFlagAlternate,
+      )
+    {
+      let diff = match state.app_state.typing_context.lock() {
+        Ok(mut guard) => guard.take_backspace(),
+        Err(_) => return CallbackResult::Keep,
+      };
+      if diff.to_delete_chars_count == 0 && diff.diff_add_text.is_empty() {
+        history::forget(&state.app_state);
+        return CallbackResult::Keep;
+      }
+      let deleted = diff.to_delete_chars_count == 0 || send_backspaces(diff.to_delete_chars_count);
+      let added = diff.diff_add_text.is_empty() || send_unicode_text(&diff.diff_add_text);
+      history::record(&state.app_state, None, &diff);
+      return if deleted && added {
+        CallbackResult::Drop
+      } else {
+        CallbackResult::Keep
+      };
+    }
+
+    // Plain Tab → complete the word being typed (no completion: pass through)
+    if keycode == VK_TAB
+      && !flags.intersects(
+        CGEventFlags::CGEventFlagCommand
+          | CGEventFlags::CGEventFlagControl
+          | CGEventFlags::CGEventFlagAlternate,
+      )
+      && let Some((to_delete, text)) = completion::take_completion(&state.app_state)
+    {
+      let deleted = to_delete == 0 || send_backspaces(to_delete);
+      let added = send_unicode_text(&text);
+      history::forget(&state.app_state);
+      return if deleted && added {
+        CallbackResult::Drop
+      } else {
+        CallbackResult::Keep
+      };
+    }
+
     // Context-clearing keys
     if is_context_clear_key(keycode) {
       clear_context(&state);
@@ -282,28 +385,34 @@ pub fn build_event_tap_callback(
       return CallbackResult::Keep;
     }
 
+    // A `.` after a word may turn it back into its keys (domains, emails)
+    if let Some((to_delete, text)) = history::take_auto_revert(&state.app_state, &text) {
+      let deleted = send_backspaces(to_delete);
+      let added = send_unicode_text(&text);
+      return if deleted && added {
+        CallbackResult::Drop
+      } else {
+        CallbackResult::Keep
+      };
+    }
+
     // Lock discipline: release mutex BEFORE injecting (SendInput/post re-enters the hook)
-    let (total_delete, combined_add) = {
+    let (diff, finished) = {
       let mut guard = match state.app_state.typing_context.lock() {
         Ok(g) => g,
         Err(_) => return CallbackResult::Keep,
       };
 
-      let mut total_delete: usize = 0;
-      let mut combined_add = String::new();
-
-      for ch in text.chars() {
-        let key = ch.to_string();
-        let diff = guard.take_key_input(&key);
-        total_delete += diff.to_delete_chars_count;
-        combined_add.push_str(&diff.diff_add_text);
-      }
-
-      (total_delete, combined_add)
+      let prev = guard.preedit().to_string();
+      let diff = guard.take_text_input(&text);
+      let finished = completion::finished_text(&prev, &diff, guard.preedit());
+      (diff, finished)
     };
 
-    let deleted = total_delete == 0 || send_backspaces(total_delete);
-    let added = combined_add.is_empty() || send_unicode_text(&combined_add);
+    let deleted = diff.to_delete_chars_count == 0 || send_backspaces(diff.to_delete_chars_count);
+    let added = diff.diff_add_text.is_empty() || send_unicode_text(&diff.diff_add_text);
+    completion::learn(&state.app_state, &finished);
+    history::record(&state.app_state, Some(&text), &diff);
 
     if deleted && added {
       CallbackResult::Drop
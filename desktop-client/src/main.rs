@@ -1,23 +1,29 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 // ^ hides console in windows release builds
 
-use lipilekhika::Script;
-use lipilekhika::typing::{
-  DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS, TypingContext, TypingContextOptions,
-};
+use completion::WordCompletion;
+use history::TypingHistory;
+use lipilekhika::typing::TypingContext;
 use persistent_state::PersitentState;
 use std::{
-  str::FromStr,
   sync::{Arc, Mutex, atomic::AtomicBool},
   thread,
 };
+use window_context::WindowContexts;
 
+mod clipboard;
+mod completion;
 mod data;
+mod history;
+mod hotkeys;
+mod keymap;
 mod persistent_state;
 mod platform;
 mod posthog;
+mod privacy;
 mod tray;
 mod ui;
+mod window_context;
 
 /// shared app state for both the platform specific hook code, UI, etc
 pub struct AppState {
@@ -25,6 +31,14 @@ pub struct AppState {
   /// both typing script and typing options are stored in the typing context
   pub typing_context: Mutex<TypingContext>,
   pub persitent_state: Mutex<PersitentState>,
+  /// lexicon for Tab completion, learning from what is typed (when turned on)
+  pub completion: Mutex<WordCompletion>,
+  /// compositions left in other windows, restored when typing there again
+  pub window_contexts: Mutex<WindowContexts>,
+  /// diffs typed since the caret last moved, for undoing the conversion of the last word
+  pub history: Mutex<TypingHistory>,
+  /// set while the UI records a hotkey, so that the hooks pass every key on to it
+  pub recording_hotkey: AtomicBool,
 }
 
 /// use to pass messages between threads
@@ -49,6 +63,10 @@ pub enum ThreadMessageType {
   RerenderUI,
   /// used to display typing notification when from hook or tray in ui
   TriggerTypingNotification,
+  /// script switched with a hotkey, to be saved and shown in the ui
+  ScriptChanged,
+  /// message to show in the notification window (eg. result of the clipboard hotkey)
+  ShowNotification(String),
   /// send from tray
   MaximizeUI,
   // Close app request from hook (shortcut)
@@ -62,21 +80,36 @@ fn main() {
   let (tx_ui, rx_ui) = crossbeam_channel::bounded::<ThreadMessage>(100);
   let (tx_tray, rx_tray) = crossbeam_channel::bounded::<ThreadMessage>(100);
 
-  let persitent_state = PersitentState::read_app_config();
+  // Settings that cannot be read are kept aside for the user to fix, and the defaults used
+  let (persitent_state, startup_notice) = match PersitentState::read_app_config() {
+    Ok(state) => (state, None),
+    Err(err) => {
+      eprintln!("Could not read the settings: {err}");
+      let kept = match PersitentState::back_up_app_config() {
+        Some(path) => format!(" The file was kept as {}.", path.display()),
+        None => String::new(),
+      };
+      (
+        PersitentState::default(),
+        Some(format!("Settings not loaded: {err}.{kept}")),
+      )
+    }
+  };
 
-  let script = Script::from_str(&persitent_state.script).unwrap_or(Script::Devanagari);
-  let typing_context = TypingContext::new(
-    script,
-    Some(TypingContextOptions {
-      auto_context_clear_time_ms: DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS,
-      use_native_numerals: persitent_state.native_numerals,
-      include_inherent_vowel: persitent_state.inherent_vowel,
-    }),
+  let analytics = privacy::analytics_enabled(&persitent_state);
+  keymap::set_config_keymaps(&persitent_state.keymaps);
+  let typing_context = keymap::new_typing_context(
+    persitent_state.typing_script(),
+    Some(persitent_state.typing_options()),
   );
   let app_state = Arc::new(AppState {
     typing_context: Mutex::new(typing_context),
     typing_enabled: AtomicBool::new(false),
     persitent_state: Mutex::new(persitent_state),
+    completion: Mutex::new(WordCompletion::load()),
+    window_contexts: Mutex::new(WindowContexts::default()),
+    history: Mutex::new(TypingHistory::default()),
+    recording_hotkey: AtomicBool::new(false),
   });
 
   // Start keyboard hook thread
@@ -96,12 +129,24 @@ fn main() {
   let tx_ui_clone = tx_ui.clone();
   let _handle_tray = tray::run_tray_thread(state_clone, tx_ui_clone, rx_tray);
 
-  thread::spawn(|| {
-    posthog::init_posthog();
-  });
+  // Start clipboard thread
+  let state_clone = Arc::clone(&app_state);
+  let tx_ui_clone = tx_ui.clone();
+  let _handle_clipboard = clipboard::run_clipboard_thread(state_clone, tx_ui_clone);
+
+  // off until the first-run question is answered (the ui sends it then)
+  if analytics {
+    thread::spawn(|| {
+      posthog::init_posthog();
+    });
+  }
 
   // starts the UI event loop
   let state_clone = Arc::clone(&app_state);
   let tx_tray_clone = tx_tray.clone();
-  ui::run(state_clone, rx_ui, tx_tray_clone).unwrap();
+  ui::run(state_clone, rx_ui, tx_tray_clone, startup_notice).unwrap();
+
+  if let Ok(mut completion) = app_state.completion.lock() {
+    completion.flush();
+  }
 }
@@ -1,9 +1,24 @@
-use lipilekhika::Script;
+use crate::clipboard::ClipboardSettings;
+use crate::hotkeys::HotkeySettings;
+use lipilekhika::keymap::Keymap;
+use lipilekhika::layout::TypingLayout;
+use lipilekhika::typing::{
+  DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS, LatinEscape, LatinEscapeScope, TypingContextOptions,
+};
+use lipilekhika::{CustomOptions, Script};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use toml::from_str;
+use toml::{Table, Value};
+
+/// Version of the `app_conf.toml` schema written by this build. Files without a `version`
+/// are version 1.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Migrations of the config table; the one at index `i` turns version `i + 1` into `i + 2`.
+const MIGRATIONS: [fn(&mut Table); (CONFIG_VERSION - 1) as usize] = [];
 
 fn app_config_path() -> PathBuf {
   let mut path = match dirs::data_dir() {
@@ -28,8 +43,28 @@ fn default_script() -> String {
   "Devanagari".to_string()
 }
 
+fn default_layout() -> String {
+  TypingLayout::Phonetic.to_string()
+}
+
+fn default_latin_escape() -> String {
+  String::new()
+}
+
+fn default_auto_clear_time_ms() -> u64 {
+  DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS
+}
+
+fn default_version() -> u32 {
+  CONFIG_VERSION
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PersitentState {
+  /// Schema version, see [`CONFIG_VERSION`].
+  #[serde(default = "default_version")]
+  pub version: u32,
+
   #[serde(default = "default_script")]
   pub script: String,
 
@@ -41,47 +76,536 @@ pub struct PersitentState {
 
   #[serde(default = "default_false")]
   pub inherent_vowel: bool,
+
+  /// Requested keyboard layout, kept even while the current script cannot use it.
+  #[serde(default = "default_layout")]
+  pub layout: String,
+
+  /// Key typed before an English word to type it as is: one key (eg. `\`), a key typed
+  /// twice (eg. `;;`), or empty for none (the default).
+  #[serde(default = "default_latin_escape")]
+  pub latin_escape: String,
+
+  /// Idle time after which the next key starts a new word.
+  #[serde(default = "default_auto_clear_time_ms")]
+  pub auto_clear_time_ms: u64,
+
+  /// A `.` typed right after a word turns it back into the keys typed, for domains and
+  /// emails; the rest of the word is typed as is.
+  #[serde(default = "default_false")]
+  pub auto_revert_before_dot: bool,
+
+  /// Learn the words typed (in the app data directory) and complete the word being typed
+  /// with Tab, see [`crate::completion`]. Off by default.
+  #[serde(default = "default_false")]
+  pub word_completion: bool,
+
+  /// Send an anonymous event when the app starts, see [`crate::privacy`].
+  #[serde(default = "default_true")]
+  pub analytics: bool,
+
+  /// Look for a new version when the app starts.
+  #[serde(default = "default_true")]
+  pub update_check: bool,
+
+  /// The first-run question about the two above was answered; nothing is sent before.
+  #[serde(default = "default_false")]
+  pub privacy_prompt_answered: bool,
+
+  /// Named profile applied or saved last, shown in the Settings window.
+  #[serde(default)]
+  pub active_profile: String,
+
+  /// Custom transliteration options (`category:option` keys) of the conversions, eg. of
+  /// the clipboard.
+  #[serde(default)]
+  pub options: BTreeMap<String, bool>,
+
+  #[serde(default)]
+  pub hotkeys: HotkeySettings,
+
+  #[serde(default)]
+  pub clipboard: ClipboardSettings,
+
+  /// Key overrides per script (`[keymaps.Devanagari]`), applied over the ones of the
+  /// keymap files.
+  #[serde(default)]
+  pub keymaps: BTreeMap<String, Keymap>,
+
+  /// Settings used instead of the ones above while typing in some applications.
+  #[serde(default)]
+  pub profiles: Vec<AppProfile>,
+
+  /// Typing settings saved under a name, switched to from the Settings window.
+  #[serde(default)]
+  pub named_profiles: Vec<NamedProfile>,
+
+  /// Characters pinned in the typing helper, by script name.
+  #[serde(default)]
+  pub pinned_chars: BTreeMap<String, Vec<String>>,
+}
+
+/// A named copy of the typing settings of [`PersitentState`], with its hotkeys and keymaps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedProfile {
+  pub name: String,
+
+  #[serde(default = "default_script")]
+  pub script: String,
+
+  #[serde(default = "default_true")]
+  pub native_numerals: bool,
+
+  #[serde(default = "default_false")]
+  pub inherent_vowel: bool,
+
+  #[serde(default = "default_layout")]
+  pub layout: String,
+
+  #[serde(default = "default_latin_escape")]
+  pub latin_escape: String,
+
+  #[serde(default = "default_auto_clear_time_ms")]
+  pub auto_clear_time_ms: u64,
+
+  #[serde(default)]
+  pub options: BTreeMap<String, bool>,
+
+  #[serde(default)]
+  pub hotkeys: HotkeySettings,
+
+  #[serde(default)]
+  pub keymaps: BTreeMap<String, Keymap>,
+}
+
+/// Why `app_conf.toml` (or an imported file) could not be read.
+#[derive(Debug)]
+pub enum ConfigError {
+  Io(std::io::Error),
+  /// Not valid TOML, or not a valid config of its version
+  Parse(String),
+  /// Written by a newer version of the app
+  NewerVersion(u32),
+}
+
+impl std::fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ConfigError::Io(e) => write!(f, "{e}"),
+      ConfigError::Parse(e) => write!(f, "{e}"),
+      ConfigError::NewerVersion(version) => write!(
+        f,
+        "settings of version {version} need a newer app (this one reads up to {CONFIG_VERSION})"
+      ),
+    }
+  }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Typing settings of one application.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppProfile {
+  /// Process name on Windows and macOS (`chrome`, `Code`), window class on Linux (`firefox`).
+  /// Matched ignoring case.
+  pub app: String,
+
+  /// Typing in the app while typing is on; `false` keeps the app in English.
+  #[serde(default = "default_true")]
+  pub typing_enabled: bool,
+
+  #[serde(default = "default_script")]
+  pub script: String,
+
+  #[serde(default = "default_true")]
+  pub native_numerals: bool,
+
+  #[serde(default = "default_false")]
+  pub inherent_vowel: bool,
 }
 
 impl Default for PersitentState {
   fn default() -> Self {
     PersitentState {
+      version: CONFIG_VERSION,
       script: default_script(),
       typing_status: default_true(),
       native_numerals: default_true(),
       inherent_vowel: default_false(),
+      layout: default_layout(),
+      latin_escape: default_latin_escape(),
+      auto_clear_time_ms: default_auto_clear_time_ms(),
+      auto_revert_before_dot: default_false(),
+      word_completion: default_false(),
+      analytics: default_true(),
+      update_check: default_true(),
+      privacy_prompt_answered: default_false(),
+      active_profile: String::new(),
+      options: BTreeMap::new(),
+      hotkeys: HotkeySettings::default(),
+      clipboard: ClipboardSettings::default(),
+      keymaps: BTreeMap::new(),
+      profiles: Vec::new(),
+      named_profiles: Vec::new(),
+      pinned_chars: BTreeMap::new(),
     }
   }
 }
 
 impl PersitentState {
-  pub fn read_app_config() -> PersitentState {
-    let config_path = app_config_path();
+  pub fn typing_layout(&self) -> TypingLayout {
+    TypingLayout::from_str(&self.layout).unwrap_or_default()
+  }
+
+  pub fn typing_script(&self) -> Script {
+    Script::from_str(&self.script).unwrap_or(Script::Devanagari)
+  }
 
-    match fs::read_to_string(&config_path) {
-      Ok(content) => match from_str::<PersitentState>(&content) {
-        Ok(mut config) => match Script::from_str(&config.script) {
-          Ok(_) => config,
-          Err(_) => {
-            config.script = default_script();
-            config
-          }
-        },
-        Err(_) => PersitentState::default(),
+  /// Options of a typing context with these settings.
+  pub fn typing_options(&self) -> TypingContextOptions {
+    TypingContextOptions {
+      auto_context_clear_time_ms: self.auto_clear_time_ms,
+      use_native_numerals: self.native_numerals,
+      include_inherent_vowel: self.inherent_vowel,
+      layout: self.typing_layout(),
+      latin_escape: self.latin_escape(),
+    }
+  }
+
+  /// The custom transliteration options; unknown keys are an error.
+  pub fn custom_options(&self) -> Result<CustomOptions, String> {
+    CustomOptions::try_from_map(&self.options)
+      .map_err(|_| "Unknown key in the custom options of app_conf.toml".to_string())
+  }
+
+  pub fn latin_escape(&self) -> Option<LatinEscape> {
+    let mut keys = self.latin_escape.trim().chars();
+    let key = keys.next()?;
+    let double_tap = match (keys.next(), keys.next()) {
+      (None, _) => false,
+      (Some(second), None) if second == key => true,
+      _ => return None,
+    };
+    Some(LatinEscape {
+      key,
+      double_tap,
+      scope: LatinEscapeScope::NextWord,
+    })
+  }
+
+  /// Settings of typing in `app`: its profile, or the global settings as a profile.
+  pub fn profile_for(&self, app: Option<&str>) -> AppProfile {
+    let profile = app.and_then(|app| {
+      self
+        .profiles
+        .iter()
+        .find(|profile| profile.app.trim().eq_ignore_ascii_case(app))
+    });
+    match profile {
+      Some(profile) => profile.clone(),
+      None => AppProfile {
+        app: String::new(),
+        typing_enabled: true,
+        script: self.script.clone(),
+        native_numerals: self.native_numerals,
+        inherent_vowel: self.inherent_vowel,
       },
-      Err(_) => PersitentState::default(),
     }
   }
 
-  pub fn save_app_config(&self) -> Result<(), String> {
+  /// The settings of `name` in the named profiles saved, replacing a profile of that name.
+  pub fn save_named_profile(&mut self, name: &str) {
+    let profile = NamedProfile {
+      name: name.trim().to_string(),
+      script: self.script.clone(),
+      native_numerals: self.native_numerals,
+      inherent_vowel: self.inherent_vowel,
+      layout: self.layout.clone(),
+      latin_escape: self.latin_escape.clone(),
+      auto_clear_time_ms: self.auto_clear_time_ms,
+      options: self.options.clone(),
+      hotkeys: self.hotkeys.clone(),
+      keymaps: self.keymaps.clone(),
+    };
+    match self
+      .named_profiles
+      .iter_mut()
+      .find(|saved| saved.name == profile.name)
+    {
+      Some(saved) => *saved = profile,
+      None => self.named_profiles.push(profile),
+    }
+    self.active_profile = name.trim().to_string();
+  }
+
+  /// Switches to the settings of a named profile. Returns `false` when there is none.
+  pub fn apply_named_profile(&mut self, name: &str) -> bool {
+    let Some(profile) = self.named_profiles.iter().find(|saved| saved.name == name) else {
+      return false;
+    };
+    let profile = profile.clone();
+    self.script = profile.script;
+    self.native_numerals = profile.native_numerals;
+    self.inherent_vowel = profile.inherent_vowel;
+    self.layout = profile.layout;
+    self.latin_escape = profile.latin_escape;
+    self.auto_clear_time_ms = profile.auto_clear_time_ms;
+    self.options = profile.options;
+    self.hotkeys = profile.hotkeys;
+    self.keymaps = profile.keymaps;
+    self.active_profile = profile.name;
+    self.sanitize();
+    true
+  }
+
+  pub fn remove_named_profile(&mut self, name: &str) {
+    self.named_profiles.retain(|saved| saved.name != name);
+    if self.active_profile == name {
+      self.active_profile.clear();
+    }
+  }
+
+  /// Replaces settings that cannot be used with working ones.
+  fn sanitize(&mut self) {
+    if Script::from_str(&self.script).is_err() {
+      self.script = default_script();
+    }
+    for profile in &mut self.profiles {
+      if Script::from_str(&profile.script).is_err() {
+        profile.script = self.script.clone();
+      }
+    }
+  }
+
+  /// Reads a config of any version, migrating it to [`CONFIG_VERSION`].
+  pub fn from_toml(content: &str) -> Result<PersitentState, ConfigError> {
+    Self::migrate_from_toml(content, &MIGRATIONS).map(|(state, _)| state)
+  }
+
+  /// Reads a config with `migrations` (see [`MIGRATIONS`]), returning it with the version
+  /// it was written with.
+  fn migrate_from_toml(
+    content: &str,
+    migrations: &[fn(&mut Table)],
+  ) -> Result<(PersitentState, u32), ConfigError> {
+    let latest = migrations.len() as u32 + 1;
+    let mut config: Table = content
+      .parse()
+      .map_err(|e: toml::de::Error| ConfigError::Parse(e.message().to_string()))?;
+    let version = match config.get("version") {
+      None => 1,
+      Some(version) => version
+        .as_integer()
+        .and_then(|v| u32::try_from(v).ok())
+        .filter(|v| *v >= 1)
+        .ok_or_else(|| ConfigError::Parse("`version` must be a positive number".to_string()))?,
+    };
+    if version > latest {
+      return Err(ConfigError::NewerVersion(version));
+    }
+    for migrate in &migrations[version as usize - 1..] {
+      migrate(&mut config);
+    }
+    config.insert("version".to_string(), Value::Integer(latest.into()));
+
+    let mut state: PersitentState = Value::Table(config)
+      .try_into()
+      .map_err(|e: toml::de::Error| ConfigError::Parse(e.message().to_string()))?;
+    state.sanitize();
+    Ok((state, version))
+  }
+
+  pub fn to_toml(&self) -> Result<String, String> {
+    toml::to_string_pretty(self).map_err(|e| format!("Failed to serialize config: {}", e))
+  }
+
+  /// Reads `app_conf.toml`; without one these are the defaults. A file of an older version
+  /// is migrated and saved, keeping the original next to it (`app_conf.v1.toml`).
+  pub fn read_app_config() -> Result<PersitentState, ConfigError> {
+    Self::read_config_file(&app_config_path(), &MIGRATIONS)
+  }
+
+  fn read_config_file(
+    path: &Path,
+    migrations: &[fn(&mut Table)],
+  ) -> Result<PersitentState, ConfigError> {
+    let content = match fs::read_to_string(path) {
+      Ok(content) => content,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(PersitentState::default()),
+      Err(e) => return Err(ConfigError::Io(e)),
+    };
+    let (state, version) = Self::migrate_from_toml(&content, migrations)?;
+    if version < state.version {
+      let _ = fs::write(
+        path.with_file_name(format!("app_conf.v{version}.toml")),
+        &content,
+      );
+      if let Err(e) = state.export_to(path) {
+        eprintln!("{e}");
+      }
+    }
+    Ok(state)
+  }
+
+  /// Moves an `app_conf.toml` that could not be read aside (`app_conf.toml.bak`), so that
+  /// saving the defaults does not lose it.
+  pub fn back_up_app_config() -> Option<PathBuf> {
     let config_path = app_config_path();
+    let backup = config_path.with_extension("toml.bak");
+    fs::rename(&config_path, &backup).ok()?;
+    Some(backup)
+  }
+
+  pub fn save_app_config(&self) -> Result<(), String> {
+    self.export_to(&app_config_path())
+  }
+
+  /// Writes the settings to `path`, eg. to import them on another computer.
+  pub fn export_to(&self, path: &Path) -> Result<(), String> {
+    fs::write(path, self.to_toml()?)
+      .map_err(|e| format!("Failed to write config to {:?}: {}", path, e))
+  }
+
+  /// Reads settings exported (from any version) with [`Self::export_to`]. The privacy
+  /// choices made on this computer (`analytics`, `update_check` and whether they were asked)
+  /// are kept from `local`.
+  pub fn import_from(path: &Path, local: &PersitentState) -> Result<PersitentState, ConfigError> {
+    let content = fs::read_to_string(path).map_err(ConfigError::Io)?;
+    let mut state = Self::from_toml(&content)?;
+    state.analytics = local.analytics;
+    state.update_check = local.update_check;
+    state.privacy_prompt_answered = local.privacy_prompt_answered;
+    Ok(state)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn configs_without_a_version_are_read() {
+    let state = PersitentState::from_toml(
+      r#"
+      script = "Telugu"
+      native_numerals = false
+      "#,
+    )
+    .unwrap();
+    assert_eq!(state.version, CONFIG_VERSION);
+    assert_eq!(state.script, "Telugu");
+    assert!(!state.native_numerals);
+
+    // written back with the version
+    let saved = PersitentState::from_toml(&state.to_toml().unwrap()).unwrap();
+    assert_eq!(saved.version, CONFIG_VERSION);
+    assert_eq!(saved.script, "Telugu");
+  }
+
+  #[test]
+  fn older_configs_are_migrated_and_backed_up() {
+    fn rename_lipi(config: &mut Table) {
+      if let Some(script) = config.remove("lipi") {
+        config.insert("script".to_string(), script);
+      }
+    }
+    let dir = std::env::temp_dir().join(format!("lipilekhika-migrate-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app_conf.toml");
+    let v1 = "lipi = \"Telugu\"\n";
+    fs::write(&path, v1).unwrap();
+
+    let state = PersitentState::read_config_file(&path, &[rename_lipi]).unwrap();
+    let backup = fs::read_to_string(dir.join("app_conf.v1.toml"));
+    let saved = PersitentState::read_config_file(&path, &[rename_lipi]);
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!((state.version, state.script.as_str()), (2, "Telugu"));
+    assert_eq!(backup.unwrap(), v1);
+    // saved as version 2
+    let saved = saved.unwrap();
+    assert_eq!((saved.version, saved.script.as_str()), (2, "Telugu"));
+  }
+
+  #[test]
+  fn imports_keep_the_local_privacy_choices() {
+    let path = std::env::temp_dir().join(format!("lipilekhika-import-{}.toml", std::process::id()));
+    let exported = PersitentState {
+      script: "Tamil".to_string(),
+      analytics: true,
+      update_check: true,
+      privacy_prompt_answered: false,
+      ..Default::default()
+    };
+    exported.export_to(&path).unwrap();
+    let local = PersitentState {
+      analytics: false,
+      update_check: false,
+      privacy_prompt_answered: true,
+      ..Default::default()
+    };
+    let imported = PersitentState::import_from(&path, &local).unwrap();
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(imported.script, "Tamil");
+    assert!(!imported.analytics && !imported.update_check);
+    assert!(imported.privacy_prompt_answered);
+  }
+
+  #[test]
+  fn unreadable_configs_are_errors() {
+    assert!(matches!(
+      PersitentState::from_toml("script = "),
+      Err(ConfigError::Parse(_))
+    ));
+    assert!(matches!(
+      PersitentState::from_toml("native_numerals = \"yes\""),
+      Err(ConfigError::Parse(_))
+    ));
+    assert!(matches!(
+      PersitentState::from_toml("version = 99"),
+      Err(ConfigError::NewerVersion(99))
+    ));
+    // unknown scripts fall back to the default one
+    let state = PersitentState::from_toml("version = 1\nscript = \"Klingon\"").unwrap();
+    assert_eq!(state.script, "Devanagari");
+  }
 
-    let toml_string =
-      toml::to_string_pretty(self).map_err(|e| format!("Failed to serialize config: {}", e))?;
+  #[test]
+  fn named_profiles_save_and_apply_the_typing_settings() {
+    let work_keymaps = BTreeMap::from([(
+      "Devanagari".to_string(),
+      Keymap::from_toml_str("w = \"व\"").unwrap(),
+    )]);
+    let mut state = PersitentState {
+      script: "Tamil".to_string(),
+      keymaps: work_keymaps.clone(),
+      ..Default::default()
+    };
+    state.hotkeys.undo_conversion = "Ctrl+Alt+U".to_string();
+    state.save_named_profile(" Work ");
+    state.script = "Telugu".to_string();
+    state.inherent_vowel = true;
+    state.keymaps.clear();
+    state.hotkeys = HotkeySettings::default();
+    state.save_named_profile("Home");
+    assert_eq!(state.named_profiles.len(), 2);
 
-    fs::write(&config_path, toml_string)
-      .map_err(|e| format!("Failed to write config to {:?}: {}", config_path, e))?;
+    assert!(state.apply_named_profile("Work"));
+    assert_eq!(
+      (state.script.as_str(), state.inherent_vowel),
+      ("Tamil", false)
+    );
+    assert_eq!(state.keymaps, work_keymaps);
+    assert_eq!(state.hotkeys.undo_conversion, "Ctrl+Alt+U");
+    assert_eq!(state.active_profile, "Work");
+    assert!(!state.apply_named_profile("Travel"));
 
-    Ok(())
+    let mut saved = PersitentState::from_toml(&state.to_toml().unwrap()).unwrap();
+    assert_eq!(saved.named_profiles, state.named_profiles);
+    assert_eq!(saved.keymaps, state.keymaps);
+    assert!(saved.apply_named_profile("Home"));
+    assert!(saved.keymaps.is_empty());
+    assert_eq!(saved.hotkeys, HotkeySettings::default());
   }
 }
@@ -6,9 +6,55 @@ mod win;
 #[path = "mac/mod.rs"]
 mod mac;
 
+#[cfg(target_os = "linux")]
+#[path = "linux/mod.rs"]
+mod linux;
+
 use crossbeam_channel::Sender;
 use std::sync::Arc;
 
+/// Clipboard shortcut sent to the focused app (Ctrl, or Cmd on macOS, with C or V).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardKey {
+  Copy,
+  Paste,
+}
+
+/// Sends a clipboard shortcut to the focused app, after the modifiers still held for the
+/// hotkey are released (or replaced, on macOS). Called from the clipboard thread.
+pub fn send_clipboard_key(_key: ClipboardKey) {
+  #[cfg(windows)]
+  win::hooks::send_clipboard_key(_key);
+
+  #[cfg(target_os = "macos")]
+  mac::hooks::send_clipboard_key(_key);
+
+  #[cfg(target_os = "linux")]
+  if let Err(err) = linux::runtime::send_clipboard_key(_key) {
+    eprintln!("Failed to send the clipboard shortcut: {err}");
+  }
+}
+
+/// Whether the focused field hides what is typed (a password field), so that it is not
+/// learned for completions. Windows recognizes password edit controls and macOS any field
+/// that turned on secure input; X11 does not tell, so it is always `false` on Linux.
+pub fn secure_input_focused() -> bool {
+  #[cfg(windows)]
+  {
+    win::hooks::secure_input_focused()
+  }
+
+  #[cfg(target_os = "macos")]
+  {
+    mac::hooks::secure_input_focused()
+  }
+
+  #[cfg(not(any(windows, target_os = "macos")))]
+  {
+    false
+  }
+}
+
 pub fn run(
   _app_state: Arc<crate::AppState>,
   _tx_ui: Sender<crate::ThreadMessage>,
@@ -24,7 +70,12 @@ pub fn run(
     mac::run(_app_state, _tx_ui, _tx_tray)
   }
 
-  #[cfg(not(any(windows, target_os = "macos")))]
+  #[cfg(target_os = "linux")]
+  {
+    linux::run(_app_state, _tx_ui, _tx_tray)
+  }
+
+  #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
   {
     Err(
       std::io::Error::new(
@@ -0,0 +1,174 @@
+//! Analytics and update checks: the user's choice (asked on first run, changed in the
+//! Settings window) and the overrides of managed deployments, which win over it.
+//!
+//! Managed deployments set `LIPILEKHIKA_OFFLINE=1` to turn both off, `LIPILEKHIKA_ANALYTICS`
+//! or `LIPILEKHIKA_UPDATE_CHECK` to `0`/`1` and `LIPILEKHIKA_UPDATE_URL` to a mirror of the
+//! releases, or install a `managed.toml` next to the executable:
+//!
+//! ```toml
+//! analytics = false
+//! update_check = true
+//! # mirror of the GitHub releases, see `ui::version_check::MirrorReleases`
+//! update_url = "https://updates.example.org/lipilekhika"
+//! ```
+//!
+//! The environment wins over the file. Nothing is sent before the first-run question is
+//! answered, unless both are set by the deployment.
+
+use crate::persistent_state::PersitentState;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+
+const MANAGED_FILE_NAME: &str = "managed.toml";
+
+/// Settings of a managed deployment; `None` leaves the choice to the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ManagedPolicy {
+  #[serde(default)]
+  pub analytics: Option<bool>,
+  #[serde(default)]
+  pub update_check: Option<bool>,
+  /// Base URL of a mirror of the releases, used instead of GitHub
+  #[serde(default)]
+  pub update_url: Option<String>,
+}
+
+fn parse_flag(name: &str, value: &str) -> Option<bool> {
+  match value.trim().to_ascii_lowercase().as_str() {
+    "1" | "true" | "yes" | "on" => Some(true),
+    "0" | "false" | "no" | "off" => Some(false),
+    _ => {
+      eprintln!("Ignoring {name}={value}: expected 0 or 1");
+      None
+    }
+  }
+}
+
+impl ManagedPolicy {
+  /// Applies the environment variables (read with `var`) over the policy of the file.
+  fn with_env(mut self, var: impl Fn(&str) -> Option<String>) -> Self {
+    if let Some(value) = var("LIPILEKHIKA_OFFLINE")
+      && parse_flag("LIPILEKHIKA_OFFLINE", &value) == Some(true)
+    {
+      self.analytics = Some(false);
+      self.update_check = Some(false);
+    }
+    for (name, setting) in [
+      ("LIPILEKHIKA_ANALYTICS", &mut self.analytics),
+      ("LIPILEKHIKA_UPDATE_CHECK", &mut self.update_check),
+    ] {
+      if let Some(enabled) = var(name).and_then(|value| parse_flag(name, &value)) {
+        *setting = Some(enabled);
+      }
+    }
+    if let Some(url) = var("LIPILEKHIKA_UPDATE_URL").filter(|url| !url.trim().is_empty()) {
+      self.update_url = Some(url);
+    }
+    self
+  }
+
+  fn from_file() -> Self {
+    let Some(path) = env::current_exe()
+      .ok()
+      .and_then(|exe| Some(exe.parent()?.join(MANAGED_FILE_NAME)))
+    else {
+      return Self::default();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+      return Self::default();
+    };
+    toml::from_str(&content).unwrap_or_else(|e| {
+      // a deployment asking for no network should not silently get it back: stay offline
+      eprintln!("Invalid {}: {e}", path.display());
+      Self {
+        analytics: Some(false),
+        update_check: Some(false),
+        update_url: None,
+      }
+    })
+  }
+}
+
+/// The policy of the deployment, read once.
+pub fn managed_policy() -> &'static ManagedPolicy {
+  static POLICY: OnceLock<ManagedPolicy> = OnceLock::new();
+  POLICY.get_or_init(|| ManagedPolicy::from_file().with_env(|name| env::var(name).ok()))
+}
+
+fn enabled(policy: Option<bool>, state: &PersitentState, choice: bool) -> bool {
+  policy.unwrap_or(state.privacy_prompt_answered && choice)
+}
+
+/// Whether the anonymous start event is sent.
+pub fn analytics_enabled(state: &PersitentState) -> bool {
+  enabled(managed_policy().analytics, state, state.analytics)
+}
+
+/// Whether new versions are looked for at start.
+pub fn update_check_enabled(state: &PersitentState) -> bool {
+  enabled(managed_policy().update_check, state, state.update_check)
+}
+
+/// Whether the first-run question is still to be asked.
+pub fn needs_prompt(state: &PersitentState) -> bool {
+  let policy = managed_policy();
+  !state.privacy_prompt_answered && (policy.analytics.is_none() || policy.update_check.is_none())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy_with_env(file: ManagedPolicy, vars: &[(&str, &str)]) -> ManagedPolicy {
+    file.with_env(|name| {
+      vars
+        .iter()
+        .find(|(var, _)| *var == name)
+        .map(|(_, value)| value.to_string())
+    })
+  }
+
+  #[test]
+  fn environment_overrides_the_managed_file() {
+    let file = ManagedPolicy {
+      analytics: Some(true),
+      update_check: None,
+      update_url: Some("https://mirror.example".to_string()),
+    };
+    assert_eq!(policy_with_env(file.clone(), &[]), file);
+
+    let offline = policy_with_env(file.clone(), &[("LIPILEKHIKA_OFFLINE", "1")]);
+    assert_eq!(
+      (offline.analytics, offline.update_check),
+      (Some(false), Some(false))
+    );
+
+    let policy = policy_with_env(
+      file,
+      &[
+        ("LIPILEKHIKA_OFFLINE", "0"),
+        ("LIPILEKHIKA_UPDATE_CHECK", "yes"),
+        ("LIPILEKHIKA_ANALYTICS", "maybe"),
+      ],
+    );
+    assert_eq!(
+      (policy.analytics, policy.update_check),
+      (Some(true), Some(true))
+    );
+  }
+
+  #[test]
+  fn nothing_is_enabled_before_the_prompt_is_answered() {
+    let mut state = PersitentState::default();
+    assert!(!enabled(None, &state, state.analytics));
+    assert!(enabled(Some(true), &state, state.analytics));
+
+    state.privacy_prompt_answered = true;
+    assert!(enabled(None, &state, state.analytics));
+    state.analytics = false;
+    assert!(!enabled(None, &state, state.analytics));
+    assert!(enabled(Some(true), &state, state.analytics));
+  }
+}
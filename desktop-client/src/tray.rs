@@ -1,10 +1,12 @@
+use crate::clipboard::{self, ClipboardRequest};
 use crate::data::{
-  ScriptDisplay, get_ordered_script_list, script_from_normalized_name, script_list_to_typing_script,
+  ScriptDisplay, get_ordered_script_list, normalize_script, script_from_normalized_name,
+  script_list_to_typing_script,
 };
+use crate::keymap::new_typing_context;
 use crate::{AppState, ThreadMessage, ThreadMessageOrigin, ThreadMessageType};
 use crossbeam_channel::{Receiver, Sender};
 use lipilekhika::ScriptListEnum;
-use lipilekhika::typing::TypingContext;
 use std::collections::HashMap;
 use std::sync::{Arc, atomic::Ordering};
 use tray_icon::{
@@ -20,6 +22,8 @@ enum TrayMenuEvent {
   Quit,
   OpenApp,
   ScriptSelected(ScriptListEnum),
+  ClipboardTarget(ScriptListEnum),
+  ConvertClipboard,
 }
 
 struct MenuEventMapper {
@@ -46,6 +50,8 @@ const MENU_ID_NATIVE_NUMERALS: &str = "native_numerals";
 const MENU_ID_INHERENT_VOWEL: &str = "inherent_vowel";
 const MENU_ID_QUIT: &str = "quit";
 const MENU_ID_SCRIPT_PREFIX: &str = "script_";
+const MENU_ID_CLIPBOARD_TARGET_PREFIX: &str = "clipboard_target_";
+const MENU_ID_CONVERT_CLIPBOARD: &str = "convert_clipboard";
 
 pub struct TrayManager {
   _tray_icon: TrayIcon,
@@ -54,6 +60,7 @@ pub struct TrayManager {
   native_numerals_item: CheckMenuItem,
   inherent_vowel_item: CheckMenuItem,
   script_items: Vec<(CheckMenuItem, ScriptDisplay)>,
+  clipboard_target_items: Vec<(CheckMenuItem, ScriptListEnum)>,
   event_mapper: MenuEventMapper,
 }
 
@@ -148,6 +155,47 @@ impl TrayManager {
 
     tray_menu.append(&PredefinedMenuItem::separator())?;
 
+    // Clipboard target submenu, with the scripts of `[clipboard] targets`
+    let (clipboard_targets, clipboard_target) = {
+      let state = app_state.persitent_state.lock().unwrap();
+      (
+        state.clipboard.target_scripts(),
+        state.clipboard.target.clone(),
+      )
+    };
+    let clipboard_target = normalize_script(&clipboard_target);
+    let clipboard_submenu = Submenu::new("Convert Clipboard To", true);
+    let mut clipboard_target_items = Vec::new();
+    for script in clipboard_targets {
+      let item_id = format!("{}{}", MENU_ID_CLIPBOARD_TARGET_PREFIX, script);
+      let label = get_ordered_script_list()
+        .into_iter()
+        .find(|sd| sd.script_name == script)
+        .map(|sd| sd.display_label)
+        .unwrap_or_else(|| script.to_string());
+      let item = CheckMenuItem::with_id(
+        &item_id,
+        &label,
+        true,
+        clipboard_target == Some(script),
+        None,
+      );
+      clipboard_submenu.append(&item)?;
+      event_mapper.register(item_id, TrayMenuEvent::ClipboardTarget(script));
+      clipboard_target_items.push((item, script));
+    }
+    tray_menu.append(&clipboard_submenu)?;
+
+    let convert_clipboard_item =
+      MenuItem::with_id(MENU_ID_CONVERT_CLIPBOARD, "Convert Clipboard", true, None);
+    tray_menu.append(&convert_clipboard_item)?;
+    event_mapper.register(
+      MENU_ID_CONVERT_CLIPBOARD.to_string(),
+      TrayMenuEvent::ConvertClipboard,
+    );
+
+    tray_menu.append(&PredefinedMenuItem::separator())?;
+
     // Quit option
     let quit_item = MenuItem::with_id(MENU_ID_QUIT, "Quit", true, None);
     tray_menu.append(&quit_item)?;
@@ -168,6 +216,7 @@ impl TrayManager {
       native_numerals_item,
       inherent_vowel_item,
       script_items,
+      clipboard_target_items,
       event_mapper,
     })
   }
@@ -214,10 +263,21 @@ impl TrayManager {
         .unwrap_or(false);
       item.set_checked(is_selected);
     }
+    self.update_clipboard_target_checks();
 
     self.update_tooltip();
   }
 
+  fn update_clipboard_target_checks(&self) {
+    let target = {
+      let state = self.app_state.persitent_state.lock().unwrap();
+      normalize_script(&state.clipboard.target)
+    };
+    for (item, script) in &self.clipboard_target_items {
+      item.set_checked(target == Some(*script));
+    }
+  }
+
   pub fn handle_menu_event(&mut self, event: MenuEvent) -> Vec<ThreadMessageType> {
     let event_id = event.id().0.clone();
     let mut messages = Vec::new();
@@ -267,17 +327,23 @@ impl TrayManager {
         messages.push(ThreadMessageType::MaximizeUI);
       }
       TrayMenuEvent::ScriptSelected(script) => {
+        let (layout, auto_clear_time_ms) = {
+          let state = self.app_state.persitent_state.lock().unwrap();
+          (state.typing_layout(), state.auto_clear_time_ms)
+        };
         let current_options = {
           let ctx = self.app_state.typing_context.lock().unwrap();
           Some(lipilekhika::typing::TypingContextOptions {
-            auto_context_clear_time_ms: lipilekhika::typing::DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS,
+            auto_context_clear_time_ms,
             use_native_numerals: ctx.get_use_native_numerals(),
             include_inherent_vowel: ctx.get_include_inherent_vowel(),
+            layout,
+            latin_escape: ctx.get_latin_escape(),
           })
         };
 
         let script_typing = script_list_to_typing_script(script);
-        let new_ctx = TypingContext::new(script_typing, current_options);
+        let new_ctx = new_typing_context(script_typing, current_options);
         {
           let mut ctx = self.app_state.typing_context.lock().unwrap();
           *ctx = new_ctx;
@@ -294,6 +360,20 @@ impl TrayManager {
         // Notify UI to rerender
         messages.push(ThreadMessageType::RerenderUI);
       }
+      TrayMenuEvent::ClipboardTarget(script) => {
+        let state_to_save = {
+          let mut state = self.app_state.persitent_state.lock().unwrap();
+          state.clipboard.target = script.to_string();
+          state.clone()
+        };
+        self.update_clipboard_target_checks();
+        if let Err(err) = state_to_save.save_app_config() {
+          eprintln!("Failed to save the clipboard target: {err}");
+        }
+      }
+      TrayMenuEvent::ConvertClipboard => {
+        clipboard::transliterate(ClipboardRequest::Clipboard);
+      }
     };
     messages
   }
@@ -0,0 +1,365 @@
+use crate::persistent_state::{ConfigError, PersitentState};
+use crate::privacy;
+use iced::{
+  Alignment, Element, Length, Task,
+  theme::Theme,
+  widget::{button, checkbox, column, container, pick_list, row, scrollable, text, text_input},
+  window,
+};
+use lipilekhika::CustomOptions;
+use std::path::PathBuf;
+
+/// Size of the Settings window
+pub const WINDOW_WIDTH: f32 = 560.0;
+pub const WINDOW_HEIGHT: f32 = 520.0;
+
+/// State for the Settings window
+#[derive(Clone, Debug, Default)]
+pub struct ConfigSettingsState {
+  selected_profile: Option<String>,
+  /// name typed to save the current settings under
+  new_profile: String,
+  /// file the settings are exported to and imported from
+  path: String,
+  /// result of the last import/export, `Err` for failures
+  status: Option<Result<String, String>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ConfigSettingsMessage {
+  SelectProfile(String),
+  ApplyProfile,
+  RemoveProfile,
+  SetNewProfile(String),
+  SaveProfile,
+  SetOption(&'static str, bool),
+  SetAnalytics(bool),
+  SetUpdateCheck(bool),
+  SetPath(String),
+  Import,
+  Export,
+}
+
+/// What a message changed in the persistent state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigChange {
+  None,
+  /// Only needs saving
+  Saved,
+  /// The typing settings changed: the typing context and the other windows need updating
+  Reloaded,
+}
+
+fn default_export_path() -> String {
+  dirs::document_dir()
+    .or_else(dirs::home_dir)
+    .unwrap_or_default()
+    .join("lipilekhika_settings.toml")
+    .display()
+    .to_string()
+}
+
+impl ConfigSettingsState {
+  pub fn new(state: &PersitentState) -> Self {
+    Self {
+      selected_profile: (!state.active_profile.is_empty()).then(|| state.active_profile.clone()),
+      new_profile: String::new(),
+      path: default_export_path(),
+      status: None,
+    }
+  }
+
+  /// Applies a message to `state`, returning what changed.
+  pub fn update(
+    &mut self,
+    message: ConfigSettingsMessage,
+    state: &mut PersitentState,
+  ) -> ConfigChange {
+    match message {
+      ConfigSettingsMessage::SelectProfile(name) => {
+        self.selected_profile = Some(name);
+        ConfigChange::None
+      }
+      ConfigSettingsMessage::ApplyProfile => {
+        let Some(name) = &self.selected_profile else {
+          return ConfigChange::None;
+        };
+        if state.apply_named_profile(name) {
+          ConfigChange::Reloaded
+        } else {
+          ConfigChange::None
+        }
+      }
+      ConfigSettingsMessage::RemoveProfile => {
+        let Some(name) = self.selected_profile.take() else {
+          return ConfigChange::None;
+        };
+        state.remove_named_profile(&name);
+        ConfigChange::Saved
+      }
+      ConfigSettingsMessage::SetNewProfile(name) => {
+        self.new_profile = name;
+        ConfigChange::None
+      }
+      ConfigSettingsMessage::SaveProfile => {
+        let name = self.new_profile.trim().to_string();
+        if name.is_empty() {
+          return ConfigChange::None;
+        }
+        state.save_named_profile(&name);
+        self.selected_profile = Some(name);
+        self.new_profile.clear();
+        ConfigChange::Saved
+      }
+      ConfigSettingsMessage::SetOption(key, enabled) => {
+        if enabled {
+          state.options.insert(key.to_string(), true);
+        } else {
+          state.options.remove(key);
+        }
+        ConfigChange::Saved
+      }
+      ConfigSettingsMessage::SetAnalytics(enabled) => {
+        state.analytics = enabled;
+        state.privacy_prompt_answered = true;
+        ConfigChange::Saved
+      }
+      ConfigSettingsMessage::SetUpdateCheck(enabled) => {
+        state.update_check = enabled;
+        state.privacy_prompt_answered = true;
+        ConfigChange::Saved
+      }
+      ConfigSettingsMessage::SetPath(path) => {
+        self.path = path;
+        ConfigChange::None
+      }
+      ConfigSettingsMessage::Export => {
+        let path = PathBuf::from(self.path.trim());
+        self.status = Some(
+          state
+            .export_to(&path)
+            .map(|()| format!("Exported to {}", path.display())),
+        );
+        ConfigChange::None
+      }
+      ConfigSettingsMessage::Import => {
+        let path = PathBuf::from(self.path.trim());
+        match PersitentState::import_from(&path, state) {
+          Ok(imported) => {
+            *state = imported;
+            self.selected_profile =
+              (!state.active_profile.is_empty()).then(|| state.active_profile.clone());
+            self.status = Some(Ok(format!("Imported {}", path.display())));
+            ConfigChange::Reloaded
+          }
+          Err(ConfigError::Io(e)) => {
+            self.status = Some(Err(format!("Could not read {}: {e}", path.display())));
+            ConfigChange::None
+          }
+          Err(e) => {
+            self.status = Some(Err(format!("Could not import {}: {e}", path.display())));
+            ConfigChange::None
+          }
+        }
+      }
+    }
+  }
+}
+
+/// Opens the Settings window
+pub fn open_config_settings_window(icon: Option<window::Icon>) -> (window::Id, Task<window::Id>) {
+  let settings = window::Settings {
+    icon,
+    decorations: true,
+    resizable: true,
+    size: iced::Size::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+    position: window::Position::Centered,
+    exit_on_close_request: false,
+    min_size: Some(iced::Size::new(480.0, 400.0)),
+    ..Default::default()
+  };
+
+  window::open(settings)
+}
+
+fn muted_text<'a, Message: 'a>(content: impl text::IntoFragment<'a>) -> Element<'a, Message> {
+  text(content)
+    .size(12)
+    .style(|theme: &Theme| iced::widget::text::Style {
+      color: Some(theme.extended_palette().background.weak.text),
+    })
+    .into()
+}
+
+/// Renders the Settings window content
+pub fn view_config_settings<'a, Message: 'a + Clone + From<ConfigSettingsMessage>>(
+  state: &ConfigSettingsState,
+  config: &PersitentState,
+) -> Element<'a, Message> {
+  let names: Vec<String> = config
+    .named_profiles
+    .iter()
+    .map(|profile| profile.name.clone())
+    .collect();
+  let selected = state
+    .selected_profile
+    .clone()
+    .filter(|name| names.contains(name));
+
+  let mut profiles = column![
+    text("Profiles").size(15),
+    muted_text(
+      "Named copies of the script, layout, typing options, hotkeys and keys to switch between."
+    ),
+  ]
+  .spacing(8);
+  if config.active_profile.is_empty() {
+    profiles = profiles.push(muted_text("No profile applied"));
+  } else {
+    profiles = profiles.push(text(format!("Current: {}", config.active_profile)).size(13));
+  }
+  profiles = profiles
+    .push(
+      row![
+        pick_list(names, selected.clone(), |name| {
+          Message::from(ConfigSettingsMessage::SelectProfile(name))
+        })
+        .placeholder("Profile")
+        .text_size(13)
+        .width(Length::Fixed(200.0)),
+        button(text("Apply").size(13))
+          .on_press_maybe(
+            selected
+              .is_some()
+              .then(|| Message::from(ConfigSettingsMessage::ApplyProfile))
+          )
+          .padding([2, 8]),
+        button(text("Remove").size(13))
+          .on_press_maybe(
+            selected
+              .is_some()
+              .then(|| Message::from(ConfigSettingsMessage::RemoveProfile))
+          )
+          .padding([2, 8]),
+      ]
+      .spacing(10)
+      .align_y(Alignment::Center),
+    )
+    .push(
+      row![
+        text_input("Profile name", &state.new_profile)
+          .on_input(|name| Message::from(ConfigSettingsMessage::SetNewProfile(name)))
+          .on_submit(Message::from(ConfigSettingsMessage::SaveProfile))
+          .size(13)
+          .padding(4)
+          .width(Length::Fixed(200.0)),
+        button(text("Save current settings").size(13))
+          .on_press(Message::from(ConfigSettingsMessage::SaveProfile))
+          .padding([2, 8]),
+      ]
+      .spacing(10)
+      .align_y(Alignment::Center),
+    );
+
+  let mut options = column![
+    text("Transliteration options").size(15),
+    muted_text("Used when converting the clipboard, saved with the profiles."),
+  ]
+  .spacing(6);
+  for &key in CustomOptions::KEYS {
+    let enabled = config.options.get(key).copied().unwrap_or(false);
+    options = options.push(
+      checkbox(enabled)
+        .on_toggle(move |enabled| Message::from(ConfigSettingsMessage::SetOption(key, enabled)))
+        .label(key)
+        .text_size(13),
+    );
+  }
+
+  let policy = privacy::managed_policy();
+  let mut network = column![
+    text("Privacy").size(15),
+    muted_text("Both take effect the next time the app starts."),
+  ]
+  .spacing(6);
+  for (enabled, locked, label, on_toggle) in [
+    (
+      policy.analytics.unwrap_or(config.analytics),
+      policy.analytics.is_some(),
+      "Send an anonymous start event (app version, OS)",
+      ConfigSettingsMessage::SetAnalytics as fn(bool) -> ConfigSettingsMessage,
+    ),
+    (
+      policy.update_check.unwrap_or(config.update_check),
+      policy.update_check.is_some(),
+      "Check for new versions",
+      ConfigSettingsMessage::SetUpdateCheck,
+    ),
+  ] {
+    let mut choice = checkbox(enabled).label(label).text_size(13);
+    if !locked {
+      choice = choice.on_toggle(move |enabled| Message::from(on_toggle(enabled)));
+    }
+    network = network.push(choice);
+  }
+  if policy.analytics.is_some() || policy.update_check.is_some() {
+    network = network.push(muted_text("Some of these are set by your organisation."));
+  }
+
+  let mut transfer = column![
+    text("Import and export").size(15),
+    muted_text(
+      "All the settings in one file. Files of older versions of the app are updated on import."
+    ),
+    row![
+      text_input("File", &state.path)
+        .on_input(|path| Message::from(ConfigSettingsMessage::SetPath(path)))
+        .size(13)
+        .padding(4)
+        .width(Length::Fill),
+      button(text("Import").size(13))
+        .on_press(Message::from(ConfigSettingsMessage::Import))
+        .padding([2, 8]),
+      button(text("Export").size(13))
+        .on_press(Message::from(ConfigSettingsMessage::Export))
+        .padding([2, 8]),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center),
+  ]
+  .spacing(8);
+  if let Some(status) = &state.status {
+    let (message, failed) = match status {
+      Ok(message) => (message.clone(), false),
+      Err(message) => (message.clone(), true),
+    };
+    transfer = transfer.push(text(message).size(13).style(move |theme: &Theme| {
+      let palette = theme.extended_palette();
+      iced::widget::text::Style {
+        color: Some(if failed {
+          palette.danger.base.color
+        } else {
+          palette.success.base.color
+        }),
+      }
+    }));
+  }
+
+  let content = column![
+    text("Settings").size(18),
+    scrollable(
+      column![profiles, options, network, transfer]
+        .spacing(18)
+        .padding([0, 12])
+    )
+    .height(Length::Fill),
+  ]
+  .spacing(14)
+  .padding([15, 20])
+  .height(Length::Fill);
+
+  container(content)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
+}
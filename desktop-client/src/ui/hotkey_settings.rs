@@ -0,0 +1,328 @@
+use crate::data::{ScriptDisplay, get_ordered_script_list, normalize_script};
+use crate::hotkeys::{FavouriteScript, Hotkey, HotkeySettings, HotkeySlot};
+use crate::persistent_state::PersitentState;
+use iced::{
+  Alignment, Element, Length, Task,
+  theme::Theme,
+  widget::{
+    Space, button, checkbox, column, container, pick_list, row, scrollable, text, text_input,
+  },
+  window,
+};
+use lipilekhika::ScriptListEnum;
+
+/// Size of the Hotkeys window
+pub const WINDOW_WIDTH: f32 = 560.0;
+pub const WINDOW_HEIGHT: f32 = 480.0;
+
+/// Accepted range of the auto-clear timeout; shorter ones would clear the context between
+/// the keys of a single letter.
+const MIN_AUTO_CLEAR_TIME_MS: u64 = 500;
+const MAX_AUTO_CLEAR_TIME_MS: u64 = 60_000;
+
+/// State for the Hotkeys window
+#[derive(Clone, Debug, Default)]
+pub struct HotkeySettingsState {
+  /// copy of the hotkeys in the persistent state, saved back on every change
+  pub hotkeys: HotkeySettings,
+  pub auto_clear_time_ms: u64,
+  auto_clear_input: String,
+  pub auto_revert_before_dot: bool,
+  pub word_completion: bool,
+  /// slot waiting for a key press; the keyboard hook ignores hotkeys meanwhile
+  pub recording: Option<HotkeySlot>,
+  /// conflict of the last recorded hotkey, or invalid timeout
+  warning: Option<String>,
+  new_favourite: Option<ScriptDisplay>,
+}
+
+#[derive(Clone, Debug)]
+pub enum HotkeySettingsMessage {
+  Record(HotkeySlot),
+  CancelRecording,
+  Pressed(Hotkey),
+  Clear(HotkeySlot),
+  AddToggle,
+  SetNewFavourite(ScriptDisplay),
+  AddFavourite,
+  RemoveFavourite(usize),
+  SetAutoClear(String),
+  SetAutoRevert(bool),
+  SetWordCompletion(bool),
+}
+
+impl HotkeySettingsState {
+  pub fn new(state: &PersitentState) -> Self {
+    Self {
+      hotkeys: state.hotkeys.clone(),
+      auto_clear_time_ms: state.auto_clear_time_ms,
+      auto_clear_input: state.auto_clear_time_ms.to_string(),
+      auto_revert_before_dot: state.auto_revert_before_dot,
+      word_completion: state.word_completion,
+      recording: None,
+      warning: None,
+      new_favourite: None,
+    }
+  }
+
+  /// Applies a message. Returns `true` when the hotkeys or the timeout changed and need
+  /// saving.
+  pub fn update(&mut self, message: HotkeySettingsMessage) -> bool {
+    match message {
+      HotkeySettingsMessage::Record(slot) => {
+        self.recording = Some(slot);
+        self.warning = None;
+        return false;
+      }
+      HotkeySettingsMessage::CancelRecording => {
+        self.recording = None;
+        self.warning = None;
+        return false;
+      }
+      HotkeySettingsMessage::Pressed(hotkey) => {
+        let Some(slot) = self.recording else {
+          return false;
+        };
+        // Conflicting hotkeys are refused; recording goes on for another one
+        if let Some(conflict) = self.hotkeys.conflict(slot, &hotkey) {
+          self.warning = Some(format!("{conflict}. Press another one, or Esc to cancel."));
+          return false;
+        }
+        self.hotkeys.set(slot, Some(hotkey));
+        self.recording = None;
+        self.warning = None;
+      }
+      HotkeySettingsMessage::Clear(slot) => {
+        self.hotkeys.set(slot, None);
+      }
+      HotkeySettingsMessage::AddToggle => {
+        self.hotkeys.toggle_typing.push(String::new());
+        self.recording = Some(HotkeySlot::ToggleTyping(
+          self.hotkeys.toggle_typing.len() - 1,
+        ));
+      }
+      HotkeySettingsMessage::SetNewFavourite(script) => {
+        self.new_favourite = Some(script);
+        return false;
+      }
+      HotkeySettingsMessage::AddFavourite => {
+        let Some(script) = self.new_favourite.take() else {
+          return false;
+        };
+        let exists = self
+          .hotkeys
+          .favourite_scripts
+          .iter()
+          .any(|favourite| normalize_script(&favourite.script) == Some(script.script_name));
+        if exists {
+          return false;
+        }
+        self.hotkeys.favourite_scripts.push(FavouriteScript {
+          script: script.script_name.to_string(),
+          hotkey: String::new(),
+        });
+      }
+      HotkeySettingsMessage::RemoveFavourite(idx) => {
+        if idx >= self.hotkeys.favourite_scripts.len() {
+          return false;
+        }
+        self.hotkeys.favourite_scripts.remove(idx);
+        // slots after it moved up
+        self.recording = None;
+      }
+      HotkeySettingsMessage::SetAutoClear(value) => {
+        self.auto_clear_input = value;
+        match self.auto_clear_input.trim().parse::<u64>() {
+          Ok(ms) if (MIN_AUTO_CLEAR_TIME_MS..=MAX_AUTO_CLEAR_TIME_MS).contains(&ms) => {
+            self.warning = None;
+            if ms == self.auto_clear_time_ms {
+              return false;
+            }
+            self.auto_clear_time_ms = ms;
+          }
+          _ => {
+            self.warning = Some(format!(
+              "The timeout must be between {MIN_AUTO_CLEAR_TIME_MS} and {MAX_AUTO_CLEAR_TIME_MS} ms."
+            ));
+            return false;
+          }
+        }
+      }
+      HotkeySettingsMessage::SetAutoRevert(enabled) => {
+        self.auto_revert_before_dot = enabled;
+      }
+      HotkeySettingsMessage::SetWordCompletion(enabled) => {
+        self.word_completion = enabled;
+      }
+    }
+    true
+  }
+}
+
+/// Opens the Hotkeys window
+pub fn open_hotkey_settings_window(icon: Option<window::Icon>) -> (window::Id, Task<window::Id>) {
+  let settings = window::Settings {
+    icon,
+    decorations: true,
+    resizable: true,
+    size: iced::Size::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+    position: window::Position::Centered,
+    exit_on_close_request: false,
+    min_size: Some(iced::Size::new(480.0, 360.0)),
+    ..Default::default()
+  };
+
+  window::open(settings)
+}
+
+fn muted_text<'a, Message: 'a>(content: impl text::IntoFragment<'a>) -> Element<'a, Message> {
+  text(content)
+    .size(12)
+    .style(|theme: &Theme| iced::widget::text::Style {
+      color: Some(theme.extended_palette().background.weak.text),
+    })
+    .into()
+}
+
+/// One hotkey with its Record and Clear buttons
+fn view_slot<'a, Message: 'a + Clone + From<HotkeySettingsMessage>>(
+  state: &HotkeySettingsState,
+  slot: HotkeySlot,
+  label: String,
+) -> Element<'a, Message> {
+  let binding = match state.hotkeys.get(slot) {
+    _ if state.recording == Some(slot) => "Press a hotkey…".to_string(),
+    Some(hotkey) if !hotkey.is_empty() => hotkey.to_string(),
+    _ => "Off".to_string(),
+  };
+  let record = if state.recording == Some(slot) {
+    button(text("Cancel").size(13)).on_press(Message::from(HotkeySettingsMessage::CancelRecording))
+  } else {
+    button(text("Record").size(13)).on_press(Message::from(HotkeySettingsMessage::Record(slot)))
+  };
+
+  row![
+    text(label).size(14).width(Length::Fixed(190.0)),
+    text(binding).size(14).width(Length::Fixed(130.0)),
+    Space::new().width(Length::Fill),
+    record.padding([2, 8]),
+    button(text("Clear").size(13))
+      .on_press(Message::from(HotkeySettingsMessage::Clear(slot)))
+      .padding([2, 8]),
+  ]
+  .spacing(10)
+  .align_y(Alignment::Center)
+  .into()
+}
+
+/// Renders the Hotkeys window content
+pub fn view_hotkey_settings<'a, Message: 'a + Clone + From<HotkeySettingsMessage>>(
+  state: &HotkeySettingsState,
+) -> Element<'a, Message> {
+  let scripts: Vec<ScriptDisplay> = get_ordered_script_list()
+    .into_iter()
+    .filter(|s| s.script_name != ScriptListEnum::Normal)
+    .collect();
+
+  let header = column![
+    text("Hotkeys").size(18),
+    muted_text(
+      "Hotkeys work in every app and need Ctrl, Alt or the Windows/Command key. \
+       Record one by pressing it; Esc cancels."
+    ),
+  ]
+  .spacing(4);
+
+  let mut list = column![].spacing(8);
+  for slot in state.hotkeys.slots() {
+    let label = match slot {
+      HotkeySlot::ToggleTyping(_) => "Turn typing on/off".to_string(),
+      HotkeySlot::NextScript => "Next script".to_string(),
+      HotkeySlot::CloseApp => "Close the app".to_string(),
+      HotkeySlot::TransliterateClipboard => "Convert selection/clipboard".to_string(),
+      HotkeySlot::UndoConversion => "Undo last conversion".to_string(),
+      HotkeySlot::FavouriteScript(_) => continue,
+    };
+    list = list.push(view_slot(state, slot, label));
+  }
+  list = list.push(
+    button(text("Add another typing toggle").size(13))
+      .on_press(Message::from(HotkeySettingsMessage::AddToggle))
+      .padding([2, 8]),
+  );
+
+  list = list.push(text("Favourite scripts").size(15));
+  list = list.push(muted_text(
+    "Switched to with their own hotkeys; Next script cycles through them.",
+  ));
+  for (idx, favourite) in state.hotkeys.favourite_scripts.iter().enumerate() {
+    let name = normalize_script(&favourite.script)
+      .and_then(|script| scripts.iter().find(|sd| sd.script_name == script))
+      .map(|sd| sd.to_string())
+      .unwrap_or_else(|| favourite.script.clone());
+    list = list.push(
+      row![
+        view_slot(state, HotkeySlot::FavouriteScript(idx), name),
+        button(text("Remove").size(13))
+          .on_press(Message::from(HotkeySettingsMessage::RemoveFavourite(idx)))
+          .padding([2, 8]),
+      ]
+      .spacing(10)
+      .align_y(Alignment::Center),
+    );
+  }
+  list = list.push(
+    row![
+      pick_list(scripts, state.new_favourite.clone(), |selected| {
+        Message::from(HotkeySettingsMessage::SetNewFavourite(selected))
+      })
+      .placeholder("Script")
+      .text_size(13)
+      .width(Length::Fixed(190.0)),
+      button(text("Add").size(13))
+        .on_press(Message::from(HotkeySettingsMessage::AddFavourite))
+        .padding([2, 8]),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center),
+  );
+
+  let auto_clear = row![
+    text("Clear the typing context after").size(14),
+    text_input("4500", &state.auto_clear_input)
+      .on_input(|value| Message::from(HotkeySettingsMessage::SetAutoClear(value)))
+      .size(14)
+      .padding(4)
+      .width(Length::Fixed(80.0)),
+    text("ms without typing").size(14),
+  ]
+  .spacing(8)
+  .align_y(Alignment::Center);
+  let auto_revert = checkbox(state.auto_revert_before_dot)
+    .on_toggle(|enabled| Message::from(HotkeySettingsMessage::SetAutoRevert(enabled)))
+    .label("Undo the conversion of a word followed by . (domains, emails)")
+    .text_size(14);
+  let word_completion = checkbox(state.word_completion)
+    .on_toggle(|enabled| Message::from(HotkeySettingsMessage::SetWordCompletion(enabled)))
+    .label("Learn the words typed and complete them with Tab (not in password fields)")
+    .text_size(14);
+
+  let mut content = column![header];
+  if let Some(warning) = &state.warning {
+    content = content.push(text(warning.clone()).size(13).style(|theme: &Theme| {
+      iced::widget::text::Style {
+        color: Some(theme.extended_palette().danger.base.color),
+      }
+    }));
+  }
+  content = content
+    .push(scrollable(list.padding([0, 12])).height(Length::Fill))
+    .push(auto_clear)
+    .push(auto_revert)
+    .push(word_completion);
+
+  container(content.spacing(14).padding([15, 20]).height(Length::Fill))
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
+}
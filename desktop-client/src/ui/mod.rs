@@ -1,6 +1,10 @@
+mod config_settings;
+mod hotkey_settings;
 mod notification;
+mod profiles;
 mod thread_receive;
 pub mod typing_helper;
+mod typing_practice;
 mod ui;
 pub mod version_check;
 
@@ -0,0 +1,231 @@
+use crate::data::{ScriptDisplay, get_ordered_script_list, normalize_script};
+use crate::persistent_state::{AppProfile, PersitentState};
+use iced::{
+  Alignment, Element, Length, Task,
+  theme::Theme,
+  widget::{
+    Space, button, checkbox, column, container, pick_list, row, scrollable, text, text_input,
+    toggler,
+  },
+  window,
+};
+use lipilekhika::ScriptListEnum;
+
+/// Size of the App Profiles window
+pub const WINDOW_WIDTH: f32 = 640.0;
+pub const WINDOW_HEIGHT: f32 = 420.0;
+
+/// State for the App Profiles window
+#[derive(Clone, Debug, Default)]
+pub struct ProfilesState {
+  /// copy of the profiles in the persistent state, saved back on every change
+  pub profiles: Vec<AppProfile>,
+  /// application typed in last (before the window was opened), offered for a new profile
+  pub last_app: Option<String>,
+  /// app name typed for a new profile
+  pub new_app: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum ProfilesMessage {
+  SetNewApp(String),
+  Add(String),
+  Remove(usize),
+  SetTypingEnabled(usize, bool),
+  SetScript(usize, ScriptDisplay),
+  SetNativeNumerals(usize, bool),
+  SetInherentVowel(usize, bool),
+}
+
+impl ProfilesState {
+  pub fn new(state: &PersitentState, last_app: Option<String>) -> Self {
+    Self {
+      profiles: state.profiles.clone(),
+      last_app,
+      new_app: String::new(),
+    }
+  }
+
+  /// Applies a message. Returns `true` when the profiles changed and need saving; new
+  /// profiles start from the global settings in `state`.
+  pub fn update(&mut self, message: ProfilesMessage, state: &PersitentState) -> bool {
+    match message {
+      ProfilesMessage::SetNewApp(app) => {
+        self.new_app = app;
+        return false;
+      }
+      ProfilesMessage::Add(app) => {
+        let app = app.trim();
+        if app.is_empty() || self.has_profile(app) {
+          return false;
+        }
+        self.profiles.push(AppProfile {
+          app: app.to_string(),
+          ..state.profile_for(None)
+        });
+        self.new_app.clear();
+      }
+      ProfilesMessage::Remove(idx) => {
+        if idx < self.profiles.len() {
+          self.profiles.remove(idx);
+        }
+      }
+      ProfilesMessage::SetTypingEnabled(idx, enabled) => {
+        if let Some(profile) = self.profiles.get_mut(idx) {
+          profile.typing_enabled = enabled;
+        }
+      }
+      ProfilesMessage::SetScript(idx, script) => {
+        if let Some(profile) = self.profiles.get_mut(idx) {
+          profile.script = script.script_name.to_string();
+        }
+      }
+      ProfilesMessage::SetNativeNumerals(idx, enabled) => {
+        if let Some(profile) = self.profiles.get_mut(idx) {
+          profile.native_numerals = enabled;
+        }
+      }
+      ProfilesMessage::SetInherentVowel(idx, enabled) => {
+        if let Some(profile) = self.profiles.get_mut(idx) {
+          profile.inherent_vowel = enabled;
+        }
+      }
+    }
+    true
+  }
+
+  fn has_profile(&self, app: &str) -> bool {
+    self
+      .profiles
+      .iter()
+      .any(|profile| profile.app.trim().eq_ignore_ascii_case(app))
+  }
+}
+
+/// Opens the App Profiles window
+pub fn open_profiles_window(icon: Option<window::Icon>) -> (window::Id, Task<window::Id>) {
+  let settings = window::Settings {
+    icon,
+    decorations: true,
+    resizable: true,
+    size: iced::Size::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+    position: window::Position::Centered,
+    exit_on_close_request: false,
+    min_size: Some(iced::Size::new(560.0, 300.0)),
+    ..Default::default()
+  };
+
+  window::open(settings)
+}
+
+fn muted_text<'a, Message: 'a>(content: &'a str) -> Element<'a, Message> {
+  text(content)
+    .size(12)
+    .style(|theme: &Theme| iced::widget::text::Style {
+      color: Some(theme.extended_palette().background.weak.text),
+    })
+    .into()
+}
+
+/// One row of the profile list
+fn view_profile<'a, Message: 'a + Clone + From<ProfilesMessage>>(
+  idx: usize,
+  profile: &AppProfile,
+  scripts: &[ScriptDisplay],
+) -> Element<'a, Message> {
+  let current_script = normalize_script(&profile.script)
+    .and_then(|script| scripts.iter().find(|sd| sd.script_name == script).cloned());
+
+  row![
+    text(profile.app.clone()).width(Length::Fixed(120.0)),
+    toggler(profile.typing_enabled)
+      .label("Typing")
+      .on_toggle(move |enabled| Message::from(ProfilesMessage::SetTypingEnabled(idx, enabled))),
+    pick_list(scripts.to_vec(), current_script, move |selected| {
+      Message::from(ProfilesMessage::SetScript(idx, selected))
+    })
+    .text_size(13)
+    .width(Length::Fixed(150.0)),
+    checkbox(profile.native_numerals)
+      .on_toggle(move |enabled| Message::from(ProfilesMessage::SetNativeNumerals(idx, enabled)))
+      .label("Numerals")
+      .text_size(13),
+    checkbox(profile.inherent_vowel)
+      .on_toggle(move |enabled| Message::from(ProfilesMessage::SetInherentVowel(idx, enabled)))
+      .label("Vowel")
+      .text_size(13),
+    Space::new().width(Length::Fill),
+    button(text("Remove").size(13))
+      .on_press(Message::from(ProfilesMessage::Remove(idx)))
+      .padding([2, 8]),
+  ]
+  .spacing(12)
+  .align_y(Alignment::Center)
+  .into()
+}
+
+/// Renders the App Profiles window content
+pub fn view_profiles<'a, Message: 'a + Clone + From<ProfilesMessage>>(
+  state: &ProfilesState,
+) -> Element<'a, Message> {
+  let scripts: Vec<ScriptDisplay> = get_ordered_script_list()
+    .into_iter()
+    .filter(|s| s.script_name != ScriptListEnum::Normal)
+    .collect();
+
+  let header = column![
+    text("App Profiles").size(18),
+    muted_text(
+      "Typing settings used in an app instead of the main ones. \
+       Apps are named by process (Windows, macOS) or window class (Linux)."
+    ),
+  ]
+  .spacing(4);
+
+  let mut add_row = row![
+    text_input("App name", &state.new_app)
+      .on_input(|value| Message::from(ProfilesMessage::SetNewApp(value)))
+      .on_submit(Message::from(ProfilesMessage::Add(state.new_app.clone())))
+      .size(14)
+      .padding(6)
+      .width(Length::Fixed(200.0)),
+    button(text("Add").size(14))
+      .on_press(Message::from(ProfilesMessage::Add(state.new_app.clone()))),
+  ]
+  .spacing(10)
+  .align_y(Alignment::Center);
+  if let Some(app) = &state.last_app {
+    add_row = add_row.push(
+      button(text(format!("Add {app} (typed in last)")).size(14))
+        .on_press(Message::from(ProfilesMessage::Add(app.clone()))),
+    );
+  }
+
+  let list: Element<'a, Message> = if state.profiles.is_empty() {
+    muted_text("No profiles yet: every app uses the main settings.")
+  } else {
+    scrollable(
+      column(
+        state
+          .profiles
+          .iter()
+          .enumerate()
+          .map(|(idx, profile)| view_profile(idx, profile, &scripts)),
+      )
+      .spacing(8)
+      .padding([0, 12]),
+    )
+    .height(Length::Fill)
+    .into()
+  };
+
+  container(
+    column![header, add_row, list]
+      .spacing(14)
+      .padding([15, 20])
+      .height(Length::Fill),
+  )
+  .width(Length::Fill)
+  .height(Length::Fill)
+  .into()
+}
@@ -66,6 +66,22 @@ pub fn thread_message_stream(data: &ThreadRx) -> BoxStream<UIMessage> {
                 }
               }
             }
+            ThreadMessageType::ScriptChanged => {
+              if matches!(msg.origin, ThreadMessageOrigin::KeyboardHook) {
+                let _out = output.send(UIMessage::ScriptChanged).await;
+                if _out.is_err() {
+                  break;
+                }
+              }
+            }
+            ThreadMessageType::ShowNotification(message) => {
+              if matches!(msg.origin, ThreadMessageOrigin::KeyboardHook) {
+                let _out = output.send(UIMessage::ShowNotification(message)).await;
+                if _out.is_err() {
+                  break;
+                }
+              }
+            }
             ThreadMessageType::MaximizeUI => {
               if matches!(msg.origin, ThreadMessageOrigin::Tray) {
                 let _out = output.send(UIMessage::MaximizeUI).await;
@@ -1,13 +1,20 @@
 use crate::data::{ScriptDisplay, get_ordered_script_list, script_list_to_typing_script};
+use crate::keymap::load_keymap;
+use crate::ui::typing_practice::{Mapping, Practice, search_mappings};
 use iced::{
   Alignment, Background, Color, Element, Length, Task,
   theme::Theme,
-  widget::{Space, column, container, pick_list, row, scrollable, text},
+  widget::{Space, button, column, container, pick_list, row, scrollable, text, text_input},
   window,
 };
 use iced_aw::tab_bar::{TabBar, TabLabel};
 use lipilekhika::ScriptListEnum;
-use lipilekhika::typing::{ListType, get_script_krama_data, get_script_typing_data_map};
+use lipilekhika::keymap::Keymap;
+use lipilekhika::typing::{
+  ListType, ScriptTypingDataMap, TypingContext, TypingContextOptions, get_script_krama_data,
+  get_script_typing_data_map, get_script_typing_data_map_with_keymap,
+};
+use std::collections::BTreeMap;
 
 /// Size of the Typing Helper window
 pub const WINDOW_WIDTH: f32 = 700.0;
@@ -19,14 +26,34 @@ pub enum TypingHelperTab {
   #[default]
   TypingMap,
   CompareScripts,
+  KeyHints,
+  Search,
+  Practice,
 }
 
 /// State for the Typing Helper window
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct TypingHelperState {
   pub current_script: ScriptListEnum,
   pub active_tab: TypingHelperTab,
   pub compare_script: Option<ScriptDisplay>,
+  /// user keymap of `current_script`, shown in the typing map
+  pub keymap: Option<Keymap>,
+  /// typing map of `current_script` with `keymap` applied, resolved when either changes
+  typing_map: ScriptTypingDataMap,
+  /// options of the active typing context, so that the key hints are the keys that work
+  options: TypingContextOptions,
+  /// text pasted in the Key Hints tab
+  pub hint_text: String,
+  /// each word of `hint_text` with the keys that type it (`None` if it can't be typed)
+  pub key_hints: Vec<(String, Option<String>)>,
+  /// keys or characters typed in the Search tab
+  pub search_query: String,
+  pub search_results: Vec<Mapping>,
+  /// copy of the pinned characters in the persistent state (by script), saved back on every
+  /// change
+  pub pinned: BTreeMap<String, Vec<String>>,
+  pub practice: Practice,
 }
 
 impl Default for TypingHelperState {
@@ -41,11 +68,102 @@ impl TypingHelperState {
       .into_iter()
       .find(|s| s.script_name == ScriptListEnum::Romanized);
 
+    let keymap = load_keymap(script_list_to_typing_script(script));
+    let options = TypingContextOptions::default();
     Self {
       current_script: script,
       active_tab: TypingHelperTab::TypingMap,
       compare_script,
+      typing_map: typing_data(script, keymap.as_ref()),
+      practice: Practice::new(script, options.clone(), keymap.as_ref()),
+      keymap,
+      options,
+      hint_text: String::new(),
+      key_hints: Vec::new(),
+      search_query: String::new(),
+      search_results: Vec::new(),
+      pinned: BTreeMap::new(),
+    }
+  }
+
+  /// Switches the shown script, reloading its user keymap.
+  pub fn set_script(&mut self, script: ScriptListEnum) {
+    if script != self.current_script {
+      self.practice = Practice::new(script, self.options.clone(), None);
     }
+    self.current_script = script;
+    self.reload_keymap();
+  }
+
+  /// Reloads the user keymap of the shown script, after the keymaps changed.
+  pub fn reload_keymap(&mut self) {
+    self.keymap = load_keymap(script_list_to_typing_script(self.current_script));
+    self.typing_map = typing_data(self.current_script, self.keymap.as_ref());
+    self
+      .practice
+      .set_context(self.options.clone(), self.keymap.as_ref());
+    self.update_key_hints();
+    self.update_search_results();
+  }
+
+  pub fn set_search_query(&mut self, query: String) {
+    self.search_query = query;
+    self.update_search_results();
+  }
+
+  fn update_search_results(&mut self) {
+    self.search_results = search_mappings(&self.typing_map, &self.search_query);
+  }
+
+  /// Characters pinned for the current script
+  pub fn pinned_chars(&self) -> &[String] {
+    self
+      .pinned
+      .get(self.current_script.as_ref())
+      .map_or(&[], Vec::as_slice)
+  }
+
+  /// Pins a character of the current script, or unpins it.
+  pub fn toggle_pin(&mut self, char_text: String) {
+    let pinned = self
+      .pinned
+      .entry(self.current_script.as_ref().to_string())
+      .or_default();
+    match pinned.iter().position(|pinned| *pinned == char_text) {
+      Some(idx) => {
+        pinned.remove(idx);
+      }
+      None => pinned.push(char_text),
+    }
+    self.pinned.retain(|_, pinned| !pinned.is_empty());
+  }
+
+  /// Takes the options of the active typing context (they may differ per app).
+  pub fn set_typing_options(&mut self, options: TypingContextOptions) {
+    self.options = options;
+    self
+      .practice
+      .set_context(self.options.clone(), self.keymap.as_ref());
+    self.update_key_hints();
+  }
+
+  pub fn set_hint_text(&mut self, hint_text: String) {
+    self.hint_text = hint_text;
+    self.update_key_hints();
+  }
+
+  fn update_key_hints(&mut self) {
+    let script = script_list_to_typing_script(self.current_script);
+    let mut ctx = TypingContext::new(script, Some(self.options.clone()));
+    // An invalid keymap is not applied while typing either
+    if let Some(keymap) = &self.keymap {
+      let _ = ctx.set_keymap(Some(keymap));
+    }
+    self.key_hints = self
+      .hint_text
+      .split_whitespace()
+      .map(|word| (word.to_string(), ctx.typing_sequence_for(word)))
+      .collect();
   }
 }
 
@@ -229,16 +347,22 @@ fn section<'a, Message: 'a + Clone>(
     .into()
 }
 
+/// Typing map of a script with the user keymap applied
+fn typing_data(current_script: ScriptListEnum, keymap: Option<&Keymap>) -> ScriptTypingDataMap {
+  let script = script_list_to_typing_script(current_script);
+  // An invalid keymap is not applied while typing either, so show the built-in keys.
+  keymap
+    .and_then(|keymap| get_script_typing_data_map_with_keymap(script, keymap).ok())
+    .unwrap_or_else(|| get_script_typing_data_map(script))
+}
+
 /// Renders the Typing Map tab content
 fn view_typing_map<'a, Message: 'a + Clone>(
-  current_script: ScriptListEnum,
+  typing_data: &ScriptTypingDataMap,
 ) -> Element<'a, Message> {
-  let script = script_list_to_typing_script(current_script);
-  let typing_data = get_script_typing_data_map(script);
-
   let svara_items = filter_items_by_type(typing_data.common_krama_map.clone(), "svara");
   let vyanjana_items = filter_items_by_type(typing_data.common_krama_map.clone(), "vyanjana");
-  let anya_items = filter_items_by_type(typing_data.common_krama_map, "anya");
+  let anya_items = filter_items_by_type(typing_data.common_krama_map.clone(), "anya");
 
   let svara_cards: Vec<Element<'a, Message>> = svara_items
     .into_iter()
@@ -257,9 +381,9 @@ fn view_typing_map<'a, Message: 'a + Clone>(
 
   let script_specific_cards: Vec<Element<'a, Message>> = typing_data
     .script_specific_krama_map
-    .into_iter()
+    .iter()
     .filter(|(t, _, _)| !t.is_empty())
-    .map(|(text, _, mappings)| typing_map_card(text, mappings))
+    .map(|(text, _, mappings)| typing_map_card(text.clone(), mappings.clone()))
     .collect();
 
   let content = column![
@@ -379,12 +503,247 @@ fn view_compare_scripts<'a, Message: 'a + Clone + From<TypingHelperMessage>>(
   column![header, scrollable_content].spacing(10).into()
 }
 
+/// Renders the Key Hints tab content: the keys for each word of the pasted text
+fn view_key_hints<'a, Message: 'a + Clone + From<TypingHelperMessage>>(
+  state: &TypingHelperState,
+) -> Element<'a, Message> {
+  let input = text_input("Paste text to see how to type it", &state.hint_text)
+    .on_input(|value| Message::from(TypingHelperMessage::SetHintText(value)))
+    .size(16)
+    .padding(8);
+
+  let content: Element<'a, Message> = if state.key_hints.is_empty() {
+    container(
+      text("Keys are shown for each word, with the default typing options")
+        .size(14)
+        .color(Color::from_rgb(0.6, 0.6, 0.6)),
+    )
+    .center_x(Length::Fill)
+    .padding(20)
+    .into()
+  } else {
+    let cards: Vec<Element<'a, Message>> = state
+      .key_hints
+      .iter()
+      .map(|(word, keys)| {
+        let keys = keys.clone().unwrap_or_else(|| "can't be typed".to_string());
+        typing_map_card(word.clone(), vec![keys])
+      })
+      .collect();
+
+    // Create rows of 3 cards each
+    let mut rows: Vec<Element<'a, Message>> = vec![];
+    let mut current_row: Vec<Element<'a, Message>> = vec![];
+
+    for card in cards {
+      current_row.push(card);
+      if current_row.len() == 3 {
+        rows.push(row(std::mem::take(&mut current_row)).spacing(8).into());
+      }
+    }
+
+    if !current_row.is_empty() {
+      while current_row.len() < 3 {
+        current_row.push(Space::new().width(Length::Fill).into());
+      }
+      rows.push(row(current_row).spacing(8).into());
+    }
+
+    column(rows).spacing(8).into()
+  };
+
+  let scrollable_content = scrollable(container(content).padding([10, 10]))
+    .height(Length::Fill)
+    .width(Length::Fill);
+
+  column![input, scrollable_content].spacing(10).into()
+}
+
+/// A typing map card with a button to pin or unpin it
+fn pinnable_card<'a, Message: 'a + Clone + From<TypingHelperMessage>>(
+  char_text: String,
+  mappings: Vec<String>,
+  pinned: bool,
+) -> Element<'a, Message> {
+  let label = if pinned { "Unpin" } else { "Pin" };
+  column![
+    typing_map_card(char_text.clone(), mappings),
+    button(text(label).size(11))
+      .on_press(Message::from(TypingHelperMessage::TogglePin(char_text)))
+      .padding([1, 8]),
+  ]
+  .spacing(4)
+  .width(Length::Fill)
+  .into()
+}
+
+/// Renders the Search tab content: the pinned characters and the ones found for the query
+fn view_search<'a, Message: 'a + Clone + From<TypingHelperMessage>>(
+  state: &TypingHelperState,
+) -> Element<'a, Message> {
+  let input = text_input(
+    "Type keys (eg. kSh) or paste a character",
+    &state.search_query,
+  )
+  .on_input(|value| Message::from(TypingHelperMessage::SetSearch(value)))
+  .size(16)
+  .padding(8);
+
+  let pinned = state.pinned_chars();
+  let pinned_cards: Vec<Element<'a, Message>> = if pinned.is_empty() {
+    Vec::new()
+  } else {
+    let data = &state.typing_map;
+    let mappings: Vec<_> = data
+      .common_krama_map
+      .iter()
+      .chain(&data.script_specific_krama_map)
+      .collect();
+    pinned
+      .iter()
+      .map(|char_text| {
+        let keys = mappings
+          .iter()
+          .find(|(text, _, _)| text == char_text)
+          .map(|(_, _, keys)| keys.clone())
+          .unwrap_or_default();
+        pinnable_card(char_text.clone(), keys, true)
+      })
+      .collect()
+  };
+
+  let results: Element<'a, Message> = if state.search_query.trim().is_empty() {
+    container(
+      text("Search the typing map by keys or by character; pin the ones to keep at hand")
+        .size(14)
+        .color(Color::from_rgb(0.6, 0.6, 0.6)),
+    )
+    .center_x(Length::Fill)
+    .padding(20)
+    .into()
+  } else if state.search_results.is_empty() {
+    container(
+      text("Nothing found")
+        .size(14)
+        .color(Color::from_rgb(0.6, 0.6, 0.6)),
+    )
+    .center_x(Length::Fill)
+    .padding(20)
+    .into()
+  } else {
+    let cards = state
+      .search_results
+      .iter()
+      .map(|(char_text, keys)| {
+        let is_pinned = pinned.contains(char_text);
+        pinnable_card(char_text.clone(), keys.clone(), is_pinned)
+      })
+      .collect();
+    section("Results", cards)
+  };
+
+  let content = column![section("Pinned", pinned_cards), results].spacing(20);
+  let scrollable_content = scrollable(container(content).padding([10, 10]))
+    .height(Length::Fill)
+    .width(Length::Fill);
+
+  column![input, scrollable_content].spacing(10).into()
+}
+
+/// Renders the Practice tab content: a word to type with the keys of the current script
+fn view_practice<'a, Message: 'a + Clone + From<TypingHelperMessage>>(
+  state: &TypingHelperState,
+) -> Element<'a, Message> {
+  let practice = &state.practice;
+  let muted = Color::from_rgb(0.6, 0.6, 0.6);
+
+  let input = text_input("Type the keys, Enter to check", &practice.keys)
+    .on_input(|value| Message::from(TypingHelperMessage::SetPracticeKeys(value)))
+    .on_submit(Message::from(TypingHelperMessage::SubmitPractice))
+    .size(16)
+    .padding(8);
+
+  let last: Element<'a, Message> = match &practice.last {
+    None => Space::new().height(0).into(),
+    Some(last) if last.accuracy >= 1.0 => text(format!("Correct: {}", last.word))
+      .size(14)
+      .color(Color::from_rgb(0.3, 0.7, 0.4))
+      .into(),
+    Some(last) => {
+      let keys = last
+        .expected_keys
+        .as_ref()
+        .map(|keys| format!(", typed with {keys}"))
+        .unwrap_or_default();
+      text(format!(
+        "{} instead of {} ({:.0}% accurate){keys}",
+        if last.typed.is_empty() {
+          "-"
+        } else {
+          last.typed.as_str()
+        },
+        last.word,
+        last.accuracy * 100.0,
+      ))
+      .size(14)
+      .color(Color::from_rgb(0.85, 0.5, 0.3))
+      .into()
+    }
+  };
+
+  let score = match practice.average_accuracy() {
+    Some(average) => format!(
+      "{} of {} words right, {:.0}% accuracy",
+      practice.correct,
+      practice.attempts,
+      average * 100.0
+    ),
+    None => "Type the word shown, with the keys of the typing map".to_string(),
+  };
+
+  let content = column![
+    text(practice.word.clone()).size(40),
+    input,
+    text(if practice.output.is_empty() {
+      " ".to_string()
+    } else {
+      practice.output.clone()
+    })
+    .size(28),
+    row![
+      button(text("Check").size(13))
+        .on_press(Message::from(TypingHelperMessage::SubmitPractice))
+        .padding([2, 10]),
+      button(text("Skip").size(13))
+        .on_press(Message::from(TypingHelperMessage::SkipPractice))
+        .padding([2, 10]),
+    ]
+    .spacing(10),
+    last,
+    text(score).size(13).color(muted),
+  ]
+  .spacing(14)
+  .align_x(Alignment::Center)
+  .padding([20, 10]);
+
+  scrollable(container(content).center_x(Length::Fill))
+    .height(Length::Fill)
+    .width(Length::Fill)
+    .into()
+}
+
 /// Messages for the Typing Helper
 #[derive(Clone, Debug)]
 pub enum TypingHelperMessage {
   SetScript(ScriptDisplay),
   SetTab(TypingHelperTab),
   SetCompareScript(ScriptDisplay),
+  SetHintText(String),
+  SetSearch(String),
+  TogglePin(String),
+  SetPracticeKeys(String),
+  SubmitPractice,
+  SkipPractice,
 }
 
 /// Renders the full Typing Helper window content
@@ -432,13 +791,28 @@ pub fn view_typing_helper<'a, Message: 'a + Clone + From<TypingHelperMessage>>(
       TypingHelperTab::CompareScripts,
       TabLabel::Text("Compare Scripts".to_string()),
     )
+    .push(
+      TypingHelperTab::KeyHints,
+      TabLabel::Text("Key Hints".to_string()),
+    )
+    .push(
+      TypingHelperTab::Search,
+      TabLabel::Text("Search".to_string()),
+    )
+    .push(
+      TypingHelperTab::Practice,
+      TabLabel::Text("Practice".to_string()),
+    )
     .set_active_tab(&state.active_tab)
     .width(Length::Fill);
 
   // Tab content
   let tab_content: Element<'a, Message> = match state.active_tab {
-    TypingHelperTab::TypingMap => view_typing_map(state.current_script),
+    TypingHelperTab::TypingMap => view_typing_map(&state.typing_map),
     TypingHelperTab::CompareScripts => view_compare_scripts(state, all_scripts),
+    TypingHelperTab::KeyHints => view_key_hints(state),
+    TypingHelperTab::Search => view_search(state),
+    TypingHelperTab::Practice => view_practice(state),
   };
 
   container(
@@ -0,0 +1,425 @@
+//! Interactive parts of the typing helper: searching the typing map by keys or by
+//! character, and practice words typed through a live `TypingContext`.
+
+use crate::data::script_list_to_typing_script;
+use lipilekhika::keymap::Keymap;
+use lipilekhika::typing::{ScriptTypingDataMap, TypingContext, TypingContextOptions, TypingDiff};
+use lipilekhika::{Script, ScriptListEnum};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Results shown for a search, enough for a few screens of cards
+const MAX_SEARCH_RESULTS: usize = 48;
+
+/// Practice words, shown in the script practiced
+const PRACTICE_WORDS: &[&str] = &[
+  "नमस्ते",
+  "भारत",
+  "संस्कृतम्",
+  "विद्या",
+  "पुस्तक",
+  "सूर्य",
+  "चन्द्र",
+  "जल",
+  "अग्नि",
+  "वायु",
+  "पृथ्वी",
+  "आकाश",
+  "मित्र",
+  "गुरु",
+  "शिष्य",
+  "धर्म",
+  "कर्म",
+  "ज्ञान",
+  "शान्ति",
+  "सत्य",
+  "प्रकाश",
+  "नदी",
+  "पर्वत",
+  "वृक्ष",
+  "पुष्प",
+  "फल",
+  "गृह",
+  "ग्राम",
+  "नगर",
+  "राजा",
+  "माता",
+  "पिता",
+  "भगिनी",
+  "कृष्ण",
+  "लक्ष्मी",
+  "सरस्वती",
+  "गणेश",
+  "हिमालय",
+  "आनन्द",
+  "उत्सव",
+];
+
+/// A character of the typing map with the keys that type it
+pub type Mapping = (String, Vec<String>);
+
+/// Mappings of `data` matching `query`. Native text finds itself, then the characters it is
+/// made of (in order) and the ones containing it; Roman keys find the characters typed by
+/// them, then the ones typed by longer keys starting with them.
+pub fn search_mappings(data: &ScriptTypingDataMap, query: &str) -> Vec<Mapping> {
+  let query = query.trim();
+  if query.is_empty() {
+    return Vec::new();
+  }
+  let mappings = data
+    .common_krama_map
+    .iter()
+    .chain(&data.script_specific_krama_map)
+    .filter(|(text, _, keys)| !text.is_empty() && !keys.is_empty());
+
+  // (rank, mapping): lower ranks first, in typing map order otherwise
+  let mut found: Vec<((u8, usize), Mapping)> = Vec::new();
+  for (text, _, keys) in mappings {
+    let rank = if !query.is_ascii() {
+      if text == query {
+        Some((0, 0))
+      } else if let Some(position) = query.find(text.as_str()) {
+        Some((1, position))
+      } else if text.contains(query) {
+        Some((2, 0))
+      } else {
+        None
+      }
+    } else if keys.iter().any(|key| key == query) {
+      Some((0, 0))
+    } else if keys.iter().any(|key| key.starts_with(query)) {
+      Some((1, 0))
+    } else {
+      None
+    };
+    // the same character can be in both maps
+    if let Some(rank) = rank
+      && !found.iter().any(|(_, (found_text, _))| found_text == text)
+    {
+      found.push((rank, (text.clone(), keys.clone())));
+    }
+  }
+  found.sort_by_key(|(rank, _)| *rank);
+  found
+    .into_iter()
+    .take(MAX_SEARCH_RESULTS)
+    .map(|(_, mapping)| mapping)
+    .collect()
+}
+
+/// How close `typed` is to `expected`, from 0 to 1: one minus their edit distance (in
+/// characters) over the length of the longer one.
+pub fn accuracy(expected: &str, typed: &str) -> f32 {
+  let expected: Vec<char> = expected.chars().collect();
+  let typed: Vec<char> = typed.chars().collect();
+  let longest = expected.len().max(typed.len());
+  if longest == 0 {
+    return 1.0;
+  }
+  // Levenshtein distance, one row at a time
+  let mut row: Vec<usize> = (0..=typed.len()).collect();
+  for (i, expected_char) in expected.iter().enumerate() {
+    let mut diagonal = row[0];
+    row[0] = i + 1;
+    for (j, typed_char) in typed.iter().enumerate() {
+      let substitution = diagonal + usize::from(expected_char != typed_char);
+      diagonal = row[j + 1];
+      row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+    }
+  }
+  1.0 - row[typed.len()] as f32 / longest as f32
+}
+
+fn apply_diff(output: &mut String, diff: &TypingDiff) {
+  for _ in 0..diff.to_delete_chars_count {
+    output.pop();
+  }
+  output.push_str(&diff.diff_add_text);
+}
+
+/// Result of a practice word
+#[derive(Debug, Clone, PartialEq)]
+pub struct PracticeResult {
+  pub word: String,
+  pub typed: String,
+  pub accuracy: f32,
+  /// keys that type the word, shown when it was missed
+  pub expected_keys: Option<String>,
+}
+
+/// The practice pane: a word to type, the keys typed for it so far and the session score.
+#[derive(Debug)]
+pub struct Practice {
+  script: Script,
+  pub word: String,
+  /// keys typed, as in the input box
+  pub keys: String,
+  /// what the keys typed through `ctx`
+  pub output: String,
+  /// context with the options and keymap of the one typing in the apps
+  ctx: TypingContext,
+  /// state of the word picker
+  seed: u64,
+  pub last: Option<PracticeResult>,
+  pub attempts: u32,
+  pub correct: u32,
+  total_accuracy: f32,
+}
+
+/// Typing context of `script` with `options` and `keymap`; an invalid keymap is not
+/// applied while typing either.
+fn practice_context(
+  script: Script,
+  options: TypingContextOptions,
+  keymap: Option<&Keymap>,
+) -> TypingContext {
+  let mut ctx = TypingContext::new(script, Some(options));
+  if keymap.is_some() {
+    let _ = ctx.set_keymap(keymap);
+  }
+  ctx
+}
+
+impl Practice {
+  pub fn new(
+    script: ScriptListEnum,
+    options: TypingContextOptions,
+    keymap: Option<&Keymap>,
+  ) -> Self {
+    let seed = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |time| time.as_nanos() as u64);
+    Self::with_seed(script, options, keymap, seed)
+  }
+
+  fn with_seed(
+    script: ScriptListEnum,
+    options: TypingContextOptions,
+    keymap: Option<&Keymap>,
+    seed: u64,
+  ) -> Self {
+    let script = script_list_to_typing_script(script);
+    let mut practice = Self {
+      script,
+      word: String::new(),
+      keys: String::new(),
+      output: String::new(),
+      ctx: practice_context(script, options, keymap),
+      // xorshift needs a non zero state
+      seed: seed | 1,
+      last: None,
+      attempts: 0,
+      correct: 0,
+      total_accuracy: 0.0,
+    };
+    practice.next_word();
+    practice
+  }
+
+  /// Types with other options or keymap from now on, keeping the score. The word is
+  /// changed if it can no longer be typed.
+  pub fn set_context(&mut self, options: TypingContextOptions, keymap: Option<&Keymap>) {
+    self.ctx = practice_context(self.script, options, keymap);
+    if self.ctx.typing_sequence_for(&self.word).is_none() {
+      self.next_word();
+    } else {
+      self.clear_input();
+    }
+  }
+
+  fn random(&mut self) -> u64 {
+    self.seed ^= self.seed << 13;
+    self.seed ^= self.seed >> 7;
+    self.seed ^= self.seed << 17;
+    self.seed
+  }
+
+  /// Picks another word (not the current one) that can be typed in the script.
+  fn next_word(&mut self) {
+    let words: Vec<String> = PRACTICE_WORDS
+      .iter()
+      .map(|word| lipilekhika::transliterate(word, Script::Devanagari, self.script, None))
+      .map(|word| word.into_owned())
+      .filter(|word| *word != self.word)
+      .collect();
+    for _ in 0..words.len() {
+      let word = &words[self.random() as usize % words.len()];
+      if self.ctx.typing_sequence_for(word).is_some() {
+        self.word = word.clone();
+        break;
+      }
+    }
+    self.clear_input();
+  }
+
+  fn clear_input(&mut self) {
+    self.keys.clear();
+    self.output.clear();
+    self.ctx.clear_context();
+  }
+
+  /// Takes the new content of the input box: added keys and a Backspace go through the
+  /// typing context like in an app, other edits type the keys again.
+  pub fn set_keys(&mut self, keys: String) {
+    if let Some(added) = keys.strip_prefix(self.keys.as_str()) {
+      let added = added.to_string();
+      self.type_keys(&added);
+    } else if self.keys.strip_suffix(|_: char| true) == Some(keys.as_str()) {
+      let diff = self.ctx.take_backspace();
+      if diff.to_delete_chars_count > 0 || !diff.diff_add_text.is_empty() {
+        apply_diff(&mut self.output, &diff);
+      } else {
+        // the key was no longer in the context (eg. after a space)
+        self.retype(&keys);
+      }
+    } else {
+      self.retype(&keys);
+    }
+    self.keys = keys;
+  }
+
+  fn type_keys(&mut self, keys: &str) {
+    for key in keys.chars() {
+      let diff = self.ctx.take_key_input_char(key);
+      apply_diff(&mut self.output, &diff);
+    }
+  }
+
+  fn retype(&mut self, keys: &str) {
+    self.ctx.clear_context();
+    self.output.clear();
+    self.type_keys(keys);
+  }
+
+  /// Scores the word typed and moves to the next one.
+  pub fn submit(&mut self) {
+    if self.keys.trim().is_empty() {
+      return;
+    }
+    let typed = self.output.trim().to_string();
+    let accuracy = accuracy(&self.word, &typed);
+    self.attempts += 1;
+    self.total_accuracy += accuracy;
+    if typed == self.word {
+      self.correct += 1;
+    }
+    self.last = Some(PracticeResult {
+      expected_keys: (typed != self.word)
+        .then(|| self.ctx.typing_sequence_for(&self.word))
+        .flatten(),
+      word: self.word.clone(),
+      typed,
+      accuracy,
+    });
+    self.next_word();
+  }
+
+  /// Moves to another word without scoring this one.
+  pub fn skip(&mut self) {
+    self.next_word();
+  }
+
+  /// Mean accuracy of the words submitted, from 0 to 1
+  pub fn average_accuracy(&self) -> Option<f32> {
+    (self.attempts > 0).then(|| self.total_accuracy / self.attempts as f32)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lipilekhika::typing::get_script_typing_data_map;
+
+  fn search(query: &str) -> Vec<String> {
+    search_mappings(&get_script_typing_data_map(Script::Devanagari), query)
+      .into_iter()
+      .map(|(text, _)| text)
+      .collect()
+  }
+
+  #[test]
+  fn search_finds_characters_by_keys_or_by_text() {
+    let found = search("kh");
+    assert_eq!(found.first().map(String::as_str), Some("ख"));
+    // characters of longer keys come after
+    assert!(search("k").iter().position(|text| text == "ख").unwrap() > 0);
+
+    // the characters of the text, in order
+    let found = search("क्ष");
+    assert_eq!(found[..3], ["क", "्", "ष"]);
+    assert_eq!(search("ख").first().map(String::as_str), Some("ख"));
+
+    assert!(search("  ").is_empty());
+    assert!(search("qqqq").is_empty());
+  }
+
+  #[test]
+  fn accuracy_is_the_edit_distance_over_the_length() {
+    assert_eq!(accuracy("नमस्ते", "नमस्ते"), 1.0);
+    assert_eq!(accuracy("abcd", "abed"), 0.75);
+    assert_eq!(accuracy("abcd", ""), 0.0);
+    assert_eq!(accuracy("ab", "abcd"), 0.5);
+    assert_eq!(accuracy("", ""), 1.0);
+  }
+
+  #[test]
+  fn practice_words_are_typed_through_the_context() {
+    let mut practice = Practice::with_seed(
+      ScriptListEnum::Devanagari,
+      TypingContextOptions::default(),
+      None,
+      7,
+    );
+    assert!(!practice.word.is_empty());
+    practice.word = "नमस्ते".to_string();
+
+    for keys in ["n", "na", "nam", "nama", "namas", "namast", "namaste"] {
+      practice.set_keys(keys.to_string());
+    }
+    assert_eq!(practice.output, "नमस्ते");
+    // a Backspace steps back a key
+    practice.set_keys("namast".to_string());
+    assert_eq!(practice.output, "नमस्त्");
+    // other edits type the keys again
+    practice.set_keys("namaste".to_string());
+    practice.set_keys("nmaste".to_string());
+    assert_eq!(practice.output, "न्मस्ते");
+    practice.set_keys("namaste".to_string());
+
+    practice.submit();
+    assert_eq!(practice.correct, 1);
+    assert_eq!(practice.last.as_ref().unwrap().accuracy, 1.0);
+    assert_ne!(practice.word, "नमस्ते");
+    assert!(practice.keys.is_empty() && practice.output.is_empty());
+
+    practice.word = "राम".to_string();
+    practice.set_keys("ram".to_string());
+    practice.submit();
+    let last = practice.last.clone().unwrap();
+    assert_eq!(last.typed, "रम्");
+    assert!(last.accuracy < 1.0);
+    assert_eq!(last.expected_keys.as_deref(), Some("rAma"));
+    assert_eq!((practice.attempts, practice.correct), (2, 1));
+    assert!(practice.average_accuracy().unwrap() < 1.0);
+  }
+
+  #[test]
+  fn practice_types_with_the_keymap_and_options() {
+    let mut practice = Practice::with_seed(
+      ScriptListEnum::Devanagari,
+      TypingContextOptions::default(),
+      None,
+      7,
+    );
+    let keymap = Keymap::from_toml_str("q = \"ष\"").unwrap();
+    practice.set_context(TypingContextOptions::default(), Some(&keymap));
+    practice.word = "षा".to_string();
+    practice.set_keys("qA".to_string());
+    assert_eq!(practice.output, "षा");
+
+    practice.word = "षा".to_string();
+    practice.set_keys("q".to_string());
+    practice.submit();
+    assert_eq!(practice.last.unwrap().expected_keys.as_deref(), Some("qA"));
+    assert_eq!(practice.attempts, 1);
+  }
+}
@@ -1,14 +1,27 @@
 use crate::data::{
-  ScriptDisplay, get_ordered_script_list, script_from_normalized_name, script_list_to_typing_script,
+  ScriptDisplay, get_ordered_script_list, layouts_for_script, script_from_normalized_name,
+  script_list_to_typing_script,
+};
+use crate::hotkeys::{self, Hotkey, HotkeyAction, HotkeyKey, HotkeyModifiers};
+use crate::keymap::{self, new_typing_context};
+use crate::ui::config_settings::{
+  ConfigChange, ConfigSettingsMessage, ConfigSettingsState, open_config_settings_window,
+  view_config_settings,
+};
+use crate::ui::hotkey_settings::{
+  HotkeySettingsMessage, HotkeySettingsState, open_hotkey_settings_window, view_hotkey_settings,
 };
 use crate::ui::notification::{self, NotificationConfig};
+use crate::ui::profiles::{ProfilesMessage, ProfilesState, open_profiles_window, view_profiles};
 use crate::ui::thread_receive::{ThreadRx, thread_message_stream};
 use crate::ui::typing_helper::{
   TypingHelperMessage, TypingHelperState, TypingHelperTab, open_typing_helper_window,
   view_typing_helper,
 };
 use crate::ui::version_check::{self, UpdateResult, VersionCheckResult};
+use crate::window_context;
 use crate::{AppState, ThreadMessage, ThreadMessageOrigin, ThreadMessageType};
+use crate::{history, posthog, privacy};
 use crossbeam_channel::{Receiver, Sender};
 use dark_light::detect;
 use iced::Padding;
@@ -16,13 +29,14 @@ use iced::theme::Theme;
 use iced::widget::{Space, button, center, mouse_area, opaque, stack};
 use iced::{
   Element, Length, Subscription, Task,
-  keyboard::{self, Key, key::Named},
+  keyboard::{self, Key, Modifiers, key::Named},
   mouse,
   widget::{checkbox, column, container, image, pick_list, row, svg, text, toggler, tooltip},
   window,
 };
 use iced_aw::menu::{Item, Menu, MenuBar};
 use lipilekhika::ScriptListEnum;
+use lipilekhika::layout::TypingLayout;
 use lipilekhika::typing::TypingContext;
 use std::env;
 use std::path::PathBuf;
@@ -32,8 +46,11 @@ use std::sync::{Arc, Mutex, atomic::Ordering};
 #[derive(Clone, Debug)]
 pub enum UIMessage {
   ToggleTypingMode(bool),
-  KeyboardToggleTypingMode, // Toggle from keyboard shortcut (triggers notification)
+  HotkeyPressed(Hotkey), // Hotkey pressed while a window of the app has focus
+  ScriptChanged,         // Script switched with a hotkey (triggers notification)
+  ShowNotification(String),
   SetScript(ScriptDisplay),
+  SetLayout(TypingLayout),
   TriggerTypingNotification,
   NotificationOpened(window::Id),
   CloseNotification(window::Id),
@@ -57,6 +74,22 @@ pub enum UIMessage {
   OpenTypingHelperCompare,
   TypingHelperOpened(window::Id),
   TypingHelper(TypingHelperMessage),
+  // App profiles
+  OpenProfiles,
+  ProfilesOpened(window::Id),
+  Profiles(ProfilesMessage),
+  // Hotkeys and auto-clear timeout
+  OpenHotkeySettings,
+  HotkeySettingsOpened(window::Id),
+  HotkeySettings(HotkeySettingsMessage),
+  // Named profiles, options and import/export
+  OpenConfigSettings,
+  ConfigSettingsOpened(window::Id),
+  ConfigSettings(ConfigSettingsMessage),
+  // First-run question about analytics and update checks
+  SetPromptAnalytics(bool),
+  SetPromptUpdateCheck(bool),
+  AnswerPrivacyPrompt,
   // Version check
   VersionCheckResult(VersionCheckResult),
   UpdateApp,
@@ -72,6 +105,31 @@ impl From<TypingHelperMessage> for UIMessage {
   }
 }
 
+impl From<ProfilesMessage> for UIMessage {
+  fn from(msg: ProfilesMessage) -> Self {
+    UIMessage::Profiles(msg)
+  }
+}
+
+impl From<HotkeySettingsMessage> for UIMessage {
+  fn from(msg: HotkeySettingsMessage) -> Self {
+    UIMessage::HotkeySettings(msg)
+  }
+}
+
+impl From<ConfigSettingsMessage> for UIMessage {
+  fn from(msg: ConfigSettingsMessage) -> Self {
+    UIMessage::ConfigSettings(msg)
+  }
+}
+
+/// Answers of the first-run question, before it is confirmed
+#[derive(Clone, Copy, Debug)]
+struct PrivacyPrompt {
+  analytics: bool,
+  update_check: bool,
+}
+
 struct App {
   global_app_state: Arc<AppState>,
   rx: Arc<Mutex<Receiver<ThreadMessage>>>,
@@ -91,6 +149,17 @@ struct App {
   // Typing Helper window state
   typing_helper_window: Option<window::Id>,
   typing_helper_state: TypingHelperState,
+  // App Profiles window state
+  profiles_window: Option<window::Id>,
+  profiles_state: ProfilesState,
+  // Hotkeys window state
+  hotkey_settings_window: Option<window::Id>,
+  hotkey_settings_state: HotkeySettingsState,
+  // Settings window state
+  config_settings_window: Option<window::Id>,
+  config_settings_state: ConfigSettingsState,
+  // First-run question, None once answered
+  privacy_prompt: Option<PrivacyPrompt>,
   // Version check result
   version_check_result: Option<VersionCheckResult>,
   update_notification_dismissed: bool,
@@ -111,11 +180,70 @@ impl App {
     })
   }
 
+  /// Looks for a new version
+  fn check_for_updates() -> Task<UIMessage> {
+    Task::future(async {
+      let result = version_check::check_for_updates().await;
+      UIMessage::VersionCheckResult(result)
+    })
+  }
+
+  /// Shows `message` in the notification window, replacing the current one
+  fn show_notification(&mut self, message: String) -> Task<UIMessage> {
+    self.notification_message = message;
+
+    // Close existing notification if any, then open new one
+    let close_task = if let Some(old_id) = self.notification_window.take() {
+      window::close(old_id)
+    } else {
+      Task::none()
+    };
+
+    let (new_id, open_task) = notification::open_notification_window();
+    self.notification_window = Some(new_id);
+
+    // Start timeout timer
+    let timeout_task = notification::notification_timeout(
+      &self.notification_config,
+      UIMessage::CloseNotification(new_id),
+    );
+
+    Task::batch([
+      close_task,
+      open_task.map(UIMessage::NotificationOpened),
+      timeout_task,
+    ])
+  }
+
+  /// Applies settings replaced as a whole (a named profile or an import) to the typing
+  /// context, the keymaps, the tray and the open windows.
+  fn reload_settings(&mut self) {
+    let (script, options) = {
+      let state = self.global_app_state.persitent_state.lock().unwrap();
+      keymap::set_config_keymaps(&state.keymaps);
+      self.hotkey_settings_state = HotkeySettingsState::new(&state);
+      self.profiles_state = ProfilesState::new(&state, self.profiles_state.last_app.clone());
+      self.typing_helper_state.pinned = state.pinned_chars.clone();
+      (state.typing_script(), state.typing_options())
+    };
+    self.typing_helper_state.reload_keymap();
+    self.stop_hotkey_recording();
+    let new_context = new_typing_context(script, Some(options));
+    *self.global_app_state.typing_context.lock().unwrap() = new_context;
+    history::forget(&self.global_app_state);
+    window_context::reload_profiles(&self.global_app_state);
+    let _ = self.tx_tray.lock().unwrap().send(ThreadMessage {
+      origin: ThreadMessageOrigin::UI,
+      msg: ThreadMessageType::RerenderTray,
+    });
+  }
+
   fn new(
     app_state: Arc<AppState>,
     rx: Arc<Mutex<Receiver<ThreadMessage>>>,
     tx_tray: Arc<Mutex<Sender<ThreadMessage>>>,
     icon: window::Icon,
+    startup_notice: Option<String>,
   ) -> (Self, Task<UIMessage>) {
     // Open main window since daemon mode doesn't create one automatically
     let (main_id, main_open_task) = window::open(window::Settings {
@@ -127,31 +255,58 @@ impl App {
       ..Default::default()
     });
 
+    let (privacy_prompt, update_check) = {
+      let state = app_state.persitent_state.lock().unwrap();
+      (
+        privacy::needs_prompt(&state).then_some(PrivacyPrompt {
+          analytics: state.analytics,
+          update_check: state.update_check,
+        }),
+        privacy::update_check_enabled(&state),
+      )
+    };
+
+    let mut app = Self {
+      global_app_state: app_state,
+      rx,
+      tx_tray,
+      main_window: Some(main_id),
+      window_icon: icon,
+      notification_window: None,
+      notification_message: String::new(),
+      notification_config: NotificationConfig::default(),
+      about_modal_open: false,
+      inherent_vowel_info_open: false,
+      typing_helper_window: None,
+      typing_helper_state: TypingHelperState::new(ScriptListEnum::Devanagari),
+      profiles_window: None,
+      profiles_state: ProfilesState::default(),
+      hotkey_settings_window: None,
+      hotkey_settings_state: HotkeySettingsState::default(),
+      config_settings_window: None,
+      config_settings_state: ConfigSettingsState::default(),
+      privacy_prompt,
+      version_check_result: None,
+      update_notification_dismissed: false,
+      update_in_progress: false,
+    };
+    // eg. the settings could not be read
+    let notice_task = match startup_notice {
+      Some(notice) => app.show_notification(notice),
+      None => Task::none(),
+    };
+
     (
-      Self {
-        global_app_state: app_state,
-        rx,
-        tx_tray,
-        main_window: Some(main_id),
-        window_icon: icon,
-        notification_window: None,
-        notification_message: String::new(),
-        notification_config: NotificationConfig::default(),
-        about_modal_open: false,
-        inherent_vowel_info_open: false,
-        typing_helper_window: None,
-        typing_helper_state: TypingHelperState::new(ScriptListEnum::Devanagari),
-        version_check_result: None,
-        update_notification_dismissed: false,
-        update_in_progress: false,
-      },
+      app,
       Task::batch([
         main_open_task.discard(),
-        // Trigger version check on startup
-        Task::future(async {
-          let result = version_check::check_for_updates().await;
-          UIMessage::VersionCheckResult(result)
-        }),
+        notice_task,
+        // Trigger version check on startup, unless turned off
+        if update_check {
+          Self::check_for_updates()
+        } else {
+          Task::none()
+        },
       ]),
     )
   }
@@ -162,17 +317,23 @@ impl App {
       UIMessage::RerenderUI => Task::none(),
       UIMessage::SetScript(script_display) => {
         // Get current options before creating new context
+        let (layout, auto_clear_time_ms) = {
+          let state = self.global_app_state.persitent_state.lock().unwrap();
+          (state.typing_layout(), state.auto_clear_time_ms)
+        };
         let current_options = {
           let ctx = self.global_app_state.typing_context.lock().unwrap();
           Some(lipilekhika::typing::TypingContextOptions {
-            auto_context_clear_time_ms: lipilekhika::typing::DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS,
+            auto_context_clear_time_ms: auto_clear_time_ms,
             use_native_numerals: ctx.get_use_native_numerals(),
             include_inherent_vowel: ctx.get_include_inherent_vowel(),
+            layout,
+            latin_escape: ctx.get_latin_escape(),
           })
         };
 
         let script = script_list_to_typing_script(script_display.script_name);
-        let new_script_context = TypingContext::new(script, current_options);
+        let new_script_context = new_typing_context(script, current_options);
         {
           let mut ctx = self.global_app_state.typing_context.lock().unwrap();
           *ctx = new_script_context;
@@ -189,6 +350,40 @@ impl App {
         // Return async save task
         Self::save_persistent_state_async(Arc::clone(&self.global_app_state))
       }
+      UIMessage::SetLayout(layout) => {
+        let auto_clear_time_ms = self
+          .global_app_state
+          .persitent_state
+          .lock()
+          .unwrap()
+          .auto_clear_time_ms;
+        let (script, current_options) = {
+          let ctx = self.global_app_state.typing_context.lock().unwrap();
+          let script = script_from_normalized_name(ctx.get_normalized_script())
+            .unwrap_or(ScriptListEnum::Devanagari);
+          (
+            script_list_to_typing_script(script),
+            Some(lipilekhika::typing::TypingContextOptions {
+              auto_context_clear_time_ms: auto_clear_time_ms,
+              use_native_numerals: ctx.get_use_native_numerals(),
+              include_inherent_vowel: ctx.get_include_inherent_vowel(),
+              layout,
+              latin_escape: ctx.get_latin_escape(),
+            }),
+          )
+        };
+        let new_context = new_typing_context(script, current_options);
+        {
+          let mut ctx = self.global_app_state.typing_context.lock().unwrap();
+          *ctx = new_context;
+        }
+        // Update persistent state and save asynchronously
+        {
+          let mut state = self.global_app_state.persitent_state.lock().unwrap();
+          state.layout = layout.to_string();
+        }
+        Self::save_persistent_state_async(Arc::clone(&self.global_app_state))
+      }
       UIMessage::ToggleTypingMode(enabled) => {
         self
           .global_app_state
@@ -200,19 +395,30 @@ impl App {
         });
         Task::none()
       }
-      UIMessage::KeyboardToggleTypingMode => {
-        // Toggle typing mode (flip current state)
-        let current = self.global_app_state.typing_enabled.load(Ordering::SeqCst);
-        self
-          .global_app_state
-          .typing_enabled
-          .store(!current, Ordering::SeqCst);
+      UIMessage::HotkeyPressed(hotkey) => {
+        if self.hotkey_settings_state.recording.is_some() {
+          return self.update(UIMessage::HotkeySettings(HotkeySettingsMessage::Pressed(
+            hotkey,
+          )));
+        }
+        let Some(action) = hotkeys::run(&self.global_app_state, &hotkey) else {
+          return Task::none();
+        };
         let _ = self.tx_tray.lock().unwrap().send(ThreadMessage {
           origin: ThreadMessageOrigin::UI,
           msg: ThreadMessageType::RerenderTray,
         });
-        // Trigger notification after toggling
-        self.update(UIMessage::TriggerTypingNotification)
+        match action {
+          HotkeyAction::ToggleTyping => self.update(UIMessage::TriggerTypingNotification),
+          HotkeyAction::NextScript | HotkeyAction::SwitchScript(_) => {
+            self.update(UIMessage::ScriptChanged)
+          }
+          HotkeyAction::CloseApp => iced::exit(),
+          // the clipboard thread shows the result
+          HotkeyAction::TransliterateClipboard => Task::none(),
+          // nothing was typed in the app window through the hooks
+          HotkeyAction::UndoConversion => Task::none(),
+        }
       }
       UIMessage::ToogleUseNativeNumerals(use_native_numerals) => {
         {
@@ -264,28 +470,26 @@ impl App {
         } else {
           "Typing : Off".to_string()
         };
-        self.notification_message = msg.clone();
-
-        // Close existing notification if any, then open new one
-        let close_task = if let Some(old_id) = self.notification_window.take() {
-          window::close(old_id)
-        } else {
-          Task::none()
+        self.show_notification(msg)
+      }
+      UIMessage::ShowNotification(message) => self.show_notification(message),
+      UIMessage::ScriptChanged => {
+        // The hotkey already switched the typing context; save and show the new script
+        let script = {
+          let ctx = self.global_app_state.typing_context.lock().unwrap();
+          script_from_normalized_name(ctx.get_normalized_script())
         };
-
-        let (new_id, open_task) = notification::open_notification_window();
-        self.notification_window = Some(new_id);
-
-        // Start timeout timer
-        let timeout_task = notification::notification_timeout(
-          &self.notification_config,
-          UIMessage::CloseNotification(new_id),
-        );
-
+        let Some(script) = script else {
+          return Task::none();
+        };
+        let name = get_ordered_script_list()
+          .into_iter()
+          .find(|sd| sd.script_name == script)
+          .map(|sd| sd.to_string())
+          .unwrap_or_else(|| script.to_string());
         Task::batch([
-          close_task,
-          open_task.map(UIMessage::NotificationOpened),
-          timeout_task,
+          self.show_notification(name),
+          Self::save_persistent_state_async(Arc::clone(&self.global_app_state)),
         ])
       }
       UIMessage::NotificationOpened(_id) => {
@@ -311,6 +515,13 @@ impl App {
         } else if self.typing_helper_window == Some(id) {
           // Clean up typing helper window
           self.typing_helper_window = None;
+        } else if self.profiles_window == Some(id) {
+          self.profiles_window = None;
+        } else if self.hotkey_settings_window == Some(id) {
+          self.hotkey_settings_window = None;
+          self.stop_hotkey_recording();
+        } else if self.config_settings_window == Some(id) {
+          self.config_settings_window = None;
         }
         Task::none()
       }
@@ -326,6 +537,16 @@ impl App {
           // Close typing helper window
           self.typing_helper_window = None;
           window::close(id)
+        } else if self.profiles_window == Some(id) {
+          self.profiles_window = None;
+          window::close(id)
+        } else if self.hotkey_settings_window == Some(id) {
+          self.hotkey_settings_window = None;
+          self.stop_hotkey_recording();
+          window::close(id)
+        } else if self.config_settings_window == Some(id) {
+          self.config_settings_window = None;
+          window::close(id)
         } else {
           Task::none()
         }
@@ -360,7 +581,7 @@ impl App {
         }
       }
       UIMessage::CloseApp => {
-        // Exit the application (triggered by Win+Esc shortcut)
+        // Exit the application (triggered by the close app hotkey)
         iced::exit()
       }
       UIMessage::OpenAbout => {
@@ -438,14 +659,25 @@ impl App {
           return Task::none();
         }
 
-        // Sync script from current context
-        let curr_script = {
+        // Sync script and options from current context
+        let (curr_script, options) = {
           let ctx = self.global_app_state.typing_context.lock().unwrap();
-          script_from_normalized_name(ctx.get_normalized_script())
+          (
+            script_from_normalized_name(ctx.get_normalized_script()),
+            ctx.snapshot().options,
+          )
         };
+        self.typing_helper_state.set_typing_options(options);
         if let Some(curr_script) = curr_script {
-          self.typing_helper_state.current_script = curr_script;
+          self.typing_helper_state.set_script(curr_script);
         }
+        self.typing_helper_state.pinned = self
+          .global_app_state
+          .persitent_state
+          .lock()
+          .unwrap()
+          .pinned_chars
+          .clone();
 
         let (new_id, open_task) = open_typing_helper_window(Some(self.window_icon.clone()));
         self.typing_helper_window = Some(new_id);
@@ -462,14 +694,25 @@ impl App {
           return Task::none();
         }
 
-        // Sync script from current context
-        let curr_script = {
+        // Sync script and options from current context
+        let (curr_script, options) = {
           let ctx = self.global_app_state.typing_context.lock().unwrap();
-          script_from_normalized_name(ctx.get_normalized_script())
+          (
+            script_from_normalized_name(ctx.get_normalized_script()),
+            ctx.snapshot().options,
+          )
         };
+        self.typing_helper_state.set_typing_options(options);
         if let Some(curr_script) = curr_script {
-          self.typing_helper_state.current_script = curr_script;
+          self.typing_helper_state.set_script(curr_script);
         }
+        self.typing_helper_state.pinned = self
+          .global_app_state
+          .persitent_state
+          .lock()
+          .unwrap()
+          .pinned_chars
+          .clone();
         // Set active tab to Compare Scripts
         self.typing_helper_state.active_tab = TypingHelperTab::CompareScripts;
 
@@ -484,7 +727,9 @@ impl App {
       UIMessage::TypingHelper(msg) => {
         match msg {
           TypingHelperMessage::SetScript(script_display) => {
-            self.typing_helper_state.current_script = script_display.script_name;
+            self
+              .typing_helper_state
+              .set_script(script_display.script_name);
           }
           TypingHelperMessage::SetTab(tab) => {
             self.typing_helper_state.active_tab = tab;
@@ -492,9 +737,169 @@ impl App {
           TypingHelperMessage::SetCompareScript(script_display) => {
             self.typing_helper_state.compare_script = Some(script_display);
           }
+          TypingHelperMessage::SetHintText(hint_text) => {
+            self.typing_helper_state.set_hint_text(hint_text);
+          }
+          TypingHelperMessage::SetSearch(query) => {
+            self.typing_helper_state.set_search_query(query);
+          }
+          TypingHelperMessage::TogglePin(char_text) => {
+            self.typing_helper_state.toggle_pin(char_text);
+            self
+              .global_app_state
+              .persitent_state
+              .lock()
+              .unwrap()
+              .pinned_chars = self.typing_helper_state.pinned.clone();
+            return Self::save_persistent_state_async(Arc::clone(&self.global_app_state));
+          }
+          TypingHelperMessage::SetPracticeKeys(keys) => {
+            self.typing_helper_state.practice.set_keys(keys);
+          }
+          TypingHelperMessage::SubmitPractice => {
+            self.typing_helper_state.practice.submit();
+          }
+          TypingHelperMessage::SkipPractice => {
+            self.typing_helper_state.practice.skip();
+          }
         }
         Task::none()
       }
+      UIMessage::OpenProfiles => {
+        if let Some(id) = self.profiles_window {
+          return window::gain_focus(id);
+        }
+        self.profiles_state = ProfilesState::new(
+          &self.global_app_state.persitent_state.lock().unwrap(),
+          window_context::current_app(&self.global_app_state),
+        );
+        let (new_id, open_task) = open_profiles_window(Some(self.window_icon.clone()));
+        self.profiles_window = Some(new_id);
+        open_task.map(UIMessage::ProfilesOpened)
+      }
+      UIMessage::ProfilesOpened(_id) => Task::none(),
+      UIMessage::Profiles(msg) => {
+        let changed = {
+          let mut state = self.global_app_state.persitent_state.lock().unwrap();
+          let changed = self.profiles_state.update(msg, &state);
+          if changed {
+            state.profiles = self.profiles_state.profiles.clone();
+          }
+          changed
+        };
+        if !changed {
+          return Task::none();
+        }
+        // The app typed in next picks up its new settings
+        window_context::reload_profiles(&self.global_app_state);
+        Self::save_persistent_state_async(Arc::clone(&self.global_app_state))
+      }
+      UIMessage::OpenHotkeySettings => {
+        if let Some(id) = self.hotkey_settings_window {
+          return window::gain_focus(id);
+        }
+        self.hotkey_settings_state =
+          HotkeySettingsState::new(&self.global_app_state.persitent_state.lock().unwrap());
+        let (new_id, open_task) = open_hotkey_settings_window(Some(self.window_icon.clone()));
+        self.hotkey_settings_window = Some(new_id);
+        open_task.map(UIMessage::HotkeySettingsOpened)
+      }
+      UIMessage::HotkeySettingsOpened(_id) => Task::none(),
+      UIMessage::HotkeySettings(msg) => {
+        let changed = self.hotkey_settings_state.update(msg);
+        // The keyboard hook lets keys through to the recorder
+        self.global_app_state.recording_hotkey.store(
+          self.hotkey_settings_state.recording.is_some(),
+          Ordering::SeqCst,
+        );
+        if !changed {
+          return Task::none();
+        }
+        let auto_clear_time_ms = self.hotkey_settings_state.auto_clear_time_ms;
+        {
+          let mut state = self.global_app_state.persitent_state.lock().unwrap();
+          state.hotkeys = self.hotkey_settings_state.hotkeys.clone();
+          state.auto_clear_time_ms = auto_clear_time_ms;
+          state.auto_revert_before_dot = self.hotkey_settings_state.auto_revert_before_dot;
+          state.word_completion = self.hotkey_settings_state.word_completion;
+        }
+        // Apply a new timeout to the current context, keeping what is being typed
+        {
+          let mut ctx = self.global_app_state.typing_context.lock().unwrap();
+          let mut snapshot = ctx.snapshot();
+          if snapshot.options.auto_context_clear_time_ms != auto_clear_time_ms {
+            snapshot.options.auto_context_clear_time_ms = auto_clear_time_ms;
+            if let Ok(restored) = TypingContext::restore(&snapshot) {
+              *ctx = restored;
+            }
+          }
+        }
+        Self::save_persistent_state_async(Arc::clone(&self.global_app_state))
+      }
+      UIMessage::OpenConfigSettings => {
+        if let Some(id) = self.config_settings_window {
+          return window::gain_focus(id);
+        }
+        self.config_settings_state =
+          ConfigSettingsState::new(&self.global_app_state.persitent_state.lock().unwrap());
+        let (new_id, open_task) = open_config_settings_window(Some(self.window_icon.clone()));
+        self.config_settings_window = Some(new_id);
+        open_task.map(UIMessage::ConfigSettingsOpened)
+      }
+      UIMessage::ConfigSettingsOpened(_id) => Task::none(),
+      UIMessage::ConfigSettings(msg) => {
+        let change = {
+          let mut state = self.global_app_state.persitent_state.lock().unwrap();
+          self.config_settings_state.update(msg, &mut state)
+        };
+        match change {
+          ConfigChange::None => return Task::none(),
+          ConfigChange::Saved => {}
+          ConfigChange::Reloaded => self.reload_settings(),
+        }
+        Self::save_persistent_state_async(Arc::clone(&self.global_app_state))
+      }
+      UIMessage::SetPromptAnalytics(enabled) => {
+        if let Some(prompt) = &mut self.privacy_prompt {
+          prompt.analytics = enabled;
+        }
+        Task::none()
+      }
+      UIMessage::SetPromptUpdateCheck(enabled) => {
+        if let Some(prompt) = &mut self.privacy_prompt {
+          prompt.update_check = enabled;
+        }
+        Task::none()
+      }
+      UIMessage::AnswerPrivacyPrompt => {
+        let Some(prompt) = self.privacy_prompt.take() else {
+          return Task::none();
+        };
+        // only what was not already on (set by the deployment) starts now
+        let (start_analytics, start_update_check) = {
+          let mut state = self.global_app_state.persitent_state.lock().unwrap();
+          let before = (
+            privacy::analytics_enabled(&state),
+            privacy::update_check_enabled(&state),
+          );
+          state.analytics = prompt.analytics;
+          state.update_check = prompt.update_check;
+          state.privacy_prompt_answered = true;
+          (
+            !before.0 && privacy::analytics_enabled(&state),
+            !before.1 && privacy::update_check_enabled(&state),
+          )
+        };
+        if start_analytics {
+          std::thread::spawn(posthog::init_posthog);
+        }
+        let save_task = Self::save_persistent_state_async(Arc::clone(&self.global_app_state));
+        if start_update_check {
+          Task::batch([save_task, Self::check_for_updates()])
+        } else {
+          save_task
+        }
+      }
       UIMessage::VersionCheckResult(result) => {
         self.version_check_result = Some(result);
         Task::none()
@@ -547,6 +952,14 @@ impl App {
     }
   }
 
+  fn stop_hotkey_recording(&mut self) {
+    self.hotkey_settings_state.recording = None;
+    self
+      .global_app_state
+      .recording_hotkey
+      .store(false, Ordering::SeqCst);
+  }
+
   fn subscription(&self) -> Subscription<UIMessage> {
     Subscription::batch([
       Subscription::run_with(ThreadRx::new(Arc::clone(&self.rx)), thread_message_stream),
@@ -554,31 +967,21 @@ impl App {
       window::close_requests().map(UIMessage::WindowCloseRequested),
       // Listen for actual window closes (to track programmatic closes)
       window::close_events().map(UIMessage::WindowClosed),
-      // Listen for keyboard shortcuts
+      // Listen for hotkeys (and Esc to stop recording one)
       keyboard::listen().filter_map(|event| {
-        if let keyboard::Event::KeyPressed { key, modifiers, .. } = event {
-          // Alt+X or Alt+C: Toggle typing mode
-          if modifiers.alt()
-            && !modifiers.control()
-            && !modifiers.logo()
-            && !modifiers.shift()
-            && let Key::Character(ref c) = key
-          {
-            let c_lower = c.to_lowercase();
-            if c_lower == "x" || c_lower == "c" {
-              // Toggle typing mode and trigger notification
-              return Some(UIMessage::KeyboardToggleTypingMode);
-            }
-          }
-          // Win+Esc: Close the application
-          if modifiers.logo()
-            && !modifiers.alt()
-            && !modifiers.control()
-            && !modifiers.shift()
-            && key == Key::Named(Named::Escape)
-          {
-            return Some(UIMessage::CloseApp);
+        if let keyboard::Event::KeyPressed {
+          key,
+          physical_key,
+          modifiers,
+          ..
+        } = event
+        {
+          if modifiers.is_empty() && key == Key::Named(Named::Escape) {
+            return Some(UIMessage::HotkeySettings(
+              HotkeySettingsMessage::CancelRecording,
+            ));
           }
+          return pressed_hotkey(&key, physical_key, modifiers).map(UIMessage::HotkeyPressed);
         }
         None
       }),
@@ -592,6 +995,15 @@ impl App {
     } else if Some(window_id) == self.typing_helper_window {
       // Render typing helper view
       view_typing_helper(&self.typing_helper_state)
+    } else if Some(window_id) == self.profiles_window {
+      view_profiles(&self.profiles_state)
+    } else if Some(window_id) == self.hotkey_settings_window {
+      view_hotkey_settings(&self.hotkey_settings_state)
+    } else if Some(window_id) == self.config_settings_window {
+      view_config_settings(
+        &self.config_settings_state,
+        &self.global_app_state.persitent_state.lock().unwrap(),
+      )
     } else {
       // Render main app view
       let scripts = get_ordered_script_list();
@@ -601,16 +1013,32 @@ impl App {
         .collect();
 
       let typing_enabled = self.global_app_state.typing_enabled.load(Ordering::SeqCst);
+      let toggle_hotkeys = self
+        .global_app_state
+        .persitent_state
+        .lock()
+        .unwrap()
+        .hotkeys
+        .toggle_typing
+        .iter()
+        .filter_map(|hotkey| hotkey.parse::<Hotkey>().ok())
+        .map(|hotkey| hotkey.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
 
-      let (use_native_numerals, include_inherent_vowel, curr_script) = {
+      let (use_native_numerals, include_inherent_vowel, curr_script, curr_layout) = {
         let ctx = self.global_app_state.typing_context.lock().unwrap();
         (
           ctx.get_use_native_numerals(),
           ctx.get_include_inherent_vowel(),
           script_from_normalized_name(ctx.get_normalized_script()),
+          ctx.get_layout(),
         )
         // auto drops lock
       };
+      let layouts = curr_script
+        .map(|script| layouts_for_script(script_list_to_typing_script(script)))
+        .unwrap_or_default();
 
       // Find the ScriptDisplay that matches the current script
       let current_script_display = curr_script.and_then(|curr_script| {
@@ -672,6 +1100,99 @@ impl App {
                     },
                   ),
                 ),
+                Item::new(
+                  button(
+                    row![
+                      svg(iced::widget::svg::Handle::from_memory(include_bytes!(
+                        "../../assets/keyboard.svg"
+                      )))
+                      .width(Length::Fixed(26.0))
+                      .height(Length::Fixed(26.0)),
+                      text("App Profiles")
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center),
+                  )
+                  .width(Length::Fill)
+                  .on_press(UIMessage::OpenProfiles)
+                  .style(
+                    |theme: &Theme, status: iced::widget::button::Status| {
+                      let palette = theme.extended_palette();
+                      iced::widget::button::Style {
+                        background: Some(iced::Background::Color(match status {
+                          iced::widget::button::Status::Hovered => palette.background.weak.color,
+                          _ => iced::Color::TRANSPARENT,
+                        })),
+                        text_color: palette.background.base.text,
+                        border: iced::Border::default(),
+                        shadow: iced::Shadow::default(),
+                        snap: false,
+                      }
+                    },
+                  ),
+                ),
+                Item::new(
+                  button(
+                    row![
+                      svg(iced::widget::svg::Handle::from_memory(include_bytes!(
+                        "../../assets/keyboard.svg"
+                      )))
+                      .width(Length::Fixed(26.0))
+                      .height(Length::Fixed(26.0)),
+                      text("Hotkeys")
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center),
+                  )
+                  .width(Length::Fill)
+                  .on_press(UIMessage::OpenHotkeySettings)
+                  .style(
+                    |theme: &Theme, status: iced::widget::button::Status| {
+                      let palette = theme.extended_palette();
+                      iced::widget::button::Style {
+                        background: Some(iced::Background::Color(match status {
+                          iced::widget::button::Status::Hovered => palette.background.weak.color,
+                          _ => iced::Color::TRANSPARENT,
+                        })),
+                        text_color: palette.background.base.text,
+                        border: iced::Border::default(),
+                        shadow: iced::Shadow::default(),
+                        snap: false,
+                      }
+                    },
+                  ),
+                ),
+                Item::new(
+                  button(
+                    row![
+                      svg(iced::widget::svg::Handle::from_memory(include_bytes!(
+                        "../../assets/keyboard.svg"
+                      )))
+                      .width(Length::Fixed(26.0))
+                      .height(Length::Fixed(26.0)),
+                      text("Settings")
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center),
+                  )
+                  .width(Length::Fill)
+                  .on_press(UIMessage::OpenConfigSettings)
+                  .style(
+                    |theme: &Theme, status: iced::widget::button::Status| {
+                      let palette = theme.extended_palette();
+                      iced::widget::button::Style {
+                        background: Some(iced::Background::Color(match status {
+                          iced::widget::button::Status::Hovered => palette.background.weak.color,
+                          _ => iced::Color::TRANSPARENT,
+                        })),
+                        text_color: palette.background.base.text,
+                        border: iced::Border::default(),
+                        shadow: iced::Shadow::default(),
+                        snap: false,
+                      }
+                    },
+                  ),
+                ),
                 Item::new(
                   button(
                     row![
@@ -772,15 +1293,20 @@ impl App {
           toggler(typing_enabled)
             .label("Typing")
             .on_toggle(UIMessage::ToggleTypingMode),
-          text("Alt+X/C")
+          text(toggle_hotkeys)
             .style(|theme: &Theme| iced::widget::text::Style {
               color: Some(theme.extended_palette().background.weak.text),
             })
             .center()
             .size(12),
+          Space::new().width(Length::Fill),
+          pick_list(layouts, Some(curr_layout), UIMessage::SetLayout)
+            .text_size(13)
+            .width(Length::Fixed(110.0)),
         ]
         .padding([12, 0])
-        .spacing(20),
+        .spacing(20)
+        .align_y(iced::Alignment::Center),
         // row![
         //   pick_list(scripts, current_script_display, UIMessage::SetScript)
         //     .width(Length::Fixed(200.0))
@@ -1021,7 +1547,59 @@ impl App {
       ])
       .padding([7, 10]);
 
-      if self.about_modal_open {
+      if let Some(prompt) = self.privacy_prompt {
+        let policy = privacy::managed_policy();
+        let mut choices = column![].spacing(6);
+        if policy.analytics.is_none() {
+          choices = choices.push(
+            checkbox(prompt.analytics)
+              .on_toggle(UIMessage::SetPromptAnalytics)
+              .label("Send an anonymous start event (app version, OS)")
+              .text_size(12),
+          );
+        }
+        if policy.update_check.is_none() {
+          choices = choices.push(
+            checkbox(prompt.update_check)
+              .on_toggle(UIMessage::SetPromptUpdateCheck)
+              .label("Check for new versions on GitHub")
+              .text_size(12),
+          );
+        }
+        let privacy_modal = container(
+          column![
+            text("Before you start").size(16),
+            text("Lipi Lekhika can use the internet for these. Change them later in Settings.")
+              .size(11)
+              .style(|theme: &Theme| iced::widget::text::Style {
+                color: Some(theme.extended_palette().background.weak.text),
+              }),
+            choices,
+            button("Continue")
+              .on_press(UIMessage::AnswerPrivacyPrompt)
+              .padding([4, 16]),
+          ]
+          .spacing(8)
+          .align_x(iced::Alignment::Center),
+        )
+        .max_width(360.0)
+        .padding([8, 15])
+        .style(|theme: &Theme| {
+          let palette = theme.extended_palette();
+          container::Style {
+            background: Some(iced::Background::Color(palette.background.base.color)),
+            border: iced::Border {
+              color: palette.background.strong.color,
+              width: 1.0,
+              radius: 8.0.into(),
+            },
+            ..Default::default()
+          }
+        });
+
+        // answered with Continue only: clicks outside do not dismiss it
+        stack![main_content, center(opaque(privacy_modal))].into()
+      } else if self.about_modal_open {
         let about_modal = container(
           column![
             // Header with icon and title
@@ -1279,16 +1857,47 @@ impl App {
       String::new()
     } else if self.typing_helper_window == Some(_window_id) {
       "Typing Help".to_string()
+    } else if self.profiles_window == Some(_window_id) {
+      "App Profiles".to_string()
+    } else if self.hotkey_settings_window == Some(_window_id) {
+      "Hotkeys".to_string()
+    } else if self.config_settings_window == Some(_window_id) {
+      "Settings".to_string()
     } else {
       "Lipi Lekhika".to_string()
     }
   }
 }
 
+/// The hotkey of a key press in a window of the app, when Ctrl, Alt or the logo key is held.
+fn pressed_hotkey(
+  key: &Key,
+  physical_key: keyboard::key::Physical,
+  modifiers: Modifiers,
+) -> Option<Hotkey> {
+  let key = match key {
+    Key::Named(Named::Escape) => HotkeyKey::Escape,
+    Key::Named(Named::Space) => HotkeyKey::Space,
+    // Function keys are named F1..F24
+    Key::Named(named) => HotkeyKey::from_name(&format!("{named:?}"))?,
+    // Letters and digits of the physical key for non-Latin layouts
+    Key::Character(_) => HotkeyKey::from_char(key.to_latin(physical_key)?)?,
+    Key::Unidentified => return None,
+  };
+  let modifiers = HotkeyModifiers {
+    ctrl: modifiers.control(),
+    alt: modifiers.alt(),
+    shift: modifiers.shift(),
+    super_key: modifiers.logo(),
+  };
+  modifiers.has_command().then_some(Hotkey { modifiers, key })
+}
+
 pub fn run(
   app_state: Arc<AppState>,
   rx: Receiver<ThreadMessage>,
   tx_tray: Sender<ThreadMessage>,
+  startup_notice: Option<String>,
 ) -> iced::Result {
   let icon = window::icon::from_file_data(include_bytes!("../../assets/icon.png"), None)
     .expect("icon should be valid");
@@ -1302,6 +1911,7 @@ pub fn run(
         Arc::clone(&rx),
         Arc::clone(&tx_tray),
         icon.clone(),
+        startup_notice.clone(),
       )
     },
     App::update,
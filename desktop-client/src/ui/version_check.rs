@@ -1,7 +1,13 @@
+use crate::privacy;
 use octocrab::Octocrab;
+use serde::Deserialize;
 use std::env;
 use std::process::Command;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const GITHUB_RELEASES_URL: &str = "https://github.com/shubhattin/lipilekhika/releases";
+
 /// Current app version from Cargo.toml
 pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 // pub const CURRENT_VERSION: &str = "0.0.1";
@@ -15,11 +21,7 @@ pub struct VersionCheckResult {
 }
 
 fn parse_pc_app_tag(tag: &str) -> Option<&str> {
-  if tag.starts_with("pc-app@v") {
-    Some(&tag[8..]) // Skip "pc-app@"
-  } else {
-    None
-  }
+  tag.strip_prefix("pc-app@v")
 }
 
 /// Compare two semantic versions
@@ -54,16 +56,118 @@ fn is_version_greater(version_a: &str, version_b: &str) -> bool {
   }
 }
 
-/// Check for updates by fetching releases from GitHub
-/// This function is designed to work with iced's Task::future by spawning
-/// the tokio-dependent octocrab call in a dedicated tokio runtime on a blocking thread
+/// A release as listed by the GitHub API (the fields used)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+  pub tag_name: String,
+  #[serde(default)]
+  pub draft: bool,
+  #[serde(default)]
+  pub prerelease: bool,
+}
+
+/// Where releases and installers come from: GitHub, or a mirror for managed deployments
+/// (a local stand-in server in tests)
+pub trait UpdateSource {
+  /// Releases of the repository, newest first
+  async fn releases(&self) -> Result<Vec<Release>, BoxError>;
+
+  /// URL of the Windows installer of `version`
+  fn installer_url(&self, version: &str) -> String;
+
+  async fn download(&self, url: &str) -> Result<Vec<u8>, BoxError> {
+    let response = reqwest::get(url).await?;
+    if !response.status().is_success() {
+      return Err(format!("HTTP error: {}", response.status()).into());
+    }
+    Ok(response.bytes().await?.to_vec())
+  }
+}
+
+/// The releases of the GitHub repository
+pub struct GitHubReleases;
+
+impl UpdateSource for GitHubReleases {
+  async fn releases(&self) -> Result<Vec<Release>, BoxError> {
+    let octocrab = Octocrab::builder()
+      .set_connect_timeout(Some(std::time::Duration::from_secs(10)))
+      .build()?;
+
+    // Fetch releases from the repository (already in descending order)
+    let releases = octocrab
+      .repos("shubhattin", "lipilekhika")
+      .releases()
+      .list()
+      .per_page(50)
+      .send()
+      .await?;
+    Ok(
+      releases
+        .items
+        .into_iter()
+        .map(|release| Release {
+          tag_name: release.tag_name,
+          draft: release.draft,
+          prerelease: release.prerelease,
+        })
+        .collect(),
+    )
+  }
+
+  fn installer_url(&self, version: &str) -> String {
+    installer_url(GITHUB_RELEASES_URL, version)
+  }
+}
+
+/// A mirror of the releases laid out like GitHub: `<base>/releases` answers like the GitHub
+/// API (a JSON list of releases) and installers are under `<base>/download/<tag>/`.
+pub struct MirrorReleases {
+  base_url: String,
+}
+
+impl MirrorReleases {
+  pub fn new(base_url: &str) -> Self {
+    Self {
+      base_url: base_url.trim_end_matches('/').to_string(),
+    }
+  }
+}
+
+impl UpdateSource for MirrorReleases {
+  async fn releases(&self) -> Result<Vec<Release>, BoxError> {
+    let response = reqwest::get(format!("{}/releases", self.base_url)).await?;
+    if !response.status().is_success() {
+      return Err(format!("HTTP error: {}", response.status()).into());
+    }
+    Ok(response.json().await?)
+  }
+
+  fn installer_url(&self, version: &str) -> String {
+    installer_url(&self.base_url, version)
+  }
+}
+
+fn installer_url(releases_url: &str, version: &str) -> String {
+  format!(
+    "{}/download/pc-app%40v{}/lipilekhika-{}.msi",
+    releases_url, version, version
+  )
+}
+
+/// Check for updates by fetching releases from GitHub, or the mirror of a managed
+/// deployment
 pub async fn check_for_updates() -> VersionCheckResult {
   // Use smol::unblock to run the tokio-based octocrab code on a blocking thread pool
   // This avoids conflicts between iced's smol runtime and octocrab's tokio dependency
   smol::unblock(|| {
     // Create a new tokio runtime for this blocking call
     match tokio::runtime::Runtime::new() {
-      Ok(rt) => rt.block_on(check_for_updates_inner()),
+      Ok(rt) => rt.block_on(async {
+        match &privacy::managed_policy().update_url {
+          Some(url) => check_for_updates_inner(&MirrorReleases::new(url)).await,
+          None => check_for_updates_inner(&GitHubReleases).await,
+        }
+      }),
       Err(e) => {
         eprintln!("Failed to create tokio runtime: {}", e);
         VersionCheckResult::default()
@@ -73,8 +177,8 @@ pub async fn check_for_updates() -> VersionCheckResult {
   .await
 }
 
-async fn check_for_updates_inner() -> VersionCheckResult {
-  let result = check_for_updates_fetch().await;
+async fn check_for_updates_inner(source: &impl UpdateSource) -> VersionCheckResult {
+  let result = check_for_updates_fetch(source).await;
   match result {
     Ok(r) => r,
     Err(e) => {
@@ -84,24 +188,14 @@ async fn check_for_updates_inner() -> VersionCheckResult {
   }
 }
 
-async fn check_for_updates_fetch()
--> Result<VersionCheckResult, Box<dyn std::error::Error + Send + Sync>> {
-  let octocrab = Octocrab::builder()
-    .set_connect_timeout(Some(std::time::Duration::from_secs(10)))
-    .build()?;
-
-  // Fetch releases from the repository (already in descending order)
-  let releases = octocrab
-    .repos("shubhattin", "lipilekhika")
-    .releases()
-    .list()
-    .per_page(50)
-    .send()
-    .await?;
+async fn check_for_updates_fetch(
+  source: &impl UpdateSource,
+) -> Result<VersionCheckResult, BoxError> {
+  let releases = source.releases().await?;
 
   // Find the first stable pc-app release
   // Releases are already ordered in descending order, so first match is latest
-  for release in releases.items {
+  for release in releases {
     // Skip drafts and pre-releases
     if release.draft || release.prerelease {
       continue;
@@ -121,13 +215,7 @@ async fn check_for_updates_fetch()
       return Ok(VersionCheckResult {
         update_available,
         latest_version: Some(version.to_string()),
-        windows_msi_download_url: Some(
-          format!(
-            "https://github.com/shubhattin/lipilekhika/releases/download/pc-app%40v{}/lipilekhika-{}.msi",
-            version, version
-          )
-          .to_string(),
-        ),
+        windows_msi_download_url: Some(source.installer_url(version)),
       });
     }
   }
@@ -155,13 +243,24 @@ pub enum UpdateResult {
 /// This is an async function that downloads the MSI to temp dir and launches the installer
 pub async fn download_and_install_update(download_url: String, version: String) -> UpdateResult {
   smol::unblock(move || match tokio::runtime::Runtime::new() {
-    Ok(rt) => rt.block_on(download_and_install_inner(download_url, version)),
+    Ok(rt) => rt.block_on(async {
+      match &privacy::managed_policy().update_url {
+        Some(url) => {
+          download_and_install_inner(&MirrorReleases::new(url), download_url, version).await
+        }
+        None => download_and_install_inner(&GitHubReleases, download_url, version).await,
+      }
+    }),
     Err(e) => UpdateResult::LaunchFailed(format!("Failed to create runtime: {}", e)),
   })
   .await
 }
 
-async fn download_and_install_inner(download_url: String, version: String) -> UpdateResult {
+async fn download_and_install_inner(
+  source: &impl UpdateSource,
+  download_url: String,
+  version: String,
+) -> UpdateResult {
   // Get MSI path in temp directory
   let temp_dir = env::temp_dir();
   let msi_filename = format!("lipilekhika-{}.msi", version);
@@ -181,7 +280,7 @@ async fn download_and_install_inner(download_url: String, version: String) -> Up
 
     // Install failed, try redownloading once
     println!("Install from existing file failed, redownloading...");
-    match download_file(&download_url, &msi_path).await {
+    match download_file(source, &download_url, &msi_path).await {
       Ok(_) => {
         println!("Redownload successful, trying install again...");
         if launch_installer_wizard(&msi_path) {
@@ -202,7 +301,7 @@ async fn download_and_install_inner(download_url: String, version: String) -> Up
 
   // File doesn't exist, download it
   println!("Downloading MSI to {:?}...", msi_path);
-  match download_file(&download_url, &msi_path).await {
+  match download_file(source, &download_url, &msi_path).await {
     Ok(_) => {
       println!("Download successful, launching installer...");
     }
@@ -218,16 +317,11 @@ async fn download_and_install_inner(download_url: String, version: String) -> Up
 }
 
 async fn download_file(
+  source: &impl UpdateSource,
   url: &str,
   dest_path: &PathBuf,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-  let response = reqwest::get(url).await?;
-
-  if !response.status().is_success() {
-    return Err(format!("HTTP error: {}", response.status()).into());
-  }
-
-  let bytes = response.bytes().await?;
+) -> Result<(), BoxError> {
+  let bytes = source.download(url).await?;
   std::fs::write(dest_path, bytes)?;
 
   Ok(())
@@ -268,4 +362,127 @@ mod tests {
     assert!(!is_version_greater("0.0.5", "0.0.5"));
     assert!(!is_version_greater("0.0.4", "0.0.5"));
   }
+
+  fn release(tag: &str, draft: bool, prerelease: bool) -> Release {
+    Release {
+      tag_name: tag.to_string(),
+      draft,
+      prerelease,
+    }
+  }
+
+  /// Releases kept in memory
+  struct FakeReleases(Vec<Release>);
+
+  impl UpdateSource for FakeReleases {
+    async fn releases(&self) -> Result<Vec<Release>, BoxError> {
+      Ok(self.0.clone())
+    }
+
+    fn installer_url(&self, version: &str) -> String {
+      installer_url("http://fake", version)
+    }
+  }
+
+  fn block_on<T>(future: impl Future<Output = T>) -> T {
+    tokio::runtime::Runtime::new().unwrap().block_on(future)
+  }
+
+  #[test]
+  fn latest_stable_app_release_is_picked() {
+    let source = FakeReleases(vec![
+      release("pc-app@v99.0.0", true, false),
+      release("pc-app@v98.0.0", false, true),
+      release("pc-app@v97.0.0-beta", false, false),
+      release("rust-lib@v96.0.0", false, false),
+      release("pc-app@v95.0.0", false, false),
+      release("pc-app@v94.0.0", false, false),
+    ]);
+    let result = block_on(check_for_updates_fetch(&source)).unwrap();
+    assert!(result.update_available);
+    assert_eq!(result.latest_version.as_deref(), Some("95.0.0"));
+    assert_eq!(
+      result.windows_msi_download_url.as_deref(),
+      Some("http://fake/download/pc-app%40v95.0.0/lipilekhika-95.0.0.msi")
+    );
+
+    let current = format!("pc-app@v{CURRENT_VERSION}");
+    let result = block_on(check_for_updates_fetch(&FakeReleases(vec![release(
+      &current, false, false,
+    )])))
+    .unwrap();
+    assert!(!result.update_available);
+  }
+
+  /// Serves `routes` (path, body) over HTTP on localhost until the test ends, 404 for
+  /// other paths. Returns the base URL.
+  fn serve(routes: Vec<(String, Vec<u8>)>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+      for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+          continue;
+        };
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        let _ = reader.read_line(&mut request_line);
+        // skip the headers
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+          line.clear();
+        }
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+        let (status, body) = match routes.iter().find(|(route, _)| route == path) {
+          Some((_, body)) => ("200 OK", body.as_slice()),
+          None => ("404 Not Found", &b""[..]),
+        };
+        let _ = write!(
+          stream,
+          "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+          body.len()
+        );
+        let _ = stream.write_all(body);
+      }
+    });
+    base_url
+  }
+
+  #[test]
+  fn updates_are_checked_and_downloaded_from_a_mirror() {
+    let installer = b"not really an msi".to_vec();
+    let base_url = serve(vec![
+      (
+        "/releases".to_string(),
+        br#"[{"tag_name": "pc-app@v99.1.0", "draft": false, "prerelease": false}]"#.to_vec(),
+      ),
+      (
+        "/download/pc-app%40v99.1.0/lipilekhika-99.1.0.msi".to_string(),
+        installer.clone(),
+      ),
+    ]);
+    let source = MirrorReleases::new(&format!("{base_url}/"));
+
+    let result = block_on(check_for_updates_fetch(&source)).unwrap();
+    assert!(result.update_available);
+    assert_eq!(result.latest_version.as_deref(), Some("99.1.0"));
+    let url = result.windows_msi_download_url.unwrap();
+    assert_eq!(
+      url,
+      format!("{base_url}/download/pc-app%40v99.1.0/lipilekhika-99.1.0.msi")
+    );
+
+    let dest = env::temp_dir().join(format!("lipilekhika-test-{}.msi", std::process::id()));
+    block_on(download_file(&source, &url, &dest)).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), installer);
+    let _ = std::fs::remove_file(&dest);
+
+    // a missing installer is an error, not an empty file
+    let missing = source.installer_url("0.0.1");
+    assert!(block_on(download_file(&source, &missing, &dest)).is_err());
+    assert!(!dest.exists());
+  }
 }
@@ -13,9 +13,17 @@ pub const WM_LBUTTONDOWN: u32 = 0x0201;
 pub const WM_RBUTTONDOWN: u32 = 0x0204;
 pub const WM_MBUTTONDOWN: u32 = 0x0207;
 
-// Letters
-pub const VK_X: u32 = 0x58;
-pub const VK_C: u32 = 0x43;
+// Letters of the clipboard shortcuts
+pub const VK_C_KEY: u16 = 0x43;
+pub const VK_V_KEY: u16 = 0x56;
+
+// Longest wait for the modifiers of a hotkey to be released before a clipboard shortcut
+pub const MODIFIER_RELEASE_TIMEOUT_MS: u64 = 1000;
+
+// Keys of hotkeys besides letters and digits
+pub const VK_SPACE_KEY: u32 = 0x20;
+pub const VK_F1_KEY: u32 = 0x70;
+pub const VK_F24_KEY: u32 = 0x87;
 
 // Navigation & editing keys that should clear context
 pub const VK_LEFT: u32 = 0x25;
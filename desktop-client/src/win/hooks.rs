@@ -1,13 +1,24 @@
-use crate::{ThreadMessage, ThreadMessageOrigin, ThreadMessageType};
+use crate::completion;
+use crate::history;
+use crate::hotkeys::{self, Hotkey, HotkeyAction, HotkeyKey, HotkeyModifiers};
+use crate::platform::ClipboardKey;
+use crate::window_context::{self, WindowId};
 
 use super::WinAppState;
 use super::constants::*;
 use std::cell::RefCell;
+use std::ffi::c_void;
 use std::mem::size_of;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use windows::Win32::Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Foundation::{CloseHandle, HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM};
+use windows::Win32::System::Threading::{
+  OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION, QueryFullProcessImageNameW,
+};
 use windows::Win32::UI::Input::KeyboardAndMouse::{
   GetAsyncKeyState, GetKeyState, GetKeyboardLayout, GetKeyboardState, INPUT, INPUT_0,
   INPUT_KEYBOARD, KEYBD_EVENT_FLAGS, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE,
@@ -15,9 +26,12 @@ use windows::Win32::UI::Input::KeyboardAndMouse::{
   VK_CONTROL, VK_LWIN, VK_MENU, VK_RWIN, VK_SHIFT,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-  CallNextHookEx, HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, LLKHF_ALTDOWN, SetWindowsHookExW,
-  UnhookWindowsHookEx, WH_KEYBOARD_LL, WH_MOUSE_LL,
+  CallNextHookEx, ES_PASSWORD, GA_ROOT, GUITHREADINFO, GWL_STYLE, GetAncestor, GetClassNameW,
+  GetForegroundWindow, GetGUIThreadInfo, GetWindowLongW, GetWindowThreadProcessId, HC_ACTION,
+  HHOOK, KBDLLHOOKSTRUCT, LLKHF_ALTDOWN, MSLLHOOKSTRUCT, SetWindowsHookExW, UnhookWindowsHookEx,
+  WH_KEYBOARD_LL, WH_MOUSE_LL, WindowFromPoint,
 };
+use windows::core::PWSTR;
 
 thread_local! {
   static STATE: RefCell<Option<Arc<WinAppState>>> = const { RefCell::new(None) };
@@ -68,10 +82,81 @@ impl Drop for HookManager {
   }
 }
 
+/// Ends the composition; what was typed stays in the app and is learned for completions.
 fn clear_context(state: &WinAppState) {
-  if let Ok(mut guard) = state.app_state.typing_context.lock() {
-    guard.clear_context();
+  completion::commit_context(&state.app_state);
+  history::forget(&state.app_state);
+}
+
+/// Types the keys of the last converted word over it, once the modifiers of the hotkey are
+/// released so that they do not turn the keys into shortcuts. The hook must return at
+/// once, so this waits on another thread.
+fn undo_conversion(state: &WinAppState) {
+  let Some((to_delete, text)) = history::take_undo(&state.app_state) else {
+    return;
+  };
+  thread::spawn(move || {
+    wait_for_modifier_release();
+    send_backspaces(to_delete);
+    send_unicode_text(&text);
+  });
+}
+
+/// Top-level window receiving the keyboard input.
+fn foreground_window() -> Option<WindowId> {
+  let hwnd = unsafe { GetForegroundWindow() };
+  (!hwnd.is_invalid()).then_some(hwnd.0 as usize as WindowId)
+}
+
+/// Top-level window under a screen point.
+fn window_at(point: POINT) -> Option<WindowId> {
+  let hwnd = unsafe { GetAncestor(WindowFromPoint(point), GA_ROOT) };
+  (!hwnd.is_invalid()).then_some(hwnd.0 as usize as WindowId)
+}
+
+/// Executable name (without `.exe`) of the process owning a window, for the app profiles.
+fn app_name(window: WindowId) -> Option<String> {
+  let hwnd = HWND(window as usize as *mut c_void);
+  let mut pid = 0u32;
+  unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+  if pid == 0 {
+    return None;
   }
+  let mut buf = [0u16; 1024];
+  let mut len = buf.len() as u32;
+  unsafe {
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+    let result = QueryFullProcessImageNameW(
+      process,
+      PROCESS_NAME_WIN32,
+      PWSTR(buf.as_mut_ptr()),
+      &mut len,
+    );
+    let _ = CloseHandle(process);
+    result.ok()?;
+  }
+  let path = String::from_utf16_lossy(&buf[..len as usize]);
+  Path::new(&path)
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().into_owned())
+}
+
+/// Whether the focused control of the foreground window is a password edit control. Fields
+/// drawn by the app itself (browsers, UWP apps) are not recognized.
+pub fn secure_input_focused() -> bool {
+  let mut info = GUITHREADINFO {
+    cbSize: size_of::<GUITHREADINFO>() as u32,
+    ..Default::default()
+  };
+  if unsafe { GetGUIThreadInfo(0, &mut info) }.is_err() || info.hwndFocus.is_invalid() {
+    return false;
+  }
+  let mut class = [0u16; 64];
+  let len = unsafe { GetClassNameW(info.hwndFocus, &mut class) }.max(0) as usize;
+  let class = String::from_utf16_lossy(&class[..len]).to_ascii_lowercase();
+  // ES_PASSWORD is another style bit in other window classes
+  let is_edit = class == "edit" || class.starts_with("richedit");
+  is_edit && unsafe { GetWindowLongW(info.hwndFocus, GWL_STYLE) } & ES_PASSWORD != 0
 }
 
 /// Check if a key is a modifier key (Shift, Ctrl, Alt, Win, Caps Lock, etc.)
@@ -127,6 +212,26 @@ fn is_alt_pressed() -> bool {
   unsafe { GetAsyncKeyState(VK_MENU.0 as i32) < 0 }
 }
 
+/// The hotkey of a key press, when Ctrl, Alt or Win is held.
+fn pressed_hotkey(vk: u32, kb: &KBDLLHOOKSTRUCT) -> Option<Hotkey> {
+  let key = match vk {
+    // Virtual keys of digits and letters are their ASCII codes
+    0x30..=0x39 | 0x41..=0x5A => HotkeyKey::from_char(char::from(vk as u8))?,
+    VK_F1_KEY..=VK_F24_KEY => HotkeyKey::F((vk - VK_F1_KEY + 1) as u8),
+    VK_ESCAPE => HotkeyKey::Escape,
+    VK_SPACE_KEY => HotkeyKey::Space,
+    _ => return None,
+  };
+  let pressed = |key: VIRTUAL_KEY| unsafe { GetAsyncKeyState(key.0 as i32) < 0 };
+  let modifiers = HotkeyModifiers {
+    ctrl: pressed(VK_CONTROL),
+    alt: (kb.flags.0 & LLKHF_ALTDOWN.0) != 0,
+    shift: pressed(VK_SHIFT),
+    super_key: pressed(VK_LWIN) || pressed(VK_RWIN),
+  };
+  modifiers.has_command().then_some(Hotkey { modifiers, key })
+}
+
 /// Send a Unicode string using SendInput (KEYEVENTF_UNICODE)
 fn send_unicode_text(s: &str) {
   let mut inputs: Vec<INPUT> = Vec::new();
@@ -207,6 +312,51 @@ fn send_backspaces(n: usize) {
   }
 }
 
+/// Waits (for a while at most) until Ctrl, Alt, Shift and the Windows keys are released.
+fn wait_for_modifier_release() {
+  let held = || {
+    [VK_CONTROL, VK_MENU, VK_SHIFT, VK_LWIN, VK_RWIN]
+      .iter()
+      .any(|vk| unsafe { GetAsyncKeyState(vk.0 as i32) < 0 })
+  };
+  let deadline = Instant::now() + Duration::from_millis(MODIFIER_RELEASE_TIMEOUT_MS);
+  while held() && Instant::now() < deadline {
+    thread::sleep(Duration::from_millis(10));
+  }
+}
+
+/// Send Ctrl+C or Ctrl+V once the modifiers of the hotkey are released, so that they do
+/// not change the shortcut.
+pub fn send_clipboard_key(key: ClipboardKey) {
+  wait_for_modifier_release();
+
+  let letter = VIRTUAL_KEY(match key {
+    ClipboardKey::Copy => VK_C_KEY,
+    ClipboardKey::Paste => VK_V_KEY,
+  });
+  let input = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+    r#type: INPUT_KEYBOARD,
+    Anonymous: INPUT_0 {
+      ki: KEYBDINPUT {
+        wVk: vk,
+        wScan: 0,
+        dwFlags: flags,
+        time: 0,
+        dwExtraInfo: 0,
+      },
+    },
+  };
+  let inputs = [
+    input(VK_CONTROL, KEYBD_EVENT_FLAGS(0)),
+    input(letter, KEYBD_EVENT_FLAGS(0)),
+    input(letter, KEYEVENTF_KEYUP),
+    input(VK_CONTROL, KEYEVENTF_KEYUP),
+  ];
+  unsafe {
+    let _ = SendInput(&inputs, size_of::<INPUT>() as i32);
+  }
+}
+
 /// Convert a virtual key code + scan code to Unicode using ToUnicodeEx.
 /// Returns Some(String) if conversion yields at least one character.
 ///
@@ -283,11 +433,12 @@ unsafe extern "system" fn low_level_mouse_proc(
   if code == HC_ACTION as i32 {
     let msg = wparam.0 as u32;
 
-    // Clear context on any mouse button click (like onblur in web)
+    // A click ends the composition of the clicked window (like onblur in web)
     if msg == WM_LBUTTONDOWN || msg == WM_RBUTTONDOWN || msg == WM_MBUTTONDOWN {
       let _ = with_state(|state| {
         if state.app_state.typing_enabled.load(Ordering::SeqCst) {
-          clear_context(state);
+          let info = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
+          window_context::click(&state.app_state, window_at(info.pt));
         }
       });
     }
@@ -323,49 +474,18 @@ unsafe extern "system" fn low_level_keyboard_proc(
         return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
       }
 
-      // ---- Handle Alt+X or Alt+C toggle (case insensitive, works regardless of typing mode) ----
-      if is_keydown && (vk == VK_X || vk == VK_C) && (kb.flags.0 & LLKHF_ALTDOWN.0) != 0 {
-        let prev = state
-          .app_state
-          .typing_enabled
-          .fetch_xor(true, Ordering::SeqCst);
-        // ^ a xor 1 = !a
-        let now_enabled = !prev;
-
-        if now_enabled {
-          // println!("[Typing Mode: ON] - Press Alt+X/Alt+C to disable");
-        } else {
-          // println!("[Typing Mode: OFF] - Press Alt+X/Alt+C to enable");
-          clear_context(state);
+      // ---- Hotkeys: typing toggle, script switching, close app (regardless of typing mode) ----
+      if is_keydown
+        && let Some(hotkey) = pressed_hotkey(vk, kb)
+        && let Some(action) = hotkeys::run(&state.app_state, &hotkey)
+      {
+        if action == HotkeyAction::UndoConversion {
+          undo_conversion(state);
         }
-
         // Notify UI and tray to rerender based on latest app state
-        let _ = state.tx_ui.send(ThreadMessage {
-          origin: ThreadMessageOrigin::KeyboardHook,
-          msg: ThreadMessageType::TriggerTypingNotification,
-        });
-        let _ = state.tx_ui.send(ThreadMessage {
-          origin: ThreadMessageOrigin::KeyboardHook,
-          msg: ThreadMessageType::RerenderUI,
-        });
-        let _ = state.tx_tray.send(ThreadMessage {
-          origin: ThreadMessageOrigin::KeyboardHook,
-          msg: ThreadMessageType::RerenderTray,
-        });
-
-        // Suppress Alt+X/Alt+C so it doesn't reach apps
-        return LRESULT(1);
-      }
-
-      // ---- Handle Win+Esc to close the app ----
-      if is_keydown && vk == VK_ESCAPE && is_ctrl_or_win_pressed() {
-        // Send close app message to UI
-        let _ = state.tx_ui.send(ThreadMessage {
-          origin: ThreadMessageOrigin::KeyboardHook,
-          msg: ThreadMessageType::CloseApp,
-        });
+        hotkeys::notify(&state.tx_ui, &state.tx_tray, action);
 
-        // Suppress Win+Esc so it doesn't reach apps
+        // Suppress the hotkey so it doesn't reach apps
         return LRESULT(1);
       }
 
@@ -388,20 +508,63 @@ unsafe extern "system" fn low_level_keyboard_proc(
         return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
       }
 
-      // 2. Clear context and pass through for navigation/editing keys
+      // Switch to the composition and settings of the window being typed in
+      if let Some(window) = foreground_window()
+        && !window_context::focus(&state.app_state, window, || app_name(window))
+      {
+        return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
+      }
+
+      // Alt+Tab switches windows; the composition is kept for when typing resumes here
+      if vk == VK_TAB && is_alt_pressed() {
+        return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
+      }
+
+      // 2. Plain Backspace steps the typing context back one key. The app's own Backspace
+      //    is replaced by the diff, unless the context is empty.
+      if vk == VK_BACKSPACE && !is_ctrl_or_win_pressed() && !is_alt_pressed() {
+        let diff = match state.app_state.typing_context.lock() {
+          Ok(mut guard) => guard.take_backspace(),
+          Err(_) => return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam),
+        };
+        if diff.to_delete_chars_count == 0 && diff.diff_add_text.is_empty() {
+          history::forget(&state.app_state);
+          return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
+        }
+        send_backspaces(diff.to_delete_chars_count);
+        if !diff.diff_add_text.is_empty() {
+          send_unicode_text(&diff.diff_add_text);
+        }
+        history::record(&state.app_state, None, &diff);
+        return LRESULT(1);
+      }
+
+      // Plain Tab completes the word being typed; without a completion it passes through.
+      if vk == VK_TAB
+        && !is_ctrl_or_win_pressed()
+        && !is_alt_pressed()
+        && let Some((to_delete, text)) = completion::take_completion(&state.app_state)
+      {
+        send_backspaces(to_delete);
+        send_unicode_text(&text);
+        history::forget(&state.app_state);
+        return LRESULT(1);
+      }
+
+      // Clear context and pass through for navigation/editing keys
       if is_context_clear_key(vk) {
         clear_context(state);
         return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
       }
 
-      // 3. Pass through shortcuts (Ctrl+anything, Win+anything, Alt+anything except Alt+X)
+      // 3. Pass through shortcuts (Ctrl+anything, Win+anything, Alt+anything that is not a hotkey)
       //    This allows Ctrl+C, Ctrl+V, Ctrl+Z, Win+D, Alt+Tab, etc. to work
       if is_ctrl_or_win_pressed() {
         clear_context(state);
         return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
       }
 
-      // Alt combinations (except Alt+X which is handled above)
+      // Alt combinations (hotkeys are handled above)
       if is_alt_pressed() {
         clear_context(state);
         return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam);
@@ -422,35 +585,37 @@ unsafe extern "system" fn low_level_keyboard_proc(
           }
         }
 
+        // A `.` after a word may turn it back into its keys (domains, emails)
+        if let Some((to_delete, text)) = history::take_auto_revert(&state.app_state, &text) {
+          send_backspaces(to_delete);
+          send_unicode_text(&text);
+          return LRESULT(1);
+        }
+
         // NOTE: Do NOT call SendInput while holding the context lock.
         // SendInput creates injected key events that re-enter this same hook, which can deadlock.
-        // Process all characters in a single locked section, accumulating diffs.
-        let (total_delete, combined_add) = {
+        // All characters are typed in a single locked section, as one net diff.
+        let (diff, finished) = {
           let mut guard = match state.app_state.typing_context.lock() {
             Ok(g) => g,
             Err(_) => return CallNextHookEx(Some(HHOOK::default()), code, wparam, lparam),
           };
 
-          let mut total_delete: usize = 0;
-          let mut combined_add = String::new();
-
-          for ch in text.chars() {
-            let key = ch.to_string();
-            let diff = guard.take_key_input(&key);
-            total_delete += diff.to_delete_chars_count;
-            combined_add.push_str(&diff.diff_add_text);
-          }
-
-          (total_delete, combined_add)
+          let prev = guard.preedit().to_string();
+          let diff = guard.take_text_input(&text);
+          let finished = completion::finished_text(&prev, &diff, guard.preedit());
+          (diff, finished)
         };
 
         // Now inject, with the lock released.
-        if total_delete > 0 {
-          send_backspaces(total_delete);
+        if diff.to_delete_chars_count > 0 {
+          send_backspaces(diff.to_delete_chars_count);
         }
-        if !combined_add.is_empty() {
-          send_unicode_text(&combined_add);
+        if !diff.diff_add_text.is_empty() {
+          send_unicode_text(&diff.diff_add_text);
         }
+        completion::learn(&state.app_state, &finished);
+        history::record(&state.app_state, Some(&text), &diff);
 
         // Suppress original key (we've already handled it)
         return LRESULT(1);
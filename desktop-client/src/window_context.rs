@@ -0,0 +1,181 @@
+//! Typing compositions kept per application window (per application on macOS), so that
+//! switching to another window and back continues the word being typed.
+//!
+//! The hooks report the window of each typed key with [`focus`]. A composition left in a
+//! window is saved as a `TypingSnapshot` and restored when typing there again, unless a
+//! click in that window (see [`click`]) may have moved the caret.
+//!
+//! The application of the window also selects the typing settings: its `AppProfile` if
+//! it has one, else the global settings.
+
+use crate::AppState;
+use crate::completion;
+use crate::data::{normalize_script, script_list_to_typing_script};
+use crate::history;
+use crate::keymap::new_typing_context;
+use crate::persistent_state::AppProfile;
+use lipilekhika::layout::TypingLayout;
+use lipilekhika::typing::{TypingContext, TypingContextOptions, TypingSnapshot};
+
+/// Windows with a saved composition; the oldest are dropped beyond this.
+const MAX_SAVED_WINDOWS: usize = 32;
+
+/// Platform id of a window (`HWND` on Windows, process id on macOS, X window on Linux).
+pub type WindowId = u64;
+
+pub struct WindowContexts {
+  /// Window the typing context belongs to.
+  current: Option<WindowId>,
+  /// Application of `current`, when known.
+  app: Option<String>,
+  /// Typing is on in `current` (off when its profile disables it).
+  typing_enabled: bool,
+  /// Saved compositions, oldest first.
+  saved: Vec<(WindowId, TypingSnapshot)>,
+}
+
+impl Default for WindowContexts {
+  fn default() -> Self {
+    Self {
+      current: None,
+      app: None,
+      typing_enabled: true,
+      saved: Vec::new(),
+    }
+  }
+}
+
+impl WindowContexts {
+  fn take(&mut self, window: WindowId) -> Option<TypingSnapshot> {
+    let idx = self.saved.iter().position(|(id, _)| *id == window)?;
+    Some(self.saved.remove(idx).1)
+  }
+
+  fn save(&mut self, window: WindowId, snapshot: TypingSnapshot) {
+    self.take(window);
+    if self.saved.len() == MAX_SAVED_WINDOWS {
+      self.saved.remove(0);
+    }
+    self.saved.push((window, snapshot));
+  }
+}
+
+/// Switches the typing context to the settings of `profile`, keeping the other options.
+fn apply_profile(
+  ctx: &mut TypingContext,
+  profile: &AppProfile,
+  layout: TypingLayout,
+  auto_clear_time_ms: u64,
+) {
+  let Some(script) = normalize_script(&profile.script) else {
+    return;
+  };
+  if script.as_ref() != ctx.get_normalized_script() {
+    let options = TypingContextOptions {
+      auto_context_clear_time_ms: auto_clear_time_ms,
+      use_native_numerals: profile.native_numerals,
+      include_inherent_vowel: profile.inherent_vowel,
+      layout,
+      latin_escape: ctx.get_latin_escape(),
+    };
+    *ctx = new_typing_context(script_list_to_typing_script(script), Some(options));
+    return;
+  }
+  if ctx.get_use_native_numerals() != profile.native_numerals {
+    ctx.update_use_native_numerals(profile.native_numerals);
+  }
+  if ctx.get_include_inherent_vowel() != profile.inherent_vowel {
+    ctx.update_include_inherent_vowel(profile.inherent_vowel);
+  }
+}
+
+/// Called before a key typed in `window`; `app_name` gives its application, and is only
+/// called when the window changed. When it is not the window typed in last, the
+/// composition there is saved, the settings of the application are applied and the
+/// composition of `window` (if any) is restored.
+///
+/// Returns whether to type in `window`, `false` when its profile turns typing off.
+pub fn focus(
+  app_state: &AppState,
+  window: WindowId,
+  app_name: impl FnOnce() -> Option<String>,
+) -> bool {
+  let Ok(mut contexts) = app_state.window_contexts.lock() else {
+    return true;
+  };
+  if contexts.current == Some(window) {
+    return contexts.typing_enabled;
+  }
+  let previous = contexts.current.replace(window);
+  history::forget(app_state);
+  contexts.app = app_name();
+  let settings = app_state.persitent_state.lock().ok().map(|state| {
+    (
+      state.profile_for(contexts.app.as_deref()),
+      state.typing_layout(),
+      state.auto_clear_time_ms,
+    )
+  });
+  let Ok(mut ctx) = app_state.typing_context.lock() else {
+    return true;
+  };
+
+  let left = ctx.snapshot();
+  ctx.clear_context();
+  if let Some(previous) = previous
+    && !left.input.is_empty()
+  {
+    contexts.save(previous, left);
+  }
+  if let Some((profile, layout, auto_clear_time_ms)) = settings {
+    contexts.typing_enabled = profile.typing_enabled;
+    apply_profile(&mut ctx, &profile, layout, auto_clear_time_ms);
+  }
+
+  // The script or options may have changed since; such compositions are dropped.
+  let Some(snapshot) = contexts.take(window) else {
+    return contexts.typing_enabled;
+  };
+  let current = ctx.snapshot();
+  let same_settings = snapshot.script == current.script
+    && snapshot.options == current.options
+    && snapshot.keymap == current.keymap;
+  if same_settings && let Ok(restored) = TypingContext::restore(&snapshot) {
+    *ctx = restored;
+  }
+  contexts.typing_enabled
+}
+
+/// Application typed in last, if known.
+pub fn current_app(app_state: &AppState) -> Option<String> {
+  app_state.window_contexts.lock().ok()?.app.clone()
+}
+
+/// Makes the next key look up the settings of its application again, after the profiles
+/// were edited.
+pub fn reload_profiles(app_state: &AppState) {
+  if let Ok(mut contexts) = app_state.window_contexts.lock() {
+    contexts.current = None;
+    contexts.app = None;
+    contexts.typing_enabled = true;
+  }
+}
+
+/// Handles a mouse click in `window` (`None` when unknown). The click may move the caret,
+/// so a composition in that window ends (like onblur in web); one being typed in another
+/// window is kept for when typing resumes there.
+pub fn click(app_state: &AppState, window: Option<WindowId>) {
+  let current = match app_state.window_contexts.lock() {
+    Ok(mut contexts) => {
+      if let Some(window) = window {
+        contexts.take(window);
+      }
+      contexts.current
+    }
+    Err(_) => None,
+  };
+  if window.is_none() || window == current {
+    completion::commit_context(app_state);
+    history::forget(app_state);
+  }
+}
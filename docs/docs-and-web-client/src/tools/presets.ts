@@ -46,3 +46,33 @@ export const PRESETS: Preset = {
     }))
   }
 };
+
+/**
+ * Options of a preset for a script pair: every option in `all_options` (those available
+ * from `from` to `to`, see `getAllOptions`) set, enabled for the rules of the preset
+ */
+export const get_preset_options = (
+  all_options: string[],
+  preset_name: PresetListType,
+  from: ScriptListType,
+  to: ScriptListType
+): TransliterationOptions => {
+  const options: TransliterationOptions = Object.fromEntries(all_options.map((v) => [v, false]));
+  const preset = PRESETS[preset_name];
+  if (!preset) return options;
+  for (const rule of preset.direct_apply_rules) {
+    if (all_options.includes(rule)) {
+      options[rule] = true;
+    }
+  }
+  for (const conditional of preset.conditional_rules) {
+    if (
+      from === conditional.from &&
+      to === conditional.to &&
+      all_options.includes(conditional.rule)
+    ) {
+      options[conditional.rule] = true;
+    }
+  }
+  return options;
+};
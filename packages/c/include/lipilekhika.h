@@ -0,0 +1,604 @@
+/* C interface of Lipi Lekhika, see packages/c/README.md. */
+
+#ifndef LIPILEKHIKA_H
+#define LIPILEKHIKA_H
+
+#pragma once
+
+/* Autogenerated by cbindgen. Do not edit manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Status codes returned by the C ABI.
+ */
+typedef enum LipiStatus {
+  LIPI_STATUS_OK = 0,
+  /**
+   * A required pointer argument was NULL.
+   */
+  LIPI_STATUS_NULL_PTR = 1,
+  /**
+   * A string argument was not valid UTF-8.
+   */
+  LIPI_STATUS_INVALID_UTF8 = 2,
+  /**
+   * Lipilekhika panicked; the call had no effect on its outputs.
+   */
+  LIPI_STATUS_PANIC = 3,
+  /**
+   * Any other failure, described by the `out_err` message where the function has one.
+   */
+  LIPI_STATUS_ERROR = 4,
+  /**
+   * A script name is not a known script, language or alias.
+   */
+  LIPI_STATUS_UNKNOWN_SCRIPT = 5,
+  /**
+   * A transliteration option key is not one of `lipi_get_custom_option_keys`.
+   */
+  LIPI_STATUS_UNKNOWN_OPTION = 6,
+} LipiStatus;
+
+/**
+ * Keyboard layout used for typing.
+ */
+typedef enum LipiTypingLayout {
+  /**
+   * Phonetic Roman input.
+   */
+  LIPI_TYPING_LAYOUT_PHONETIC = 0,
+  /**
+   * InScript, for any Brahmic script.
+   */
+  LIPI_TYPING_LAYOUT_IN_SCRIPT = 1,
+  /**
+   * Tamil99, for Tamil.
+   */
+  LIPI_TYPING_LAYOUT_TAMIL99 = 2,
+} LipiTypingLayout;
+
+/**
+ * How long the Latin text of a Latin escape key lasts.
+ */
+typedef enum LipiLatinEscapeScope {
+  /**
+   * Until a whitespace (typed as well) or the escape key.
+   */
+  LIPI_LATIN_ESCAPE_SCOPE_NEXT_WORD = 0,
+  /**
+   * Until the escape key is typed again.
+   */
+  LIPI_LATIN_ESCAPE_SCOPE_UNTIL_ESCAPE = 1,
+} LipiLatinEscapeScope;
+
+/**
+ * Format of a keymap passed to `lipi_typing_context_set_keymap`.
+ */
+typedef enum LipiKeymapFormat {
+  LIPI_KEYMAP_FORMAT_TOML = 0,
+  LIPI_KEYMAP_FORMAT_JSON = 1,
+} LipiKeymapFormat;
+
+/**
+ * Type of a character in a script's list.
+ */
+typedef enum LipiListType {
+  LIPI_LIST_TYPE_ANYA = 0,
+  LIPI_LIST_TYPE_VYANJANA = 1,
+  LIPI_LIST_TYPE_MATRA = 2,
+  LIPI_LIST_TYPE_SVARA = 3,
+} LipiListType;
+
+/**
+ * Opaque handle type used by the C ABI.
+ *
+ * Intentionally **not** `#[repr(C)]` so cbindgen will treat it as opaque.
+ */
+typedef struct LipiTypingContext LipiTypingContext;
+
+/**
+ * Owned UTF-8 string allocated by Rust.
+ *
+ * - `ptr` points to a heap allocation of **len + 1** bytes.
+ * - The final byte is always `\\0` for convenience.
+ * - The content may be treated as a byte slice of length `len`.
+ * - Call `lipi_string_free` to free.
+ */
+typedef struct LipiString {
+  char *ptr;
+  size_t len;
+  size_t cap;
+} LipiString;
+
+/**
+ * Owned array of strings allocated by Rust; free with `lipi_string_list_free`.
+ */
+typedef struct LipiStringList {
+  struct LipiString *ptr;
+  size_t len;
+  size_t cap;
+} LipiStringList;
+
+/**
+ * A transliteration option, by its `category:option` key.
+ */
+typedef struct LipiOption {
+  /**
+   * NUL-terminated key, eg. `"all_to_normal:replace_avagraha_with_a"`.
+   */
+  const char *key;
+  bool enabled;
+} LipiOption;
+
+/**
+ * An alternative output for the current composition.
+ */
+typedef struct LipiCandidate {
+  /**
+   * UTF-8 text the composition would produce.
+   */
+  struct LipiString text;
+  /**
+   * UTF-8 keys that produce `text`; pass them to `lipi_typing_context_select_candidate`.
+   */
+  struct LipiString input;
+} LipiCandidate;
+
+/**
+ * Owned array of candidates allocated by Rust; free with `lipi_candidate_list_free`.
+ */
+typedef struct LipiCandidateList {
+  struct LipiCandidate *ptr;
+  size_t len;
+  size_t cap;
+} LipiCandidateList;
+
+/**
+ * Options for configuring a typing context; start from `lipi_typing_default_options`.
+ */
+typedef struct LipiTypingContextOptions {
+  uint64_t auto_context_clear_time_ms;
+  bool use_native_numerals;
+  bool include_inherent_vowel;
+  /**
+   * Layouts that cannot type the script fall back to `Phonetic`.
+   */
+  enum LipiTypingLayout layout;
+  /**
+   * Unicode scalar of the key that switches to typing raw Latin text, 0 for none.
+   */
+  uint32_t latin_escape_key;
+  /**
+   * Escape with the key typed twice in a row, so that a single one is still typed.
+   */
+  bool latin_escape_double_tap;
+  enum LipiLatinEscapeScope latin_escape_scope;
+} LipiTypingContextOptions;
+
+/**
+ * Result of processing a key in a typing context.
+ */
+typedef struct LipiTypingDiff {
+  /**
+   * Number of Unicode scalar chars to delete from the end of the current text.
+   */
+  size_t to_delete_chars_count;
+  /**
+   * UTF-8 text to append after deleting.
+   */
+  struct LipiString diff_add_text;
+  /**
+   * Remaining internal context length (0 means context was cleared).
+   */
+  size_t context_length;
+  /**
+   * Keys are passed through as raw Latin text after this diff (Latin escape).
+   */
+  bool latin_passthrough;
+} LipiTypingDiff;
+
+/**
+ * Result of processing a key in preedit/commit terms.
+ */
+typedef struct LipiImeUpdate {
+  /**
+   * `false` when there was nothing to compose and the key should pass through.
+   */
+  bool handled;
+  /**
+   * UTF-8 text to commit to the client now (may be empty).
+   */
+  struct LipiString commit_text;
+  /**
+   * UTF-8 text that replaces the current preedit.
+   */
+  struct LipiString preedit;
+} LipiImeUpdate;
+
+/**
+ * A word and how often it is used, to rank candidates.
+ */
+typedef struct LipiWordFrequency {
+  /**
+   * NUL-terminated output text of the word (eg. `"शिव"`), not its keys.
+   */
+  const char *word;
+  uint64_t count;
+} LipiWordFrequency;
+
+/**
+ * A character of the typing data map with the keys that type it.
+ */
+typedef struct LipiTypingDataMapItem {
+  struct LipiString text;
+  enum LipiListType list_type;
+  struct LipiStringList mappings;
+} LipiTypingDataMapItem;
+
+/**
+ * Owned array of typing data map items.
+ */
+typedef struct LipiTypingDataMapItemList {
+  struct LipiTypingDataMapItem *ptr;
+  size_t len;
+  size_t cap;
+} LipiTypingDataMapItemList;
+
+/**
+ * Typing data map of a script; free with `lipi_typing_data_map_free`.
+ */
+typedef struct LipiTypingDataMap {
+  /**
+   * Characters common across scripts.
+   */
+  struct LipiTypingDataMapItemList common_krama_map;
+  /**
+   * Characters specific to the script.
+   */
+  struct LipiTypingDataMapItemList script_specific_krama_map;
+} LipiTypingDataMap;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns a static, NUL-terminated description of a status code.
+ */
+const char *lipi_status_message(enum LipiStatus status);
+
+/**
+ * Returns the version of this library, eg. `"0.1.0"` (static, NUL-terminated).
+ */
+const char *lipi_version(void);
+
+/**
+ * Frees a `LipiString` previously returned by this library.
+ */
+void lipi_string_free(struct LipiString s);
+
+/**
+ * Frees a `LipiStringList` and all of its strings.
+ */
+void lipi_string_list_free(struct LipiStringList list);
+
+/**
+ * Transliterates `text` from script `from` to script `to`.
+ *
+ * - `options`: `options_len` transliteration options; pass NULL and 0 for the defaults
+ * - `out_text`: receives the transliterated text
+ */
+enum LipiStatus lipi_transliterate(const char *text,
+                                   const char *from,
+                                   const char *to,
+                                   const struct LipiOption *options,
+                                   size_t options_len,
+                                   struct LipiString *out_text);
+
+/**
+ * Transliterates `texts_len` texts from script `from` to script `to`, resolving the
+ * scripts and options once.
+ *
+ * On success `out_list` receives one string per text, in order. On failure (eg. a text
+ * that is not UTF-8) nothing is returned.
+ */
+enum LipiStatus lipi_transliterate_batch(const char *const *texts,
+                                         size_t texts_len,
+                                         const char *from,
+                                         const char *to,
+                                         const struct LipiOption *options,
+                                         size_t options_len,
+                                         struct LipiStringList *out_list);
+
+/**
+ * Loads the data of a script ahead of its first use.
+ */
+enum LipiStatus lipi_preload_script_data(const char *script);
+
+/**
+ * Writes the names of all supported scripts to `out_list`.
+ */
+enum LipiStatus lipi_get_scripts(struct LipiStringList *out_list);
+
+/**
+ * Writes the names of all supported languages to `out_list`.
+ */
+enum LipiStatus lipi_get_langs(struct LipiStringList *out_list);
+
+/**
+ * Writes the script a script, language or alias name stands for to `out_name`, eg.
+ * `"Devanagari"` for `"hi"`.
+ */
+enum LipiStatus lipi_normalize_script_name(const char *name, struct LipiString *out_name);
+
+/**
+ * Writes whether the script deletes the inherent vowel (schwa) to `out_schwa`; `false`
+ * for scripts that are not Brahmic.
+ */
+enum LipiStatus lipi_get_schwa_status(const char *script, bool *out_schwa);
+
+/**
+ * Writes the keys of every transliteration option to `out_list`.
+ */
+enum LipiStatus lipi_get_custom_option_keys(struct LipiStringList *out_list);
+
+/**
+ * Writes the keys of the transliteration options that apply from script `from` to script
+ * `to` to `out_list`.
+ */
+enum LipiStatus lipi_get_all_options(const char *from,
+                                     const char *to,
+                                     struct LipiStringList *out_list);
+
+/**
+ * Frees a `LipiCandidateList` and all of its strings.
+ */
+void lipi_candidate_list_free(struct LipiCandidateList list);
+
+/**
+ * Writes default typing options to `out_opts`.
+ */
+enum LipiStatus lipi_typing_default_options(struct LipiTypingContextOptions *out_opts);
+
+/**
+ * Writes whether `layout` can type `script` to `out_available`.
+ */
+enum LipiStatus lipi_typing_layout_is_available(enum LipiTypingLayout layout,
+                                                const char *script,
+                                                bool *out_available);
+
+/**
+ * Creates a new typing context.
+ *
+ * - `script`: script/language name or alias to type in
+ * - `opts`: optional; pass NULL to use the defaults
+ * - `out_ctx`: receives the context, free it with `lipi_typing_context_free`
+ */
+enum LipiStatus lipi_typing_context_new(const char *script,
+                                        const struct LipiTypingContextOptions *opts,
+                                        struct LipiTypingContext **out_ctx);
+
+/**
+ * Destroys a typing context created by `lipi_typing_context_new` or
+ * `lipi_typing_context_restore`.
+ */
+void lipi_typing_context_free(struct LipiTypingContext *ctx);
+
+/**
+ * Clears all internal state and contexts.
+ */
+enum LipiStatus lipi_typing_context_clear(struct LipiTypingContext *ctx);
+
+/**
+ * Processes a single key (a UTF-8 string, usually of one character).
+ */
+enum LipiStatus lipi_typing_context_take_key_input(struct LipiTypingContext *ctx,
+                                                   const char *key,
+                                                   struct LipiTypingDiff *out_diff);
+
+/**
+ * Processes every key of a UTF-8 string (eg. pasted text) and writes the single net diff
+ * against the output before the call. `context_length` is that of the last key.
+ */
+enum LipiStatus lipi_typing_context_take_text_input(struct LipiTypingContext *ctx,
+                                                    const char *text,
+                                                    struct LipiTypingDiff *out_diff);
+
+/**
+ * Removes the last typed key from the context (Backspace inside a composition).
+ *
+ * An empty diff with `context_length == 0` means the context was empty and the key should
+ * pass through.
+ */
+enum LipiStatus lipi_typing_context_take_backspace(struct LipiTypingContext *ctx,
+                                                   struct LipiTypingDiff *out_diff);
+
+/**
+ * Types the keys of a candidate in place of the current composition.
+ */
+enum LipiStatus lipi_typing_context_select_candidate(struct LipiTypingContext *ctx,
+                                                     const char *input,
+                                                     struct LipiTypingDiff *out_diff);
+
+/**
+ * Processes a single key and reports the text to commit and the new preedit.
+ *
+ * `out_update->handled` is `false` for an empty key.
+ */
+enum LipiStatus lipi_typing_context_ime_key_input(struct LipiTypingContext *ctx,
+                                                  const char *key,
+                                                  struct LipiImeUpdate *out_update);
+
+/**
+ * Removes the last typed key from the composition and reports the new preedit.
+ *
+ * `out_update->handled` is `false` when there is no composition; the key should then
+ * pass through to the client.
+ */
+enum LipiStatus lipi_typing_context_ime_backspace(struct LipiTypingContext *ctx,
+                                                  struct LipiImeUpdate *out_update);
+
+/**
+ * Writes the text being composed to `out_text`.
+ */
+enum LipiStatus lipi_typing_context_preedit(struct LipiTypingContext *ctx,
+                                            struct LipiString *out_text);
+
+/**
+ * Writes the keys typed in the current composition to `out_text`.
+ */
+enum LipiStatus lipi_typing_context_raw_input(struct LipiTypingContext *ctx,
+                                              struct LipiString *out_text);
+
+/**
+ * Writes the prefix of the preedit that no further key can change to `out_text`, keeping
+ * it in the composition.
+ */
+enum LipiStatus lipi_typing_context_committable(struct LipiTypingContext *ctx,
+                                                struct LipiString *out_text);
+
+/**
+ * Writes the prefix of the preedit that no further key can change to `out_text`,
+ * removing it from the composition.
+ */
+enum LipiStatus lipi_typing_context_commit_committable(struct LipiTypingContext *ctx,
+                                                       struct LipiString *out_text);
+
+/**
+ * Finishes the composition: writes the whole preedit to `out_text` and clears the context.
+ */
+enum LipiStatus lipi_typing_context_commit(struct LipiTypingContext *ctx,
+                                           struct LipiString *out_text);
+
+/**
+ * Abandons the composition: writes the raw typed keys to `out_text` and clears the context.
+ */
+enum LipiStatus lipi_typing_context_cancel(struct LipiTypingContext *ctx,
+                                           struct LipiString *out_text);
+
+/**
+ * Writes up to `limit` alternative outputs for the current composition to `out_list`,
+ * best first. The list is empty when nothing is being composed.
+ */
+enum LipiStatus lipi_typing_context_candidates(struct LipiTypingContext *ctx,
+                                               size_t limit,
+                                               struct LipiCandidateList *out_list);
+
+/**
+ * Sets the word frequencies used to rank candidates, replacing any set before; pass NULL
+ * and 0 to rank by closeness to the typed keys only.
+ */
+enum LipiStatus lipi_typing_context_set_word_frequencies(struct LipiTypingContext *ctx,
+                                                         const struct LipiWordFrequency *words,
+                                                         size_t words_len);
+
+/**
+ * Applies user key overrides (a TOML or JSON keymap, as in the desktop app) on top of the
+ * script's typing map; pass NULL to remove them. The context is cleared.
+ *
+ * - `out_conflicts`: optional; receives the number of overrides that change built-in keys
+ * - `out_err`: optional; receives why the keymap was rejected (`LipiStatus::Error`), in
+ *   which case the previous keymap stays
+ */
+enum LipiStatus lipi_typing_context_set_keymap(struct LipiTypingContext *ctx,
+                                               const char *keymap,
+                                               enum LipiKeymapFormat format,
+                                               size_t *out_conflicts,
+                                               struct LipiString *out_err);
+
+/**
+ * Writes the script, options, keymap and current composition of the context to
+ * `out_json`, to continue typing later with `lipi_typing_context_restore`.
+ */
+enum LipiStatus lipi_typing_context_snapshot(struct LipiTypingContext *ctx,
+                                             struct LipiString *out_json);
+
+/**
+ * Creates a typing context from a `lipi_typing_context_snapshot`; the next key continues
+ * its composition.
+ *
+ * - `out_err`: optional; receives why the snapshot could not be restored
+ */
+enum LipiStatus lipi_typing_context_restore(const char *snapshot_json,
+                                            struct LipiTypingContext **out_ctx,
+                                            struct LipiString *out_err);
+
+/**
+ * Updates whether native numerals should be used for subsequent typing.
+ */
+enum LipiStatus lipi_typing_context_set_use_native_numerals(struct LipiTypingContext *ctx,
+                                                            bool use_native_numerals);
+
+/**
+ * Updates whether inherent vowels should be included for subsequent typing.
+ */
+enum LipiStatus lipi_typing_context_set_include_inherent_vowel(struct LipiTypingContext *ctx,
+                                                               bool include_inherent_vowel);
+
+/**
+ * Turns raw Latin typing on or off, as the Latin escape key does.
+ */
+enum LipiStatus lipi_typing_context_set_latin_passthrough(struct LipiTypingContext *ctx,
+                                                          bool latin_passthrough);
+
+/**
+ * Writes the options the context types with to `out_opts`; `layout` is the layout in
+ * use, `Phonetic` if the requested one cannot type the script.
+ */
+enum LipiStatus lipi_typing_context_get_options(struct LipiTypingContext *ctx,
+                                                struct LipiTypingContextOptions *out_opts);
+
+/**
+ * Writes whether keys are currently typed as raw Latin text to `out_passthrough`.
+ */
+enum LipiStatus lipi_typing_context_is_latin_passthrough(struct LipiTypingContext *ctx,
+                                                         bool *out_passthrough);
+
+/**
+ * Writes the normalized name of the script the context types in to `out_name`.
+ */
+enum LipiStatus lipi_typing_context_get_normalized_script(struct LipiTypingContext *ctx,
+                                                          struct LipiString *out_name);
+
+/**
+ * Types every key of `keys` in a new context and writes the resulting text to `out_text`.
+ *
+ * - `opts`: optional; pass NULL to use the defaults
+ */
+enum LipiStatus lipi_emulate_typing(const char *keys,
+                                    const char *script,
+                                    const struct LipiTypingContextOptions *opts,
+                                    struct LipiString *out_text);
+
+/**
+ * Writes the phonetic keys that type `text` in `script` to `out_keys`.
+ *
+ * `out_keys->ptr` is NULL (with `LipiStatus::Ok`) when some part of `text` cannot be
+ * typed.
+ */
+enum LipiStatus lipi_typing_sequence_for(const char *text,
+                                         const char *script,
+                                         const struct LipiTypingContextOptions *opts,
+                                         struct LipiString *out_keys);
+
+/**
+ * Writes the typing data map of a script (its characters with the keys that type them)
+ * to `out_map`.
+ */
+enum LipiStatus lipi_typing_data_map(const char *script, struct LipiTypingDataMap *out_map);
+
+/**
+ * Frees a `LipiTypingDataMap` and everything in it.
+ */
+void lipi_typing_data_map_free(struct LipiTypingDataMap map);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LIPILEKHIKA_H */
@@ -0,0 +1,58 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+SCRIPT_DIR="$(cd -- "$(dirname -- "${BASH_SOURCE[0]}")" && pwd)"
+
+# Parse arguments
+for arg in "$@"; do
+  case $arg in
+    --help|-h)
+      echo "Usage: $0 [OPTIONS]"
+      echo ""
+      echo "Builds the Lipilekhika C library and installs the shared and static libraries,"
+      echo "lipilekhika.h and lipilekhika.pc"
+      echo ""
+      echo "Options:"
+      echo "  --help      Show this help message"
+      echo ""
+      echo "Environment:"
+      echo "  PREFIX      install prefix (default: /usr/local)"
+      echo "  LIBDIR      library directory (default: \$PREFIX/lib)"
+      echo "  DESTDIR     staging directory, eg. for packaging"
+      exit 0
+      ;;
+  esac
+done
+
+PREFIX="${PREFIX:-/usr/local}"
+LIBDIR="${LIBDIR:-$PREFIX/lib}"
+INCLUDEDIR="${PREFIX}/include"
+DESTDIR="${DESTDIR:-}"
+TARGET_DIR="${CARGO_TARGET_DIR:-$SCRIPT_DIR/../../target}"
+VERSION="$(sed -n 's/^version = "\(.*\)"/\1/p' "$SCRIPT_DIR/Cargo.toml" | head -n 1)"
+
+echo "==> Building Lipilekhika C library ${VERSION}"
+echo "    prefix:     ${PREFIX}"
+echo "    libdir:     ${LIBDIR}"
+
+cargo build --release --manifest-path "$SCRIPT_DIR/Cargo.toml"
+
+install -Dm644 "$SCRIPT_DIR/include/lipilekhika.h" "${DESTDIR}${INCLUDEDIR}/lipilekhika.h"
+install -Dm755 "$TARGET_DIR/release/liblipilekhika_c.so" "${DESTDIR}${LIBDIR}/liblipilekhika_c.so"
+install -Dm644 "$TARGET_DIR/release/liblipilekhika_c.a" "${DESTDIR}${LIBDIR}/liblipilekhika_c.a"
+
+mkdir -p "${DESTDIR}${LIBDIR}/pkgconfig"
+sed -e "s|@PREFIX@|${PREFIX}|" \
+  -e "s|@LIBDIR@|${LIBDIR}|" \
+  -e "s|@INCLUDEDIR@|${INCLUDEDIR}|" \
+  -e "s|@VERSION@|${VERSION}|" \
+  "$SCRIPT_DIR/lipilekhika.pc.in" > "${DESTDIR}${LIBDIR}/pkgconfig/lipilekhika.pc"
+
+cat <<END
+
+==> Installed to ${DESTDIR}${PREFIX}
+
+Build against it with:
+  cc app.c \$(pkg-config --cflags --libs lipilekhika)
+
+END
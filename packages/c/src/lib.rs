@@ -0,0 +1,377 @@
+//! General purpose C ABI of Lipilekhika: transliteration, script and option lists, and
+//! typing (see [`typing`]). The header `include/lipilekhika.h` is generated from this crate
+//! by cbindgen on every build.
+//!
+//! Conventions of the ABI:
+//!
+//! - Every string passed in is NUL-terminated UTF-8; script names may be any script or
+//!   language name or alias (`"Devanagari"`, `"hi"`, `"Hindi"`, ...).
+//! - Fallible functions return a [`LipiStatus`] and write their results through `out_*`
+//!   pointers. Outputs are reset first, so they are always safe to free.
+//! - Strings and lists returned are owned by the caller and freed with the matching
+//!   `lipi_*_free` function.
+//! - Panics never cross the boundary; they are reported as [`LipiStatus::Panic`].
+//!
+//! # Safety
+//!
+//! Every exported function trusts its caller for what C cannot check: pointers are NULL or
+//! valid for their use (strings NUL-terminated, arrays of the length passed with them),
+//! handles and returned values come from this library and are freed at most once, and a
+//! typing context is not used from two threads at a time.
+
+#![allow(unsafe_op_in_unsafe_fn)]
+// The contract above applies to every function; see `# Safety`.
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::str::FromStr;
+
+use lipilekhika::{CustomOptions, Script, ScriptListEnum};
+
+pub mod typing;
+
+/// Status codes returned by the C ABI.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LipiStatus {
+    Ok = 0,
+    /// A required pointer argument was NULL.
+    NullPtr = 1,
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// Lipilekhika panicked; the call had no effect on its outputs.
+    Panic = 3,
+    /// Any other failure, described by the `out_err` message where the function has one.
+    Error = 4,
+    /// A script name is not a known script, language or alias.
+    UnknownScript = 5,
+    /// A transliteration option key is not one of `lipi_get_custom_option_keys`.
+    UnknownOption = 6,
+}
+
+/// Owned UTF-8 string allocated by Rust.
+///
+/// - `ptr` points to a heap allocation of **len + 1** bytes.
+/// - The final byte is always `\\0` for convenience.
+/// - The content may be treated as a byte slice of length `len`.
+/// - Call `lipi_string_free` to free.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiString {
+    pub ptr: *mut c_char,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl LipiString {
+    fn null() -> Self {
+        Self {
+            ptr: std::ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        }
+    }
+}
+
+/// Owned array of strings allocated by Rust; free with `lipi_string_list_free`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiStringList {
+    pub ptr: *mut LipiString,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl LipiStringList {
+    fn null() -> Self {
+        Self {
+            ptr: std::ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        }
+    }
+}
+
+/// A transliteration option, by its `category:option` key.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiOption {
+    /// NUL-terminated key, eg. `"all_to_normal:replace_avagraha_with_a"`.
+    pub key: *const c_char,
+    pub enabled: bool,
+}
+
+fn alloc_lipi_string(s: String) -> LipiString {
+    let mut bytes = s.into_bytes();
+    bytes.push(0);
+    let len = bytes.len().saturating_sub(1);
+    let cap = bytes.capacity();
+    let ptr = bytes.as_mut_ptr() as *mut c_char;
+    std::mem::forget(bytes);
+    LipiString { ptr, len, cap }
+}
+
+fn alloc_string_list(strings: impl IntoIterator<Item = String>) -> LipiStringList {
+    let mut items: Vec<LipiString> = strings.into_iter().map(alloc_lipi_string).collect();
+    let list = LipiStringList {
+        ptr: items.as_mut_ptr(),
+        len: items.len(),
+        cap: items.capacity(),
+    };
+    std::mem::forget(items);
+    list
+}
+
+/// Resets `out` so that it is safe to free whatever the call returns, and checks it.
+unsafe fn reset_out<T>(out: *mut T, empty: T) -> Result<(), LipiStatus> {
+    if out.is_null() {
+        return Err(LipiStatus::NullPtr);
+    }
+    *out = empty;
+    Ok(())
+}
+
+unsafe fn set_out_string(out: *mut LipiString, value: Option<String>) {
+    if out.is_null() {
+        return;
+    }
+    *out = match value {
+        Some(s) => alloc_lipi_string(s),
+        None => LipiString::null(),
+    };
+}
+
+unsafe fn cstr_to_str<'a>(ptr: *const c_char) -> Result<&'a str, LipiStatus> {
+    if ptr.is_null() {
+        return Err(LipiStatus::NullPtr);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| LipiStatus::InvalidUtf8)
+}
+
+unsafe fn parse_script(name: *const c_char) -> Result<Script, LipiStatus> {
+    Script::from_str(cstr_to_str(name)?.trim()).map_err(|_| LipiStatus::UnknownScript)
+}
+
+/// Options from a C array; no options (NULL or empty) means the defaults.
+unsafe fn parse_options(
+    options: *const LipiOption,
+    options_len: usize,
+) -> Result<Option<CustomOptions>, LipiStatus> {
+    if options_len == 0 {
+        return Ok(None);
+    }
+    if options.is_null() {
+        return Err(LipiStatus::NullPtr);
+    }
+    let mut parsed = CustomOptions::default();
+    for option in std::slice::from_raw_parts(options, options_len) {
+        parsed
+            .try_set(cstr_to_str(option.key)?, option.enabled)
+            .map_err(|_| LipiStatus::UnknownOption)?;
+    }
+    Ok(Some(parsed))
+}
+
+/// Runs the body of an exported function, turning panics into [`LipiStatus::Panic`].
+fn ffi_call(body: impl FnOnce() -> Result<(), LipiStatus>) -> LipiStatus {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Err(_) => LipiStatus::Panic,
+        Ok(Err(status)) => status,
+        Ok(Ok(())) => LipiStatus::Ok,
+    }
+}
+
+/// Returns a static, NUL-terminated description of a status code.
+#[unsafe(no_mangle)]
+pub extern "C" fn lipi_status_message(status: LipiStatus) -> *const c_char {
+    let message: &'static CStr = match status {
+        LipiStatus::Ok => c"ok",
+        LipiStatus::NullPtr => c"a required pointer argument was NULL",
+        LipiStatus::InvalidUtf8 => c"a string argument is not valid UTF-8",
+        LipiStatus::Panic => c"lipilekhika panicked",
+        LipiStatus::Error => c"operation failed",
+        LipiStatus::UnknownScript => c"unknown script or language name",
+        LipiStatus::UnknownOption => c"unknown transliteration option key",
+    };
+    message.as_ptr()
+}
+
+/// Returns the version of this library, eg. `"0.1.0"` (static, NUL-terminated).
+#[unsafe(no_mangle)]
+pub extern "C" fn lipi_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Frees a `LipiString` previously returned by this library.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_string_free(s: LipiString) {
+    if s.ptr.is_null() {
+        return;
+    }
+    // We always allocate len + 1 bytes, with final NUL.
+    let total_len = s.len.saturating_add(1);
+    let _ = Vec::<u8>::from_raw_parts(s.ptr as *mut u8, total_len, s.cap);
+}
+
+/// Frees a `LipiStringList` and all of its strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_string_list_free(list: LipiStringList) {
+    if list.ptr.is_null() {
+        return;
+    }
+    for item in Vec::from_raw_parts(list.ptr, list.len, list.cap) {
+        lipi_string_free(item);
+    }
+}
+
+/// Transliterates `text` from script `from` to script `to`.
+///
+/// - `options`: `options_len` transliteration options; pass NULL and 0 for the defaults
+/// - `out_text`: receives the transliterated text
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_transliterate(
+    text: *const c_char,
+    from: *const c_char,
+    to: *const c_char,
+    options: *const LipiOption,
+    options_len: usize,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_text, LipiString::null())?;
+        let text = cstr_to_str(text)?;
+        let (from, to) = (parse_script(from)?, parse_script(to)?);
+        let options = parse_options(options, options_len)?;
+        let output = lipilekhika::transliterate(text, from, to, options.as_ref());
+        *out_text = alloc_lipi_string(output.into_owned());
+        Ok(())
+    })
+}
+
+/// Transliterates `texts_len` texts from script `from` to script `to`, resolving the
+/// scripts and options once.
+///
+/// On success `out_list` receives one string per text, in order. On failure (eg. a text
+/// that is not UTF-8) nothing is returned.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_transliterate_batch(
+    texts: *const *const c_char,
+    texts_len: usize,
+    from: *const c_char,
+    to: *const c_char,
+    options: *const LipiOption,
+    options_len: usize,
+    out_list: *mut LipiStringList,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_list, LipiStringList::null())?;
+        if texts.is_null() && texts_len > 0 {
+            return Err(LipiStatus::NullPtr);
+        }
+        let (from, to) = (parse_script(from)?, parse_script(to)?);
+        let options = parse_options(options, options_len)?;
+        let texts: &[*const c_char] = if texts_len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(texts, texts_len)
+        };
+        let texts = texts
+            .iter()
+            .map(|text| cstr_to_str(*text))
+            .collect::<Result<Vec<_>, _>>()?;
+        *out_list =
+            alloc_string_list(texts.into_iter().map(|text| {
+                lipilekhika::transliterate(text, from, to, options.as_ref()).into_owned()
+            }));
+        Ok(())
+    })
+}
+
+/// Loads the data of a script ahead of its first use.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_preload_script_data(script: *const c_char) -> LipiStatus {
+    ffi_call(|| {
+        lipilekhika::preload_script_data(parse_script(script)?);
+        Ok(())
+    })
+}
+
+/// Writes the names of all supported scripts to `out_list`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_get_scripts(out_list: *mut LipiStringList) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_list, LipiStringList::null())?;
+        *out_list = alloc_string_list(lipilekhika::get_script_list_data().scripts.clone());
+        Ok(())
+    })
+}
+
+/// Writes the names of all supported languages to `out_list`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_get_langs(out_list: *mut LipiStringList) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_list, LipiStringList::null())?;
+        *out_list = alloc_string_list(lipilekhika::get_script_list_data().langs.clone());
+        Ok(())
+    })
+}
+
+/// Writes the script a script, language or alias name stands for to `out_name`, eg.
+/// `"Devanagari"` for `"hi"`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_normalize_script_name(
+    name: *const c_char,
+    out_name: *mut LipiString,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_name, LipiString::null())?;
+        let normalized: ScriptListEnum = parse_script(name)?.into();
+        *out_name = alloc_lipi_string(normalized.to_string());
+        Ok(())
+    })
+}
+
+/// Writes whether the script deletes the inherent vowel (schwa) to `out_schwa`; `false`
+/// for scripts that are not Brahmic.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_get_schwa_status(
+    script: *const c_char,
+    out_schwa: *mut bool,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_schwa, false)?;
+        *out_schwa =
+            lipilekhika::get_schwa_status_for_script(parse_script(script)?).unwrap_or(false);
+        Ok(())
+    })
+}
+
+/// Writes the keys of every transliteration option to `out_list`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_get_custom_option_keys(out_list: *mut LipiStringList) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_list, LipiStringList::null())?;
+        *out_list = alloc_string_list(CustomOptions::KEYS.iter().map(|key| key.to_string()));
+        Ok(())
+    })
+}
+
+/// Writes the keys of the transliteration options that apply from script `from` to script
+/// `to` to `out_list`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_get_all_options(
+    from: *const c_char,
+    to: *const c_char,
+    out_list: *mut LipiStringList,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_list, LipiStringList::null())?;
+        let (from, to) = (parse_script(from)?, parse_script(to)?);
+        *out_list = alloc_string_list(lipilekhika::get_all_options(from, to));
+        Ok(())
+    })
+}
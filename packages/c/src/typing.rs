@@ -0,0 +1,884 @@
+//! Typing part of the C ABI: [`LipiTypingContext`] for key-by-key typing (diff and
+//! preedit/commit styles), candidates, keymaps, snapshots and the typing data map.
+
+use std::os::raw::c_char;
+
+use lipilekhika::keymap::Keymap;
+use lipilekhika::typing::{
+    Candidate, FrequencyModel, ImeUpdate, LatinEscape, LatinEscapeScope, ListType, TypingDiff,
+    TypingLayout, TypingSnapshot,
+};
+
+use crate::{
+    LipiStatus, LipiString, LipiStringList, alloc_lipi_string, alloc_string_list, cstr_to_str,
+    ffi_call, parse_script, reset_out, set_out_string,
+};
+
+type RustTypingContext = lipilekhika::typing::TypingContext;
+type RustTypingContextOptions = lipilekhika::typing::TypingContextOptions;
+
+/// Keyboard layout used for typing.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LipiTypingLayout {
+    /// Phonetic Roman input.
+    Phonetic = 0,
+    /// InScript, for any Brahmic script.
+    InScript = 1,
+    /// Tamil99, for Tamil.
+    Tamil99 = 2,
+}
+
+impl From<LipiTypingLayout> for TypingLayout {
+    fn from(layout: LipiTypingLayout) -> Self {
+        match layout {
+            LipiTypingLayout::Phonetic => TypingLayout::Phonetic,
+            LipiTypingLayout::InScript => TypingLayout::InScript,
+            LipiTypingLayout::Tamil99 => TypingLayout::Tamil99,
+        }
+    }
+}
+
+impl From<TypingLayout> for LipiTypingLayout {
+    fn from(layout: TypingLayout) -> Self {
+        match layout {
+            TypingLayout::Phonetic => LipiTypingLayout::Phonetic,
+            TypingLayout::InScript => LipiTypingLayout::InScript,
+            TypingLayout::Tamil99 => LipiTypingLayout::Tamil99,
+        }
+    }
+}
+
+/// How long the Latin text of a Latin escape key lasts.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LipiLatinEscapeScope {
+    /// Until a whitespace (typed as well) or the escape key.
+    NextWord = 0,
+    /// Until the escape key is typed again.
+    UntilEscape = 1,
+}
+
+/// Options for configuring a typing context; start from `lipi_typing_default_options`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiTypingContextOptions {
+    pub auto_context_clear_time_ms: u64,
+    pub use_native_numerals: bool,
+    pub include_inherent_vowel: bool,
+    /// Layouts that cannot type the script fall back to `Phonetic`.
+    pub layout: LipiTypingLayout,
+    /// Unicode scalar of the key that switches to typing raw Latin text, 0 for none.
+    pub latin_escape_key: u32,
+    /// Escape with the key typed twice in a row, so that a single one is still typed.
+    pub latin_escape_double_tap: bool,
+    pub latin_escape_scope: LipiLatinEscapeScope,
+}
+
+impl LipiTypingContextOptions {
+    fn to_rust(self) -> Result<RustTypingContextOptions, LipiStatus> {
+        let latin_escape = match self.latin_escape_key {
+            0 => None,
+            key => Some(LatinEscape {
+                key: char::from_u32(key).ok_or(LipiStatus::Error)?,
+                double_tap: self.latin_escape_double_tap,
+                scope: match self.latin_escape_scope {
+                    LipiLatinEscapeScope::NextWord => LatinEscapeScope::NextWord,
+                    LipiLatinEscapeScope::UntilEscape => LatinEscapeScope::UntilEscape,
+                },
+            }),
+        };
+        Ok(RustTypingContextOptions {
+            auto_context_clear_time_ms: self.auto_context_clear_time_ms,
+            use_native_numerals: self.use_native_numerals,
+            include_inherent_vowel: self.include_inherent_vowel,
+            layout: self.layout.into(),
+            latin_escape,
+        })
+    }
+}
+
+impl From<RustTypingContextOptions> for LipiTypingContextOptions {
+    fn from(options: RustTypingContextOptions) -> Self {
+        let escape = options.latin_escape;
+        Self {
+            auto_context_clear_time_ms: options.auto_context_clear_time_ms,
+            use_native_numerals: options.use_native_numerals,
+            include_inherent_vowel: options.include_inherent_vowel,
+            layout: options.layout.into(),
+            latin_escape_key: escape.map_or(0, |escape| escape.key as u32),
+            latin_escape_double_tap: escape.is_some_and(|escape| escape.double_tap),
+            latin_escape_scope: match escape.map(|escape| escape.scope) {
+                Some(LatinEscapeScope::UntilEscape) => LipiLatinEscapeScope::UntilEscape,
+                _ => LipiLatinEscapeScope::NextWord,
+            },
+        }
+    }
+}
+
+/// Result of processing a key in a typing context.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiTypingDiff {
+    /// Number of Unicode scalar chars to delete from the end of the current text.
+    pub to_delete_chars_count: usize,
+    /// UTF-8 text to append after deleting.
+    pub diff_add_text: LipiString,
+    /// Remaining internal context length (0 means context was cleared).
+    pub context_length: usize,
+    /// Keys are passed through as raw Latin text after this diff (Latin escape).
+    pub latin_passthrough: bool,
+}
+
+impl LipiTypingDiff {
+    fn null() -> Self {
+        Self {
+            to_delete_chars_count: 0,
+            diff_add_text: LipiString::null(),
+            context_length: 0,
+            latin_passthrough: false,
+        }
+    }
+}
+
+impl From<TypingDiff> for LipiTypingDiff {
+    fn from(diff: TypingDiff) -> Self {
+        Self {
+            to_delete_chars_count: diff.to_delete_chars_count,
+            diff_add_text: alloc_lipi_string(diff.diff_add_text),
+            context_length: diff.context_length,
+            latin_passthrough: diff.latin_passthrough,
+        }
+    }
+}
+
+/// Result of processing a key in preedit/commit terms.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiImeUpdate {
+    /// `false` when there was nothing to compose and the key should pass through.
+    pub handled: bool,
+    /// UTF-8 text to commit to the client now (may be empty).
+    pub commit_text: LipiString,
+    /// UTF-8 text that replaces the current preedit.
+    pub preedit: LipiString,
+}
+
+impl LipiImeUpdate {
+    fn null() -> Self {
+        Self {
+            handled: false,
+            commit_text: LipiString::null(),
+            preedit: LipiString::null(),
+        }
+    }
+}
+
+impl From<Option<ImeUpdate>> for LipiImeUpdate {
+    fn from(update: Option<ImeUpdate>) -> Self {
+        match update {
+            Some(update) => Self {
+                handled: true,
+                commit_text: alloc_lipi_string(update.commit_text),
+                preedit: alloc_lipi_string(update.preedit),
+            },
+            None => Self::null(),
+        }
+    }
+}
+
+/// An alternative output for the current composition.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiCandidate {
+    /// UTF-8 text the composition would produce.
+    pub text: LipiString,
+    /// UTF-8 keys that produce `text`; pass them to `lipi_typing_context_select_candidate`.
+    pub input: LipiString,
+}
+
+/// Owned array of candidates allocated by Rust; free with `lipi_candidate_list_free`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiCandidateList {
+    pub ptr: *mut LipiCandidate,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl LipiCandidateList {
+    fn null() -> Self {
+        Self {
+            ptr: std::ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        }
+    }
+}
+
+/// A word and how often it is used, to rank candidates.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiWordFrequency {
+    /// NUL-terminated output text of the word (eg. `"शिव"`), not its keys.
+    pub word: *const c_char,
+    pub count: u64,
+}
+
+/// Format of a keymap passed to `lipi_typing_context_set_keymap`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LipiKeymapFormat {
+    Toml = 0,
+    Json = 1,
+}
+
+/// Type of a character in a script's list.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LipiListType {
+    Anya = 0,
+    Vyanjana = 1,
+    Matra = 2,
+    Svara = 3,
+}
+
+impl From<ListType> for LipiListType {
+    fn from(list_type: ListType) -> Self {
+        match list_type {
+            ListType::Anya => LipiListType::Anya,
+            ListType::Vyanjana => LipiListType::Vyanjana,
+            ListType::Matra => LipiListType::Matra,
+            ListType::Svara => LipiListType::Svara,
+        }
+    }
+}
+
+/// A character of the typing data map with the keys that type it.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiTypingDataMapItem {
+    pub text: LipiString,
+    pub list_type: LipiListType,
+    pub mappings: LipiStringList,
+}
+
+/// Owned array of typing data map items.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiTypingDataMapItemList {
+    pub ptr: *mut LipiTypingDataMapItem,
+    pub len: usize,
+    pub cap: usize,
+}
+
+/// Typing data map of a script; free with `lipi_typing_data_map_free`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiTypingDataMap {
+    /// Characters common across scripts.
+    pub common_krama_map: LipiTypingDataMapItemList,
+    /// Characters specific to the script.
+    pub script_specific_krama_map: LipiTypingDataMapItemList,
+}
+
+/// Opaque handle type used by the C ABI.
+///
+/// Intentionally **not** `#[repr(C)]` so cbindgen will treat it as opaque.
+pub struct LipiTypingContext {
+    _private: *mut std::ffi::c_void,
+}
+
+fn alloc_candidate_list(candidates: Vec<Candidate>) -> LipiCandidateList {
+    let mut items: Vec<LipiCandidate> = candidates
+        .into_iter()
+        .map(|c| LipiCandidate {
+            text: alloc_lipi_string(c.text),
+            input: alloc_lipi_string(c.input),
+        })
+        .collect();
+    let list = LipiCandidateList {
+        ptr: items.as_mut_ptr(),
+        len: items.len(),
+        cap: items.capacity(),
+    };
+    std::mem::forget(items);
+    list
+}
+
+fn alloc_typing_data_items(
+    items: Vec<lipilekhika::TypingDataMapItem>,
+) -> LipiTypingDataMapItemList {
+    let mut items: Vec<LipiTypingDataMapItem> = items
+        .into_iter()
+        .map(|(text, list_type, mappings)| LipiTypingDataMapItem {
+            text: alloc_lipi_string(text),
+            list_type: list_type.into(),
+            mappings: alloc_string_list(mappings),
+        })
+        .collect();
+    let list = LipiTypingDataMapItemList {
+        ptr: items.as_mut_ptr(),
+        len: items.len(),
+        cap: items.capacity(),
+    };
+    std::mem::forget(items);
+    list
+}
+
+unsafe fn free_typing_data_items(list: LipiTypingDataMapItemList) {
+    if list.ptr.is_null() {
+        return;
+    }
+    for item in Vec::from_raw_parts(list.ptr, list.len, list.cap) {
+        crate::lipi_string_free(item.text);
+        crate::lipi_string_list_free(item.mappings);
+    }
+}
+
+unsafe fn ctx_from_ptr<'a>(
+    ctx: *mut LipiTypingContext,
+) -> Result<&'a mut RustTypingContext, LipiStatus> {
+    if ctx.is_null() {
+        return Err(LipiStatus::NullPtr);
+    }
+    Ok(&mut *(ctx as *mut RustTypingContext))
+}
+
+/// Options from C; NULL means the defaults.
+unsafe fn parse_typing_options(
+    opts: *const LipiTypingContextOptions,
+) -> Result<Option<RustTypingContextOptions>, LipiStatus> {
+    if opts.is_null() {
+        return Ok(None);
+    }
+    (*opts).to_rust().map(Some)
+}
+
+fn into_handle(ctx: RustTypingContext) -> *mut LipiTypingContext {
+    Box::into_raw(Box::new(ctx)) as *mut LipiTypingContext
+}
+
+/// Runs a context operation that produces a diff and writes it to `out_diff`.
+unsafe fn context_diff(
+    ctx: *mut LipiTypingContext,
+    out_diff: *mut LipiTypingDiff,
+    op: impl FnOnce(&mut RustTypingContext) -> Result<TypingDiff, LipiStatus>,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_diff, LipiTypingDiff::null())?;
+        let diff = op(ctx_from_ptr(ctx)?)?;
+        *out_diff = diff.into();
+        Ok(())
+    })
+}
+
+/// Runs a context operation that produces a string and writes it to `out_text`.
+unsafe fn context_string(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+    op: impl FnOnce(&mut RustTypingContext) -> String,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_text, LipiString::null())?;
+        *out_text = alloc_lipi_string(op(ctx_from_ptr(ctx)?));
+        Ok(())
+    })
+}
+
+/// Runs a context operation that produces nothing.
+unsafe fn context_update(
+    ctx: *mut LipiTypingContext,
+    op: impl FnOnce(&mut RustTypingContext),
+) -> LipiStatus {
+    ffi_call(|| {
+        op(ctx_from_ptr(ctx)?);
+        Ok(())
+    })
+}
+
+/// Frees a `LipiCandidateList` and all of its strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_candidate_list_free(list: LipiCandidateList) {
+    if list.ptr.is_null() {
+        return;
+    }
+    for item in Vec::from_raw_parts(list.ptr, list.len, list.cap) {
+        crate::lipi_string_free(item.text);
+        crate::lipi_string_free(item.input);
+    }
+}
+
+/// Writes default typing options to `out_opts`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_default_options(
+    out_opts: *mut LipiTypingContextOptions,
+) -> LipiStatus {
+    if out_opts.is_null() {
+        return LipiStatus::NullPtr;
+    }
+    *out_opts = RustTypingContextOptions::default().into();
+    LipiStatus::Ok
+}
+
+/// Writes whether `layout` can type `script` to `out_available`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_layout_is_available(
+    layout: LipiTypingLayout,
+    script: *const c_char,
+    out_available: *mut bool,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_available, false)?;
+        *out_available = TypingLayout::from(layout).is_available_for(parse_script(script)?);
+        Ok(())
+    })
+}
+
+/// Creates a new typing context.
+///
+/// - `script`: script/language name or alias to type in
+/// - `opts`: optional; pass NULL to use the defaults
+/// - `out_ctx`: receives the context, free it with `lipi_typing_context_free`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_new(
+    script: *const c_char,
+    opts: *const LipiTypingContextOptions,
+    out_ctx: *mut *mut LipiTypingContext,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_ctx, std::ptr::null_mut())?;
+        let script = parse_script(script)?;
+        let opts = parse_typing_options(opts)?;
+        *out_ctx = into_handle(RustTypingContext::new(script, opts));
+        Ok(())
+    })
+}
+
+/// Destroys a typing context created by `lipi_typing_context_new` or
+/// `lipi_typing_context_restore`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_free(ctx: *mut LipiTypingContext) {
+    if ctx.is_null() {
+        return;
+    }
+    drop(Box::from_raw(ctx as *mut RustTypingContext));
+}
+
+/// Clears all internal state and contexts.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_clear(ctx: *mut LipiTypingContext) -> LipiStatus {
+    context_update(ctx, |ctx| ctx.clear_context())
+}
+
+/// Processes a single key (a UTF-8 string, usually of one character).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_take_key_input(
+    ctx: *mut LipiTypingContext,
+    key: *const c_char,
+    out_diff: *mut LipiTypingDiff,
+) -> LipiStatus {
+    context_diff(ctx, out_diff, |ctx| {
+        Ok(ctx.take_key_input(cstr_to_str(key)?))
+    })
+}
+
+/// Processes every key of a UTF-8 string (eg. pasted text) and writes the single net diff
+/// against the output before the call. `context_length` is that of the last key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_take_text_input(
+    ctx: *mut LipiTypingContext,
+    text: *const c_char,
+    out_diff: *mut LipiTypingDiff,
+) -> LipiStatus {
+    context_diff(ctx, out_diff, |ctx| {
+        Ok(ctx.take_text_input(cstr_to_str(text)?))
+    })
+}
+
+/// Removes the last typed key from the context (Backspace inside a composition).
+///
+/// An empty diff with `context_length == 0` means the context was empty and the key should
+/// pass through.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_take_backspace(
+    ctx: *mut LipiTypingContext,
+    out_diff: *mut LipiTypingDiff,
+) -> LipiStatus {
+    context_diff(ctx, out_diff, |ctx| Ok(ctx.take_backspace()))
+}
+
+/// Types the keys of a candidate in place of the current composition.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_select_candidate(
+    ctx: *mut LipiTypingContext,
+    input: *const c_char,
+    out_diff: *mut LipiTypingDiff,
+) -> LipiStatus {
+    context_diff(ctx, out_diff, |ctx| {
+        Ok(ctx.select_candidate(cstr_to_str(input)?))
+    })
+}
+
+/// Processes a single key and reports the text to commit and the new preedit.
+///
+/// `out_update->handled` is `false` for an empty key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_ime_key_input(
+    ctx: *mut LipiTypingContext,
+    key: *const c_char,
+    out_update: *mut LipiImeUpdate,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_update, LipiImeUpdate::null())?;
+        let key = cstr_to_str(key)?;
+        let ctx = ctx_from_ptr(ctx)?;
+        let update = key.chars().next().map(|ch| ctx.take_key_input_ime(ch));
+        *out_update = update.into();
+        Ok(())
+    })
+}
+
+/// Removes the last typed key from the composition and reports the new preedit.
+///
+/// `out_update->handled` is `false` when there is no composition; the key should then
+/// pass through to the client.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_ime_backspace(
+    ctx: *mut LipiTypingContext,
+    out_update: *mut LipiImeUpdate,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_update, LipiImeUpdate::null())?;
+        *out_update = ctx_from_ptr(ctx)?.take_backspace_ime().into();
+        Ok(())
+    })
+}
+
+/// Writes the text being composed to `out_text`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_preedit(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    context_string(ctx, out_text, |ctx| ctx.preedit().to_string())
+}
+
+/// Writes the keys typed in the current composition to `out_text`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_raw_input(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    context_string(ctx, out_text, |ctx| ctx.raw_input().to_string())
+}
+
+/// Writes the prefix of the preedit that no further key can change to `out_text`, keeping
+/// it in the composition.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_committable(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    context_string(ctx, out_text, |ctx| ctx.committable().to_string())
+}
+
+/// Writes the prefix of the preedit that no further key can change to `out_text`,
+/// removing it from the composition.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_commit_committable(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    context_string(ctx, out_text, |ctx| ctx.commit_committable())
+}
+
+/// Finishes the composition: writes the whole preedit to `out_text` and clears the context.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_commit(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    context_string(ctx, out_text, |ctx| ctx.commit())
+}
+
+/// Abandons the composition: writes the raw typed keys to `out_text` and clears the context.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_cancel(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    context_string(ctx, out_text, |ctx| ctx.cancel())
+}
+
+/// Writes up to `limit` alternative outputs for the current composition to `out_list`,
+/// best first. The list is empty when nothing is being composed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_candidates(
+    ctx: *mut LipiTypingContext,
+    limit: usize,
+    out_list: *mut LipiCandidateList,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_list, LipiCandidateList::null())?;
+        *out_list = alloc_candidate_list(ctx_from_ptr(ctx)?.candidates(limit));
+        Ok(())
+    })
+}
+
+/// Sets the word frequencies used to rank candidates, replacing any set before; pass NULL
+/// and 0 to rank by closeness to the typed keys only.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_set_word_frequencies(
+    ctx: *mut LipiTypingContext,
+    words: *const LipiWordFrequency,
+    words_len: usize,
+) -> LipiStatus {
+    ffi_call(|| {
+        let ctx = ctx_from_ptr(ctx)?;
+        if words_len == 0 {
+            ctx.set_frequency_model(None);
+            return Ok(());
+        }
+        if words.is_null() {
+            return Err(LipiStatus::NullPtr);
+        }
+        let mut model = FrequencyModel::new();
+        for word in std::slice::from_raw_parts(words, words_len) {
+            model.set_frequency(cstr_to_str(word.word)?, word.count);
+        }
+        ctx.set_frequency_model(Some(model));
+        Ok(())
+    })
+}
+
+/// Applies user key overrides (a TOML or JSON keymap, as in the desktop app) on top of the
+/// script's typing map; pass NULL to remove them. The context is cleared.
+///
+/// - `out_conflicts`: optional; receives the number of overrides that change built-in keys
+/// - `out_err`: optional; receives why the keymap was rejected (`LipiStatus::Error`), in
+///   which case the previous keymap stays
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_set_keymap(
+    ctx: *mut LipiTypingContext,
+    keymap: *const c_char,
+    format: LipiKeymapFormat,
+    out_conflicts: *mut usize,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+    ffi_call(|| {
+        let ctx = ctx_from_ptr(ctx)?;
+        let keymap = if keymap.is_null() {
+            None
+        } else {
+            let text = cstr_to_str(keymap)?;
+            let parsed = match format {
+                LipiKeymapFormat::Toml => Keymap::from_toml_str(text),
+                LipiKeymapFormat::Json => Keymap::from_json_str(text),
+            };
+            Some(parsed.map_err(|e| {
+                set_out_string(out_err, Some(e.to_string()));
+                LipiStatus::Error
+            })?)
+        };
+        let conflicts = ctx.set_keymap(keymap.as_ref()).map_err(|e| {
+            set_out_string(out_err, Some(e.to_string()));
+            LipiStatus::Error
+        })?;
+        if !out_conflicts.is_null() {
+            *out_conflicts = conflicts.len();
+        }
+        Ok(())
+    })
+}
+
+/// Writes the script, options, keymap and current composition of the context to
+/// `out_json`, to continue typing later with `lipi_typing_context_restore`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_snapshot(
+    ctx: *mut LipiTypingContext,
+    out_json: *mut LipiString,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_json, LipiString::null())?;
+        let snapshot = ctx_from_ptr(ctx)?.snapshot();
+        let json = serde_json::to_string(&snapshot).map_err(|_| LipiStatus::Error)?;
+        *out_json = alloc_lipi_string(json);
+        Ok(())
+    })
+}
+
+/// Creates a typing context from a `lipi_typing_context_snapshot`; the next key continues
+/// its composition.
+///
+/// - `out_err`: optional; receives why the snapshot could not be restored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_restore(
+    snapshot_json: *const c_char,
+    out_ctx: *mut *mut LipiTypingContext,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+    ffi_call(|| {
+        reset_out(out_ctx, std::ptr::null_mut())?;
+        let fail = |message: String| {
+            set_out_string(out_err, Some(message));
+            LipiStatus::Error
+        };
+        let snapshot: TypingSnapshot = serde_json::from_str(cstr_to_str(snapshot_json)?)
+            .map_err(|e| fail(format!("invalid snapshot: {e}")))?;
+        let ctx = RustTypingContext::restore(&snapshot).map_err(|e| fail(e.to_string()))?;
+        *out_ctx = into_handle(ctx);
+        Ok(())
+    })
+}
+
+/// Updates whether native numerals should be used for subsequent typing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_set_use_native_numerals(
+    ctx: *mut LipiTypingContext,
+    use_native_numerals: bool,
+) -> LipiStatus {
+    context_update(ctx, |ctx| {
+        ctx.update_use_native_numerals(use_native_numerals)
+    })
+}
+
+/// Updates whether inherent vowels should be included for subsequent typing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_set_include_inherent_vowel(
+    ctx: *mut LipiTypingContext,
+    include_inherent_vowel: bool,
+) -> LipiStatus {
+    context_update(ctx, |ctx| {
+        ctx.update_include_inherent_vowel(include_inherent_vowel)
+    })
+}
+
+/// Turns raw Latin typing on or off, as the Latin escape key does.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_set_latin_passthrough(
+    ctx: *mut LipiTypingContext,
+    latin_passthrough: bool,
+) -> LipiStatus {
+    context_update(ctx, |ctx| ctx.set_latin_passthrough(latin_passthrough))
+}
+
+/// Writes the options the context types with to `out_opts`; `layout` is the layout in
+/// use, `Phonetic` if the requested one cannot type the script.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_get_options(
+    ctx: *mut LipiTypingContext,
+    out_opts: *mut LipiTypingContextOptions,
+) -> LipiStatus {
+    ffi_call(|| {
+        if out_opts.is_null() {
+            return Err(LipiStatus::NullPtr);
+        }
+        *out_opts = ctx_from_ptr(ctx)?.snapshot().options.into();
+        Ok(())
+    })
+}
+
+/// Writes whether keys are currently typed as raw Latin text to `out_passthrough`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_is_latin_passthrough(
+    ctx: *mut LipiTypingContext,
+    out_passthrough: *mut bool,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_passthrough, false)?;
+        *out_passthrough = ctx_from_ptr(ctx)?.is_latin_passthrough();
+        Ok(())
+    })
+}
+
+/// Writes the normalized name of the script the context types in to `out_name`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_get_normalized_script(
+    ctx: *mut LipiTypingContext,
+    out_name: *mut LipiString,
+) -> LipiStatus {
+    context_string(ctx, out_name, |ctx| ctx.get_normalized_script().to_string())
+}
+
+/// Types every key of `keys` in a new context and writes the resulting text to `out_text`.
+///
+/// - `opts`: optional; pass NULL to use the defaults
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_emulate_typing(
+    keys: *const c_char,
+    script: *const c_char,
+    opts: *const LipiTypingContextOptions,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_text, LipiString::null())?;
+        let keys = cstr_to_str(keys)?;
+        let script = parse_script(script)?;
+        let opts = parse_typing_options(opts)?;
+        *out_text = alloc_lipi_string(lipilekhika::typing::emulate_typing(keys, script, opts));
+        Ok(())
+    })
+}
+
+/// Writes the phonetic keys that type `text` in `script` to `out_keys`.
+///
+/// `out_keys->ptr` is NULL (with `LipiStatus::Ok`) when some part of `text` cannot be
+/// typed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_sequence_for(
+    text: *const c_char,
+    script: *const c_char,
+    opts: *const LipiTypingContextOptions,
+    out_keys: *mut LipiString,
+) -> LipiStatus {
+    ffi_call(|| {
+        reset_out(out_keys, LipiString::null())?;
+        let text = cstr_to_str(text)?;
+        let script = parse_script(script)?;
+        let opts = parse_typing_options(opts)?;
+        set_out_string(
+            out_keys,
+            lipilekhika::typing::typing_sequence_for(text, script, opts),
+        );
+        Ok(())
+    })
+}
+
+/// Writes the typing data map of a script (its characters with the keys that type them)
+/// to `out_map`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_data_map(
+    script: *const c_char,
+    out_map: *mut LipiTypingDataMap,
+) -> LipiStatus {
+    let empty = LipiTypingDataMapItemList {
+        ptr: std::ptr::null_mut(),
+        len: 0,
+        cap: 0,
+    };
+    ffi_call(|| {
+        reset_out(
+            out_map,
+            LipiTypingDataMap {
+                common_krama_map: empty,
+                script_specific_krama_map: empty,
+            },
+        )?;
+        let data = lipilekhika::get_script_typing_data_map(parse_script(script)?);
+        *out_map = LipiTypingDataMap {
+            common_krama_map: alloc_typing_data_items(data.common_krama_map),
+            script_specific_krama_map: alloc_typing_data_items(data.script_specific_krama_map),
+        };
+        Ok(())
+    })
+}
+
+/// Frees a `LipiTypingDataMap` and everything in it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_data_map_free(map: LipiTypingDataMap) {
+    free_typing_data_items(map.common_krama_map);
+    free_typing_data_items(map.script_specific_krama_map);
+}
@@ -0,0 +1,54 @@
+/* Minimal assertions shared by the C tests. */
+
+#ifndef LIPILEKHIKA_CHECK_H
+#define LIPILEKHIKA_CHECK_H
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "lipilekhika.h"
+
+#define CHECK(cond)                                                            \
+  do {                                                                         \
+    if (!(cond)) {                                                             \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+      exit(1);                                                                 \
+    }                                                                          \
+  } while (0)
+
+#define CHECK_STATUS(call, expected)                                           \
+  do {                                                                         \
+    LipiStatus status_ = (call);                                               \
+    if (status_ != (expected)) {                                               \
+      fprintf(stderr, "%s:%d: %s returned %d (%s)\n", __FILE__, __LINE__,      \
+              #call, (int)status_, lipi_status_message(status_));              \
+      exit(1);                                                                 \
+    }                                                                          \
+  } while (0)
+
+#define CHECK_OK(call) CHECK_STATUS(call, LIPI_STATUS_OK)
+
+/* Checks a returned string and frees it. */
+#define CHECK_STRING(s, expected)                                              \
+  do {                                                                         \
+    LipiString s_ = (s);                                                       \
+    if (s_.ptr == NULL || strcmp(s_.ptr, (expected)) != 0 ||                   \
+        s_.len != strlen(expected)) {                                          \
+      fprintf(stderr, "%s:%d: got \"%s\", expected \"%s\"\n", __FILE__,        \
+              __LINE__, s_.ptr ? s_.ptr : "(null)", (expected));               \
+      exit(1);                                                                 \
+    }                                                                          \
+    lipi_string_free(s_);                                                      \
+  } while (0)
+
+static inline int list_contains(LipiStringList list, const char *text) {
+  for (size_t i = 0; i < list.len; i++) {
+    if (strcmp(list.ptr[i].ptr, text) == 0) {
+      return 1;
+    }
+  }
+  return 0;
+}
+
+#endif
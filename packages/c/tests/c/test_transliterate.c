@@ -0,0 +1,115 @@
+/* Transliteration, script and option lists. */
+
+#include "check.h"
+
+static void test_transliterate(void) {
+  LipiString out;
+  CHECK_OK(lipi_transliterate("namaste", "Normal", "Devanagari", NULL, 0, &out));
+  CHECK_STRING(out, "नमस्ते");
+
+  /* languages and aliases name scripts too */
+  CHECK_OK(lipi_transliterate("नमस्ते", "hi", "Telugu", NULL, 0, &out));
+  CHECK_STRING(out, "నమస్తే");
+
+  LipiOption options[] = {
+      {"all_to_normal:replace_avagraha_with_a", true},
+  };
+  CHECK_OK(lipi_transliterate("सोऽहम्", "Devanagari", "Normal", options, 1, &out));
+  CHECK_STRING(out, "sOaham");
+}
+
+static void test_errors(void) {
+  LipiString out = {0};
+  CHECK_STATUS(lipi_transliterate("a", "Klingon", "Devanagari", NULL, 0, &out),
+               LIPI_STATUS_UNKNOWN_SCRIPT);
+  /* outputs are reset on failure, so freeing them is always safe */
+  CHECK(out.ptr == NULL);
+  lipi_string_free(out);
+
+  LipiOption unknown[] = {{"no_such:option", true}};
+  CHECK_STATUS(lipi_transliterate("a", "Normal", "Devanagari", unknown, 1, &out),
+               LIPI_STATUS_UNKNOWN_OPTION);
+  CHECK_STATUS(lipi_transliterate(NULL, "Normal", "Devanagari", NULL, 0, &out),
+               LIPI_STATUS_NULL_PTR);
+  CHECK_STATUS(lipi_transliterate("\xff", "Normal", "Devanagari", NULL, 0, &out),
+               LIPI_STATUS_INVALID_UTF8);
+  CHECK_STATUS(lipi_transliterate("a", "Normal", "Devanagari", NULL, 0, NULL),
+               LIPI_STATUS_NULL_PTR);
+  CHECK(strlen(lipi_status_message(LIPI_STATUS_UNKNOWN_SCRIPT)) > 0);
+}
+
+static void test_batch(void) {
+  const char *texts[] = {"rAma", "", "kRShNa"};
+  LipiStringList out;
+  CHECK_OK(lipi_transliterate_batch(texts, 3, "Normal", "Devanagari", NULL, 0, &out));
+  CHECK(out.len == 3);
+  CHECK(strcmp(out.ptr[0].ptr, "राम") == 0);
+  CHECK(strcmp(out.ptr[1].ptr, "") == 0);
+  CHECK(strcmp(out.ptr[2].ptr, "कृष्ण") == 0);
+  lipi_string_list_free(out);
+
+  CHECK_OK(lipi_transliterate_batch(NULL, 0, "Normal", "Devanagari", NULL, 0, &out));
+  CHECK(out.len == 0);
+  lipi_string_list_free(out);
+
+  const char *invalid[] = {"rAma", "\xff"};
+  CHECK_STATUS(
+      lipi_transliterate_batch(invalid, 2, "Normal", "Devanagari", NULL, 0, &out),
+      LIPI_STATUS_INVALID_UTF8);
+  CHECK(out.ptr == NULL && out.len == 0);
+}
+
+static void test_scripts(void) {
+  LipiStringList scripts;
+  CHECK_OK(lipi_get_scripts(&scripts));
+  CHECK(list_contains(scripts, "Devanagari"));
+  CHECK(list_contains(scripts, "Tamil"));
+  lipi_string_list_free(scripts);
+
+  LipiStringList langs;
+  CHECK_OK(lipi_get_langs(&langs));
+  CHECK(list_contains(langs, "Sanskrit"));
+  lipi_string_list_free(langs);
+
+  LipiString name;
+  CHECK_OK(lipi_normalize_script_name("hi", &name));
+  CHECK_STRING(name, "Devanagari");
+  CHECK_OK(lipi_normalize_script_name("  telugu ", &name));
+  CHECK_STRING(name, "Telugu");
+  CHECK_STATUS(lipi_normalize_script_name("nope", &name), LIPI_STATUS_UNKNOWN_SCRIPT);
+
+  bool schwa = true;
+  CHECK_OK(lipi_get_schwa_status("Bengali", &schwa));
+  CHECK(schwa);
+  CHECK_OK(lipi_get_schwa_status("Telugu", &schwa));
+  CHECK(!schwa);
+
+  CHECK_OK(lipi_preload_script_data("Bengali"));
+  CHECK(strlen(lipi_version()) > 0);
+}
+
+static void test_options(void) {
+  LipiStringList keys;
+  CHECK_OK(lipi_get_custom_option_keys(&keys));
+  CHECK(keys.len > 0);
+  CHECK(list_contains(keys, "all_to_normal:replace_avagraha_with_a"));
+
+  LipiStringList options;
+  CHECK_OK(lipi_get_all_options("Devanagari", "Normal", &options));
+  CHECK(options.len > 0 && options.len <= keys.len);
+  for (size_t i = 0; i < options.len; i++) {
+    CHECK(list_contains(keys, options.ptr[i].ptr));
+  }
+  lipi_string_list_free(options);
+  lipi_string_list_free(keys);
+}
+
+int main(void) {
+  test_transliterate();
+  test_errors();
+  test_batch();
+  test_scripts();
+  test_options();
+  puts("test_transliterate: ok");
+  return 0;
+}
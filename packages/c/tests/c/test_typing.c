@@ -0,0 +1,231 @@
+/* Typing contexts, the typing helpers and the typing data map. */
+
+#include "check.h"
+
+/* Applies a diff to `text` (UTF-8, at most `size` bytes) like an application would. */
+static void apply_diff(char *text, size_t size, LipiTypingDiff diff) {
+  size_t len = strlen(text);
+  for (size_t i = 0; i < diff.to_delete_chars_count && len > 0; i++) {
+    /* step back over continuation bytes to the start of the last char */
+    do {
+      len--;
+    } while (len > 0 && ((unsigned char)text[len] & 0xC0) == 0x80);
+  }
+  text[len] = '\0';
+  CHECK(len + diff.diff_add_text.len < size);
+  strcat(text, diff.diff_add_text.ptr);
+  lipi_string_free(diff.diff_add_text);
+}
+
+static void test_diffs(void) {
+  LipiTypingContext *ctx = NULL;
+  CHECK_OK(lipi_typing_context_new("Devanagari", NULL, &ctx));
+
+  char text[256] = "";
+  const char *keys[] = {"n", "a", "m", "a", "s", "t", "e"};
+  LipiTypingDiff diff;
+  for (size_t i = 0; i < sizeof keys / sizeof keys[0]; i++) {
+    CHECK_OK(lipi_typing_context_take_key_input(ctx, keys[i], &diff));
+    apply_diff(text, sizeof text, diff);
+  }
+  CHECK(strcmp(text, "नमस्ते") == 0);
+
+  LipiString s;
+  CHECK_OK(lipi_typing_context_raw_input(ctx, &s));
+  CHECK_STRING(s, "namaste");
+
+  CHECK_OK(lipi_typing_context_take_backspace(ctx, &diff));
+  apply_diff(text, sizeof text, diff);
+  CHECK(strcmp(text, "नमस्त्") == 0);
+
+  CHECK_OK(lipi_typing_context_clear(ctx));
+  text[0] = '\0';
+  CHECK_OK(lipi_typing_context_take_text_input(ctx, "bhakti", &diff));
+  apply_diff(text, sizeof text, diff);
+  CHECK(strcmp(text, "भक्ति") == 0);
+
+  CHECK_STATUS(lipi_typing_context_take_key_input(NULL, "a", &diff), LIPI_STATUS_NULL_PTR);
+  CHECK_STATUS(lipi_typing_context_take_key_input(ctx, "\xff", &diff),
+               LIPI_STATUS_INVALID_UTF8);
+  lipi_typing_context_free(ctx);
+}
+
+static void test_options(void) {
+  LipiTypingContextOptions opts;
+  CHECK_OK(lipi_typing_default_options(&opts));
+  CHECK(opts.layout == LIPI_TYPING_LAYOUT_PHONETIC);
+  CHECK(opts.latin_escape_key == 0);
+
+  opts.include_inherent_vowel = true;
+  opts.use_native_numerals = false;
+  opts.layout = LIPI_TYPING_LAYOUT_TAMIL99;
+  LipiTypingContext *ctx = NULL;
+  CHECK_OK(lipi_typing_context_new("Hindi", &opts, &ctx));
+
+  LipiTypingContextOptions used;
+  CHECK_OK(lipi_typing_context_get_options(ctx, &used));
+  CHECK(used.include_inherent_vowel && !used.use_native_numerals);
+  /* Tamil99 cannot type Devanagari */
+  CHECK(used.layout == LIPI_TYPING_LAYOUT_PHONETIC);
+
+  LipiString s;
+  CHECK_OK(lipi_typing_context_get_normalized_script(ctx, &s));
+  CHECK_STRING(s, "Devanagari");
+  lipi_typing_context_free(ctx);
+
+  bool available = false;
+  CHECK_OK(lipi_typing_layout_is_available(LIPI_TYPING_LAYOUT_TAMIL99, "Tamil", &available));
+  CHECK(available);
+  CHECK_OK(lipi_typing_layout_is_available(LIPI_TYPING_LAYOUT_TAMIL99, "Telugu", &available));
+  CHECK(!available);
+
+  CHECK_OK(lipi_emulate_typing("k", "Hindi", &opts, &s));
+  CHECK_STRING(s, "क");
+  CHECK_OK(lipi_emulate_typing("k", "Hindi", NULL, &s));
+  CHECK_STRING(s, "क्");
+
+  CHECK_OK(lipi_typing_sequence_for("भक्ति", "Devanagari", NULL, &s));
+  CHECK_STRING(s, "bhakti");
+  /* Latin text is not typed by any keys */
+  CHECK_OK(lipi_typing_sequence_for("abc", "Devanagari", NULL, &s));
+  CHECK(s.ptr == NULL);
+
+  CHECK_STATUS(lipi_typing_context_new("Klingon", NULL, &ctx), LIPI_STATUS_UNKNOWN_SCRIPT);
+  CHECK(ctx == NULL);
+}
+
+static void test_ime(void) {
+  LipiTypingContext *ctx = NULL;
+  CHECK_OK(lipi_typing_context_new("Devanagari", NULL, &ctx));
+
+  LipiImeUpdate update;
+  CHECK_OK(lipi_typing_context_ime_key_input(ctx, "r", &update));
+  CHECK(update.handled);
+  lipi_string_free(update.commit_text);
+  lipi_string_free(update.preedit);
+  CHECK_OK(lipi_typing_context_ime_key_input(ctx, "A", &update));
+  CHECK(update.handled);
+  lipi_string_free(update.commit_text);
+  CHECK_STRING(update.preedit, "रा");
+
+  LipiString s;
+  CHECK_OK(lipi_typing_context_preedit(ctx, &s));
+  CHECK_STRING(s, "रा");
+
+  CHECK_OK(lipi_typing_context_ime_backspace(ctx, &update));
+  CHECK(update.handled);
+  lipi_string_free(update.commit_text);
+  CHECK_STRING(update.preedit, "र्");
+
+  CHECK_OK(lipi_typing_context_cancel(ctx, &s));
+  CHECK_STRING(s, "r");
+  CHECK_OK(lipi_typing_context_ime_backspace(ctx, &update));
+  CHECK(!update.handled);
+
+  LipiTypingDiff diff;
+  CHECK_OK(lipi_typing_context_take_text_input(ctx, "rAma", &diff));
+  lipi_string_free(diff.diff_add_text);
+  CHECK_OK(lipi_typing_context_commit(ctx, &s));
+  CHECK_STRING(s, "राम");
+  CHECK_OK(lipi_typing_context_preedit(ctx, &s));
+  CHECK_STRING(s, "");
+  lipi_typing_context_free(ctx);
+}
+
+static void test_candidates(void) {
+  LipiTypingContext *ctx = NULL;
+  CHECK_OK(lipi_typing_context_new("Devanagari", NULL, &ctx));
+  LipiTypingDiff diff;
+  CHECK_OK(lipi_typing_context_take_text_input(ctx, "shiva", &diff));
+  lipi_string_free(diff.diff_add_text);
+
+  LipiCandidateList list;
+  CHECK_OK(lipi_typing_context_candidates(ctx, 5, &list));
+  CHECK(list.len > 1 && list.len <= 5);
+  /* without word frequencies the current preedit comes first */
+  CHECK(strcmp(list.ptr[0].text.ptr, "शिव") == 0);
+  lipi_candidate_list_free(list);
+
+  LipiWordFrequency words[] = {{"शीव", 10}};
+  CHECK_OK(lipi_typing_context_set_word_frequencies(ctx, words, 1));
+  CHECK_OK(lipi_typing_context_candidates(ctx, 5, &list));
+  CHECK(strcmp(list.ptr[0].text.ptr, "शीव") == 0);
+
+  CHECK_OK(lipi_typing_context_select_candidate(ctx, list.ptr[0].input.ptr, &diff));
+  lipi_string_free(diff.diff_add_text);
+  lipi_candidate_list_free(list);
+  LipiString s;
+  CHECK_OK(lipi_typing_context_preedit(ctx, &s));
+  CHECK_STRING(s, "शीव");
+  lipi_typing_context_free(ctx);
+}
+
+static void test_keymap_and_snapshot(void) {
+  LipiTypingContext *ctx = NULL;
+  CHECK_OK(lipi_typing_context_new("Devanagari", NULL, &ctx));
+
+  LipiString err;
+  size_t conflicts = 99;
+  CHECK_OK(lipi_typing_context_set_keymap(ctx, "q = { keys = \"kSh\" }", LIPI_KEYMAP_FORMAT_TOML,
+                                          &conflicts, &err));
+  /* `q` starts built-in keys too, so the override is reported */
+  CHECK(err.ptr == NULL && conflicts > 0 && conflicts != 99);
+  CHECK_STATUS(lipi_typing_context_set_keymap(ctx, "{", LIPI_KEYMAP_FORMAT_JSON, NULL, &err),
+               LIPI_STATUS_ERROR);
+  CHECK(err.ptr != NULL && err.len > 0);
+  lipi_string_free(err);
+
+  LipiTypingDiff diff;
+  CHECK_OK(lipi_typing_context_take_text_input(ctx, "qa", &diff));
+  CHECK_STRING(diff.diff_add_text, "क्ष");
+
+  LipiString json;
+  CHECK_OK(lipi_typing_context_snapshot(ctx, &json));
+  lipi_typing_context_free(ctx);
+
+  LipiTypingContext *restored = NULL;
+  CHECK_OK(lipi_typing_context_restore(json.ptr, &restored, NULL));
+  lipi_string_free(json);
+  CHECK_OK(lipi_typing_context_take_key_input(restored, "q", &diff));
+  lipi_string_free(diff.diff_add_text);
+  LipiString s;
+  CHECK_OK(lipi_typing_context_preedit(restored, &s));
+  CHECK_STRING(s, "क्षक्ष्");
+  lipi_typing_context_free(restored);
+
+  CHECK_STATUS(lipi_typing_context_restore("{}", &restored, &err), LIPI_STATUS_ERROR);
+  CHECK(restored == NULL && err.len > 0);
+  lipi_string_free(err);
+}
+
+static void test_typing_data_map(void) {
+  LipiTypingDataMap map;
+  CHECK_OK(lipi_typing_data_map("Devanagari", &map));
+  CHECK(map.common_krama_map.len > 0);
+
+  int found = 0;
+  for (size_t i = 0; i < map.common_krama_map.len; i++) {
+    LipiTypingDataMapItem item = map.common_krama_map.ptr[i];
+    if (strcmp(item.text.ptr, "ख") == 0) {
+      found = 1;
+      CHECK(item.list_type == LIPI_LIST_TYPE_VYANJANA);
+      CHECK(list_contains(item.mappings, "kh"));
+    }
+  }
+  CHECK(found);
+  lipi_typing_data_map_free(map);
+
+  CHECK_STATUS(lipi_typing_data_map("Klingon", &map), LIPI_STATUS_UNKNOWN_SCRIPT);
+  lipi_typing_data_map_free(map);
+}
+
+int main(void) {
+  test_diffs();
+  test_options();
+  test_ime();
+  test_candidates();
+  test_keymap_and_snapshot();
+  test_typing_data_map();
+  puts("test_typing: ok");
+  return 0;
+}
@@ -0,0 +1,89 @@
+//! Compiles the C tests in `tests/c` against the static library and the generated header,
+//! and runs them. The static library is built first, as `cargo test` only builds the crate
+//! for the Rust tests.
+
+#![cfg(target_os = "linux")]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// `target/<profile>`, where cargo puts the static library built for these tests.
+fn profile_dir() -> PathBuf {
+    let exe = env::current_exe().expect("test executable path");
+    // target/<profile>/deps/c_api-<hash>
+    exe.parent()
+        .and_then(Path::parent)
+        .expect("target profile dir")
+        .to_path_buf()
+}
+
+/// Builds the static library with the profile of the tests, once for all of them.
+fn static_lib() -> &'static Path {
+    static STATIC_LIB: OnceLock<PathBuf> = OnceLock::new();
+    STATIC_LIB.get_or_init(|| {
+        let profile_dir = profile_dir();
+        let target_dir = profile_dir.parent().expect("target dir");
+        let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let mut build = Command::new(&cargo);
+        build
+            .args(["build", "--lib", "--manifest-path"])
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(target_dir);
+        if profile_dir.ends_with("release") {
+            build.arg("--release");
+        }
+        let status = build
+            .status()
+            .unwrap_or_else(|e| panic!("running {cargo}: {e}"));
+        assert!(status.success(), "building the static library failed");
+
+        let static_lib = profile_dir.join("liblipilekhika_c.a");
+        assert!(static_lib.exists(), "{} not found", static_lib.display());
+        static_lib
+    })
+}
+
+fn run_c_test(name: &str) {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let static_lib = static_lib();
+    let exe = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let compiled = Command::new(&cc)
+        .args(["-std=c11", "-Wall", "-Wextra", "-Werror", "-o"])
+        .arg(&exe)
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg(crate_dir.join("tests/c").join(format!("{name}.c")))
+        .arg(static_lib)
+        // system libraries the Rust standard library needs
+        .args(["-lpthread", "-ldl", "-lm"])
+        .output()
+        .unwrap_or_else(|e| panic!("running {cc}: {e}"));
+    assert!(
+        compiled.status.success(),
+        "compiling {name}.c failed:\n{}",
+        String::from_utf8_lossy(&compiled.stderr)
+    );
+
+    let run = Command::new(&exe).output().expect("run C test");
+    assert!(
+        run.status.success(),
+        "{name} failed:\n{}{}",
+        String::from_utf8_lossy(&run.stdout),
+        String::from_utf8_lossy(&run.stderr)
+    );
+}
+
+#[test]
+fn c_transliterate() {
+    run_c_test("test_transliterate");
+}
+
+#[test]
+fn c_typing() {
+    run_c_test("test_typing");
+}
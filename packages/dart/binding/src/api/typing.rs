@@ -50,6 +50,7 @@ impl From<TypingContextOptions> for lipilekhika::typing::TypingContextOptions {
             auto_context_clear_time_ms: opts.auto_context_clear_time_ms,
             use_native_numerals: opts.use_native_numerals,
             include_inherent_vowel: opts.include_inherent_vowel,
+            ..Default::default()
         }
     }
 }
@@ -63,6 +64,8 @@ pub struct TypingDiff {
     pub diff_add_text: String,
     /// Remaining internal context length (0 means context was cleared).
     pub context_length: usize,
+    /// Keys are passed through as raw Latin text after this diff, eg. to show an indicator.
+    pub latin_passthrough: bool,
 }
 
 /// Stateful isolated context for character-by-character input typing.
@@ -110,6 +113,43 @@ impl TypingContext {
             to_delete_chars_count: diff.to_delete_chars_count,
             diff_add_text: diff.diff_add_text,
             context_length: diff.context_length,
+            latin_passthrough: diff.latin_passthrough,
+        })
+    }
+
+    /// Processes every char of `text` (eg. a pasted Roman word) as typed keys and returns a
+    /// single diff against the output before the call.
+    #[frb(sync)]
+    pub fn take_text_input(&self, text: String) -> Result<TypingDiff, String> {
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|_| lock_poisoned("take_text_input"))?;
+        let diff = inner.take_text_input(&text);
+        Ok(TypingDiff {
+            to_delete_chars_count: diff.to_delete_chars_count,
+            diff_add_text: diff.diff_add_text,
+            context_length: diff.context_length,
+            latin_passthrough: diff.latin_passthrough,
+        })
+    }
+
+    /// Removes the last typed key from the current context and returns the diff.
+    ///
+    /// When the context is empty the diff is empty with `context_length == 0` and the
+    /// backspace should be left to the app.
+    #[frb(sync)]
+    pub fn take_backspace(&self) -> Result<TypingDiff, String> {
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|_| lock_poisoned("take_backspace"))?;
+        let diff = inner.take_backspace();
+        Ok(TypingDiff {
+            to_delete_chars_count: diff.to_delete_chars_count,
+            diff_add_text: diff.diff_add_text,
+            context_length: diff.context_length,
+            latin_passthrough: diff.latin_passthrough,
         })
     }
 
@@ -156,6 +196,26 @@ impl TypingContext {
             .map_err(|_| lock_poisoned("get_include_inherent_vowel"))
     }
 
+    /// Gets whether keys are currently passed through as raw Latin text.
+    #[frb(sync)]
+    pub fn is_latin_passthrough(&self) -> Result<bool, String> {
+        self.inner
+            .read()
+            .map(|inner| inner.is_latin_passthrough())
+            .map_err(|_| lock_poisoned("is_latin_passthrough"))
+    }
+
+    /// Starts or ends passing keys through as raw Latin text, eg. from a UI toggle.
+    #[frb(sync)]
+    pub fn set_latin_passthrough(&self, latin_passthrough: bool) -> Result<(), String> {
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|_| lock_poisoned("set_latin_passthrough"))?;
+        inner.set_latin_passthrough(latin_passthrough);
+        Ok(())
+    }
+
     /// Returns the normalized script name.
     #[frb(sync)]
     pub fn get_normalized_script(&self) -> Result<String, String> {
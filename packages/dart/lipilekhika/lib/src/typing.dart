@@ -40,6 +40,8 @@ bool get defaultIncludeInherentVowel =>
 /// Returns a typing context object with the following methods:
 /// - `clearContext()`: Clears all internal states and contexts
 /// - `takeKeyInput(key)`: Accepts character input and returns the diff
+/// - `takeTextInput(text)`: Types every character of `text` and returns a single diff
+/// - `takeBackspace()`: Removes the last typed key and returns the diff
 /// - `updateUseNativeNumerals(value)`: Update native numerals setting
 /// - `updateIncludeInherentVowel(value)`: Update inherent vowel setting
 /// - `getUseNativeNumerals()`: Get current native numerals setting
@@ -118,6 +118,58 @@ void main() {
       expect(result, isNot('namaste')); // Should be transliterated
     });
 
+    test('takeBackspace should step back one key', () {
+      String typeKeys(TypingContext ctx, String keys) {
+        var result = '';
+        for (final char in keys.split('')) {
+          final diff = ctx.takeKeyInput(key: char);
+          final keep = result.runes.length - diff.toDeleteCharsCount.toInt();
+          result = String.fromCharCodes(result.runes.take(keep)) + diff.diffAddText;
+        }
+        return result;
+      }
+
+      final ctx = createTypingContext(typingLang: 'Devanagari');
+      var result = typeKeys(ctx, 'kSh');
+      final diff = ctx.takeBackspace();
+      final keep = result.runes.length - diff.toDeleteCharsCount.toInt();
+      result = String.fromCharCodes(result.runes.take(keep)) + diff.diffAddText;
+
+      expect(diff.contextLength.toInt(), greaterThan(0));
+      expect(
+        result,
+        typeKeys(createTypingContext(typingLang: 'Devanagari'), 'kS'),
+      );
+    });
+
+    test('takeTextInput should return the net diff of its keys', () {
+      final ctx = createTypingContext(typingLang: 'Devanagari');
+      ctx.takeKeyInput(key: 'r');
+      final diff = ctx.takeTextInput(text: 'Am namaste');
+
+      expect(diff.toDeleteCharsCount.toInt(), 1);
+      expect(diff.diffAddText, 'ाम् नमस्ते');
+      expect(diff.contextLength.toInt(), greaterThan(0));
+    });
+
+    test('takeBackspace on empty context returns empty diff', () {
+      final ctx = createTypingContext(typingLang: 'Devanagari');
+      final diff = ctx.takeBackspace();
+      expect(diff.toDeleteCharsCount.toInt(), 0);
+      expect(diff.diffAddText, isEmpty);
+      expect(diff.contextLength.toInt(), 0);
+    });
+
+    test('diffs report Latin passthrough', () {
+      final ctx = createTypingContext(typingLang: 'Devanagari');
+      expect(ctx.takeKeyInput(key: 'k').latinPassthrough, isFalse);
+      ctx.setLatinPassthrough(latinPassthrough: true);
+      expect(ctx.isLatinPassthrough(), isTrue);
+      final diff = ctx.takeKeyInput(key: 'k');
+      expect(diff.diffAddText, 'k');
+      expect(diff.latinPassthrough, isTrue);
+    });
+
     test('should update use_native_numerals', () {
       final ctx = createTypingContext(typingLang: 'Devanagari');
       ctx.updateUseNativeNumerals(useNativeNumerals: true);
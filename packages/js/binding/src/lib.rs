@@ -23,6 +23,8 @@ pub struct TypingDiffOutput {
     pub diff_add_text: String,
     #[napi(js_name = "context_length")]
     pub context_length: u32,
+    #[napi(js_name = "latin_passthrough")]
+    pub latin_passthrough: bool,
 }
 
 #[napi]
@@ -68,6 +70,7 @@ impl NativeTypingContext {
             include_inherent_vowel: opts
                 .include_inherent_vowel
                 .unwrap_or(lipilekhika::typing::DEFAULT_INCLUDE_INHERENT_VOWEL),
+            ..Default::default()
         });
 
         let typing_script = Script::from_str(typing_lang.trim()).map_err(|e| {
@@ -93,6 +96,33 @@ impl NativeTypingContext {
             diff_add_text: diff.diff_add_text,
             context_length: u32::try_from(diff.context_length)
                 .map_err(|_| Error::from_reason("context_length exceeds u32 range"))?,
+            latin_passthrough: diff.latin_passthrough,
+        })
+    }
+
+    #[napi(js_name = "take_text_input")]
+    pub fn take_text_input(&mut self, text: String) -> Result<TypingDiffOutput> {
+        let diff = self.inner.take_text_input(&text);
+        Ok(TypingDiffOutput {
+            to_delete_chars_count: u32::try_from(diff.to_delete_chars_count)
+                .map_err(|_| Error::from_reason("to_delete_chars_count exceeds u32 range"))?,
+            diff_add_text: diff.diff_add_text,
+            context_length: u32::try_from(diff.context_length)
+                .map_err(|_| Error::from_reason("context_length exceeds u32 range"))?,
+            latin_passthrough: diff.latin_passthrough,
+        })
+    }
+
+    #[napi(js_name = "take_backspace")]
+    pub fn take_backspace(&mut self) -> Result<TypingDiffOutput> {
+        let diff = self.inner.take_backspace();
+        Ok(TypingDiffOutput {
+            to_delete_chars_count: u32::try_from(diff.to_delete_chars_count)
+                .map_err(|_| Error::from_reason("to_delete_chars_count exceeds u32 range"))?,
+            diff_add_text: diff.diff_add_text,
+            context_length: u32::try_from(diff.context_length)
+                .map_err(|_| Error::from_reason("context_length exceeds u32 range"))?,
+            latin_passthrough: diff.latin_passthrough,
         })
     }
 
@@ -121,4 +151,14 @@ impl NativeTypingContext {
     pub fn get_normalized_script(&self) -> String {
         self.inner.get_normalized_script().to_string()
     }
+
+    #[napi(js_name = "is_latin_passthrough")]
+    pub fn is_latin_passthrough(&self) -> bool {
+        self.inner.is_latin_passthrough()
+    }
+
+    #[napi(js_name = "set_latin_passthrough")]
+    pub fn set_latin_passthrough(&mut self, latin_passthrough: bool) {
+        self.inner.set_latin_passthrough(latin_passthrough);
+    }
 }
@@ -20,16 +20,21 @@ type TypingDiffOutput = {
   to_delete_chars_count: number;
   diff_add_text: string;
   context_length: number;
+  latin_passthrough: boolean;
 };
 
 type NativeTypingContextInstance = {
   clear_context(): void;
   take_key_input(key: string): TypingDiffOutput;
+  take_text_input(text: string): TypingDiffOutput;
+  take_backspace(): TypingDiffOutput;
   update_use_native_numerals(useNativeNumerals: boolean): void;
   update_include_inherent_vowel(includeInherentVowel: boolean): void;
   get_use_native_numerals(): boolean;
   get_include_inherent_vowel(): boolean;
   get_normalized_script(): string;
+  is_latin_passthrough(): boolean;
+  set_latin_passthrough(latinPassthrough: boolean): void;
 };
 
 export type NativeModule = {
@@ -81,7 +81,26 @@ export function createTypingContext(typing_lang: ScriptLangType, options?: Typin
       return {
         to_delete_chars_count: diff.to_delete_chars_count,
         diff_add_text: diff.diff_add_text,
-        context_length: diff.context_length
+        context_length: diff.context_length,
+        latin_passthrough: diff.latin_passthrough
+      };
+    },
+    takeTextInput: (text: string): TypingDiff => {
+      const diff = getNativeContext().take_text_input(text);
+      return {
+        to_delete_chars_count: diff.to_delete_chars_count,
+        diff_add_text: diff.diff_add_text,
+        context_length: diff.context_length,
+        latin_passthrough: diff.latin_passthrough
+      };
+    },
+    takeBackspace: (): TypingDiff => {
+      const diff = getNativeContext().take_backspace();
+      return {
+        to_delete_chars_count: diff.to_delete_chars_count,
+        diff_add_text: diff.diff_add_text,
+        context_length: diff.context_length,
+        latin_passthrough: diff.latin_passthrough
       };
     },
     updateUseNativeNumerals: (useNativeNumerals: boolean) => {
@@ -95,7 +114,12 @@ export function createTypingContext(typing_lang: ScriptLangType, options?: Typin
     getUseNativeNumerals: () => native_ctx?.get_use_native_numerals() ?? use_native_numerals,
     getIncludeInherentVowel: () =>
       native_ctx?.get_include_inherent_vowel() ?? include_inherent_vowel,
-    getNormalizedScript: () => native_ctx?.get_normalized_script() ?? normalized_typing_lang
+    getNormalizedScript: () => native_ctx?.get_normalized_script() ?? normalized_typing_lang,
+    /** Whether keys are currently passed through as raw Latin text */
+    isLatinPassthrough: () => getNativeContext().is_latin_passthrough(),
+    /** Turns Latin passthrough on or off, eg. from a UI toggle. The composition is cleared. */
+    setLatinPassthrough: (latinPassthrough: boolean) =>
+      getNativeContext().set_latin_passthrough(latinPassthrough)
   };
 }
 
@@ -43,6 +43,11 @@ export type TypingDiff = {
   diff_add_text: string;
   /** Remaining internal context length (0 means context was cleared) */
   context_length: number;
+  /**
+   * Keys are passed through as raw Latin text after this diff, eg. to show an indicator.
+   * Always `false` for this TS engine, only the native contexts support Latin passthrough.
+   */
+  latin_passthrough: boolean;
 };
 
 /**
@@ -113,13 +118,60 @@ export function createTypingContext(typing_lang: ScriptLangType, options?: Typin
     }
     const char_key = key?.[0] ?? '';
     curr_input += char_key;
+    const diff = retransliterate();
+    last_time_ms = Date.now();
+    return diff;
+  }
+
+  /**
+   * Removes the last typed key from the current context and returns the diff.
+   * Eg: after typing `kSh`, a backspace leaves the context in the `kS` state instead of clearing it.
+   *
+   * When the context is empty (nothing typed, cleared or timed out) the diff is empty with
+   * `context_length` 0 and the backspace should be left to the app.
+   * @returns The diff of the previous and current output
+   */
+  function takeBackspace(): TypingDiff {
+    if (!from_script_data || !to_script_data) {
+      throw new Error('Typing context not ready. Await `ctx.ready` before calling takeBackspace.');
+    }
+    const curr_time_ms = Date.now();
+    if (last_time_ms && curr_time_ms - last_time_ms > auto_context_clear_time_ms) {
+      clearContext();
+    }
+    if (curr_input.length === 0) {
+      return {
+        to_delete_chars_count: 0,
+        diff_add_text: '',
+        context_length: 0,
+        latin_passthrough: false
+      };
+    }
+    curr_input = curr_input.slice(0, -1);
+    if (curr_input.length === 0) {
+      const to_delete_chars_count = curr_output.length;
+      clearContext();
+      return {
+        to_delete_chars_count,
+        diff_add_text: '',
+        context_length: 0,
+        latin_passthrough: false
+      };
+    }
+    const diff = retransliterate();
+    last_time_ms = Date.now();
+    return diff;
+  }
+
+  /** Transliterates `curr_input` and returns the diff against the previous output */
+  function retransliterate(): TypingDiff {
     const prev_output = curr_output;
     const { context_length, output } = transliterate_text_core(
       curr_input,
       'Normal',
       normalized_typing_lang!,
-      from_script_data,
-      to_script_data,
+      from_script_data!,
+      to_script_data!,
       trans_options,
       custom_rules,
       {
@@ -134,24 +186,32 @@ export function createTypingContext(typing_lang: ScriptLangType, options?: Typin
       clearContext();
     }
 
-    // calculate the diff
-    let common_index = 0;
-    while (common_index < output.length && common_index < prev_output.length) {
-      if (output[common_index] !== prev_output[common_index]) break;
-      common_index++;
-    }
-    const diff_add_text = output.substring(common_index);
-    const to_delete_chars_count = prev_output.length - common_index;
+    return computeDiff(prev_output, output, context_length);
+  }
 
-    last_time_ms = Date.now();
-    return {
-      /** These number of characters need to be deleted from the current "app" input state */
-      to_delete_chars_count,
-      /** These characters need to be added to the current "app" input state */
-      diff_add_text,
-      /** Remaining internal context length (0 means context was cleared) */
-      context_length
-    };
+  /**
+   * Types every character of `text` (eg. a pasted Roman word) and returns a single diff
+   * against the output before the call.
+   * @param text The keys to type
+   * @returns The net diff of all the keys, with the `context_length` of the last one
+   */
+  function takeTextInput(text: string): TypingDiff {
+    if (!from_script_data || !to_script_data) {
+      throw new Error('Typing context not ready. Await `ctx.ready` before calling takeTextInput.');
+    }
+    if (text.length === 0) {
+      // nothing typed: the current context is left as it is
+      return retransliterate();
+    }
+    const prev_output = curr_output;
+    let output = prev_output;
+    let context_length = 0;
+    for (const key of text) {
+      const diff = takeKeyInput(key);
+      output = output.substring(0, output.length - diff.to_delete_chars_count) + diff.diff_add_text;
+      context_length = diff.context_length;
+    }
+    return computeDiff(prev_output, output, context_length);
   }
 
   return {
@@ -159,6 +219,8 @@ export function createTypingContext(typing_lang: ScriptLangType, options?: Typin
     ready,
     clearContext,
     takeKeyInput,
+    takeTextInput,
+    takeBackspace,
     updateUseNativeNumerals: (useNativeNumerals: boolean) => {
       use_native_numerals = useNativeNumerals ?? DEFAULT_USE_NATIVE_NUMERALS;
     },
@@ -171,6 +233,27 @@ export function createTypingContext(typing_lang: ScriptLangType, options?: Typin
   };
 }
 
+/** Diff turning `prev_output` into `output`, by common prefix length */
+function computeDiff(prev_output: string, output: string, context_length: number): TypingDiff {
+  let common_index = 0;
+  while (common_index < output.length && common_index < prev_output.length) {
+    if (output[common_index] !== prev_output[common_index]) break;
+    common_index++;
+  }
+  const diff_add_text = output.substring(common_index);
+  const to_delete_chars_count = prev_output.length - common_index;
+
+  return {
+    /** These number of characters need to be deleted from the current "app" input state */
+    to_delete_chars_count,
+    /** These characters need to be added to the current "app" input state */
+    diff_add_text,
+    /** Remaining internal context length (0 means context was cleared) */
+    context_length,
+    latin_passthrough: false
+  };
+}
+
 function isTextInputElement(value: unknown): value is HTMLInputElement | HTMLTextAreaElement {
   return (
     (typeof HTMLInputElement !== 'undefined' && value instanceof HTMLInputElement) ||
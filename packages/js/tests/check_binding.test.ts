@@ -118,6 +118,17 @@ describe('node binding smoke checks', () => {
       expect(result).toBe(out);
     }
   });
+
+  it('createTypingContext reports Latin passthrough in the diffs', async () => {
+    const ctx = createTypingContext('Devanagari');
+    await ctx.ready;
+    expect(ctx.takeKeyInput('k').latin_passthrough).toBe(false);
+    ctx.setLatinPassthrough(true);
+    expect(ctx.isLatinPassthrough()).toBe(true);
+    const diff = ctx.takeKeyInput('k');
+    expect(diff.diff_add_text).toBe('k');
+    expect(diff.latin_passthrough).toBe(true);
+  });
 });
 
 describe('wasm binding smoke check', () => {
@@ -54,6 +54,7 @@ impl From<TypingContextOptions> for lipilekhika::typing::TypingContextOptions {
             auto_context_clear_time_ms: opts.auto_context_clear_time_ms,
             use_native_numerals: opts.use_native_numerals,
             include_inherent_vowel: opts.include_inherent_vowel,
+            ..Default::default()
         }
     }
 }
@@ -71,6 +72,9 @@ pub struct TypingDiff {
     /// Remaining internal context length (0 means context was cleared).
     #[pyo3(get)]
     pub context_length: usize,
+    /// Keys are passed through as raw Latin text after this diff, eg. to show an indicator.
+    #[pyo3(get)]
+    pub latin_passthrough: bool,
 }
 
 #[pymethods]
@@ -100,6 +104,27 @@ impl TypingContext {
             to_delete_chars_count: diff.to_delete_chars_count,
             diff_add_text: diff.diff_add_text,
             context_length: diff.context_length,
+            latin_passthrough: diff.latin_passthrough,
+        }
+    }
+
+    fn take_text_input(&mut self, text: &str) -> TypingDiff {
+        let diff = self.inner.take_text_input(text);
+        TypingDiff {
+            to_delete_chars_count: diff.to_delete_chars_count,
+            diff_add_text: diff.diff_add_text,
+            context_length: diff.context_length,
+            latin_passthrough: diff.latin_passthrough,
+        }
+    }
+
+    fn take_backspace(&mut self) -> TypingDiff {
+        let diff = self.inner.take_backspace();
+        TypingDiff {
+            to_delete_chars_count: diff.to_delete_chars_count,
+            diff_add_text: diff.diff_add_text,
+            context_length: diff.context_length,
+            latin_passthrough: diff.latin_passthrough,
         }
     }
 
@@ -119,6 +144,14 @@ impl TypingContext {
     fn get_include_inherent_vowel(&self) -> bool {
         self.inner.get_include_inherent_vowel()
     }
+
+    fn is_latin_passthrough(&self) -> bool {
+        self.inner.is_latin_passthrough()
+    }
+
+    fn set_latin_passthrough(&mut self, latin_passthrough: bool) {
+        self.inner.set_latin_passthrough(latin_passthrough);
+    }
 }
 
 #[pyfunction]
@@ -84,6 +84,11 @@ class TypingDiff:
         """Remaining internal context length (0 means context was cleared)."""
         pass
 
+    @property
+    def latin_passthrough(self) -> bool:  # ty:ignore[empty-body]
+        """Whether keys are passed through as raw Latin text after this diff."""
+        pass
+
     def __repr__(self) -> str:  # ty:ignore[empty-body]
         """Return a string representation of the TypingDiff."""
         pass
@@ -110,6 +115,31 @@ class TypingContext:
         """
         ...
 
+    def take_text_input(self, text: str) -> TypingDiff:  # ty:ignore[empty-body]
+        """Types every character of `text` (eg. a pasted Roman word) and returns
+        a single diff against the output before the call.
+
+        Args:
+            text: The keys to type
+
+        Returns:
+            The net diff of all the keys, with the `context_length` of the last one
+        """
+        ...
+
+    def take_backspace(self) -> TypingDiff:  # ty:ignore[empty-body]
+        """Removes the last typed key from the current context and returns the diff.
+
+        Eg: after typing `kSh`, a backspace leaves the context in the `kS` state
+        instead of clearing it.
+
+        Returns:
+            The diff to apply instead of the backspace. When the context is empty
+            the diff is empty with `context_length` 0 and the backspace should be
+            left to the app.
+        """
+        ...
+
     def update_use_native_numerals(self, use_native_numerals: bool) -> None: ...
 
     def update_include_inherent_vowel(self, include_inherent_vowel: bool) -> None: ...
@@ -120,6 +150,15 @@ class TypingContext:
     def get_include_inherent_vowel(self) -> bool:  # ty:ignore[empty-body]
         pass
 
+    def is_latin_passthrough(self) -> bool:  # ty:ignore[empty-body]
+        """Whether keys are currently passed through as raw Latin text."""
+        pass
+
+    def set_latin_passthrough(self, latin_passthrough: bool) -> None:
+        """Starts or ends passing keys through as raw Latin text, eg. from a UI toggle.
+        The current composition is cleared."""
+        ...
+
 
 class ScriptTypingDataMap:
     """Result containing typing data for a script."""
@@ -168,6 +207,8 @@ def create_typing_context(
         A typing context object with the following methods:
         - `clear_context()`: Clears all internal states and contexts
         - `take_key_input(key)`: Accepts character input and returns the diff
+        - `take_text_input(text)`: Types every character of `text` and returns a single diff
+        - `take_backspace()`: Removes the last typed key and returns the diff
         - `update_use_native_numerals(use_native_numerals)`: Update native numerals setting
         - `update_include_inherent_vowel(include_inherent_vowel)`: Update inherent vowel setting
         - `get_use_native_numerals()`: Get current native numerals setting
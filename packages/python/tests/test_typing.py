@@ -137,6 +137,66 @@ class TestTypingContext:
         # Result should be in Devanagari script
         assert result != "namaste"  # Should be transliterated
 
+    def test_take_backspace(self):
+        """Test backspace steps back one key instead of clearing the context."""
+
+        def type_keys(ctx, keys, result_chars):
+            for char in keys:
+                diff = ctx.take_key_input(char)
+                if diff.to_delete_chars_count > 0:
+                    result_chars = result_chars[: -diff.to_delete_chars_count]
+                result_chars.extend(diff.diff_add_text)
+            return result_chars
+
+        ctx = create_typing_context("Devanagari")
+        result_chars = type_keys(ctx, "kSh", [])
+        diff = ctx.take_backspace()
+        if diff.to_delete_chars_count > 0:
+            result_chars = result_chars[: -diff.to_delete_chars_count]
+        result_chars.extend(diff.diff_add_text)
+        assert diff.context_length > 0
+
+        expected = type_keys(create_typing_context("Devanagari"), "kS", [])
+        assert "".join(result_chars) == "".join(expected)
+
+    def test_take_text_input(self):
+        """Test a whole string gives the net diff of typing its keys one by one."""
+        ctx = create_typing_context("Devanagari")
+        ctx.take_key_input("r")
+        diff = ctx.take_text_input("Am namaste")
+        assert diff.to_delete_chars_count == 1
+        assert diff.diff_add_text == "ाम् नमस्ते"
+        assert diff.context_length > 0
+
+        keyed = create_typing_context("Devanagari")
+        result_chars = []
+        for char in "rAm namaste":
+            key_diff = keyed.take_key_input(char)
+            if key_diff.to_delete_chars_count > 0:
+                result_chars = result_chars[: -key_diff.to_delete_chars_count]
+            result_chars.extend(key_diff.diff_add_text)
+        assert "".join(result_chars) == "र" + diff.diff_add_text
+
+    def test_take_backspace_empty_context(self):
+        """Test backspace on an empty context returns an empty diff."""
+        ctx = create_typing_context("Devanagari")
+        diff = ctx.take_backspace()
+        assert diff.to_delete_chars_count == 0
+        assert diff.diff_add_text == ""
+        assert diff.context_length == 0
+
+    def test_latin_passthrough(self):
+        """Keys are typed as is while Latin passthrough is on, and diffs report it."""
+        ctx = create_typing_context("Devanagari")
+        assert ctx.take_key_input("k").latin_passthrough is False
+        ctx.set_latin_passthrough(True)
+        assert ctx.is_latin_passthrough() is True
+        diff = ctx.take_key_input("k")
+        assert diff.diff_add_text == "k"
+        assert diff.latin_passthrough is True
+        ctx.set_latin_passthrough(False)
+        assert ctx.take_key_input("k").latin_passthrough is False
+
     def test_update_use_native_numerals(self):
         """Test updating native numerals setting."""
         ctx = create_typing_context("Devanagari")
@@ -3,8 +3,13 @@
 //! Run: `cargo run --example parivartak --release -p lipilekhika -- --help`
 
 use clap::Parser;
+use lipilekhika::formats::catalog::{transliterate_json_catalog, transliterate_yaml_catalog};
+use lipilekhika::formats::gettext::{transliterate_mo, transliterate_po};
+use lipilekhika::formats::subtitle::{SubtitleFormat, transliterate_subtitle};
 use lipilekhika::scripts::Script;
 use lipilekhika::transliterate;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 #[derive(Parser)]
@@ -12,8 +17,28 @@ use std::str::FromStr;
 #[command(about = "A CLI tool for transliterating text between Indian scripts")]
 struct Args {
     /// The text to transliterate
-    #[arg(short = 't', long)]
-    text: String,
+    #[arg(
+        short = 't',
+        long,
+        required_unless_present = "input",
+        conflicts_with = "input"
+    )]
+    text: Option<String>,
+
+    /// Input file to transliterate. Subtitles (`.srt`, `.vtt`) and catalogues (`.po`, `.mo`,
+    /// `.json`, `.yaml`) only have their translatable text converted. Documents (`.epub`,
+    /// `.docx`, `.odt`) need the `documents` feature.
+    #[arg(short = 'i', long)]
+    input: Option<PathBuf>,
+
+    /// Column of a `.csv`/`.tsv` input to convert, as `name` (in place) or `name=new_column`
+    /// (appended). Repeatable; needs the `std` feature.
+    #[arg(short = 'c', long = "column")]
+    columns: Vec<String>,
+
+    /// Write the result to this file instead of stdout
+    #[arg(long)]
+    output_file: Option<PathBuf>,
 
     /// Source script name (e.g., Devanagari, Bengali, etc.)
     #[arg(short = 'f', long)]
@@ -24,18 +49,102 @@ struct Args {
     to: String,
 }
 
+fn exit_with(msg: String) -> ! {
+    eprintln!("Error: {}", msg);
+    std::process::exit(1);
+}
+
+fn read_failed(path: &Path, e: std::io::Error) -> ! {
+    exit_with(format!("cannot read {}: {}", path.display(), e))
+}
+
+#[cfg_attr(not(feature = "std"), allow(unused_variables))]
+fn transliterate_file(path: &Path, from: Script, to: Script, columns: &[String]) -> Vec<u8> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    if ext == "mo" {
+        let data = std::fs::read(path).unwrap_or_else(|e| read_failed(path, e));
+        return transliterate_mo(&data, from, to, None)
+            .unwrap_or_else(|e| exit_with(format!("{}: {}", path.display(), e)));
+    }
+
+    #[cfg(feature = "std")]
+    if let Some(format) = lipilekhika::formats::table::TableFormat::from_extension(&ext) {
+        use lipilekhika::formats::table::{ColumnTransliteration, transliterate_table};
+        if columns.is_empty() {
+            exit_with(format!("{}: pass at least one --column", path.display()));
+        }
+        let columns: Vec<_> = columns
+            .iter()
+            .map(|spec| match spec.split_once('=') {
+                Some((name, new_column)) => {
+                    ColumnTransliteration::new(name, from, to).into_column(new_column)
+                }
+                None => ColumnTransliteration::new(spec.as_str(), from, to),
+            })
+            .collect();
+        let file = std::fs::File::open(path).unwrap_or_else(|e| read_failed(path, e));
+        let mut output = Vec::new();
+        transliterate_table(std::io::BufReader::new(file), &mut output, format, &columns)
+            .unwrap_or_else(|e| exit_with(format!("{}: {}", path.display(), e)));
+        return output;
+    }
+
+    #[cfg(feature = "documents")]
+    if let Some(kind) = lipilekhika::formats::document::DocumentKind::from_extension(&ext) {
+        let file = std::fs::File::open(path).unwrap_or_else(|e| read_failed(path, e));
+        let output = lipilekhika::formats::document::transliterate_document(
+            file,
+            std::io::Cursor::new(Vec::new()),
+            kind,
+            from,
+            to,
+            None,
+        )
+        .unwrap_or_else(|e| exit_with(format!("{}: {}", path.display(), e)));
+        return output.into_inner();
+    }
+
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| read_failed(path, e));
+    let result = match ext.as_str() {
+        "po" | "pot" => transliterate_po(&text, from, to, None),
+        "json" => transliterate_json_catalog(&text, from, to, None),
+        "yaml" | "yml" => transliterate_yaml_catalog(&text, from, to, None),
+        _ => match SubtitleFormat::from_extension(&ext) {
+            Some(format) => transliterate_subtitle(&text, Some(format), from, to, None),
+            None => transliterate(&text, from, to, None).into_owned(),
+        },
+    };
+    result.into_bytes()
+}
+
 fn main() {
     let args = Args::parse();
 
-    let from = Script::from_str(&args.from).unwrap_or_else(|e| {
-        eprintln!("Error: invalid --from {:?}: {}", args.from, e);
-        std::process::exit(1);
-    });
-    let to = Script::from_str(&args.to).unwrap_or_else(|e| {
-        eprintln!("Error: invalid --to {:?}: {}", args.to, e);
-        std::process::exit(1);
-    });
-
-    let result = transliterate(&args.text, from, to, None);
-    println!("{}", result);
+    let from = Script::from_str(&args.from)
+        .unwrap_or_else(|e| exit_with(format!("invalid --from {:?}: {}", args.from, e)));
+    let to = Script::from_str(&args.to)
+        .unwrap_or_else(|e| exit_with(format!("invalid --to {:?}: {}", args.to, e)));
+
+    let result = match (&args.text, &args.input) {
+        (Some(text), _) => {
+            let mut result = transliterate(text, from, to, None).into_owned();
+            if args.output_file.is_none() {
+                result.push('\n');
+            }
+            result.into_bytes()
+        }
+        (None, Some(path)) => transliterate_file(path, from, to, &args.columns),
+        (None, None) => unreachable!("clap requires --text or --input"),
+    };
+
+    let written = match &args.output_file {
+        Some(path) => std::fs::write(path, result),
+        None => std::io::stdout().write_all(&result),
+    };
+    written.unwrap_or_else(|e| exit_with(format!("cannot write output: {}", e)));
 }
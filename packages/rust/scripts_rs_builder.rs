@@ -91,6 +91,12 @@ pub fn render_scripts_rs(script_list: &ScriptListDataJson) -> String {
         })
         .collect();
 
+    let mut script_list_in_id_order = script_from_id_arms.clone();
+    script_list_in_id_order.sort_by_key(|(id, _)| *id);
+    let script_list_all = script_list_in_id_order
+        .iter()
+        .map(|(_, variant)| quote! { Self::#variant, });
+
     let script_list_from_id_arms = script_from_id_arms.iter().map(|(id, variant)| {
         quote! {
             #id => Some(Self::#variant),
@@ -119,6 +125,9 @@ pub fn render_scripts_rs(script_list: &ScriptListDataJson) -> String {
       }
 
       impl ScriptListEnum {
+          /// All scripts, in `script_list.json` id order.
+          pub const ALL: &'static [Self] = &[#(#script_list_all)*];
+
           /// Resolves a canonical `script_list.json` script id to the internal script enum.
           #[inline]
           pub const fn from_id(id: u8) -> Option<Self> {
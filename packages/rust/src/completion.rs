@@ -0,0 +1,248 @@
+//! Word completion from a user-supplied lexicon, with learned frequencies.
+//!
+//! Words can be given in any supported script. They are stored in a canonical form, the
+//! Normal (roman typing) keys of the word, so one lexicon serves every typing script: a
+//! Devanagari word list also completes Telugu typing.
+
+use crate::scripts::ScriptListEnum;
+use crate::transliterate::transliterate_text;
+use crate::typing::{Candidate, TypingContext};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Bound;
+
+#[derive(Debug, Clone, Copy)]
+struct WordEntry {
+    /// Position in the word list; earlier words rank higher on equal learned counts.
+    rank: u32,
+    /// How often the word was typed, see [`Lexicon::learn`].
+    learned: u64,
+}
+
+/// Prefix index of words used by [`Lexicon::complete`].
+#[derive(Debug, Clone, Default)]
+pub struct Lexicon {
+    words: BTreeMap<String, WordEntry>,
+    next_rank: u32,
+}
+
+impl Lexicon {
+    /// Creates an empty lexicon.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of words in the lexicon.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns `true` if the lexicon has no words.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Adds the words of a word list (one word per line, any script) and returns how many
+    /// were new. Blank lines and lines starting with `#` are skipped.
+    ///
+    /// Lists are usually sorted by frequency, so earlier words rank higher.
+    pub fn load_word_list(&mut self, text: &str) -> usize {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter(|line| self.add_word(line))
+            .count()
+    }
+
+    /// Adds a word (any script) and returns `true` if it was not known yet.
+    pub fn add_word(&mut self, word: &str) -> bool {
+        let Some(key) = canonical_word(word) else {
+            return false;
+        };
+        if self.words.contains_key(&key) {
+            return false;
+        }
+        self.insert(key, 0);
+        true
+    }
+
+    /// Counts one more use of every word in `text` (any script), adding unknown words.
+    ///
+    /// Call this with committed text so completions follow what the user actually types.
+    pub fn learn(&mut self, text: &str) {
+        for key in text.split_whitespace().filter_map(canonical_word) {
+            match self.words.get_mut(&key) {
+                Some(entry) => entry.learned = entry.learned.saturating_add(1),
+                None => self.insert(key, 1),
+            }
+        }
+    }
+
+    /// Returns up to `limit` words completing the word being typed in `ctx`, most used
+    /// first, as candidates in the typing script of `ctx`.
+    ///
+    /// [`Candidate::input`] holds the keys of the whole word, so
+    /// [`TypingContext::select_candidate`] replaces the composition with it.
+    pub fn complete(&self, ctx: &TypingContext, limit: usize) -> Vec<Candidate> {
+        if limit == 0 || ctx.preedit().is_empty() {
+            return Vec::new();
+        }
+        let prefix = ctx.canonical_preedit();
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<(&String, &WordEntry)> = self
+            .words
+            .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix.as_str()))
+            .filter(|(key, _)| key.len() > prefix.len())
+            .collect();
+        matches.sort_by(|a, b| b.1.learned.cmp(&a.1.learned).then(a.1.rank.cmp(&b.1.rank)));
+
+        matches
+            .into_iter()
+            .map(|(key, _)| Candidate {
+                text: ctx.transliterate_keys(key).0,
+                input: key.clone(),
+            })
+            .filter(|candidate| candidate.text != ctx.preedit())
+            .take(limit)
+            .collect()
+    }
+
+    /// Serializes the learned counts as `word<TAB>count` lines, in canonical form.
+    pub fn learned_frequencies(&self) -> String {
+        let mut out = String::new();
+        for (key, entry) in self.words.iter().filter(|(_, e)| e.learned > 0) {
+            out.push_str(&format!("{key}\t{}\n", entry.learned));
+        }
+        out
+    }
+
+    /// Loads counts written by [`Self::learned_frequencies`], adding unknown words.
+    /// Malformed lines are skipped.
+    pub fn load_learned_frequencies(&mut self, text: &str) {
+        for line in text.lines() {
+            let Some((key, count)) = line.split_once('\t') else {
+                continue;
+            };
+            let (Some(key), Ok(count)) = (canonical_word(key), count.trim().parse::<u64>()) else {
+                continue;
+            };
+            match self.words.get_mut(&key) {
+                Some(entry) => entry.learned = count,
+                None => self.insert(key, count),
+            }
+        }
+    }
+
+    /// Reads a word list file, see [`Self::load_word_list`].
+    #[cfg(feature = "std")]
+    pub fn load_word_list_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<usize> {
+        Ok(self.load_word_list(&std::fs::read_to_string(path)?))
+    }
+
+    /// Reads a learned frequencies file, see [`Self::load_learned_frequencies`].
+    #[cfg(feature = "std")]
+    pub fn load_learned_frequencies_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<()> {
+        self.load_learned_frequencies(&std::fs::read_to_string(path)?);
+        Ok(())
+    }
+
+    /// Writes the learned counts to a file, see [`Self::learned_frequencies`].
+    #[cfg(feature = "std")]
+    pub fn save_learned_frequencies_file(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<()> {
+        std::fs::write(path, self.learned_frequencies())
+    }
+
+    fn insert(&mut self, key: String, learned: u64) {
+        let rank = self.next_rank;
+        self.next_rank = self.next_rank.saturating_add(1);
+        self.words.insert(key, WordEntry { rank, learned });
+    }
+}
+
+/// Returns the Normal keys of a word written in any script, dropping surrounding
+/// punctuation. ASCII words are taken to already be Normal keys.
+fn canonical_word(word: &str) -> Option<String> {
+    let word = word.trim_matches(|c: char| {
+        c.is_whitespace() || c.is_ascii_punctuation() || matches!(c, '।' | '॥')
+    });
+    if word.is_empty() {
+        return None;
+    }
+    let key = match crate::detect_script(word) {
+        None => word.to_string(),
+        Some(script) => transliterate_text(word, script, ScriptListEnum::Normal, None, None).output,
+    };
+    (!key.is_empty()).then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripts::Script;
+
+    fn typed(script: Script, keys: &str) -> TypingContext {
+        let mut ctx = TypingContext::new(script, None);
+        for ch in keys.chars() {
+            ctx.take_key_input_char(ch);
+        }
+        ctx
+    }
+
+    #[test]
+    fn completes_words_from_any_script_and_learns() {
+        let mut lexicon = Lexicon::new();
+        let added = lexicon.load_word_list("# words\nनमस्ते\nनमस्कार\n\nnamaH\nనమస్తే\n");
+        assert_eq!(
+            added, 3,
+            "the Telugu word is the same as the Devanagari one"
+        );
+
+        let ctx = typed(Script::Devanagari, "nam");
+        let texts: Vec<String> = lexicon
+            .complete(&ctx, 5)
+            .into_iter()
+            .map(|c| c.text)
+            .collect();
+        assert_eq!(texts, ["नमस्ते", "नमस्कार", "नमः"]);
+
+        lexicon.learn("नमस्कार, नमस्कार।");
+        let telugu = typed(Script::Telugu, "nam");
+        let best = lexicon.complete(&telugu, 1).remove(0);
+        assert_eq!(best.text, "నమస్కార");
+
+        let mut telugu = telugu;
+        telugu.select_candidate(&best.input);
+        assert_eq!(telugu.preedit(), "నమస్కార");
+
+        let mut restored = Lexicon::new();
+        restored.load_learned_frequencies(&lexicon.learned_frequencies());
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored.complete(&ctx, 5)[0].text, "नमस्कार");
+
+        assert!(
+            lexicon
+                .complete(&typed(Script::Devanagari, ""), 5)
+                .is_empty()
+        );
+        assert!(
+            lexicon
+                .complete(&typed(Script::Devanagari, "k"), 5)
+                .is_empty()
+        );
+    }
+}
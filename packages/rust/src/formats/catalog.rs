@@ -0,0 +1,415 @@
+//! JSON and YAML i18n catalogues.
+//!
+//! String values are transliterated with their placeholders protected; keys, numbers,
+//! booleans, comments and the original layout are kept. Both scanners are lenient: input
+//! they cannot make sense of is copied through instead of failing.
+
+use super::SegmentTransliterator;
+use super::message::transliterate_message;
+use crate::custom_options::CustomOptions;
+use crate::scripts::Script;
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Transliterates every string value of a JSON (or JSONC) catalogue.
+///
+/// Object keys are never touched. Values that do not change are written back byte for byte,
+/// so `\u` escapes survive in untouched strings.
+pub fn transliterate_json_catalog(
+    text: &str,
+    from: Script,
+    to: Script,
+    trans_options: Option<&CustomOptions>,
+) -> String {
+    let transliterator = SegmentTransliterator::new(from, to, trans_options);
+    if transliterator.is_identity() {
+        return text.to_string();
+    }
+
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    // one entry per open container: `true` for objects
+    let mut containers: Vec<bool> = Vec::new();
+    let mut expect_key = false;
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let Some(end) = json_string_end(bytes, i) else {
+                    break;
+                };
+                if !expect_key {
+                    let raw = &text[i + 1..end];
+                    let value = unescape_json(raw);
+                    let converted = transliterate_message(&value, &transliterator);
+                    if converted != value {
+                        out.push_str(&text[copied..i]);
+                        out.push('"');
+                        escape_json(&converted, &mut out);
+                        out.push('"');
+                        copied = end + 1;
+                    }
+                }
+                i = end + 1;
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = text[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = text[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+                continue;
+            }
+            b'{' => {
+                containers.push(true);
+                expect_key = true;
+            }
+            b'[' => {
+                containers.push(false);
+                expect_key = false;
+            }
+            b'}' | b']' => {
+                containers.pop();
+                expect_key = false;
+            }
+            b':' => expect_key = false,
+            b',' => expect_key = containers.last().copied().unwrap_or(false),
+            _ => {}
+        }
+        i += 1;
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+/// Byte index of the closing quote of the JSON string opening at `start`.
+fn json_string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn unescape_json(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('b') => out.push('\u{08}'),
+            Some('f') => out.push('\u{0c}'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let unit = u32::from_str_radix(&hex, 16).unwrap_or(0xfffd);
+                // surrogate pairs: `\uD83D\uDE00`
+                let code = if (0xd800..0xdc00).contains(&unit) {
+                    let rest = chars.as_str();
+                    match rest
+                        .strip_prefix("\\u")
+                        .and_then(|r| r.get(..4))
+                        .and_then(|h| u32::from_str_radix(h, 16).ok())
+                    {
+                        Some(low) if (0xdc00..0xe000).contains(&low) => {
+                            chars = rest[6..].chars();
+                            0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)
+                        }
+                        _ => 0xfffd,
+                    }
+                } else {
+                    unit
+                };
+                out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn escape_json(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&alloc::format!("\\u{:04x}", c as u32));
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Transliterates the scalar values of a YAML catalogue.
+///
+/// Handles the block-style mappings and sequences used by i18n tools: plain, single- and
+/// double-quoted scalars, `|`/`>` block scalars, anchors and tags. Keys, comments, aliases,
+/// booleans, numbers and flow collections (`{}`/`[]`) are left as they are.
+pub fn transliterate_yaml_catalog(
+    text: &str,
+    from: Script,
+    to: Script,
+    trans_options: Option<&CustomOptions>,
+) -> String {
+    let transliterator = SegmentTransliterator::new(from, to, trans_options);
+    if transliterator.is_identity() {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    // indentation of the line that opened the current block scalar
+    let mut block_parent_indent: Option<usize> = None;
+    let mut block_indent: Option<usize> = None;
+
+    for line in text.split_inclusive('\n') {
+        let eol_len = line.len() - line.trim_end_matches(['\r', '\n']).len();
+        let (content, eol) = line.split_at(line.len() - eol_len);
+        let indent = content.len() - content.trim_start_matches(' ').len();
+
+        if let Some(parent) = block_parent_indent {
+            if content.trim().is_empty() {
+                out.push_str(line);
+                continue;
+            }
+            if indent > parent {
+                let at = (*block_indent.get_or_insert(indent)).min(indent);
+                out.push_str(&content[..at]);
+                out.push_str(&transliterate_message(&content[at..], &transliterator));
+                out.push_str(eol);
+                continue;
+            }
+            block_parent_indent = None;
+            block_indent = None;
+        }
+
+        let body = &content[indent..];
+        if body.is_empty() || body.starts_with('#') || body.starts_with("---") || body == "..." {
+            out.push_str(line);
+            continue;
+        }
+
+        let value_start = indent + yaml_value_offset(body);
+        out.push_str(&content[..value_start]);
+        if write_yaml_value(&content[value_start..], &transliterator, &mut out) {
+            block_parent_indent = Some(indent);
+        }
+        out.push_str(eol);
+    }
+    out
+}
+
+/// Offset of the value within a mapping/sequence line body (after `- ` and `key: `).
+/// Returns `body.len()` for lines without a value, such as `key:`.
+fn yaml_value_offset(body: &str) -> usize {
+    let mut offset = 0;
+    while let Some(rest) = body[offset..].strip_prefix('-') {
+        if rest.is_empty() {
+            return body.len();
+        }
+        if !rest.starts_with(' ') {
+            break;
+        }
+        offset += 1 + (rest.len() - rest.trim_start_matches(' ').len());
+    }
+
+    let item = &body[offset..];
+    let key_end = match item.as_bytes().first() {
+        Some(q @ (b'"' | b'\'')) => match item[1..].find(*q as char) {
+            Some(close) => close + 2,
+            None => return offset,
+        },
+        _ => 0,
+    };
+    let separator = item[key_end..]
+        .find(": ")
+        .map(|at| key_end + at)
+        .filter(|at| !item[..*at].contains(" #"))
+        .or_else(|| item.ends_with(':').then(|| item.len() - 1));
+    match separator {
+        Some(at) => {
+            let after = &item[at + 1..];
+            offset + at + 1 + (after.len() - after.trim_start_matches(' ').len())
+        }
+        // a bare sequence item, or a line that is not a mapping entry at all
+        None if offset > 0 => offset,
+        None => body.len(),
+    }
+}
+
+/// Writes a scalar value. Returns `true` when it opens a `|`/`>` block scalar.
+fn write_yaml_value(value: &str, transliterator: &SegmentTransliterator, out: &mut String) -> bool {
+    let mut value = value;
+    // anchors (`&a`) and tags (`!!str`) precede the scalar itself
+    while value.starts_with(['&', '!']) {
+        let token_end = value.find(' ').unwrap_or(value.len());
+        let after = &value[token_end..];
+        let next = token_end + (after.len() - after.trim_start_matches(' ').len());
+        out.push_str(&value[..next]);
+        value = &value[next..];
+    }
+
+    match value.as_bytes().first() {
+        None | Some(b'*' | b'{' | b'[' | b'#') => out.push_str(value),
+        Some(b'|' | b'>') => {
+            out.push_str(value);
+            return true;
+        }
+        Some(b'"') => match json_string_end(value.as_bytes(), 0) {
+            Some(end) => {
+                let inner = unescape_json(&value[1..end]);
+                let converted = transliterate_message(&inner, transliterator);
+                if converted == inner {
+                    out.push_str(&value[..=end]);
+                } else {
+                    out.push('"');
+                    escape_json(&converted, out);
+                    out.push('"');
+                }
+                out.push_str(&value[end + 1..]);
+            }
+            None => out.push_str(value),
+        },
+        Some(b'\'') => match single_quoted_end(value) {
+            Some(end) => {
+                let inner = value[1..end].replace("''", "'");
+                let converted = transliterate_message(&inner, transliterator);
+                out.push('\'');
+                out.push_str(&converted.replace('\'', "''"));
+                out.push('\'');
+                out.push_str(&value[end + 1..]);
+            }
+            None => out.push_str(value),
+        },
+        Some(_) => {
+            let scalar_end = value.find(" #").unwrap_or(value.len());
+            let scalar = value[..scalar_end].trim_end();
+            if is_yaml_non_string(scalar) {
+                out.push_str(value);
+            } else {
+                let converted = transliterate_message(scalar, transliterator);
+                if needs_quoting(&converted) {
+                    out.push('"');
+                    escape_json(&converted, out);
+                    out.push('"');
+                } else {
+                    out.push_str(&converted);
+                }
+                out.push_str(&value[scalar.len()..]);
+            }
+        }
+    }
+    false
+}
+
+fn single_quoted_end(value: &str) -> Option<usize> {
+    let bytes = value.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        if bytes[i] == b'\'' {
+            if bytes.get(i + 1) == Some(&b'\'') {
+                i += 2;
+                continue;
+            }
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Plain scalars YAML resolves to something other than a string.
+fn is_yaml_non_string(scalar: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "~", "null", "Null", "NULL", "true", "True", "TRUE", "false", "False", "FALSE", "yes",
+        "Yes", "YES", "no", "No", "NO", "on", "On", "ON", "off", "Off", "OFF",
+    ];
+    KEYWORDS.contains(&scalar)
+        || (!scalar.is_empty()
+            && scalar
+                .bytes()
+                .all(|b| b.is_ascii_digit() || b"+-.eE_xXoObB".contains(&b))
+            && scalar.bytes().any(|b| b.is_ascii_digit()))
+}
+
+/// Whether a converted plain scalar would no longer parse as the same string.
+fn needs_quoting(scalar: &str) -> bool {
+    scalar.starts_with([
+        '\'', '"', '&', '*', '!', '|', '>', '%', '@', '`', '#', '{', '[', '-', '?', ':', ',',
+    ]) || scalar.contains(": ")
+        || scalar.contains(" #")
+        || scalar.ends_with(':')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate;
+    use alloc::format;
+
+    fn dev(text: &str) -> String {
+        transliterate(text, Script::Normal, Script::Devanagari, None).into_owned()
+    }
+
+    #[test]
+    fn transliterates_json_values_only() {
+        let json = "{\n  // nav\n  \"home\": \"gRRiham\",\n  \"count\": \"{n, plural, one {# phalam} other {# phalAni}}\",\n  \"list\": [\"rAma\", 3, true],\n  \"nested\": { \"rAma\": \"sIt\\\"A\" },\n  \"keep\": \"\\u0915\"\n}\n";
+        let output = transliterate_json_catalog(json, Script::Normal, Script::Devanagari, None);
+        let expected = json
+            .replace("\"gRRiham\"", &format!("\"{}\"", dev("gRRiham")))
+            .replace("{# phalam}", &format!("{{#{}}}", dev(" phalam")))
+            .replace("{# phalAni}", &format!("{{#{}}}", dev(" phalAni")))
+            .replace("[\"rAma\"", &format!("[\"{}\"", dev("rAma")))
+            .replace(
+                "\"sIt\\\"A\"",
+                &format!("\"{}\"", dev("sIt\"A").replace('"', "\\\"")),
+            );
+        assert_eq!(output, expected);
+        assert!(output.contains("{ \"rAma\":"));
+    }
+
+    #[test]
+    fn transliterates_yaml_values_only() {
+        let yaml = "# catalogue\nhi:\n  home: gRRiham # trailing\n  greet: \"namaH %{name}\"\n  quote: 'rAma''s'\n  enabled: true\n  items:\n    - phalam\n    - key: jalam\n  about: |\n    prathamA\n    dvitIyA\n  alias: *home\n";
+        let output = transliterate_yaml_catalog(yaml, Script::Normal, Script::Devanagari, None);
+        let expected = yaml
+            .replace("home: gRRiham ", &format!("home: {} ", dev("gRRiham")))
+            .replace(
+                "\"namaH %{name}\"",
+                &format!("\"{}%{{name}}\"", dev("namaH ")),
+            )
+            .replace(
+                "'rAma''s'",
+                &format!("'{}'", dev("rAma's").replace('\'', "''")),
+            )
+            .replace("- phalam", &format!("- {}", dev("phalam")))
+            .replace("key: jalam", &format!("key: {}", dev("jalam")))
+            .replace("    prathamA", &format!("    {}", dev("prathamA")))
+            .replace("    dvitIyA", &format!("    {}", dev("dvitIyA")));
+        assert_eq!(output, expected);
+        assert!(output.starts_with("# catalogue\nhi:\n"));
+        assert!(output.contains("enabled: true"));
+    }
+}
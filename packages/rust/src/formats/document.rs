@@ -0,0 +1,395 @@
+//! EPUB, DOCX and ODT transliteration.
+//!
+//! The documents are zip containers of XML parts. Parts holding body text and metadata are
+//! rewritten through a streaming XML scanner that keeps every byte of markup, so run
+//! formatting, styles and relationships are untouched; every other entry (images, fonts,
+//! the EPUB `mimetype`) is copied raw in its original order and compression.
+//!
+//! Text is converted per paragraph, which lets words split across formatting runs
+//! (`<w:r>`, `<text:span>`, `<b>`) come out right. Language metadata (`xml:lang`, `lang`,
+//! `dc:language`) is rewritten to name the target script, e.g. `sa` → `sa-Telu`.
+//!
+//! Requires the `documents` feature.
+
+use super::SegmentTransliterator;
+use super::xml::{XmlRewriter, XmlRole};
+use crate::custom_options::CustomOptions;
+use crate::scripts::{Script, ScriptListEnum};
+use std::io::{self, Read, Seek, Write};
+use std::string::{String, ToString};
+use std::vec::Vec;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Container format of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Epub,
+    Docx,
+    Odt,
+}
+
+impl DocumentKind {
+    /// Maps a file extension (`epub`, `docx`, `odt`), case-insensitively.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "epub" => Some(Self::Epub),
+            "docx" => Some(Self::Docx),
+            "odt" => Some(Self::Odt),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+    /// The input is not a readable zip archive or could not be written back.
+    Zip(zip::result::ZipError),
+    Io(io::Error),
+    /// An XML part that should be rewritten is not valid UTF-8.
+    InvalidUtf8 {
+        part: String,
+    },
+}
+
+impl core::fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Zip(err) => write!(f, "invalid document archive: {err}"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::InvalidUtf8 { part } => write!(f, "document part `{part}` is not valid UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Zip(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::InvalidUtf8 { .. } => None,
+        }
+    }
+}
+
+impl From<zip::result::ZipError> for DocumentError {
+    fn from(err: zip::result::ZipError) -> Self {
+        Self::Zip(err)
+    }
+}
+
+impl From<io::Error> for DocumentError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Reads a document from `reader` and writes the transliterated document to `writer`.
+pub fn transliterate_document<R, W>(
+    reader: R,
+    writer: W,
+    kind: DocumentKind,
+    from: Script,
+    to: Script,
+    trans_options: Option<&CustomOptions>,
+) -> Result<W, DocumentError>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let transliterator = SegmentTransliterator::new(from, to, trans_options);
+    let script_code = iso_15924_code(to.into());
+    let mut archive = ZipArchive::new(reader)?;
+    let mut out = ZipWriter::new(writer);
+
+    for index in 0..archive.len() {
+        let role_of = {
+            let entry = archive.by_index_raw(index)?;
+            match transliterator.is_identity() {
+                true => None,
+                false => part_roles(kind, entry.name()),
+            }
+        };
+        let Some(role_of) = role_of else {
+            out.raw_copy_file(archive.by_index_raw(index)?)?;
+            continue;
+        };
+
+        let mut entry = archive.by_index(index)?;
+        let name = entry.name().to_string();
+        let mut options = SimpleFileOptions::default().compression_method(entry.compression());
+        if let Some(modified) = entry.last_modified() {
+            options = options.last_modified_time(modified);
+        }
+        if let Some(mode) = entry.unix_mode() {
+            options = options.unix_permissions(mode);
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        drop(entry);
+
+        let xml = String::from_utf8(bytes)
+            .map_err(|_| DocumentError::InvalidUtf8 { part: name.clone() })?;
+        let rewriter = XmlRewriter {
+            transliterator: &transliterator,
+            role_of,
+            script_code,
+        };
+        out.start_file(name, options)?;
+        out.write_all(rewriter.rewrite(&xml).as_bytes())?;
+    }
+    Ok(out.finish()?)
+}
+
+/// Element roles for the parts of `kind` that carry text, `None` for parts copied as is.
+fn part_roles(kind: DocumentKind, name: &str) -> Option<fn(&str) -> XmlRole> {
+    let file = name.rsplit('/').next().unwrap_or(name);
+    let extension = file
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match kind {
+        DocumentKind::Epub => match extension.as_deref()? {
+            "xhtml" | "html" | "htm" => Some(xhtml_role),
+            "ncx" => Some(ncx_role),
+            "opf" => Some(metadata_role),
+            _ => None,
+        },
+        DocumentKind::Docx => {
+            let is_story = name.starts_with("word/")
+                && !name["word/".len()..].contains('/')
+                && (file == "document.xml"
+                    || file == "footnotes.xml"
+                    || file == "endnotes.xml"
+                    || file == "comments.xml"
+                    || file.starts_with("header")
+                    || file.starts_with("footer"));
+            match name {
+                "docProps/core.xml" => Some(metadata_role),
+                _ if is_story && extension.as_deref() == Some("xml") => Some(docx_role),
+                _ => None,
+            }
+        }
+        DocumentKind::Odt => match name {
+            "content.xml" | "styles.xml" => Some(odt_role),
+            "meta.xml" => Some(metadata_role),
+            _ => None,
+        },
+    }
+}
+
+fn xhtml_role(name: &str) -> XmlRole {
+    match name {
+        "a" | "abbr" | "b" | "bdi" | "bdo" | "cite" | "data" | "dfn" | "em" | "i" | "kbd"
+        | "label" | "mark" | "q" | "rb" | "rp" | "rt" | "ruby" | "s" | "samp" | "small"
+        | "span" | "strong" | "sub" | "sup" | "time" | "u" | "var" | "del" | "ins" => {
+            XmlRole::Inline
+        }
+        "br" | "wbr" | "img" => XmlRole::Break,
+        "script" | "style" | "code" | "pre" | "math" | "svg" => XmlRole::Opaque,
+        _ => XmlRole::Paragraph,
+    }
+}
+
+fn ncx_role(name: &str) -> XmlRole {
+    match name {
+        "text" => XmlRole::Paragraph,
+        _ => XmlRole::Structure,
+    }
+}
+
+/// Dublin Core metadata shared by EPUB `.opf`, DOCX `docProps/core.xml` and ODT `meta.xml`.
+fn metadata_role(name: &str) -> XmlRole {
+    match name.rsplit(':').next().unwrap_or(name) {
+        "title" | "creator" | "contributor" | "subject" | "description" | "publisher"
+        | "keyword" | "keywords" => XmlRole::Paragraph,
+        "language" => XmlRole::Language,
+        _ => XmlRole::Structure,
+    }
+}
+
+fn docx_role(name: &str) -> XmlRole {
+    match name {
+        "w:p" => XmlRole::Structure,
+        "w:t" => XmlRole::Text,
+        "w:tab" | "w:ptab" | "w:br" | "w:cr" => XmlRole::Break,
+        "w:instrText" | "w:delInstrText" | "w:fldData" => XmlRole::Opaque,
+        _ => XmlRole::Inline,
+    }
+}
+
+fn odt_role(name: &str) -> XmlRole {
+    match name {
+        "text:p" | "text:h" => XmlRole::Paragraph,
+        "text:s" | "text:tab" | "text:line-break" | "text:soft-page-break" => XmlRole::Break,
+        "text:note-citation" | "text:page-number" | "text:page-count" | "text:date"
+        | "text:time" | "text:sequence" | "text:bookmark-ref" | "text:reference-ref" => {
+            XmlRole::Opaque
+        }
+        _ if name.starts_with("text:") => XmlRole::Inline,
+        _ => XmlRole::Structure,
+    }
+}
+
+/// ISO 15924 code of a script, as used in BCP 47 language tags.
+fn iso_15924_code(script: ScriptListEnum) -> &'static str {
+    match script {
+        ScriptListEnum::Devanagari | ScriptListEnum::PurnaDevanagari => "Deva",
+        ScriptListEnum::Telugu => "Telu",
+        ScriptListEnum::Tamil | ScriptListEnum::TamilExtended => "Taml",
+        ScriptListEnum::Bengali | ScriptListEnum::Assamese => "Beng",
+        ScriptListEnum::Kannada => "Knda",
+        ScriptListEnum::Gujarati => "Gujr",
+        ScriptListEnum::Malayalam => "Mlym",
+        ScriptListEnum::Odia => "Orya",
+        ScriptListEnum::Sinhala => "Sinh",
+        ScriptListEnum::Normal | ScriptListEnum::Romanized => "Latn",
+        ScriptListEnum::Gurumukhi => "Guru",
+        ScriptListEnum::Brahmi => "Brah",
+        ScriptListEnum::Granth => "Gran",
+        ScriptListEnum::Modi => "Modi",
+        ScriptListEnum::Sharada => "Shrd",
+        ScriptListEnum::Siddham => "Sidd",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate;
+    use std::format;
+    use std::io::Cursor;
+    use zip::CompressionMethod;
+
+    fn dev(text: &str) -> String {
+        transliterate(text, Script::Normal, Script::Devanagari, None).into_owned()
+    }
+
+    fn build_zip(entries: &[(&str, &str, CompressionMethod)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content, method) in entries {
+            let options = SimpleFileOptions::default().compression_method(*method);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn read_entries(bytes: Vec<u8>) -> Vec<(String, String, CompressionMethod)> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        (0..archive.len())
+            .map(|index| {
+                let mut entry = archive.by_index(index).unwrap();
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                (entry.name().to_string(), content, entry.compression())
+            })
+            .collect()
+    }
+
+    fn convert(kind: DocumentKind, input: Vec<u8>) -> Vec<(String, String, CompressionMethod)> {
+        let output = transliterate_document(
+            Cursor::new(input),
+            Cursor::new(Vec::new()),
+            kind,
+            Script::Normal,
+            Script::Devanagari,
+            None,
+        )
+        .unwrap();
+        read_entries(output.into_inner())
+    }
+
+    #[test]
+    fn converts_epub() {
+        let input = build_zip(&[
+            (
+                "mimetype",
+                "application/epub+zip",
+                CompressionMethod::Stored,
+            ),
+            (
+                "OEBPS/content.opf",
+                "<package><metadata><dc:title>rAmAyaNam</dc:title><dc:language>sa</dc:language><dc:identifier>id-rAma</dc:identifier></metadata></package>",
+                CompressionMethod::Deflated,
+            ),
+            (
+                "OEBPS/toc.ncx",
+                "<ncx><navPoint><navLabel><text>prathamaH</text></navLabel><content src=\"c1.xhtml\"/></navPoint></ncx>",
+                CompressionMethod::Deflated,
+            ),
+            (
+                "OEBPS/c1.xhtml",
+                "<html xml:lang=\"sa\"><head><title>rAma</title><style>p{}</style></head><body><p>na<b>ma</b>H<br/>rAma</p></body></html>",
+                CompressionMethod::Deflated,
+            ),
+        ]);
+        let entries = convert(DocumentKind::Epub, input);
+
+        assert_eq!(entries[0].0, "mimetype");
+        assert_eq!(entries[0].2, CompressionMethod::Stored);
+        assert_eq!(
+            entries[1].1,
+            format!(
+                "<package><metadata><dc:title>{}</dc:title><dc:language>sa-Deva</dc:language><dc:identifier>id-rAma</dc:identifier></metadata></package>",
+                dev("rAmAyaNam")
+            )
+        );
+        assert!(
+            entries[2]
+                .1
+                .contains(&format!("<text>{}</text>", dev("prathamaH")))
+        );
+        assert_eq!(
+            entries[3].1,
+            format!(
+                "<html xml:lang=\"sa-Deva\"><head><title>{}</title><style>p{{}}</style></head><body><p>{}<b>{}</b>{}<br/>{}</p></body></html>",
+                dev("rAma"),
+                dev("na"),
+                dev("ma"),
+                dev("H"),
+                dev("rAma")
+            )
+        );
+    }
+
+    #[test]
+    fn converts_docx_and_odt() {
+        let docx = build_zip(&[
+            (
+                "word/document.xml",
+                "<w:document><w:body><w:p><w:r><w:t>ga</w:t></w:r><w:r><w:rPr><w:i/></w:rPr><w:t>Nesha</w:t></w:r></w:p></w:body></w:document>",
+                CompressionMethod::Deflated,
+            ),
+            (
+                "word/styles.xml",
+                "<w:styles>rAma</w:styles>",
+                CompressionMethod::Deflated,
+            ),
+        ]);
+        let entries = convert(DocumentKind::Docx, docx);
+        assert_eq!(
+            entries[0].1,
+            format!(
+                "<w:document><w:body><w:p><w:r><w:t>{}</w:t></w:r><w:r><w:rPr><w:i/></w:rPr><w:t>{}</w:t></w:r></w:p></w:body></w:document>",
+                dev("ga"),
+                dev("Nesha")
+            )
+        );
+        assert_eq!(entries[1].1, "<w:styles>rAma</w:styles>");
+
+        let odt = build_zip(&[(
+            "content.xml",
+            "<office:text><text:p>rAma<text:s/><text:span>sItA</text:span><text:page-number>1</text:page-number></text:p></office:text>",
+            CompressionMethod::Deflated,
+        )]);
+        let entries = convert(DocumentKind::Odt, odt);
+        assert_eq!(
+            entries[0].1,
+            format!(
+                "<office:text><text:p>{}<text:s/><text:span>{}</text:span><text:page-number>1</text:page-number></text:p></office:text>",
+                dev("rAma"),
+                dev("sItA")
+            )
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Gettext catalogues: PO (text) and MO (compiled binary).
+//!
+//! Only translations (`msgstr`, `msgstr[n]`) are transliterated. `msgctxt`, `msgid`,
+//! `msgid_plural`, comments (including obsolete `#~` entries) and the header entry are
+//! copied unchanged, and placeholders inside translations are protected.
+
+use super::SegmentTransliterator;
+use super::message::transliterate_message;
+use crate::custom_options::CustomOptions;
+use crate::scripts::Script;
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PoKeyword {
+    Msgctxt,
+    Msgid,
+    MsgidPlural,
+    Msgstr,
+}
+
+fn po_keyword(line: &str) -> Option<PoKeyword> {
+    let line = line.trim_start();
+    let (keyword, rest) = if let Some(rest) = line.strip_prefix("msgctxt") {
+        (PoKeyword::Msgctxt, rest)
+    } else if let Some(rest) = line.strip_prefix("msgid_plural") {
+        (PoKeyword::MsgidPlural, rest)
+    } else if let Some(rest) = line.strip_prefix("msgid") {
+        (PoKeyword::Msgid, rest)
+    } else if let Some(rest) = line.strip_prefix("msgstr") {
+        let rest = match rest.strip_prefix('[') {
+            Some(index) => &index[index.find(']')? + 1..],
+            None => rest,
+        };
+        (PoKeyword::Msgstr, rest)
+    } else {
+        return None;
+    };
+    rest.starts_with([' ', '\t', '"']).then_some(keyword)
+}
+
+/// Contents between the first and last `"` of a PO string line, still escaped.
+fn po_quoted(line: &str) -> &str {
+    match (line.find('"'), line.rfind('"')) {
+        (Some(start), Some(end)) if end > start => &line[start + 1..end],
+        _ => "",
+    }
+}
+
+fn line_ending(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+fn unescape_c(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('a') => out.push('\u{07}'),
+            Some('b') => out.push('\u{08}'),
+            Some('f') => out.push('\u{0c}'),
+            Some('v') => out.push('\u{0b}'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn escape_c(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{07}' => out.push_str("\\a"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\u{0b}' => out.push_str("\\v"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Transliterates the translations of a PO file.
+///
+/// A translation that changes is re-emitted on one line, or gettext style as `msgstr ""`
+/// followed by one string line per `\n` when it originally spanned several lines.
+pub fn transliterate_po(
+    text: &str,
+    from: Script,
+    to: Script,
+    trans_options: Option<&CustomOptions>,
+) -> String {
+    let transliterator = SegmentTransliterator::new(from, to, trans_options);
+    if transliterator.is_identity() {
+        return text.to_string();
+    }
+
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(text.len());
+    // state of the entry being read, used to recognise the header (`msgid ""` without context)
+    let mut has_context = false;
+    let mut msgid_empty = false;
+    let mut after_msgstr = false;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let keyword = po_keyword(lines[i]);
+        let mut end = i + 1;
+        if keyword.is_some() {
+            while end < lines.len() && lines[end].trim_start().starts_with('"') {
+                end += 1;
+            }
+        }
+        let group = &lines[i..end];
+
+        if after_msgstr && keyword != Some(PoKeyword::Msgstr) {
+            has_context = false;
+            msgid_empty = false;
+            after_msgstr = false;
+        }
+        match keyword {
+            Some(PoKeyword::Msgctxt) => has_context = true,
+            Some(PoKeyword::Msgid) => {
+                msgid_empty = group.iter().all(|line| po_quoted(line).is_empty());
+            }
+            Some(PoKeyword::Msgstr) => after_msgstr = true,
+            Some(PoKeyword::MsgidPlural) | None => {}
+        }
+
+        let is_header = msgid_empty && !has_context;
+        if keyword == Some(PoKeyword::Msgstr) && !is_header {
+            write_po_msgstr(group, &transliterator, &mut out);
+        } else {
+            group.iter().for_each(|line| out.push_str(line));
+        }
+        i = end;
+    }
+    out
+}
+
+fn write_po_msgstr(group: &[&str], transliterator: &SegmentTransliterator, out: &mut String) {
+    let escaped: String = group.iter().map(|line| po_quoted(line)).collect();
+    let value = unescape_c(&escaped);
+    let converted = transliterate_message(&value, transliterator);
+    if converted == value {
+        group.iter().for_each(|line| out.push_str(line));
+        return;
+    }
+
+    let first = group[0];
+    let prefix = &first[..first.find('"').unwrap_or(first.len())];
+    let eol = match line_ending(first) {
+        "" => "\n",
+        eol => eol,
+    };
+    let last_eol = line_ending(group[group.len() - 1]);
+
+    out.push_str(prefix);
+    if group.len() == 1 {
+        out.push('"');
+        escape_c(&converted, out);
+        out.push('"');
+        out.push_str(last_eol);
+        return;
+    }
+    out.push_str("\"\"");
+    for part in converted.split_inclusive('\n') {
+        out.push_str(eol);
+        out.push('"');
+        escape_c(part, out);
+        out.push('"');
+    }
+    out.push_str(last_eol);
+}
+
+/// Error returned by [`transliterate_mo`] for input that is not a valid MO file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoFileError {
+    /// The file does not start with the MO magic number.
+    InvalidMagic,
+    /// A table or string points outside the file.
+    Truncated,
+}
+
+impl fmt::Display for MoFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMagic => f.write_str("not a gettext MO file"),
+            Self::Truncated => f.write_str("MO file is truncated or corrupt"),
+        }
+    }
+}
+
+const MO_MAGIC: u32 = 0x9504_12de;
+const MO_HEADER_LEN: usize = 28;
+
+struct MoReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> MoReader<'a> {
+    fn u32_at(&self, offset: usize) -> Result<u32, MoFileError> {
+        let bytes: [u8; 4] = self
+            .slice(offset, 4)?
+            .try_into()
+            .map_err(|_| MoFileError::Truncated)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn usize_at(&self, offset: usize) -> Result<usize, MoFileError> {
+        self.u32_at(offset).map(|v| v as usize)
+    }
+
+    /// String `index` of the table starting at `table`.
+    fn string(&self, table: usize, index: usize) -> Result<&'a [u8], MoFileError> {
+        let len = self.usize_at(table + index * 8)?;
+        let offset = self.usize_at(table + index * 8 + 4)?;
+        self.slice(offset, len)
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8], MoFileError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(MoFileError::Truncated)
+    }
+}
+
+/// Transliterates the translations of a compiled MO file and returns the rebuilt file.
+///
+/// The original strings and the hash table are kept, so lookups behave exactly as before.
+/// Translations that are not valid UTF-8 are copied unchanged.
+pub fn transliterate_mo(
+    data: &[u8],
+    from: Script,
+    to: Script,
+    trans_options: Option<&CustomOptions>,
+) -> Result<Vec<u8>, MoFileError> {
+    let magic = data.get(..4).ok_or(MoFileError::InvalidMagic)?;
+    let big_endian = if magic == MO_MAGIC.to_le_bytes() {
+        false
+    } else if magic == MO_MAGIC.to_be_bytes() {
+        true
+    } else {
+        return Err(MoFileError::InvalidMagic);
+    };
+    let reader = MoReader { data, big_endian };
+    let revision = reader.u32_at(4)?;
+    let count = reader.usize_at(8)?;
+    let originals_table = reader.usize_at(12)?;
+    let translations_table = reader.usize_at(16)?;
+    let hash_size = reader.usize_at(20)?;
+    let hash_offset = reader.usize_at(24)?;
+    let hash_table = match hash_size {
+        0 => &[][..],
+        _ => reader.slice(hash_offset, hash_size.saturating_mul(4))?,
+    };
+
+    let transliterator = SegmentTransliterator::new(from, to, trans_options);
+    let mut originals = Vec::with_capacity(count);
+    let mut translations: Vec<Cow<'_, [u8]>> = Vec::with_capacity(count);
+    for index in 0..count {
+        let original = reader.string(originals_table, index)?;
+        let translation = reader.string(translations_table, index)?;
+        originals.push(original);
+        // the empty msgid holds the catalogue header
+        let converted = match core::str::from_utf8(translation) {
+            Ok(text) if !original.is_empty() && !transliterator.is_identity() => {
+                let forms: Vec<String> = text
+                    .split('\0')
+                    .map(|form| transliterate_message(form, &transliterator))
+                    .collect();
+                Cow::Owned(forms.join("\0").into_bytes())
+            }
+            _ => Cow::Borrowed(translation),
+        };
+        translations.push(converted);
+    }
+
+    let write_u32 = |out: &mut Vec<u8>, value: usize| {
+        let value = value as u32;
+        if big_endian {
+            out.extend_from_slice(&value.to_be_bytes());
+        } else {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    };
+
+    let originals_at = MO_HEADER_LEN;
+    let translations_at = originals_at + count * 8;
+    let hash_at = translations_at + count * 8;
+    let strings_at = hash_at + hash_table.len();
+
+    let mut out = Vec::with_capacity(data.len());
+    write_u32(&mut out, MO_MAGIC as usize);
+    write_u32(&mut out, revision as usize);
+    write_u32(&mut out, count);
+    write_u32(&mut out, originals_at);
+    write_u32(&mut out, translations_at);
+    write_u32(&mut out, hash_size);
+    write_u32(&mut out, hash_at);
+
+    let mut next_offset = strings_at;
+    let strings = originals
+        .iter()
+        .copied()
+        .chain(translations.iter().map(|t| t.as_ref()));
+    let mut string_data = Vec::new();
+    for s in strings {
+        write_u32(&mut out, s.len());
+        write_u32(&mut out, next_offset);
+        string_data.extend_from_slice(s);
+        string_data.push(0);
+        next_offset += s.len() + 1;
+    }
+    out.extend_from_slice(hash_table);
+    out.extend_from_slice(&string_data);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate;
+    use alloc::format;
+
+    fn dev(text: &str) -> String {
+        transliterate(text, Script::Normal, Script::Devanagari, None).into_owned()
+    }
+
+    const PO: &str = r#"# translator comment
+msgid ""
+msgstr ""
+"Language: sa\n"
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#: src/app.rs:10
+#, c-format
+msgctxt "greeting"
+msgid "Hello %s"
+msgstr "namaste %s"
+
+msgid "Files"
+msgid_plural "%d files"
+msgstr[0] ""
+"ekam\n"
+"patram"
+msgstr[1] "%d patrANi"
+
+#~ msgid "Old"
+#~ msgstr "purANam"
+"#;
+
+    #[test]
+    fn transliterates_po_translations_only() {
+        let output = transliterate_po(PO, Script::Normal, Script::Devanagari, None);
+        let expected = PO
+            .replace(
+                "msgstr \"namaste %s\"",
+                &format!("msgstr \"{}%s\"", dev("namaste ")),
+            )
+            .replace("\"ekam\\n\"", &format!("\"{}\\n\"", dev("ekam")))
+            .replace("\"patram\"", &format!("\"{}\"", dev("patram")))
+            .replace("\"%d patrANi\"", &format!("\"%d{}\"", dev(" patrANi")));
+        assert_eq!(output, expected);
+        assert!(output.contains("msgid \"Hello %s\""));
+        assert!(output.contains("\"Language: sa\\n\""));
+        assert!(output.contains("#~ msgstr \"purANam\""));
+    }
+
+    fn build_mo(entries: &[(&str, &str)]) -> Vec<u8> {
+        let count = entries.len();
+        let mut out = Vec::new();
+        for v in [
+            MO_MAGIC as usize,
+            0,
+            count,
+            28,
+            28 + 8 * count,
+            0,
+            28 + 16 * count,
+        ] {
+            out.extend_from_slice(&(v as u32).to_le_bytes());
+        }
+        let mut offset = 28 + 16 * count;
+        let mut strings = Vec::new();
+        for column in [0, 1] {
+            for entry in entries {
+                let s = if column == 0 { entry.0 } else { entry.1 };
+                out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                out.extend_from_slice(&(offset as u32).to_le_bytes());
+                strings.extend_from_slice(s.as_bytes());
+                strings.push(0);
+                offset += s.len() + 1;
+            }
+        }
+        out.extend_from_slice(&strings);
+        out
+    }
+
+    #[test]
+    fn transliterates_mo_translations() {
+        let input = build_mo(&[
+            ("", "Language: sa\n"),
+            ("Hello %s", "namaste %s"),
+            ("file\0files", "patram\0patrANi"),
+        ]);
+        let output = transliterate_mo(&input, Script::Normal, Script::Devanagari, None).unwrap();
+        let expected = build_mo(&[
+            ("", "Language: sa\n"),
+            ("Hello %s", &format!("{}%s", dev("namaste "))),
+            (
+                "file\0files",
+                &format!("{}\0{}", dev("patram"), dev("patrANi")),
+            ),
+        ]);
+        assert_eq!(output, expected);
+        assert_eq!(
+            transliterate_mo(b"nope", Script::Normal, Script::Devanagari, None),
+            Err(MoFileError::InvalidMagic)
+        );
+        assert_eq!(
+            transliterate_mo(&input[..40], Script::Normal, Script::Devanagari, None),
+            Err(MoFileError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Placeholder-aware transliteration of UI message strings shared by the catalogue formats.
+//!
+//! printf conversions (`%s`, `%1$d`, `%(name)s`, `%{name}`), ICU/brace arguments (`{name}`,
+//! `{{name}}`, `{count, number}`), inline tags and the selector keywords of ICU
+//! `plural`/`select` arguments are kept verbatim. The sub-messages of `plural`/`select` are human text and
+//! are transliterated recursively, with `#` protected inside plural branches.
+
+use super::SegmentTransliterator;
+use alloc::string::String;
+
+pub(crate) fn transliterate_message(text: &str, transliterator: &SegmentTransliterator) -> String {
+    let mut out = String::with_capacity(text.len());
+    write_message(text, transliterator, false, &mut out);
+    out
+}
+
+fn write_message(
+    text: &str,
+    transliterator: &SegmentTransliterator,
+    in_plural: bool,
+    out: &mut String,
+) {
+    let bytes = text.as_bytes();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let protected_end = match bytes[i] {
+            b'%' => printf_len(&text[i..]).map(|len| i + len),
+            b'{' if bytes.get(i + 1) == Some(&b'{') => text[i..].find("}}").map(|end| i + end + 2),
+            b'{' => match matching_brace(text, i) {
+                Some(close) => {
+                    out.push_str(&transliterator.transliterate(&text[text_start..i]));
+                    write_icu_argument(&text[i..=close], transliterator, in_plural, out);
+                    i = close + 1;
+                    text_start = i;
+                    continue;
+                }
+                None => None,
+            },
+            b'#' if in_plural => Some(i + 1),
+            b'<' if bytes
+                .get(i + 1)
+                .is_some_and(|b| b.is_ascii_alphabetic() || *b == b'/' || *b == b'!') =>
+            {
+                text[i..].find('>').map(|end| i + end + 1)
+            }
+            _ => None,
+        };
+        match protected_end {
+            Some(end) => {
+                out.push_str(&transliterator.transliterate(&text[text_start..i]));
+                out.push_str(&text[i..end]);
+                i = end;
+                text_start = end;
+            }
+            None => i += 1,
+        }
+    }
+    out.push_str(&transliterator.transliterate(&text[text_start..]));
+}
+
+/// Writes a complete `{...}` argument. Only the branch messages of `plural`, `selectordinal`
+/// and `select` arguments are transliterated; everything else is copied.
+fn write_icu_argument(
+    arg: &str,
+    transliterator: &SegmentTransliterator,
+    in_plural: bool,
+    out: &mut String,
+) {
+    let inner = &arg[1..arg.len() - 1];
+    let mut parts = inner.splitn(3, ',');
+    let (_name, arg_type, branches) = (parts.next(), parts.next(), parts.next());
+    let branch_plural = match arg_type.map(str::trim) {
+        Some("plural" | "selectordinal") => true,
+        Some("select") => in_plural,
+        _ => {
+            out.push_str(arg);
+            return;
+        }
+    };
+    let Some(branches) = branches else {
+        out.push_str(arg);
+        return;
+    };
+
+    let branches_start = arg.len() - 1 - branches.len();
+    out.push_str(&arg[..branches_start]);
+    let mut i = branches_start;
+    let end = arg.len() - 1;
+    while i < end {
+        let close = match arg.as_bytes()[i] {
+            b'{' => matching_brace(arg, i),
+            _ => None,
+        };
+        if let Some(close) = close {
+            out.push('{');
+            write_message(&arg[i + 1..close], transliterator, branch_plural, out);
+            out.push('}');
+            i = close + 1;
+            continue;
+        }
+        // selector keywords (`one`, `=0`, `offset:1`) and whitespace are ASCII
+        let ch_len = arg[i..].chars().next().map_or(1, char::len_utf8);
+        out.push_str(&arg[i..i + ch_len]);
+        i += ch_len;
+    }
+    out.push('}');
+}
+
+/// Byte index of the `}` closing the `{` at `open`, honouring nesting.
+fn matching_brace(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (offset, b) in text.as_bytes()[open..].iter().enumerate() {
+        match b {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + offset);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Length in bytes of a printf-style conversion at the start of `s` (which begins with `%`).
+fn printf_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    match bytes.get(i) {
+        Some(b'%') => return Some(2),
+        Some(b'(') => i += s[i..].find(')')? + 1,
+        // Ruby/Rails style `%{name}`
+        Some(b'{') => return s.find('}').map(|end| end + 1),
+        _ => {
+            // positional argument: `%1$s`
+            let digits = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+            if digits > 0 && bytes.get(i + digits) == Some(&b'$') {
+                i += digits + 1;
+            }
+        }
+    }
+    while bytes.get(i).is_some_and(|b| b"-+#0'".contains(b)) {
+        i += 1;
+    }
+    while bytes
+        .get(i)
+        .is_some_and(|b| b.is_ascii_digit() || *b == b'*')
+    {
+        i += 1;
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        while bytes
+            .get(i)
+            .is_some_and(|b| b.is_ascii_digit() || *b == b'*')
+        {
+            i += 1;
+        }
+    }
+    while bytes.get(i).is_some_and(|b| b"hlLqjzt".contains(b)) {
+        i += 1;
+    }
+    bytes
+        .get(i)
+        .is_some_and(|b| b"diouxXeEfFgGaAcspn@".contains(b))
+        .then_some(i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripts::Script;
+    use crate::transliterate;
+    use alloc::format;
+
+    fn dev(text: &str) -> String {
+        transliterate(text, Script::Normal, Script::Devanagari, None).into_owned()
+    }
+
+    #[test]
+    fn keeps_placeholders() {
+        let t = SegmentTransliterator::new(Script::Normal, Script::Devanagari, None);
+        assert_eq!(
+            transliterate_message("namaH %s, %1$d %(name)s 100%% {name} {{user}}", &t),
+            format!(
+                "{}%s,{}%1$d{}%(name)s{}%%{}{{name}}{}{{{{user}}}}",
+                dev("namaH "),
+                dev(" "),
+                dev(" "),
+                dev(" 100"),
+                dev(" "),
+                dev(" ")
+            )
+        );
+        assert_eq!(
+            transliterate_message("<b>rAma</b> {n, number}", &t),
+            format!("<b>{}</b>{}{{n, number}}", dev("rAma"), dev(" "))
+        );
+    }
+
+    #[test]
+    fn transliterates_icu_branches() {
+        let t = SegmentTransliterator::new(Script::Normal, Script::Devanagari, None);
+        assert_eq!(
+            transliterate_message(
+                "{count, plural, =0 {na} one {# phalam} other {# phalAni}}",
+                &t
+            ),
+            format!(
+                "{{count, plural, =0 {{{}}} one {{#{}}} other {{#{}}}}}",
+                dev("na"),
+                dev(" phalam"),
+                dev(" phalAni")
+            )
+        );
+    }
+}
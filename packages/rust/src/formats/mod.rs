@@ -0,0 +1,102 @@
+//! Structure-aware transliteration of file formats.
+//!
+//! Each submodule parses its format only as far as needed to locate human-readable
+//! text, transliterates that text and writes everything else back unchanged.
+
+use crate::custom_options::CustomOptions;
+use crate::script_data::ScriptData;
+use crate::scripts::{Script, ScriptListEnum};
+use crate::transliterate::transliterate::{
+    ResolvedTransliterationRules, resolve_transliteration_rules, transliterate_text_core,
+};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+mod message;
+#[cfg(feature = "documents")]
+mod xml;
+
+pub mod catalog;
+#[cfg(feature = "documents")]
+pub mod document;
+pub mod gettext;
+pub mod subtitle;
+#[cfg(any(test, feature = "std"))]
+pub mod table;
+
+/// Script pair with its custom rules resolved once, reused for every text segment of a file.
+pub(crate) struct SegmentTransliterator {
+    from: ScriptListEnum,
+    to: ScriptListEnum,
+    from_data: &'static ScriptData,
+    to_data: &'static ScriptData,
+    resolved: ResolvedTransliterationRules,
+    /// Target characters that attach to the preceding akshara (mātrās, halant, nuqta).
+    attached_marks: Vec<char>,
+}
+
+impl SegmentTransliterator {
+    pub(crate) fn new(from: Script, to: Script, trans_options: Option<&CustomOptions>) -> Self {
+        let from: ScriptListEnum = from.into();
+        let to: ScriptListEnum = to.into();
+        let from_data = ScriptData::get_script_data(&from);
+        let to_data = ScriptData::get_script_data(&to);
+        let resolved = resolve_transliteration_rules(from_data, to_data, trans_options);
+        Self {
+            from,
+            to,
+            from_data,
+            to_data,
+            resolved,
+            attached_marks: attached_marks(to_data),
+        }
+    }
+
+    /// `true` when source and target are the same script and text passes through untouched.
+    pub(crate) fn is_identity(&self) -> bool {
+        self.from == self.to
+    }
+
+    /// `true` for a target character that cannot start a run of its own.
+    #[cfg_attr(not(feature = "documents"), allow(dead_code))]
+    pub(crate) fn is_attached_mark(&self, c: char) -> bool {
+        self.attached_marks.contains(&c)
+    }
+
+    pub(crate) fn transliterate<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.is_identity() || text.is_empty() {
+            return Cow::Borrowed(text);
+        }
+        Cow::Owned(
+            transliterate_text_core(
+                text,
+                &self.from,
+                &self.to,
+                self.from_data,
+                self.to_data,
+                &self.resolved.trans_options,
+                &self.resolved.custom_rules,
+                None,
+            )
+            .output,
+        )
+    }
+}
+
+fn attached_marks(data: &ScriptData) -> Vec<char> {
+    let ScriptData::Brahmic { halant, nuqta, .. } = data else {
+        return Vec::new();
+    };
+    let mut marks: Vec<char> = data
+        .list
+        .iter()
+        .filter(|item| item.is_matra())
+        .flat_map(|item| item.get_krama_ref())
+        .flat_map(|&idx| data.krama_text_or_empty(idx).chars())
+        .chain(halant.chars())
+        .chain(nuqta.iter().flat_map(|nuqta| nuqta.chars()))
+        .collect();
+    marks.sort_unstable();
+    marks.dedup();
+    marks
+}
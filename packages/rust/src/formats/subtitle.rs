@@ -0,0 +1,381 @@
+//! SRT and WebVTT subtitle reader/writer.
+//!
+//! Only cue payload text is transliterated. Cue identifiers, timing lines (including
+//! WebVTT cue settings), the `WEBVTT` header, `NOTE`/`STYLE`/`REGION` blocks, inline
+//! tags such as `<i>` or `<c.yellow>`, HTML entities and `{\an8}` style overrides are
+//! written back exactly as they were read.
+
+use super::SegmentTransliterator;
+use crate::custom_options::CustomOptions;
+use crate::scripts::Script;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Subtitle file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    /// SubRip (`.srt`)
+    Srt,
+    /// WebVTT (`.vtt`)
+    WebVtt,
+}
+
+impl SubtitleFormat {
+    /// Detects the format from file contents: WebVTT files must start with a `WEBVTT` line,
+    /// everything else is treated as SRT.
+    pub fn detect(text: &str) -> Self {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        match text.strip_prefix("WEBVTT") {
+            Some(rest) if rest.is_empty() || rest.starts_with([' ', '\t', '\r', '\n']) => {
+                Self::WebVtt
+            }
+            _ => Self::Srt,
+        }
+    }
+
+    /// Maps a file extension (without the dot, case-insensitive) to a format.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        if ext.eq_ignore_ascii_case("srt") {
+            Some(Self::Srt)
+        } else if ext.eq_ignore_ascii_case("vtt") {
+            Some(Self::WebVtt)
+        } else {
+            None
+        }
+    }
+}
+
+/// A single timed cue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleCue {
+    /// SRT sequence number or WebVTT cue identifier, kept verbatim.
+    pub identifier: Option<String>,
+    /// Timing line including any WebVTT cue settings, kept verbatim.
+    pub timing: String,
+    /// Cue payload lines. This is the only part that gets transliterated.
+    pub text: Vec<String>,
+}
+
+/// A blank-line separated block of a subtitle file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubtitleBlock {
+    Cue(SubtitleCue),
+    /// `WEBVTT` header, `NOTE`, `STYLE` and `REGION` blocks, and anything not recognised
+    /// as a cue. Written back untouched.
+    Verbatim(Vec<String>),
+}
+
+/// Parsed subtitle file that round-trips through [`fmt::Display`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subtitle {
+    format: SubtitleFormat,
+    bom: bool,
+    line_ending: &'static str,
+    blocks: Vec<SubtitleBlock>,
+    /// Number of blank lines preceding each block in `blocks`.
+    blank_lines_before: Vec<usize>,
+    trailing_blank_lines: usize,
+    trailing_newline: bool,
+}
+
+impl Subtitle {
+    /// Parses `text` as the given format. Parsing never fails: blocks that do not look like
+    /// cues are kept as [`SubtitleBlock::Verbatim`].
+    pub fn parse(text: &str, format: SubtitleFormat) -> Self {
+        let (bom, text) = match text.strip_prefix('\u{feff}') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let line_ending = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let trailing_newline = text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(text);
+
+        let mut blocks = Vec::new();
+        let mut blank_lines_before = Vec::new();
+        let mut blank_run = 0;
+        let mut current: Vec<String> = Vec::new();
+
+        if !text.is_empty() {
+            for line in body.split('\n') {
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                if line.trim().is_empty() {
+                    if !current.is_empty() {
+                        let is_first = blocks.is_empty();
+                        blocks.push(Self::classify_block(
+                            core::mem::take(&mut current),
+                            format,
+                            is_first,
+                        ));
+                    }
+                    blank_run += 1;
+                    continue;
+                }
+                if current.is_empty() {
+                    blank_lines_before.push(blank_run);
+                    blank_run = 0;
+                }
+                current.push(line.to_string());
+            }
+        }
+        if !current.is_empty() {
+            let is_first = blocks.is_empty();
+            blocks.push(Self::classify_block(current, format, is_first));
+        }
+
+        Self {
+            format,
+            bom,
+            line_ending,
+            blocks,
+            blank_lines_before,
+            trailing_blank_lines: blank_run,
+            trailing_newline,
+        }
+    }
+
+    fn classify_block(lines: Vec<String>, format: SubtitleFormat, is_first: bool) -> SubtitleBlock {
+        if format == SubtitleFormat::WebVtt {
+            let first = lines[0].as_str();
+            let is_meta = (is_first && first.starts_with("WEBVTT"))
+                || ["NOTE", "STYLE", "REGION"].iter().any(|kw| {
+                    first
+                        .strip_prefix(kw)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+                });
+            if is_meta {
+                return SubtitleBlock::Verbatim(lines);
+            }
+        }
+
+        let timing_index = if lines[0].contains("-->") {
+            0
+        } else if lines.len() > 1 && lines[1].contains("-->") {
+            1
+        } else {
+            return SubtitleBlock::Verbatim(lines);
+        };
+
+        let mut lines = lines.into_iter();
+        let identifier = if timing_index == 1 {
+            lines.next()
+        } else {
+            None
+        };
+        let timing = lines.next().unwrap_or_default();
+        SubtitleBlock::Cue(SubtitleCue {
+            identifier,
+            timing,
+            text: lines.collect(),
+        })
+    }
+
+    pub fn format(&self) -> SubtitleFormat {
+        self.format
+    }
+
+    pub fn blocks(&self) -> &[SubtitleBlock] {
+        &self.blocks
+    }
+
+    pub fn blocks_mut(&mut self) -> &mut [SubtitleBlock] {
+        &mut self.blocks
+    }
+
+    /// Iterates over the timed cues only.
+    pub fn cues(&self) -> impl Iterator<Item = &SubtitleCue> {
+        self.blocks.iter().filter_map(|block| match block {
+            SubtitleBlock::Cue(cue) => Some(cue),
+            SubtitleBlock::Verbatim(_) => None,
+        })
+    }
+
+    /// Transliterates the text of every cue in place.
+    pub fn transliterate(
+        &mut self,
+        from: Script,
+        to: Script,
+        trans_options: Option<&CustomOptions>,
+    ) {
+        let transliterator = SegmentTransliterator::new(from, to, trans_options);
+        if transliterator.is_identity() {
+            return;
+        }
+        for block in self.blocks.iter_mut() {
+            if let SubtitleBlock::Cue(cue) = block {
+                for line in cue.text.iter_mut() {
+                    *line = transliterate_cue_line(line, &transliterator);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Subtitle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bom {
+            f.write_str("\u{feff}")?;
+        }
+        let mut first_line = true;
+        let mut write_line = |f: &mut fmt::Formatter<'_>, line: &str| -> fmt::Result {
+            if !first_line {
+                f.write_str(self.line_ending)?;
+            }
+            first_line = false;
+            f.write_str(line)
+        };
+
+        for (block, blank_lines) in self.blocks.iter().zip(&self.blank_lines_before) {
+            for _ in 0..*blank_lines {
+                write_line(f, "")?;
+            }
+            match block {
+                SubtitleBlock::Cue(cue) => {
+                    if let Some(identifier) = &cue.identifier {
+                        write_line(f, identifier)?;
+                    }
+                    write_line(f, &cue.timing)?;
+                    for line in &cue.text {
+                        write_line(f, line)?;
+                    }
+                }
+                SubtitleBlock::Verbatim(lines) => {
+                    for line in lines {
+                        write_line(f, line)?;
+                    }
+                }
+            }
+        }
+        for _ in 0..self.trailing_blank_lines {
+            write_line(f, "")?;
+        }
+        if self.trailing_newline {
+            f.write_str(self.line_ending)?;
+        }
+        Ok(())
+    }
+}
+
+/// Length in bytes of an HTML character reference (`&amp;`, `&#x202A;`) at the start of `s`.
+fn entity_len(s: &str) -> Option<usize> {
+    let rest = s.strip_prefix('&')?;
+    let end = rest.find(';')?;
+    let name = &rest[..end];
+    let valid = !name.is_empty()
+        && name.len() <= 32
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'#');
+    valid.then_some(end + 2)
+}
+
+/// Transliterates the plain-text runs of a cue line, copying markup through unchanged.
+fn transliterate_cue_line(line: &str, transliterator: &SegmentTransliterator) -> String {
+    let bytes = line.as_bytes();
+    let mut out = String::with_capacity(line.len());
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let markup_end = match bytes[i] {
+            b'<' => line[i..].find('>').map(|end| i + end + 1),
+            b'{' if bytes.get(i + 1) == Some(&b'\\') => line[i..].find('}').map(|end| i + end + 1),
+            b'&' => entity_len(&line[i..]).map(|len| i + len),
+            _ => None,
+        };
+        match markup_end {
+            Some(end) => {
+                out.push_str(&transliterator.transliterate(&line[text_start..i]));
+                out.push_str(&line[i..end]);
+                i = end;
+                text_start = end;
+            }
+            None => i += 1,
+        }
+    }
+    out.push_str(&transliterator.transliterate(&line[text_start..]));
+    out
+}
+
+/// Transliterates the cue text of an SRT or WebVTT file, leaving its structure intact.
+///
+/// When `format` is `None` it is detected with [`SubtitleFormat::detect`].
+pub fn transliterate_subtitle(
+    text: &str,
+    format: Option<SubtitleFormat>,
+    from: Script,
+    to: Script,
+    trans_options: Option<&CustomOptions>,
+) -> String {
+    let format = format.unwrap_or_else(|| SubtitleFormat::detect(text));
+    let mut subtitle = Subtitle::parse(text, format);
+    subtitle.transliterate(from, to, trans_options);
+    subtitle.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate;
+
+    const VTT: &str = "\u{feff}WEBVTT - discourse\r\n\r\nSTYLE\r\n::cue { color: lime }\r\n\r\nNOTE ram should stay\r\n\r\nintro\r\n00:00:01.000 --> 00:00:04.000 line:0 position:20% align:start\r\n<v Guru><i>rAma</i> &amp; kRRiShNa\r\n\r\n\r\n00:00:05.000 --> 00:00:06.500\r\n<c.yellow>namaH</c> <00:00:05.500>shivAya\r\n";
+
+    fn dev(text: &str) -> String {
+        transliterate(text, Script::Normal, Script::Devanagari, None).into_owned()
+    }
+
+    #[test]
+    fn detects_format() {
+        assert_eq!(SubtitleFormat::detect(VTT), SubtitleFormat::WebVtt);
+        assert_eq!(SubtitleFormat::detect("WEBVTT\n"), SubtitleFormat::WebVtt);
+        assert_eq!(SubtitleFormat::detect("WEBVTTX\n"), SubtitleFormat::Srt);
+        assert_eq!(
+            SubtitleFormat::detect("1\n00:00:01,000 --> 00:00:02,000\n"),
+            SubtitleFormat::Srt
+        );
+        assert_eq!(
+            SubtitleFormat::from_extension("VTT"),
+            Some(SubtitleFormat::WebVtt)
+        );
+        assert_eq!(SubtitleFormat::from_extension("ass"), None);
+    }
+
+    #[test]
+    fn round_trips_without_changes() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n{\\an8}hello\n\n2\n00:00:03,000 --> 00:00:04,000\nworld\n\n";
+        for (text, format) in [(VTT, SubtitleFormat::WebVtt), (srt, SubtitleFormat::Srt)] {
+            assert_eq!(Subtitle::parse(text, format).to_string(), text);
+        }
+        assert_eq!(Subtitle::parse("", SubtitleFormat::Srt).to_string(), "");
+    }
+
+    #[test]
+    fn transliterates_webvtt_cue_text_only() {
+        let subtitle = Subtitle::parse(VTT, SubtitleFormat::WebVtt);
+        assert_eq!(subtitle.cues().count(), 2);
+        assert_eq!(subtitle.blocks().len(), 5);
+
+        let output = transliterate_subtitle(VTT, None, Script::Normal, Script::Devanagari, None);
+        let expected = VTT
+            .replace("<i>rAma</i>", &alloc::format!("<i>{}</i>", dev("rAma")))
+            .replace(" kRRiShNa", &dev(" kRRiShNa"))
+            .replace(">namaH<", &alloc::format!(">{}<", dev("namaH")))
+            .replace("</c> <", &alloc::format!("</c>{}<", dev(" ")))
+            .replace(">shivAya", &alloc::format!(">{}", dev("shivAya")));
+        assert_eq!(output, expected);
+        assert!(output.contains("NOTE ram should stay"));
+        assert!(output.contains("line:0 position:20% align:start"));
+    }
+
+    #[test]
+    fn transliterates_srt_cue_text_only() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n{\\an8}<b>gItA</b>\nsecond line\n";
+        let output = transliterate_subtitle(srt, None, Script::Normal, Script::Devanagari, None);
+        assert_eq!(
+            output,
+            alloc::format!(
+                "1\n00:00:01,000 --> 00:00:02,000\n{{\\an8}}<b>{}</b>\n{}\n",
+                dev("gItA"),
+                dev("second line")
+            )
+        );
+    }
+}
@@ -0,0 +1,498 @@
+//! Column-level transliteration of CSV and TSV tables.
+//!
+//! Each [`ColumnTransliteration`] names a source column, a script pair and its options,
+//! and either converts the column in place or appends the result as a new column
+//! (`headword` → `headword_telugu`). Rules always read the original cell, so one column
+//! can feed several outputs. Rows are read and written one at a time, and every rule
+//! resolves its transliteration rules once for the whole table.
+//!
+//! Quoting follows RFC 4180: quoted fields may contain delimiters, doubled quotes and line
+//! breaks. Cells that are not converted are written back byte-for-byte; converted cells
+//! keep their quotes and are quoted whenever their new content requires it.
+
+use super::SegmentTransliterator;
+use crate::custom_options::CustomOptions;
+use crate::scripts::Script;
+use std::io::{self, BufRead, Write};
+use std::string::String;
+use std::vec::Vec;
+
+/// Delimiter and header settings of a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableFormat {
+    pub delimiter: u8,
+    /// Quote character; `None` treats every byte literally (plain TSV).
+    pub quote: Option<u8>,
+    /// Whether the first record names the columns.
+    pub has_header: bool,
+}
+
+impl TableFormat {
+    /// Comma separated, `"` quoted, with a header row.
+    pub const fn csv() -> Self {
+        Self {
+            delimiter: b',',
+            quote: Some(b'"'),
+            has_header: true,
+        }
+    }
+
+    /// Tab separated, `"` quoted, with a header row.
+    pub const fn tsv() -> Self {
+        Self {
+            delimiter: b'\t',
+            ..Self::csv()
+        }
+    }
+
+    /// Maps a file extension (`csv`, `tsv`, `tab`), case-insensitively.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::csv()),
+            "tsv" | "tab" => Some(Self::tsv()),
+            _ => None,
+        }
+    }
+}
+
+/// Column addressed by header name or zero-based position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnRef {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for ColumnRef {
+    fn from(name: &str) -> Self {
+        Self::Name(name.into())
+    }
+}
+
+impl From<usize> for ColumnRef {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+/// Script settings for one column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnTransliteration {
+    pub column: ColumnRef,
+    pub from: Script,
+    pub to: Script,
+    pub options: Option<CustomOptions>,
+    /// Header of the column to append; `None` converts the source column in place.
+    pub output_column: Option<String>,
+}
+
+impl ColumnTransliteration {
+    pub fn new(column: impl Into<ColumnRef>, from: Script, to: Script) -> Self {
+        Self {
+            column: column.into(),
+            from,
+            to,
+            options: None,
+            output_column: None,
+        }
+    }
+
+    pub fn with_options(mut self, options: CustomOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Appends the result as a new column named `name` instead of overwriting the source.
+    pub fn into_column(mut self, name: impl Into<String>) -> Self {
+        self.output_column = Some(name.into());
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum TableError {
+    Io(io::Error),
+    /// A column name that is not in the header row.
+    UnknownColumn(String),
+    /// A column was given by name but the table has no header row.
+    MissingHeader,
+    /// A quoted field is still open at the end of the input.
+    UnterminatedQuote {
+        record: u64,
+    },
+}
+
+impl core::fmt::Display for TableError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::UnknownColumn(name) => write!(f, "column `{name}` not found in header"),
+            Self::MissingHeader => {
+                write!(f, "columns can only be named when the table has a header")
+            }
+            Self::UnterminatedQuote { record } => {
+                write!(f, "record {record} has an unterminated quoted field")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TableError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TableError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Transliterates the configured columns of the table read from `reader` into `writer` and
+/// returns the number of data records written (header excluded).
+pub fn transliterate_table<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    format: TableFormat,
+    columns: &[ColumnTransliteration],
+) -> Result<u64, TableError> {
+    let transliterators: Vec<SegmentTransliterator> = columns
+        .iter()
+        .map(|rule| SegmentTransliterator::new(rule.from, rule.to, rule.options.as_ref()))
+        .collect();
+    let mut records = RecordReader {
+        reader: &mut reader,
+        format,
+        buf: String::new(),
+        number: 0,
+        had_bom: false,
+    };
+    let mut fields = Vec::new();
+
+    let Some(mut terminator) = records.next(&mut fields)? else {
+        return Ok(0);
+    };
+    if records.had_bom {
+        writer.write_all("\u{feff}".as_bytes())?;
+    }
+    let width = fields.len();
+
+    let header = format.has_header.then(|| {
+        fields
+            .iter()
+            .map(|field| field.value(&records.buf).into_owned())
+            .collect::<Vec<_>>()
+    });
+    let mut sources = Vec::with_capacity(columns.len());
+    for rule in columns {
+        sources.push(match (&rule.column, &header) {
+            (ColumnRef::Index(index), _) => *index,
+            (ColumnRef::Name(name), Some(header)) => header
+                .iter()
+                .position(|column| column == name)
+                .ok_or_else(|| TableError::UnknownColumn(name.clone()))?,
+            (ColumnRef::Name(_), None) => return Err(TableError::MissingHeader),
+        });
+    }
+    let appended: Vec<usize> = (0..columns.len())
+        .filter(|&rule| columns[rule].output_column.is_some())
+        .collect();
+
+    let mut writer = FieldWriter {
+        writer: &mut writer,
+        format,
+    };
+    let mut written = 0;
+    let mut is_header = format.has_header;
+    loop {
+        let buf = &records.buf;
+        let mut replaced: Vec<Option<String>> = Vec::new();
+        let mut new_cells: Vec<String> = Vec::with_capacity(appended.len());
+        if is_header {
+            new_cells.extend(
+                appended
+                    .iter()
+                    .filter_map(|&rule| columns[rule].output_column.clone()),
+            );
+        } else {
+            replaced.resize(fields.len(), None);
+            for (rule, transliterator) in transliterators.iter().enumerate() {
+                let source = fields.get(sources[rule]);
+                let value = source.map(|field| field.value(buf)).unwrap_or_default();
+                let converted = transliterator.transliterate(&value);
+                match (&columns[rule].output_column, source) {
+                    (Some(_), _) => new_cells.push(converted.into_owned()),
+                    (None, Some(_)) if converted != value => {
+                        replaced[sources[rule]] = Some(converted.into_owned());
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+
+        let row_width = match appended.is_empty() {
+            true => fields.len(),
+            false => fields.len().max(width),
+        };
+        for index in 0..row_width {
+            if index > 0 {
+                writer.delimiter()?;
+            }
+            match (
+                fields.get(index),
+                replaced.get(index).and_then(Option::as_deref),
+            ) {
+                (Some(field), Some(new_value)) => writer.cell(new_value, field.quoted)?,
+                (Some(field), None) => writer.raw(field.raw(buf))?,
+                (None, _) => {}
+            }
+        }
+        for cell in &new_cells {
+            if row_width > 0 {
+                writer.delimiter()?;
+            }
+            writer.cell(cell, false)?;
+        }
+        writer.raw(terminator)?;
+        if !is_header {
+            written += 1;
+        }
+        is_header = false;
+
+        let Some(next) = records.next(&mut fields)? else {
+            break;
+        };
+        terminator = next;
+    }
+    writer.writer.flush()?;
+    Ok(written)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    start: usize,
+    end: usize,
+    quoted: bool,
+}
+
+impl Field {
+    fn raw<'a>(&self, buf: &'a str) -> &'a str {
+        &buf[self.start..self.end]
+    }
+
+    fn value<'a>(&self, buf: &'a str) -> std::borrow::Cow<'a, str> {
+        let raw = self.raw(buf);
+        match self.quoted {
+            true => {
+                let inner = &raw[1..raw.len() - 1];
+                let quote = &raw[..1];
+                match inner.contains(quote) {
+                    true => inner.replace(&[quote, quote].concat(), quote).into(),
+                    false => inner.into(),
+                }
+            }
+            false => raw.into(),
+        }
+    }
+}
+
+struct RecordReader<'r, R> {
+    reader: &'r mut R,
+    format: TableFormat,
+    /// Current record without its terminator
+    buf: String,
+    number: u64,
+    /// The first record started with a byte order mark, which is left out of `buf`
+    had_bom: bool,
+}
+
+impl<R: BufRead> RecordReader<'_, R> {
+    /// Reads the next record into `buf`, splitting it into `fields`. Returns the record
+    /// terminator (`"\n"`, `"\r\n"` or `""` at end of input), or `None` when exhausted.
+    fn next(&mut self, fields: &mut Vec<Field>) -> Result<Option<&'static str>, TableError> {
+        self.buf.clear();
+        fields.clear();
+        self.number += 1;
+        let delimiter = self.format.delimiter as char;
+        let quote = self.format.quote.map(char::from);
+
+        let mut field_start = 0;
+        let mut scan = 0;
+        let mut in_quotes = false;
+        loop {
+            if self.reader.read_line(&mut self.buf)? == 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                if in_quotes {
+                    return Err(TableError::UnterminatedQuote {
+                        record: self.number,
+                    });
+                }
+                break;
+            }
+            if self.number == 1 && scan == 0 && self.buf.starts_with('\u{feff}') {
+                self.buf.drain(..'\u{feff}'.len_utf8());
+                self.had_bom = true;
+            }
+            let mut chars = self.buf[scan..].char_indices().peekable();
+            while let Some((offset, c)) = chars.next() {
+                let at = scan + offset;
+                if in_quotes {
+                    if Some(c) == quote {
+                        match chars.peek() {
+                            Some(&(_, next)) if Some(next) == quote => {
+                                chars.next();
+                            }
+                            _ => in_quotes = false,
+                        }
+                    }
+                } else if c == delimiter {
+                    fields.push(self.field(field_start, at));
+                    field_start = at + 1;
+                } else if Some(c) == quote && at == field_start {
+                    in_quotes = true;
+                }
+            }
+            scan = self.buf.len();
+            if !in_quotes {
+                break;
+            }
+        }
+
+        let terminator = if self.buf.ends_with("\r\n") {
+            "\r\n"
+        } else if self.buf.ends_with('\n') {
+            "\n"
+        } else {
+            ""
+        };
+        self.buf.truncate(self.buf.len() - terminator.len());
+        fields.push(self.field(field_start, self.buf.len()));
+        Ok(Some(terminator))
+    }
+
+    fn field(&self, start: usize, end: usize) -> Field {
+        let raw = &self.buf[start..end];
+        let quoted = self.format.quote.is_some_and(|quote| {
+            raw.len() >= 2 && raw.as_bytes()[0] == quote && raw.as_bytes()[raw.len() - 1] == quote
+        });
+        Field { start, end, quoted }
+    }
+}
+
+struct FieldWriter<'w, W> {
+    writer: &'w mut W,
+    format: TableFormat,
+}
+
+impl<W: Write> FieldWriter<'_, W> {
+    fn raw(&mut self, text: &str) -> io::Result<()> {
+        self.writer.write_all(text.as_bytes())
+    }
+
+    fn delimiter(&mut self) -> io::Result<()> {
+        self.writer.write_all(&[self.format.delimiter])
+    }
+
+    fn cell(&mut self, value: &str, force_quotes: bool) -> io::Result<()> {
+        let Some(quote) = self.format.quote else {
+            return self.raw(value);
+        };
+        let needs_quotes = force_quotes
+            || value
+                .bytes()
+                .any(|b| b == self.format.delimiter || b == quote || b == b'\n' || b == b'\r');
+        if !needs_quotes {
+            return self.raw(value);
+        }
+        let quote = quote as char;
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push(quote);
+        for c in value.chars() {
+            if c == quote {
+                quoted.push(quote);
+            }
+            quoted.push(c);
+        }
+        quoted.push(quote);
+        self.raw(&quoted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate;
+    use std::format;
+
+    fn convert(input: &str, format: TableFormat, columns: &[ColumnTransliteration]) -> String {
+        let mut out = Vec::new();
+        transliterate_table(input.as_bytes(), &mut out, format, columns).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn converts_and_appends_columns() {
+        let dev = |text| transliterate(text, Script::Normal, Script::Devanagari, None).into_owned();
+        let tel = |text| transliterate(text, Script::Normal, Script::Telugu, None).into_owned();
+        let input = "headword,gloss,\"note, quoted\"\r\nrAma,\"king, \"\"hero\"\"\",x\r\n\"sI\ntA\",queen,y\r\n";
+        let columns = [
+            ColumnTransliteration::new("headword", Script::Normal, Script::Devanagari),
+            ColumnTransliteration::new(0, Script::Normal, Script::Telugu)
+                .into_column("headword_telugu"),
+        ];
+        assert_eq!(
+            convert(input, TableFormat::csv(), &columns),
+            format!(
+                "headword,gloss,\"note, quoted\",headword_telugu\r\n{},\"king, \"\"hero\"\"\",x,{}\r\n\"{}\",queen,y,\"{}\"\r\n",
+                dev("rAma"),
+                tel("rAma"),
+                dev("sI\ntA"),
+                tel("sI\ntA")
+            )
+        );
+    }
+
+    #[test]
+    fn handles_headerless_tsv_and_errors() {
+        let format = TableFormat {
+            has_header: false,
+            ..TableFormat::tsv()
+        };
+        let columns = [ColumnTransliteration::new(
+            1,
+            Script::Normal,
+            Script::Devanagari,
+        )];
+        assert_eq!(
+            convert("1\tka\n2", format, &columns),
+            format!(
+                "1\t{}\n2",
+                transliterate("ka", Script::Normal, Script::Devanagari, None)
+            )
+        );
+
+        let named = [ColumnTransliteration::new(
+            "gloss",
+            Script::Normal,
+            Script::Devanagari,
+        )];
+        let mut out = Vec::new();
+        assert!(matches!(
+            transliterate_table("a\n".as_bytes(), &mut out, format, &named),
+            Err(TableError::MissingHeader)
+        ));
+        assert!(matches!(
+            transliterate_table("a,b\n".as_bytes(), &mut out, TableFormat::csv(), &named),
+            Err(TableError::UnknownColumn(_))
+        ));
+        assert!(matches!(
+            transliterate_table("a\n\"b".as_bytes(), &mut out, TableFormat::csv(), &[]),
+            Err(TableError::UnterminatedQuote { record: 2 })
+        ));
+    }
+}
@@ -0,0 +1,480 @@
+//! Minimal streaming XML rewriter used by the document converters.
+//!
+//! The input is split into markup and character data without building a tree, so the
+//! output is byte-identical except for the text that actually changed. Text is gathered
+//! per paragraph, so a word that is split over several runs (`<w:r>`, `<span>`) is
+//! transliterated as a whole and then split back over its runs: each run keeps the
+//! converted part of its own text, cut at akshara boundaries.
+
+use super::SegmentTransliterator;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// How an element affects text collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum XmlRole {
+    /// Bounds a paragraph; its character data is translatable (`<p>`, `<text:p>`).
+    Paragraph,
+    /// Bounds a paragraph; only `Text` descendants are translatable (`<w:p>`).
+    Structure,
+    /// Translatable text element inside a `Structure` (`<w:t>`).
+    Text,
+    /// Formatting that does not interrupt a paragraph (`<span>`, `<w:r>`).
+    Inline,
+    /// Acts like whitespace between words (`<br/>`, `<w:tab/>`, `<text:s/>`).
+    Break,
+    /// Content is never translated (`<script>`, `<w:instrText>`).
+    Opaque,
+    /// Content is a language tag to be rewritten (`<dc:language>`).
+    Language,
+}
+
+/// Rewrites a BCP 47 language tag to carry the ISO 15924 code of the target script:
+/// `sa` → `sa-Telu`, `hi-Deva-IN` → `hi-Telu-IN`.
+pub(crate) fn retag_language(tag: &str, script_code: &str) -> String {
+    let mut subtags = tag.trim().split('-');
+    let mut out = String::from(subtags.next().unwrap_or_default());
+    out.push('-');
+    out.push_str(script_code);
+    for (index, subtag) in subtags.enumerate() {
+        let is_script = subtag.len() == 4 && subtag.bytes().all(|b| b.is_ascii_alphabetic());
+        if index == 0 && is_script {
+            continue;
+        }
+        out.push('-');
+        out.push_str(subtag);
+    }
+    out
+}
+
+enum Piece<'a> {
+    Markup(Cow<'a, str>),
+    /// Character data copied as is
+    Raw(&'a str),
+    /// Translatable character data, still entity-encoded
+    Text(&'a str),
+    Break(Cow<'a, str>),
+}
+
+pub(crate) struct XmlRewriter<'t> {
+    pub(crate) transliterator: &'t SegmentTransliterator,
+    pub(crate) role_of: fn(&str) -> XmlRole,
+    /// ISO 15924 code written into `xml:lang`/`lang` attributes and `Language` elements.
+    pub(crate) script_code: &'static str,
+}
+
+impl XmlRewriter<'_> {
+    pub(crate) fn rewrite(&self, xml: &str) -> String {
+        let mut out = String::with_capacity(xml.len() + xml.len() / 4);
+        let mut paragraph: Vec<Piece<'_>> = Vec::new();
+        let mut stack: Vec<XmlRole> = Vec::new();
+        let mut i = 0;
+
+        while i < xml.len() {
+            if !xml[i..].starts_with('<') {
+                let end = xml[i..].find('<').map_or(xml.len(), |at| i + at);
+                let text = &xml[i..end];
+                match Self::text_mode(&stack) {
+                    Some(XmlRole::Paragraph | XmlRole::Text) => paragraph.push(Piece::Text(text)),
+                    Some(XmlRole::Language) if !text.trim().is_empty() => {
+                        let leading = text.len() - text.trim_start().len();
+                        let trailing = text.len() - text.trim_end().len();
+                        paragraph.push(Piece::Raw(&text[..leading]));
+                        paragraph.push(Piece::Markup(Cow::Owned(retag_language(
+                            text.trim(),
+                            self.script_code,
+                        ))));
+                        paragraph.push(Piece::Raw(&text[text.len() - trailing..]));
+                    }
+                    _ => paragraph.push(Piece::Raw(text)),
+                }
+                i = end;
+                continue;
+            }
+
+            let end = markup_end(xml, i);
+            let markup = &xml[i..end];
+            i = end;
+            let Some((name, kind)) = tag_name(markup) else {
+                paragraph.push(Piece::Markup(Cow::Borrowed(markup)));
+                continue;
+            };
+            let role = (self.role_of)(name);
+            let bounds_paragraph =
+                !matches!(role, XmlRole::Inline | XmlRole::Break | XmlRole::Text);
+
+            if bounds_paragraph {
+                self.flush(&mut paragraph, &mut out);
+            }
+            match kind {
+                TagKind::Start | TagKind::Empty => {
+                    let markup = self.retag_attributes(markup);
+                    if role == XmlRole::Break {
+                        paragraph.push(Piece::Break(markup));
+                    } else {
+                        paragraph.push(Piece::Markup(markup));
+                    }
+                    if kind == TagKind::Start {
+                        stack.push(role);
+                    }
+                }
+                TagKind::End => {
+                    stack.pop();
+                    paragraph.push(Piece::Markup(Cow::Borrowed(markup)));
+                }
+            }
+            if bounds_paragraph {
+                self.flush(&mut paragraph, &mut out);
+            }
+        }
+        self.flush(&mut paragraph, &mut out);
+        out
+    }
+
+    /// Nearest enclosing role that decides what happens to character data.
+    fn text_mode(stack: &[XmlRole]) -> Option<XmlRole> {
+        stack
+            .iter()
+            .rev()
+            .copied()
+            .find(|role| !matches!(role, XmlRole::Inline | XmlRole::Break))
+    }
+
+    fn retag_attributes<'a>(&self, tag: &'a str) -> Cow<'a, str> {
+        if !tag.contains("lang=") {
+            return Cow::Borrowed(tag);
+        }
+        let mut out = String::with_capacity(tag.len() + 8);
+        let mut rest = tag;
+        while let Some(at) = rest.find("lang=") {
+            let (before, after) = rest.split_at(at + "lang=".len());
+            out.push_str(before);
+            let attr_start = before[..at]
+                .rfind(|c: char| c.is_ascii_whitespace())
+                .map_or(0, |ws| ws + 1);
+            let attr_name = &before[attr_start..];
+            let quote = after.chars().next();
+            let is_lang_attr = matches!(attr_name, "lang=" | "xml:lang=");
+            match quote {
+                Some(q @ ('"' | '\'')) if is_lang_attr => match after[1..].find(q) {
+                    Some(close) => {
+                        let value = &after[1..1 + close];
+                        out.push(q);
+                        if !value.is_empty() {
+                            out.push_str(&retag_language(value, self.script_code));
+                        }
+                        out.push(q);
+                        rest = &after[close + 2..];
+                    }
+                    None => rest = after,
+                },
+                _ => rest = after,
+            }
+        }
+        out.push_str(rest);
+        Cow::Owned(out)
+    }
+
+    fn flush<'a>(&self, paragraph: &mut Vec<Piece<'a>>, out: &mut String) {
+        let segments: Vec<Option<Cow<'a, str>>> = paragraph
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Text(text) => Some(Some(decode_entities(text))),
+                Piece::Break(_) => Some(None),
+                _ => None,
+            })
+            .collect();
+        let has_words = segments
+            .iter()
+            .flatten()
+            .any(|text| !text.trim().is_empty());
+        let mut converted = match has_words {
+            true => distribute(&segments, self.transliterator).into_iter(),
+            false => Vec::new().into_iter(),
+        };
+
+        for piece in paragraph.drain(..) {
+            match piece {
+                Piece::Markup(markup) | Piece::Break(markup) => out.push_str(&markup),
+                Piece::Raw(text) => out.push_str(text),
+                Piece::Text(raw) => match converted.next() {
+                    Some(Some(text)) => encode_entities(&text, out),
+                    _ => out.push_str(raw),
+                },
+            }
+        }
+    }
+}
+
+/// Transliterates a paragraph given as text segments (`None` marks a word break) and returns,
+/// per text segment, its new content or `None` when it is unchanged.
+fn distribute(
+    segments: &[Option<Cow<'_, str>>],
+    transliterator: &SegmentTransliterator,
+) -> Vec<Option<String>> {
+    let mut joined = String::new();
+    let mut bounds = Vec::new();
+    for segment in segments {
+        let start = joined.len();
+        joined.push_str(segment.as_deref().unwrap_or("\n"));
+        if segment.is_some() {
+            bounds.push((start, joined.len()));
+        }
+    }
+
+    let is_space_at = |at: usize| joined[at..].chars().next().is_none_or(char::is_whitespace);
+    let is_space_before = |at: usize| {
+        joined[..at]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace)
+    };
+    let mid_word = |at: usize| !is_space_before(at) && !is_space_at(at);
+    let word_start = |at: usize| {
+        joined[..at]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(ws, c)| ws + c.len_utf8())
+    };
+    let word_end = |at: usize| {
+        joined[at..]
+            .find(char::is_whitespace)
+            .map_or(joined.len(), |end| at + end)
+    };
+    // Converts the word around `at` and finds where its converted text is cut: after
+    // the part that converting the word's text before `at` yields, moved past any mātrā,
+    // halant or nuqta so an akshara is never split between two runs.
+    let split_word = |at: usize| {
+        let start = word_start(at);
+        let word = transliterator
+            .transliterate(&joined[start..word_end(at)])
+            .into_owned();
+        let head = transliterator.transliterate(&joined[start..at]);
+        let cut = common_prefix_len(&word, &head);
+        let marks: usize = word[cut..]
+            .chars()
+            .take_while(|&c| transliterator.is_attached_mark(c))
+            .map(char::len_utf8)
+            .sum();
+        (word, cut + marks)
+    };
+
+    bounds
+        .into_iter()
+        .map(|(start, end)| {
+            let head = mid_word(start).then(|| split_word(start));
+            let tail = mid_word(end).then(|| split_word(end));
+            let new_text = match (head, tail) {
+                (Some((word, from)), Some((_, to))) if word_end(start) >= end => {
+                    String::from(&word[from..to.max(from)])
+                }
+                (head, tail) => {
+                    let middle_start = if head.is_some() {
+                        word_end(start)
+                    } else {
+                        start
+                    };
+                    let middle_end = if tail.is_some() { word_start(end) } else { end };
+                    let mut text = String::new();
+                    if let Some((word, from)) = head {
+                        text.push_str(&word[from..]);
+                    }
+                    text.push_str(&transliterator.transliterate(&joined[middle_start..middle_end]));
+                    if let Some((word, to)) = tail {
+                        text.push_str(&word[..to]);
+                    }
+                    text
+                }
+            };
+            (new_text != joined[start..end]).then_some(new_text)
+        })
+        .collect()
+}
+
+/// Byte length of the longest common prefix of `a` and `b`, on a char boundary of both.
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((at, _), _)| at)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagKind {
+    Start,
+    End,
+    Empty,
+}
+
+/// Name and kind of an element tag; `None` for comments, CDATA, PIs and declarations.
+fn tag_name(markup: &str) -> Option<(&str, TagKind)> {
+    let (rest, kind) = match markup.strip_prefix("</") {
+        Some(rest) => (rest, TagKind::End),
+        None if markup.starts_with("<!") || markup.starts_with("<?") => return None,
+        None if markup.ends_with("/>") => (&markup[1..], TagKind::Empty),
+        None => (&markup[1..], TagKind::Start),
+    };
+    let name_end = rest
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .unwrap_or(rest.len());
+    Some((&rest[..name_end], kind))
+}
+
+/// End (exclusive) of the markup starting with `<` at `start`.
+fn markup_end(xml: &str, start: usize) -> usize {
+    let rest = &xml[start..];
+    let find_after = |pattern: &str, skip: usize| {
+        rest[skip..]
+            .find(pattern)
+            .map_or(xml.len(), |at| start + skip + at + pattern.len())
+    };
+    if rest.starts_with("<!--") {
+        return find_after("-->", 4);
+    }
+    if rest.starts_with("<![CDATA[") {
+        return find_after("]]>", 9);
+    }
+    if rest.starts_with("<?") {
+        return find_after("?>", 2);
+    }
+
+    let mut quote = None;
+    let mut bracket_depth = 0usize;
+    for (offset, b) in rest.bytes().enumerate().skip(1) {
+        match (quote, b) {
+            (Some(q), _) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(b),
+            // internal DTD subset of `<!DOCTYPE ... [ ... ]>`
+            (None, b'[') => bracket_depth += 1,
+            (None, b']') => bracket_depth = bracket_depth.saturating_sub(1),
+            (None, b'>') if bracket_depth == 0 => return start + offset + 1,
+            _ => {}
+        }
+    }
+    xml.len()
+}
+
+fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let decoded = rest.find(';').and_then(|end| {
+            let name = &rest[1..end];
+            let ch = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| name.strip_prefix('#').map(str::parse::<u32>))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            ch.map(|ch| (ch, end + 1))
+        });
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+fn encode_entities(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripts::Script;
+    use crate::transliterate;
+    use alloc::format;
+
+    fn dev(text: &str) -> String {
+        transliterate(text, Script::Normal, Script::Devanagari, None).into_owned()
+    }
+
+    fn docx_role(name: &str) -> XmlRole {
+        match name {
+            "w:p" => XmlRole::Structure,
+            "w:t" => XmlRole::Text,
+            "w:tab" => XmlRole::Break,
+            "w:instrText" => XmlRole::Opaque,
+            _ => XmlRole::Inline,
+        }
+    }
+
+    #[test]
+    fn retags_language() {
+        assert_eq!(retag_language("sa", "Telu"), "sa-Telu");
+        assert_eq!(retag_language("hi-Deva-IN", "Telu"), "hi-Telu-IN");
+        assert_eq!(retag_language("hi-IN", "Latn"), "hi-Latn-IN");
+    }
+
+    #[test]
+    fn splits_words_across_runs_at_akshara_boundaries() {
+        let t = SegmentTransliterator::new(Script::Normal, Script::Devanagari, None);
+        let rewriter = XmlRewriter {
+            transliterator: &t,
+            role_of: docx_role,
+            script_code: "Deva",
+        };
+        let xml = "<w:p><w:r><w:rPr><w:b/></w:rPr><w:t>rA</w:t></w:r><w:r><w:t xml:space=\"preserve\">ma &amp; </w:t></w:r><w:r><w:tab/><w:t>s</w:t></w:r><w:r><w:t>ItA</w:t></w:r><w:r><w:instrText>PAGE</w:instrText></w:r></w:p>";
+        let expected = format!(
+            "<w:p><w:r><w:rPr><w:b/></w:rPr><w:t>{}</w:t></w:r><w:r><w:t xml:space=\"preserve\">{}</w:t></w:r><w:r><w:tab/><w:t>{}</w:t></w:r><w:r><w:t>{}</w:t></w:r><w:r><w:instrText>PAGE</w:instrText></w:r></w:p>",
+            dev("rA"),
+            dev("ma & ").replace('&', "&amp;"),
+            dev("sI"),
+            dev("tA")
+        );
+        assert_eq!(rewriter.rewrite(xml), expected);
+    }
+
+    #[test]
+    fn rewrites_lang_attributes_only() {
+        let t = SegmentTransliterator::new(Script::Normal, Script::Devanagari, None);
+        let rewriter = XmlRewriter {
+            transliterator: &t,
+            role_of: |name| match name {
+                "dc:language" => XmlRole::Language,
+                "p" => XmlRole::Paragraph,
+                _ => XmlRole::Structure,
+            },
+            script_code: "Deva",
+        };
+        let xml = "<?xml version=\"1.0\"?><!-- rAma --><html xml:lang=\"sa\" lang='sa' data-lang=\"x\"><dc:language> sa-Latn </dc:language><p>rAma</p></html>";
+        let expected = format!(
+            "<?xml version=\"1.0\"?><!-- rAma --><html xml:lang=\"sa-Deva\" lang='sa-Deva' data-lang=\"x\"><dc:language> sa-Deva </dc:language><p>{}</p></html>",
+            dev("rAma")
+        );
+        assert_eq!(rewriter.rewrite(xml), expected);
+    }
+}
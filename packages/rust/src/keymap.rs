@@ -0,0 +1,576 @@
+//! User keymap overrides for typing, merged on top of a script's built-in typing map.
+//!
+//! A keymap maps input sequences to a character of the typing script, a run of built-in
+//! keys, or literal text. With the `keymap-files` feature it can be read from TOML or JSON:
+//!
+//! ```toml
+//! w = "व"                 # a character (krama entry) of the typing script
+//! ".n" = "ं"
+//! x = { keys = "kSh" }    # same as typing `kSh`
+//! q = { literal = "ॐ" }   # inserted as is
+//! ```
+//!
+//! Apply it with [`crate::typing::TypingContext::set_keymap`]; the typing helper data
+//! shows the custom keys through [`crate::typing::get_script_typing_data_map_with_keymap`].
+//!
+//! Where an override and a built-in key both match, the longer one wins, and an override
+//! wins a tie. So overriding `s` leaves `sh` alone, while `.n` takes over from `.`.
+
+use crate::script_data::ScriptData;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+#[cfg(feature = "keymap-files")]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ops::Bound;
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+
+/// What an input sequence of a [`Keymap`] types.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeymapTarget {
+    /// A character of the typing script, typed like its built-in key (`"व"`).
+    Char(String),
+    /// A run of built-in keys (`{ keys = "kSh" }`).
+    Keys { keys: String },
+    /// Text inserted as is, without transliteration (`{ literal = "ॐ" }`).
+    Literal { literal: String },
+}
+
+/// User key overrides, keyed by input sequence. See the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keymap {
+    entries: BTreeMap<String, KeymapTarget>,
+}
+
+/// A problem with a [`Keymap`] entry that keeps the keymap from being applied.
+#[derive(Debug)]
+pub enum KeymapError {
+    #[cfg(feature = "keymap-files")]
+    Io(std::io::Error),
+    /// The file is not a valid TOML/JSON keymap.
+    #[cfg(feature = "keymap-files")]
+    Parse(String),
+    /// An entry with an empty input sequence.
+    EmptyInput,
+    /// An entry whose keys or literal text is empty.
+    EmptyTarget { input: String },
+    /// A [`KeymapTarget::Char`] that no built-in key of the typing script types.
+    UnknownCharacter { input: String, text: String },
+}
+
+impl core::fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "keymap-files")]
+            Self::Io(err) => write!(f, "{err}"),
+            #[cfg(feature = "keymap-files")]
+            Self::Parse(err) => write!(f, "invalid keymap: {err}"),
+            Self::EmptyInput => write!(f, "keymap entry with an empty input sequence"),
+            Self::EmptyTarget { input } => write!(f, "keymap entry `{input}` types nothing"),
+            Self::UnknownCharacter { input, text } => write!(
+                f,
+                "keymap entry `{input}`: `{text}` is not a character of the typing script \
+                 (use `{{ literal = \"{text}\" }}` to insert it as is)"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeymapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "keymap-files")]
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "keymap-files")]
+impl From<std::io::Error> for KeymapError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// An override that changes how built-in keys type. Conflicts do not stop a keymap from
+/// being applied; they are returned so the user can be told.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapConflict {
+    /// The input is a built-in key that typed `builtin`; the override replaces it.
+    ShadowsBuiltin { input: String, builtin: String },
+    /// The input starts the longer built-in key `builtin_key`, which still wins when
+    /// typed in full.
+    PrefixOfBuiltin { input: String, builtin_key: String },
+    /// The override types the same as the built-in key it replaces.
+    Redundant { input: String },
+}
+
+impl core::fmt::Display for KeymapConflict {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ShadowsBuiltin { input, builtin } => {
+                write!(f, "`{input}` replaces the built-in key for `{builtin}`")
+            }
+            Self::PrefixOfBuiltin { input, builtin_key } => {
+                write!(
+                    f,
+                    "`{input}` is overridden by the built-in key `{builtin_key}`"
+                )
+            }
+            Self::Redundant { input } => write!(f, "`{input}` already types the same"),
+        }
+    }
+}
+
+impl Keymap {
+    /// Creates an empty keymap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of overrides.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no overrides.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds or replaces the override for `input`, returning the previous target.
+    pub fn insert(
+        &mut self,
+        input: impl Into<String>,
+        target: KeymapTarget,
+    ) -> Option<KeymapTarget> {
+        self.entries.insert(input.into(), target)
+    }
+
+    /// Removes the override for `input`.
+    pub fn remove(&mut self, input: &str) -> Option<KeymapTarget> {
+        self.entries.remove(input)
+    }
+
+    /// Returns the override for `input`.
+    pub fn get(&self, input: &str) -> Option<&KeymapTarget> {
+        self.entries.get(input)
+    }
+
+    /// Iterates the overrides in input order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &KeymapTarget)> {
+        self.entries
+            .iter()
+            .map(|(input, target)| (input.as_str(), target))
+    }
+
+    /// Parses a JSON keymap: an object of input sequences to targets.
+    #[cfg(feature = "keymap-files")]
+    pub fn from_json_str(text: &str) -> Result<Self, KeymapError> {
+        serde_json::from_str(text).map_err(|err| KeymapError::Parse(err.to_string()))
+    }
+
+    /// Parses a TOML keymap: a table of input sequences to targets.
+    #[cfg(feature = "keymap-files")]
+    pub fn from_toml_str(text: &str) -> Result<Self, KeymapError> {
+        toml::from_str(text).map_err(|err| KeymapError::Parse(err.to_string()))
+    }
+
+    /// Reads a keymap file, as JSON for a `.json` extension and as TOML otherwise.
+    #[cfg(feature = "keymap-files")]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, KeymapError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            Self::from_json_str(&text)
+        } else {
+            Self::from_toml_str(&text)
+        }
+    }
+}
+
+/// What a resolved override feeds into the typing engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ResolvedTarget {
+    /// Built-in keys, and the text they stand for (without a trailing halant).
+    Keys {
+        keys: String,
+        text: String,
+    },
+    Literal(String),
+}
+
+/// A run of the raw input after rewriting, see [`ResolvedKeymap::rewrite`].
+pub(crate) enum Piece<'a> {
+    /// Built-in keys, with `(raw chars, key chars)` per rewritten unit.
+    Keys {
+        keys: String,
+        units: Vec<(usize, usize)>,
+    },
+    Literal(&'a str),
+}
+
+/// A [`Keymap`] resolved against a typing script.
+#[derive(Debug, Clone)]
+pub(crate) struct ResolvedKeymap {
+    /// The keymap as given, kept for [`crate::typing::TypingSnapshot`].
+    pub(crate) source: Keymap,
+    pub(crate) entries: BTreeMap<String, ResolvedTarget>,
+    max_input_chars: usize,
+    builtin_keys: HashSet<&'static str>,
+    max_builtin_chars: usize,
+}
+
+impl ResolvedKeymap {
+    /// Resolves `keymap` for a script whose built-in keys are `builtin_keys`, where `typed`
+    /// transliterates a run of built-in keys on its own.
+    pub(crate) fn new(
+        keymap: &Keymap,
+        script_data: &ScriptData,
+        builtin_keys: Vec<&'static str>,
+        typed: impl Fn(&str) -> String,
+    ) -> Result<(Self, Vec<KeymapConflict>), KeymapError> {
+        // Shortest built-in key for each output, used to resolve `Char` targets.
+        let mut key_for_output: BTreeMap<String, &'static str> = BTreeMap::new();
+        for key in &builtin_keys {
+            let output = typed(key);
+            if output.is_empty() {
+                continue;
+            }
+            let shorter = key_for_output
+                .get(&output)
+                .is_some_and(|existing| existing.len() <= key.len());
+            if !shorter {
+                key_for_output.insert(output, key);
+            }
+        }
+        let halant = match script_data {
+            ScriptData::Brahmic { halant, .. } => Some(halant.as_str()),
+            ScriptData::Other { .. } => None,
+        };
+
+        let mut entries = BTreeMap::new();
+        let mut conflicts = Vec::new();
+        for (input, target) in &keymap.entries {
+            if input.is_empty() {
+                return Err(KeymapError::EmptyInput);
+            }
+            let resolved = match target {
+                KeymapTarget::Char(text) => {
+                    let key = key_for_output.get(text.as_str()).or_else(|| {
+                        // Consonants type with a halant until a vowel follows.
+                        let mut with_halant = text.clone();
+                        with_halant.push_str(halant?);
+                        key_for_output.get(&with_halant)
+                    });
+                    match key {
+                        Some(key) => ResolvedTarget::Keys {
+                            keys: String::from(*key),
+                            text: text.clone(),
+                        },
+                        None if text.is_empty() => {
+                            return Err(KeymapError::EmptyTarget {
+                                input: input.clone(),
+                            });
+                        }
+                        None => {
+                            return Err(KeymapError::UnknownCharacter {
+                                input: input.clone(),
+                                text: text.clone(),
+                            });
+                        }
+                    }
+                }
+                KeymapTarget::Keys { keys } if !keys.is_empty() => {
+                    let output = typed(keys);
+                    let text = halant
+                        .and_then(|halant| output.strip_suffix(halant))
+                        .unwrap_or(&output);
+                    ResolvedTarget::Keys {
+                        keys: keys.clone(),
+                        text: String::from(text),
+                    }
+                }
+                KeymapTarget::Literal { literal } if !literal.is_empty() => {
+                    ResolvedTarget::Literal(literal.clone())
+                }
+                KeymapTarget::Keys { .. } | KeymapTarget::Literal { .. } => {
+                    return Err(KeymapError::EmptyTarget {
+                        input: input.clone(),
+                    });
+                }
+            };
+
+            if builtin_keys.contains(&input.as_str()) {
+                let builtin = typed(input);
+                let output = match &resolved {
+                    ResolvedTarget::Keys { keys, .. } => typed(keys),
+                    ResolvedTarget::Literal(text) => text.clone(),
+                };
+                conflicts.push(if output == builtin {
+                    KeymapConflict::Redundant {
+                        input: input.clone(),
+                    }
+                } else {
+                    KeymapConflict::ShadowsBuiltin {
+                        input: input.clone(),
+                        builtin,
+                    }
+                });
+            }
+            if let Some(builtin_key) = builtin_keys
+                .iter()
+                .filter(|key| key.len() > input.len() && key.starts_with(input.as_str()))
+                .min_by_key(|key| (key.len(), **key))
+            {
+                conflicts.push(KeymapConflict::PrefixOfBuiltin {
+                    input: input.clone(),
+                    builtin_key: String::from(*builtin_key),
+                });
+            }
+            entries.insert(input.clone(), resolved);
+        }
+
+        let max_input_chars = entries
+            .keys()
+            .map(|input| input.chars().count())
+            .max()
+            .unwrap_or(0);
+        let max_builtin_chars = builtin_keys
+            .iter()
+            .map(|key| key.chars().count())
+            .max()
+            .unwrap_or(0);
+        let resolved = Self {
+            source: keymap.clone(),
+            entries,
+            max_input_chars,
+            builtin_keys: builtin_keys.into_iter().collect(),
+            max_builtin_chars,
+        };
+        Ok((resolved, conflicts))
+    }
+
+    /// Input sequences of the overrides.
+    pub(crate) fn inputs(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Splits typed keys into runs of built-in keys and literal text.
+    pub(crate) fn rewrite<'a>(&'a self, raw: &str) -> Vec<Piece<'a>> {
+        let mut pieces = Vec::new();
+        let mut keys = String::new();
+        let mut units = Vec::new();
+        let mut rest = raw;
+        while !rest.is_empty() {
+            let builtin = longest_prefix(rest, self.max_builtin_chars, |s| {
+                self.builtin_keys.contains(s)
+            });
+            let over = longest_prefix(rest, self.max_input_chars, |s| self.entries.contains_key(s));
+            match over {
+                Some(input) if builtin.is_none_or(|b| input.len() >= b.len()) => {
+                    match &self.entries[input] {
+                        ResolvedTarget::Keys { keys: target, .. } => {
+                            keys.push_str(target);
+                            units.push((input.chars().count(), target.chars().count()));
+                        }
+                        ResolvedTarget::Literal(text) => {
+                            if !keys.is_empty() {
+                                pieces.push(Piece::Keys {
+                                    keys: core::mem::take(&mut keys),
+                                    units: core::mem::take(&mut units),
+                                });
+                            }
+                            pieces.push(Piece::Literal(text));
+                        }
+                    }
+                    rest = &rest[input.len()..];
+                }
+                _ => {
+                    let len = builtin
+                        .map_or_else(|| rest.chars().next().map_or(0, char::len_utf8), str::len);
+                    keys.push_str(&rest[..len]);
+                    let chars = rest[..len].chars().count();
+                    units.push((chars, chars));
+                    rest = &rest[len..];
+                }
+            }
+        }
+        if !keys.is_empty() {
+            pieces.push(Piece::Keys { keys, units });
+        }
+        pieces
+    }
+
+    /// Length in chars of the longest tail of `raw` that an override could still extend,
+    /// so the context is kept open for it.
+    pub(crate) fn pending_chars(&self, raw: &str) -> usize {
+        let starts: Vec<usize> = raw.char_indices().map(|(i, _)| i).collect();
+        starts
+            .iter()
+            .rev()
+            .take(self.max_input_chars.saturating_sub(1))
+            .rev()
+            .map(|&start| &raw[start..])
+            .find(|tail| {
+                self.entries
+                    .range::<str, _>((Bound::Included(*tail), Bound::Unbounded))
+                    .take_while(|(input, _)| input.starts_with(*tail))
+                    .any(|(input, _)| input.len() > tail.len())
+            })
+            .map_or(0, |tail| tail.chars().count())
+    }
+}
+
+/// Maps a context length counted in rewritten keys back to raw keys.
+pub(crate) fn raw_context_length(units: &[(usize, usize)], context_length: usize) -> usize {
+    let mut keys = 0;
+    let mut raw = 0;
+    for (raw_chars, key_chars) in units.iter().rev() {
+        if keys >= context_length {
+            break;
+        }
+        keys += key_chars;
+        raw += raw_chars;
+    }
+    raw
+}
+
+/// Longest prefix of `text`, up to `max_chars` chars, accepted by `is_key`.
+fn longest_prefix(text: &str, max_chars: usize, is_key: impl Fn(&str) -> bool) -> Option<&str> {
+    let ends: Vec<usize> = text
+        .char_indices()
+        .map(|(i, ch)| i + ch.len_utf8())
+        .take(max_chars)
+        .collect();
+    ends.into_iter()
+        .rev()
+        .map(|end| &text[..end])
+        .find(|prefix| is_key(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripts::Script;
+    use crate::typing::{TypingContext, get_script_typing_data_map_with_keymap};
+
+    fn typed(ctx: &mut TypingContext, keys: &str) -> String {
+        ctx.clear_context();
+        let mut out = String::new();
+        for ch in keys.chars() {
+            let diff = ctx.take_key_input_char(ch);
+            for _ in 0..diff.to_delete_chars_count {
+                out.pop();
+            }
+            out.push_str(&diff.diff_add_text);
+        }
+        out
+    }
+
+    fn keymap() -> Keymap {
+        let mut keymap = Keymap::new();
+        keymap.insert(".n", KeymapTarget::Char("ं".into()));
+        keymap.insert("x", KeymapTarget::Keys { keys: "kSh".into() });
+        keymap.insert(
+            "Q",
+            KeymapTarget::Literal {
+                literal: "ॐ".into(),
+            },
+        );
+        keymap.insert("w", KeymapTarget::Char("व".into()));
+        keymap.insert("s", KeymapTarget::Char("ष".into()));
+        keymap
+    }
+
+    #[test]
+    fn overrides_apply_on_top_of_builtin_keys() {
+        let mut ctx = TypingContext::new(Script::Devanagari, None);
+        let conflicts = ctx.set_keymap(Some(&keymap())).unwrap();
+        assert_eq!(
+            conflicts,
+            [
+                KeymapConflict::ShadowsBuiltin {
+                    input: "Q".into(),
+                    builtin: "॰".into()
+                },
+                KeymapConflict::PrefixOfBuiltin {
+                    input: "Q".into(),
+                    builtin_key: "QQ".into()
+                },
+                KeymapConflict::ShadowsBuiltin {
+                    input: "s".into(),
+                    builtin: "स्".into()
+                },
+                KeymapConflict::PrefixOfBuiltin {
+                    input: "s".into(),
+                    builtin_key: "sh".into()
+                },
+                KeymapConflict::Redundant { input: "w".into() },
+            ]
+        );
+
+        assert_eq!(typed(&mut ctx, "ka.n"), "कं");
+        assert_eq!(typed(&mut ctx, "xa"), "क्ष");
+        assert_eq!(typed(&mut ctx, "Qk"), "ॐक्");
+        assert_eq!(typed(&mut ctx, "wi"), "वि");
+        assert_eq!(typed(&mut ctx, "sa shi"), "ष शि");
+        assert_eq!(typed(&mut ctx, "k."), "क्।");
+
+        ctx.set_keymap(None).unwrap();
+        assert_eq!(typed(&mut ctx, "ka.n"), "क।न्");
+    }
+
+    #[test]
+    fn typing_data_map_lists_custom_keys() {
+        let data = get_script_typing_data_map_with_keymap(Script::Devanagari, &keymap()).unwrap();
+        let mappings = |text: &str| {
+            data.common_krama_map
+                .iter()
+                .chain(&data.script_specific_krama_map)
+                .find(|(item_text, _, _)| item_text == text)
+                .map(|(_, _, mappings)| mappings.clone())
+                .unwrap_or_default()
+        };
+        assert!(mappings("ं").contains(&".n".into()));
+        assert_eq!(mappings("क्ष"), ["x"]);
+        assert!(mappings("ॐ").contains(&"Q".into()));
+        assert!(mappings("ष").contains(&"s".into()));
+        assert!(!mappings("स").contains(&"s".into()));
+        assert_eq!(mappings("व"), ["w"]);
+
+        let mut unknown = Keymap::new();
+        unknown.insert("z", KeymapTarget::Char("ক".into()));
+        assert!(matches!(
+            get_script_typing_data_map_with_keymap(Script::Devanagari, &unknown),
+            Err(KeymapError::UnknownCharacter { .. })
+        ));
+    }
+
+    #[cfg(feature = "keymap-files")]
+    #[test]
+    fn parses_toml_and_json_keymaps() {
+        let toml = Keymap::from_toml_str(
+            "w = \"व\"\n\".n\" = \"ं\"\nx = { keys = \"kSh\" }\nQ = { literal = \"ॐ\" }\n",
+        )
+        .unwrap();
+        let json = Keymap::from_json_str(
+            r#"{"w": "व", ".n": "ं", "x": {"keys": "kSh"}, "Q": {"literal": "ॐ"}}"#,
+        )
+        .unwrap();
+        assert_eq!(toml, json);
+        assert_eq!(
+            toml.get("x"),
+            Some(&KeymapTarget::Keys { keys: "kSh".into() })
+        );
+        assert!(Keymap::from_toml_str("w = 1").is_err());
+    }
+}
@@ -0,0 +1,420 @@
+//! Native keyboard layouts for typing, as an alternative to phonetic Roman input.
+//!
+//! Keys are the characters a US QWERTY layout gives for each physical key, so `k` is the
+//! key right of `j` whatever it is labelled.
+//!
+//! - [`TypingLayout::InScript`]: the standard InScript layout, the same key positions for
+//!   every Brahmic script. Keys type aksharas and matras directly; conjuncts are typed with
+//!   the halant key (`d`), and a doubled halant gives an explicit halant (with ZWNJ).
+//! - [`TypingLayout::Tamil99`]: the Tamil99 layout, with its automatic pulli for doubled
+//!   consonants and soft-hard pairs (`ங்க`, `ந்த`, ...) and vowels joining a preceding
+//!   consonant as vowel signs.
+
+use crate::script_data::ScriptData;
+use crate::scripts::{Script, ScriptListEnum};
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display, EnumString};
+
+/// Keyboard layout used by a [`crate::typing::TypingContext`].
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumString,
+    Display,
+    AsRefStr,
+    Serialize,
+    Deserialize,
+)]
+#[strum(ascii_case_insensitive)]
+pub enum TypingLayout {
+    /// Phonetic Roman input (`k` + `a` → `क`).
+    #[default]
+    Phonetic,
+    /// InScript, for any Brahmic script.
+    #[strum(serialize = "InScript")]
+    InScript,
+    /// Tamil99, for Tamil.
+    #[strum(serialize = "Tamil99")]
+    Tamil99,
+}
+
+impl TypingLayout {
+    /// Returns `true` if the layout can type `script`. Typing contexts fall back to
+    /// [`TypingLayout::Phonetic`] for other scripts.
+    pub fn is_available_for(self, script: Script) -> bool {
+        let script: ScriptListEnum = script.into();
+        match self {
+            Self::Phonetic => true,
+            Self::InScript => matches!(
+                ScriptData::get_script_data(&script),
+                ScriptData::Brahmic { .. }
+            ),
+            Self::Tamil99 => script == ScriptListEnum::Tamil,
+        }
+    }
+
+    /// Script the layout tables are written in; output is transliterated from it.
+    pub(crate) fn native_script(self) -> ScriptListEnum {
+        match self {
+            Self::Phonetic => ScriptListEnum::Normal,
+            Self::InScript => ScriptListEnum::Devanagari,
+            Self::Tamil99 => ScriptListEnum::Tamil,
+        }
+    }
+
+    /// Types `keys` in the layout's native script, returning the output and how many keys
+    /// at the end can still change it (0 when the next key cannot affect the output).
+    pub(crate) fn type_keys(self, keys: &str, use_native_numerals: bool) -> (String, usize) {
+        match self {
+            Self::Phonetic => (String::from(keys), 0),
+            Self::InScript => inscript::type_keys(keys, use_native_numerals),
+            Self::Tamil99 => tamil99::type_keys(keys, use_native_numerals),
+        }
+    }
+}
+
+/// Native digit for an ASCII digit, given the script's zero.
+fn native_digit(key: char, zero: char) -> char {
+    let offset = key as u32 - '0' as u32;
+    char::from_u32(zero as u32 + offset).unwrap_or(key)
+}
+
+mod inscript {
+    use super::native_digit;
+    use alloc::string::String;
+
+    const HALANT: char = '\u{094D}';
+    const NUQTA: char = '\u{093C}';
+    const ZWNJ: char = '\u{200C}';
+    const DEVANAGARI_ZERO: char = '\u{0966}';
+
+    /// Devanagari InScript, keyed by the US QWERTY character of each key.
+    fn key_text(key: char) -> Option<&'static str> {
+        Some(match key {
+            '`' => "ॊ",
+            '-' => "-",
+            '=' => "ृ",
+            'q' => "ौ",
+            'w' => "ै",
+            'e' => "ा",
+            'r' => "ी",
+            't' => "ू",
+            'y' => "ब",
+            'u' => "ह",
+            'i' => "ग",
+            'o' => "द",
+            'p' => "ज",
+            '[' => "ड",
+            ']' => "\u{093C}",
+            '\\' => "ॉ",
+            'a' => "ो",
+            's' => "े",
+            'd' => "\u{094D}",
+            'f' => "ि",
+            'g' => "ु",
+            'h' => "प",
+            'j' => "र",
+            'k' => "क",
+            'l' => "त",
+            ';' => "च",
+            '\'' => "ट",
+            'z' => "ॆ",
+            'x' => "ं",
+            'c' => "म",
+            'v' => "न",
+            'b' => "व",
+            'n' => "ल",
+            'm' => "स",
+            '/' => "य",
+            '~' => "ऒ",
+            '!' => "ऍ",
+            '@' => "ॅ",
+            '#' => "्र",
+            '$' => "र्",
+            '%' => "ज्ञ",
+            '^' => "त्र",
+            '&' => "क्ष",
+            '*' => "श्र",
+            '_' => "ः",
+            '+' => "ऋ",
+            'Q' => "औ",
+            'W' => "ऐ",
+            'E' => "आ",
+            'R' => "ई",
+            'T' => "ऊ",
+            'Y' => "भ",
+            'U' => "ङ",
+            'I' => "घ",
+            'O' => "ध",
+            'P' => "झ",
+            '{' => "ढ",
+            '}' => "ञ",
+            '|' => "ऑ",
+            'A' => "ओ",
+            'S' => "ए",
+            'D' => "अ",
+            'F' => "इ",
+            'G' => "उ",
+            'H' => "फ",
+            'J' => "ऱ",
+            'K' => "ख",
+            'L' => "थ",
+            ':' => "छ",
+            '"' => "ठ",
+            'Z' => "ऎ",
+            'X' => "ँ",
+            'C' => "ण",
+            'V' => "ऩ",
+            'B' => "ऴ",
+            'N' => "ळ",
+            'M' => "श",
+            '<' => "ष",
+            '>' => "।",
+            '?' => "य़",
+            _ => return None,
+        })
+    }
+
+    /// Independent vowel for a matra typed where there is no consonant to carry it.
+    fn vowel_for_matra(matra: char) -> Option<char> {
+        Some(match matra {
+            'ा' => 'आ',
+            'ि' => 'इ',
+            'ी' => 'ई',
+            'ु' => 'उ',
+            'ू' => 'ऊ',
+            'ृ' => 'ऋ',
+            'े' => 'ए',
+            'ै' => 'ऐ',
+            'ो' => 'ओ',
+            'ौ' => 'औ',
+            'ॅ' => 'ऍ',
+            'ॉ' => 'ऑ',
+            'ॆ' => 'ऎ',
+            'ॊ' => 'ऒ',
+            _ => return None,
+        })
+    }
+
+    fn is_consonant(ch: char) -> bool {
+        matches!(ch, '\u{0915}'..='\u{0939}' | '\u{0958}'..='\u{095F}' | '\u{0979}'..='\u{097F}')
+    }
+
+    pub(super) fn type_keys(keys: &str, use_native_numerals: bool) -> (String, usize) {
+        let mut out = String::new();
+        for key in keys.chars() {
+            let after_consonant = out
+                .chars()
+                .next_back()
+                .is_some_and(|ch| is_consonant(ch) || ch == NUQTA);
+            if key.is_ascii_digit() {
+                out.push(if use_native_numerals {
+                    native_digit(key, DEVANAGARI_ZERO)
+                } else {
+                    key
+                });
+                continue;
+            }
+            let Some(text) = key_text(key) else {
+                out.push(key);
+                continue;
+            };
+            let mut chars = text.chars();
+            match chars.next() {
+                Some(HALANT) if out.ends_with(HALANT) => out.push(ZWNJ),
+                Some(first) if !after_consonant && chars.as_str().is_empty() => {
+                    out.push(vowel_for_matra(first).unwrap_or(first));
+                }
+                _ => out.push_str(text),
+            }
+        }
+        let open = out
+            .chars()
+            .next_back()
+            .is_some_and(|ch| is_consonant(ch) || ch == NUQTA || ch == HALANT);
+        (out, usize::from(open))
+    }
+}
+
+mod tamil99 {
+    use super::native_digit;
+    use alloc::string::String;
+
+    const PULLI: char = '\u{0BCD}';
+    const TAMIL_ZERO: char = '\u{0BE6}';
+
+    enum Key {
+        Consonant(&'static str),
+        /// An independent vowel and its vowel sign (`அ` has none).
+        Vowel(char, Option<char>),
+        Pulli,
+        Other(&'static str),
+    }
+
+    /// Tamil99, keyed by the US QWERTY character of each key.
+    fn key(key: char) -> Option<Key> {
+        use Key::*;
+        Some(match key {
+            'q' => Vowel('ஆ', Some('ா')),
+            'w' => Vowel('ஈ', Some('ீ')),
+            'e' => Vowel('ஊ', Some('ூ')),
+            'r' => Vowel('ஐ', Some('ை')),
+            't' => Vowel('ஏ', Some('ே')),
+            'y' => Consonant("ள"),
+            'u' => Consonant("ற"),
+            'i' => Consonant("ன"),
+            'o' => Consonant("ட"),
+            'p' => Consonant("ண"),
+            '[' => Consonant("ச"),
+            ']' => Consonant("ஞ"),
+            'a' => Vowel('அ', None),
+            's' => Vowel('இ', Some('ி')),
+            'd' => Vowel('உ', Some('ு')),
+            'f' => Pulli,
+            'g' => Vowel('எ', Some('ெ')),
+            'h' => Consonant("க"),
+            'j' => Consonant("ப"),
+            'k' => Consonant("ம"),
+            'l' => Consonant("த"),
+            ';' => Consonant("ந"),
+            '\'' => Consonant("ய"),
+            'z' => Vowel('ஔ', Some('ௌ')),
+            'x' => Vowel('ஓ', Some('ோ')),
+            'c' => Vowel('ஒ', Some('ொ')),
+            'v' => Consonant("வ"),
+            'b' => Consonant("ங"),
+            'n' => Consonant("ல"),
+            'm' => Consonant("ர"),
+            '/' => Consonant("ழ"),
+            'Q' => Consonant("ஸ"),
+            'W' => Consonant("ஷ"),
+            'E' => Consonant("ஜ"),
+            'R' => Consonant("ஹ"),
+            'T' => Consonant("க்ஷ"),
+            'Y' => Other("ஸ்ரீ"),
+            'F' => Other("ஃ"),
+            _ => return None,
+        })
+    }
+
+    /// A consonant typed after `prev` makes `prev` take a pulli: doubled consonants and
+    /// the soft-hard pairs.
+    fn takes_auto_pulli(prev: char, next: char) -> bool {
+        prev == next
+            || matches!(
+                (prev, next),
+                ('ங', 'க') | ('ஞ', 'ச') | ('ண', 'ட') | ('ந', 'த') | ('ம', 'ப') | ('ன', 'ற')
+            )
+    }
+
+    pub(super) fn type_keys(keys: &str, use_native_numerals: bool) -> (String, usize) {
+        let mut out = String::new();
+        // Last consonant if it is still bare, and whether it followed an automatic pulli
+        // (a third `க` after `க்க` stays bare).
+        let mut bare: Option<(char, bool)> = None;
+        for ch in keys.chars() {
+            match key(ch) {
+                Some(Key::Consonant(text)) => {
+                    let first = text.chars().next().unwrap_or_default();
+                    let auto_pulli = bare.is_some_and(|(prev, after_auto)| {
+                        !after_auto && takes_auto_pulli(prev, first)
+                    });
+                    if auto_pulli {
+                        out.push(PULLI);
+                    }
+                    out.push_str(text);
+                    bare = text.chars().next_back().map(|last| (last, auto_pulli));
+                }
+                Some(Key::Vowel(vowel, sign)) => {
+                    match sign.filter(|_| bare.is_some()) {
+                        Some(sign) => out.push(sign),
+                        None => out.push(vowel),
+                    }
+                    bare = None;
+                }
+                Some(Key::Pulli) => {
+                    out.push(PULLI);
+                    bare = None;
+                }
+                Some(Key::Other(text)) => {
+                    out.push_str(text);
+                    bare = None;
+                }
+                None => {
+                    out.push(if ch.is_ascii_digit() && use_native_numerals {
+                        native_digit(ch, TAMIL_ZERO)
+                    } else {
+                        ch
+                    });
+                    bare = None;
+                }
+            }
+        }
+        (out, usize::from(bare.is_some()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typing::{TypingContext, TypingContextOptions};
+
+    fn typed(script: Script, layout: TypingLayout, keys: &str) -> String {
+        let mut ctx = TypingContext::new(
+            script,
+            Some(TypingContextOptions {
+                layout,
+                ..Default::default()
+            }),
+        );
+        let mut out = String::new();
+        for ch in keys.chars() {
+            let diff = ctx.take_key_input_char(ch);
+            for _ in 0..diff.to_delete_chars_count {
+                out.pop();
+            }
+            out.push_str(&diff.diff_add_text);
+        }
+        out
+    }
+
+    #[test]
+    fn inscript_types_aksharas_and_matras_by_key_position() {
+        let dev = |keys| typed(Script::Devanagari, TypingLayout::InScript, keys);
+        assert_eq!(dev("kf"), "कि");
+        assert_eq!(dev("kdk"), "क्क");
+        assert_eq!(dev("kdd"), "क्\u{200C}");
+        assert_eq!(dev("e"), "आ");
+        assert_eq!(dev("ke e"), "का आ");
+        assert_eq!(dev("Kk&"), "खकक्ष");
+        assert_eq!(dev("12"), "१२");
+        assert_eq!(typed(Script::Telugu, TypingLayout::InScript, "kf"), "కి");
+    }
+
+    #[test]
+    fn tamil99_adds_pulli_and_vowel_signs() {
+        let ta = |keys| typed(Script::Tamil, TypingLayout::Tamil99, keys);
+        assert_eq!(ta("hq"), "கா");
+        assert_eq!(ta("hh"), "க்க");
+        assert_eq!(ta("hhh"), "க்கக");
+        assert_eq!(ta("bh"), "ங்க");
+        assert_eq!(ta(";l"), "ந்த");
+        assert_eq!(ta("hfq"), "க்ஆ");
+        assert_eq!(ta("k'"), "மய");
+        assert_eq!(ta("a"), "அ");
+    }
+
+    #[test]
+    fn unavailable_layouts_fall_back_to_phonetic() {
+        assert!(TypingLayout::InScript.is_available_for(Script::Bengali));
+        assert!(!TypingLayout::InScript.is_available_for(Script::Romanized));
+        assert!(!TypingLayout::Tamil99.is_available_for(Script::Telugu));
+        assert_eq!(typed(Script::Telugu, TypingLayout::Tamil99, "ki"), "కి");
+    }
+}
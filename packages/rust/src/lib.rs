@@ -18,17 +18,23 @@ pub use crate::script_data::{ScriptListData, get_all_options, get_script_list_da
 use crate::transliterate::transliterate_text;
 pub use crate::typing::{
     KramaDataItem, ListType, ScriptTypingDataMap, TypingDataMapItem, get_script_krama_data,
-    get_script_typing_data_map,
+    get_script_typing_data_map, get_script_typing_data_map_with_keymap,
 };
 use alloc::borrow::Cow;
+use alloc::vec;
+use alloc::vec::Vec;
 pub use custom_options::{CustomOptions, CustomOptionsBuilder, UnknownCustomOptionKey};
 pub use scripts::{Script, ScriptListEnum};
 mod script_data;
 mod transliterate;
 mod utils;
 
+pub mod completion;
 #[rustfmt::skip]
 pub mod custom_options;
+pub mod formats;
+pub mod keymap;
+pub mod layout;
 #[rustfmt::skip]
 pub mod scripts;
 pub mod typing;
@@ -66,6 +72,44 @@ pub fn get_schwa_status_for_script(script: Script) -> Option<bool> {
     }
 }
 
+/// Non-ASCII characters of a text looked at by [`detect_script`].
+const DETECT_SCRIPT_SAMPLE_CHARS: usize = 256;
+
+/// Guesses the script `text` is written in: the script knowing most of its non-ASCII
+/// characters, the first in script list order on a tie.
+///
+/// Returns `None` for ASCII text (which may be Normal keys or English) and for text with no
+/// character of any script.
+pub fn detect_script(text: &str) -> Option<ScriptListEnum> {
+    let scripts: Vec<ScriptListEnum> = ScriptListEnum::ALL
+        .iter()
+        .copied()
+        .filter(|script| *script != ScriptListEnum::Normal)
+        .collect();
+    let mut votes = vec![0usize; scripts.len()];
+    let mut buf = [0u8; 4];
+    for ch in text
+        .chars()
+        .filter(|c| !c.is_ascii() && !c.is_whitespace())
+        .take(DETECT_SCRIPT_SAMPLE_CHARS)
+    {
+        let ch: &str = ch.encode_utf8(&mut buf);
+        for (script, count) in scripts.iter().zip(votes.iter_mut()) {
+            let data = ScriptData::get_script_data(script);
+            if data.krama_text_lookup.contains_key(ch) || data.text_to_krama_lookup.contains_key(ch)
+            {
+                *count += 1;
+            }
+        }
+    }
+    let (idx, &count) = votes
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, count)| **count)?;
+    (count > 0).then_some(scripts[idx])
+}
+
 /// Preload script data for a script or language.
 pub fn preload_script_data(script: Script) -> &'static ScriptData {
     let normalized_script: ScriptListEnum = script.into();
@@ -618,4 +662,26 @@ mod tests {
             panic!("failed");
         }
     }
+
+    #[test]
+    fn detects_the_script_of_text() {
+        assert_eq!(detect_script("नमस्ते, world"), Some(ScriptListEnum::Devanagari));
+        assert_eq!(detect_script("నమస్తే।"), Some(ScriptListEnum::Telugu));
+        assert_eq!(detect_script("namaskāram"), Some(ScriptListEnum::Romanized));
+        assert_eq!(detect_script("namaste"), None);
+        assert_eq!(detect_script("— “”"), None);
+    }
+
+    #[test]
+    fn detects_the_first_script_on_a_tie() {
+        // The danda is shared by most Brahmic scripts
+        assert_eq!(detect_script("।"), Some(ScriptListEnum::Devanagari));
+        assert_eq!(detect_script("राम రామ"), Some(ScriptListEnum::Devanagari));
+    }
+
+    #[test]
+    fn detects_the_majority_script_of_mixed_text() {
+        assert_eq!(detect_script("राम సీతారామ"), Some(ScriptListEnum::Telugu));
+        assert_eq!(detect_script("rāma ராம ராமா"), Some(ScriptListEnum::Tamil));
+    }
 }
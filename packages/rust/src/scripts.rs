@@ -46,6 +46,29 @@ pub enum ScriptListEnum {
     Siddham,
 }
 impl ScriptListEnum {
+    /// All scripts, in `script_list.json` id order.
+    pub const ALL: &'static [Self] = &[
+        Self::Devanagari,
+        Self::Telugu,
+        Self::Tamil,
+        Self::TamilExtended,
+        Self::Bengali,
+        Self::Kannada,
+        Self::Gujarati,
+        Self::Malayalam,
+        Self::Odia,
+        Self::Sinhala,
+        Self::Normal,
+        Self::Romanized,
+        Self::Gurumukhi,
+        Self::Assamese,
+        Self::PurnaDevanagari,
+        Self::Brahmi,
+        Self::Granth,
+        Self::Modi,
+        Self::Sharada,
+        Self::Siddham,
+    ];
     /// Resolves a canonical `script_list.json` script id to the internal script enum.
     #[inline]
     pub const fn from_id(id: u8) -> Option<Self> {
@@ -1,16 +1,23 @@
 //! Per-key typing emulation: stateful [`TypingContext`], diffs for incremental UI updates,
 //! and script typing-data helpers. Enable the crate `std` feature for idle auto-clear via
-//! `std::time`; otherwise clear context explicitly.
+//! `std::time`; otherwise pass key timestamps with [`TypingContext::take_key_input_char_at`]
+//! or clear context explicitly.
 
 use crate::custom_options::CustomOptions;
+use crate::keymap::{
+    Keymap, KeymapConflict, KeymapError, Piece, ResolvedKeymap, ResolvedTarget, raw_context_length,
+};
+pub use crate::layout::TypingLayout;
 use crate::scripts::{Script, ScriptListEnum};
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use core::str::FromStr;
 use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "std")]
-use core::time::Duration;
+use std::sync::OnceLock;
 #[cfg(feature = "std")]
 use std::time::Instant;
 
@@ -18,6 +25,7 @@ use crate::script_data::{List, ScriptData};
 use crate::transliterate::transliterate::{
     TransliterationFnOptions, resolve_transliteration_rules, transliterate_text_core,
 };
+use crate::transliterate::transliterate_text;
 
 /// Default time in milliseconds after which the context will be cleared automatically.
 pub const DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS: u64 = 4500;
@@ -28,7 +36,8 @@ pub const DEFAULT_USE_NATIVE_NUMERALS: bool = true;
 pub const DEFAULT_INCLUDE_INHERENT_VOWEL: bool = false;
 
 /// Options for configuring a typing context.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TypingContextOptions {
     /// The time in milliseconds after which the context will be cleared automatically.
     /// Defaults to [`DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS`].
@@ -43,6 +52,45 @@ pub struct TypingContextOptions {
     ///
     /// Defaults to `DEFAULT_INCLUDE_INHERENT_VOWEL`
     pub include_inherent_vowel: bool,
+    /// Keyboard layout, see [`crate::layout`]. Layouts that cannot type the script fall
+    /// back to [`TypingLayout::Phonetic`].
+    ///
+    /// Defaults to [`TypingLayout::Phonetic`]
+    pub layout: TypingLayout,
+    /// Key that switches to typing raw Latin text, eg. for an English word or an email
+    /// address in between.
+    ///
+    /// Defaults to `None` (no escape)
+    pub latin_escape: Option<LatinEscape>,
+}
+
+/// Escape key of [`TypingContextOptions::latin_escape`].
+///
+/// The key itself is not typed; the composition before it is kept as typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatinEscape {
+    /// The escape key, eg. `\`.
+    pub key: char,
+    /// Escape with the key typed twice in a row (eg. `;;`), so that a single one is still
+    /// typed as usual. The second tap takes back the first.
+    ///
+    /// With [`TypingContext::take_key_input_ime`] the first tap may already be committed,
+    /// so prefer a single tap key there.
+    #[serde(default)]
+    pub double_tap: bool,
+    /// How long the Latin text lasts.
+    #[serde(default)]
+    pub scope: LatinEscapeScope,
+}
+
+/// How long [`LatinEscape`] text lasts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LatinEscapeScope {
+    /// Until a whitespace (typed as well) or the escape key.
+    #[default]
+    NextWord,
+    /// Until the escape key is typed again.
+    UntilEscape,
 }
 
 impl Default for TypingContextOptions {
@@ -51,6 +99,8 @@ impl Default for TypingContextOptions {
             auto_context_clear_time_ms: DEFAULT_AUTO_CONTEXT_CLEAR_TIME_MS,
             use_native_numerals: DEFAULT_USE_NATIVE_NUMERALS,
             include_inherent_vowel: DEFAULT_INCLUDE_INHERENT_VOWEL,
+            layout: TypingLayout::default(),
+            latin_escape: None,
         }
     }
 }
@@ -64,27 +114,151 @@ pub struct TypingDiff {
     pub diff_add_text: String,
     /// Can be used to determine if the context should be cleared
     pub context_length: usize,
+    /// Keys are passed through as raw Latin text after this diff (see
+    /// [`TypingContextOptions::latin_escape`]), eg. to show an indicator.
+    pub latin_passthrough: bool,
+}
+
+/// Upper bound on the alternative key sequences explored by [`TypingContext::candidates`].
+const MAX_CANDIDATE_INPUTS: usize = 64;
+
+/// A possible output for the current composition, see [`TypingContext::candidates`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// Text the composition would produce.
+    pub text: String,
+    /// Keys producing [`Self::text`]; pass them to [`TypingContext::select_candidate`].
+    pub input: String,
+}
+
+/// Word frequencies used to rank [`TypingContext::candidates`].
+///
+/// Words are keyed by their output text (eg. `शिव`), not by the typed keys.
+#[derive(Debug, Clone, Default)]
+pub struct FrequencyModel {
+    counts: HashMap<String, u64>,
+}
+
+impl FrequencyModel {
+    /// Creates an empty model.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the frequency of `word`.
+    pub fn set_frequency(&mut self, word: impl Into<String>, count: u64) {
+        self.counts.insert(word.into(), count);
+    }
+
+    /// Counts one more use of `word`.
+    pub fn record(&mut self, word: &str) {
+        match self.counts.get_mut(word) {
+            Some(count) => *count = count.saturating_add(1),
+            None => {
+                self.counts.insert(word.to_string(), 1);
+            }
+        }
+    }
+
+    /// Returns the frequency of `word`, `0` when unknown.
+    pub fn frequency(&self, word: &str) -> u64 {
+        self.counts.get(word).copied().unwrap_or(0)
+    }
+}
+
+impl<W: Into<String>> FromIterator<(W, u64)> for FrequencyModel {
+    fn from_iter<I: IntoIterator<Item = (W, u64)>>(iter: I) -> Self {
+        Self {
+            counts: iter.into_iter().map(|(w, c)| (w.into(), c)).collect(),
+        }
+    }
+}
+
+/// Result of processing a key in input-method (preedit/commit) terms.
+///
+/// Apply `commit_text` to the application first, then replace the preedit with `preedit`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImeUpdate {
+    /// Finished text that should be committed to the application now.
+    pub commit_text: String,
+    /// Text still being composed, to be shown as the preedit.
+    pub preedit: String,
+}
+
+/// Serialisable state of a [`TypingContext`]: its script, options, keymap and the current
+/// composition. See [`TypingContext::snapshot`] and [`TypingContext::restore`].
+///
+/// The frequency model (see [`TypingContext::set_frequency_model`]) is not included.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypingSnapshot {
+    /// Normalized name of the typing script.
+    pub script: String,
+    pub options: TypingContextOptions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<Keymap>,
+    /// Keys typed in the current context.
+    pub input: String,
+    /// Output of the current context, as the application shows it.
+    pub output: String,
+    pub context_length: usize,
+}
+
+/// A [`TypingSnapshot`] that cannot be restored.
+#[derive(Debug)]
+pub enum TypingSnapshotError {
+    /// The snapshot's script is not a known script name.
+    UnknownScript(String),
+    /// The snapshot's keymap does not apply to its script.
+    Keymap(KeymapError),
+}
+
+impl core::fmt::Display for TypingSnapshotError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownScript(script) => write!(f, "unknown typing script `{script}`"),
+            Self::Keymap(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TypingSnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Keymap(err) => Some(err),
+            Self::UnknownScript(_) => None,
+        }
+    }
 }
 
 /// Stateful isolated context for character-by-character input typing.
 ///
-/// With the `std` feature, idle time between keys (via `std::time`) can auto-clear the
-/// context per [`TypingContextOptions::auto_context_clear_time_ms`]; without `std`, call
-/// [`Self::clear_context`] yourself.
+/// Idle time between keys auto-clears the context per
+/// [`TypingContextOptions::auto_context_clear_time_ms`]. The `*_at` methods take the key
+/// time from the caller's clock; the others read `std::time` with the `std` feature, and
+/// without it never auto-clear (call [`Self::clear_context`] yourself).
 #[derive(Debug)]
 pub struct TypingContext {
     typing_script: ScriptListEnum,
 
     use_native_numerals: bool,
     include_inherent_vowel: bool,
+    layout: TypingLayout,
 
     curr_input: String,
     curr_output: String,
+    curr_context_length: usize,
+    frequency_model: Option<FrequencyModel>,
+    keymap: Option<ResolvedKeymap>,
 
-    #[cfg(feature = "std")]
-    auto_context_clear_time: Duration,
-    #[cfg(feature = "std")]
-    last_time: Option<Instant>,
+    latin_escape: Option<LatinEscape>,
+    latin_passthrough: bool,
+    /// Output before and after a first tap of a double tap [`LatinEscape`].
+    escape_tap: Option<(String, String)>,
+
+    auto_context_clear_time_ms: u64,
+    /// Time of the previous key in milliseconds, when known.
+    last_time_ms: Option<u64>,
 
     from_script_data: &'static ScriptData,
     to_script_data: &'static ScriptData,
@@ -96,6 +270,11 @@ impl TypingContext {
     /// Creates a new typing context for the given script/language.
     pub fn new(typing_script: Script, options: Option<TypingContextOptions>) -> Self {
         let opts = options.unwrap_or_default();
+        let layout = if opts.layout.is_available_for(typing_script) {
+            opts.layout
+        } else {
+            TypingLayout::Phonetic
+        };
         let typing_script: ScriptListEnum = typing_script.into();
 
         let from_script_data = ScriptData::get_script_data(&ScriptListEnum::Normal);
@@ -107,12 +286,17 @@ impl TypingContext {
             typing_script,
             use_native_numerals: opts.use_native_numerals,
             include_inherent_vowel: opts.include_inherent_vowel,
+            layout,
             curr_input: String::new(),
             curr_output: String::new(),
-            #[cfg(feature = "std")]
-            auto_context_clear_time: Duration::from_millis(opts.auto_context_clear_time_ms),
-            #[cfg(feature = "std")]
-            last_time: None,
+            curr_context_length: 0,
+            frequency_model: None,
+            keymap: None,
+            latin_escape: opts.latin_escape,
+            latin_passthrough: false,
+            escape_tap: None,
+            auto_context_clear_time_ms: opts.auto_context_clear_time_ms,
+            last_time_ms: None,
             from_script_data,
             to_script_data,
             trans_options: resolved.trans_options,
@@ -120,14 +304,61 @@ impl TypingContext {
         }
     }
 
+    /// Saves the script, options, keymap and current composition, eg. to continue typing
+    /// after switching documents, on another thread, or after a restart.
+    pub fn snapshot(&self) -> TypingSnapshot {
+        TypingSnapshot {
+            script: self.get_normalized_script().to_string(),
+            options: TypingContextOptions {
+                auto_context_clear_time_ms: self.auto_context_clear_time_ms,
+                use_native_numerals: self.use_native_numerals,
+                include_inherent_vowel: self.include_inherent_vowel,
+                layout: self.layout,
+                latin_escape: self.latin_escape,
+            },
+            keymap: self.keymap.as_ref().map(|keymap| keymap.source.clone()),
+            input: self.curr_input.clone(),
+            output: self.curr_output.clone(),
+            context_length: self.curr_context_length,
+        }
+    }
+
+    /// Creates a context from a [`Self::snapshot`]; the next key continues its composition.
+    ///
+    /// The idle time before the snapshot is not counted towards the auto clear.
+    pub fn restore(snapshot: &TypingSnapshot) -> Result<Self, TypingSnapshotError> {
+        let script = Script::from_str(&snapshot.script)
+            .map_err(|_| TypingSnapshotError::UnknownScript(snapshot.script.clone()))?;
+        let mut ctx = Self::new(script, Some(snapshot.options.clone()));
+        ctx.set_keymap(snapshot.keymap.as_ref())
+            .map_err(TypingSnapshotError::Keymap)?;
+        ctx.curr_input = snapshot.input.clone();
+        ctx.curr_output = snapshot.output.clone();
+        ctx.curr_context_length = snapshot.context_length;
+        Ok(ctx)
+    }
+
     /// Clears all internal state and contexts.
     pub fn clear_context(&mut self) {
-        #[cfg(feature = "std")]
-        {
-            self.last_time = None;
-        }
+        self.last_time_ms = None;
+        self.escape_tap = None;
         self.curr_input.clear();
         self.curr_output.clear();
+        self.curr_context_length = 0;
+    }
+
+    /// Clears the context if the idle time since the previous key exceeds
+    /// [`TypingContextOptions::auto_context_clear_time_ms`].
+    fn clear_if_idle(&mut self, now_ms: Option<u64>) {
+        if self.is_idle_at(now_ms) {
+            self.clear_context();
+        }
+    }
+
+    fn is_idle_at(&self, now_ms: Option<u64>) -> bool {
+        now_ms
+            .zip(self.last_time_ms)
+            .is_some_and(|(now, last)| now.saturating_sub(last) > self.auto_context_clear_time_ms)
     }
 
     /// Internal helper to build transliteration options for typing mode.
@@ -140,6 +371,8 @@ impl TypingContext {
     }
 
     /// Accepts character-by-character input and returns the diff relative to the previous output.
+    ///
+    /// Only the first char of `key` is taken; use [`Self::take_text_input`] for longer input.
     pub fn take_key_input<T: AsRef<str>>(&mut self, key: T) -> TypingDiff {
         let key = key.as_ref();
         // If key is empty, nothing to do.
@@ -148,35 +381,275 @@ impl TypingContext {
                 to_delete_chars_count: 0,
                 diff_add_text: String::new(),
                 context_length: 0,
+                latin_passthrough: self.latin_passthrough,
             };
         };
 
         self.take_key_input_char(ch)
     }
 
+    /// Processes every char of `text` (eg. composed input or a pasted Roman word) as typed
+    /// keys and returns a single diff against the output before the call.
+    ///
+    /// `context_length` is that of the last key, so the text may have finished and started
+    /// contexts on the way.
+    ///
+    /// ```
+    /// use lipilekhika::Script;
+    /// use lipilekhika::typing::TypingContext;
+    ///
+    /// let mut ctx = TypingContext::new(Script::Devanagari, None);
+    /// ctx.take_key_input_char('r');
+    /// // र् becomes राम्, the space ends the word and नमस्ते starts the next
+    /// let diff = ctx.take_text_input("Am namaste");
+    /// assert_eq!((diff.to_delete_chars_count, diff.diff_add_text.as_str()), (1, "ाम् नमस्ते"));
+    /// assert_eq!(ctx.preedit(), "नमस्ते");
+    /// ```
+    pub fn take_text_input(&mut self, text: &str) -> TypingDiff {
+        let prev_output = self.curr_output.clone();
+        let mut output = prev_output.clone();
+        let mut context_length = self.curr_context_length;
+        for ch in text.chars() {
+            let diff = self.take_key_input_char(ch);
+            truncate_last_chars(&mut output, diff.to_delete_chars_count);
+            output.push_str(&diff.diff_add_text);
+            context_length = diff.context_length;
+        }
+
+        let (to_delete_chars_count, diff_add_text) = compute_diff(&prev_output, &output);
+        TypingDiff {
+            to_delete_chars_count,
+            diff_add_text,
+            context_length,
+            latin_passthrough: self.latin_passthrough,
+        }
+    }
+
     /// Process one character of typing input.
     ///
     /// When the `std` feature is enabled, the context is cleared automatically if the
     /// elapsed time since the previous key exceeds
     /// [`TypingContextOptions::auto_context_clear_time_ms`].
     ///
-    /// Without the `std` feature, there is no automatic time-based context clearing; call
+    /// Without the `std` feature, there is no automatic time-based context clearing; use
+    /// [`Self::take_key_input_char_at`] with your own clock, or call
     /// [`Self::clear_context`] yourself when the session should reset.
     pub fn take_key_input_char(&mut self, ch: char) -> TypingDiff {
-        #[cfg(feature = "std")]
-        let now = Instant::now();
-        #[cfg(feature = "std")]
-        if let Some(last) = self.last_time
-            && now.duration_since(last) > self.auto_context_clear_time
-        {
+        self.take_key_input_char_timed(ch, now_ms())
+    }
+
+    /// Like [`Self::take_key_input_char`], with the time of the key in milliseconds from
+    /// any monotonic clock (eg. a firmware tick counter or `performance.now()`), which
+    /// drives the idle auto-clear with or without the `std` feature.
+    ///
+    /// ```
+    /// use lipilekhika::Script;
+    /// use lipilekhika::typing::TypingContext;
+    ///
+    /// let mut ctx = TypingContext::new(Script::Devanagari, None);
+    /// ctx.take_key_input_char_at('k', 1_000);
+    /// // typed within 4.5s of `k`: continues the context
+    /// ctx.take_key_input_char_at('A', 2_000);
+    /// assert_eq!(ctx.preedit(), "का");
+    /// // typed after a longer pause: starts a new one
+    /// ctx.take_key_input_char_at('A', 10_000);
+    /// assert_eq!(ctx.preedit(), "आ");
+    /// ```
+    pub fn take_key_input_char_at(&mut self, ch: char, now_ms: u64) -> TypingDiff {
+        self.take_key_input_char_timed(ch, Some(now_ms))
+    }
+
+    fn take_key_input_char_timed(&mut self, ch: char, now_ms: Option<u64>) -> TypingDiff {
+        self.clear_if_idle(now_ms);
+
+        let diff = if self.latin_escape.is_some() || self.latin_passthrough {
+            self.take_key_with_latin_escape(ch)
+        } else {
+            self.curr_input.push(ch);
+            self.retransliterate_input()
+        };
+
+        self.last_time_ms = now_ms;
+
+        diff
+    }
+
+    /// Types `ch` with a [`LatinEscape`] set or during Latin text: the escape toggles Latin
+    /// text, which is passed through as typed.
+    fn take_key_with_latin_escape(&mut self, ch: char) -> TypingDiff {
+        let escape = self.latin_escape.filter(|escape| escape.key == ch);
+        let first_tap = self.escape_tap.take();
+        if escape.is_some_and(|escape| !escape.double_tap || first_tap.is_some()) {
+            let (to_delete_chars_count, diff_add_text) = match first_tap {
+                Some((before, after)) => compute_diff(&after, &before),
+                None => (0, String::new()),
+            };
+            self.clear_context();
+            self.latin_passthrough = !self.latin_passthrough;
+            return TypingDiff {
+                to_delete_chars_count,
+                diff_add_text,
+                context_length: 0,
+                latin_passthrough: self.latin_passthrough,
+            };
+        }
+
+        let before = self.curr_output.clone();
+        let diff = if self.latin_passthrough {
+            let scope = self.latin_escape.map(|escape| escape.scope);
+            if scope == Some(LatinEscapeScope::NextWord) && ch.is_whitespace() {
+                self.latin_passthrough = false;
+            }
+            TypingDiff {
+                to_delete_chars_count: 0,
+                diff_add_text: ch.to_string(),
+                context_length: 0,
+                latin_passthrough: self.latin_passthrough,
+            }
+        } else {
+            self.curr_input.push(ch);
+            self.retransliterate_input()
+        };
+        if escape.is_some() {
+            let mut after = before.clone();
+            truncate_last_chars(&mut after, diff.to_delete_chars_count);
+            after.push_str(&diff.diff_add_text);
+            self.escape_tap = Some((before, after));
+        }
+        diff
+    }
+
+    /// Whether keys are passed through as raw Latin text, see
+    /// [`TypingContextOptions::latin_escape`].
+    pub fn is_latin_passthrough(&self) -> bool {
+        self.latin_passthrough
+    }
+
+    /// Starts or ends raw Latin text as the escape key would, eg. from a UI toggle. Without
+    /// a [`TypingContextOptions::latin_escape`] it lasts until ended here.
+    pub fn set_latin_passthrough(&mut self, latin_passthrough: bool) {
+        if latin_passthrough != self.latin_passthrough {
+            self.clear_context();
+            self.latin_passthrough = latin_passthrough;
+        }
+    }
+
+    /// Removes the last typed key from the current context and returns the diff that
+    /// turns the previous output into the output for the remaining keys.
+    ///
+    /// Eg: after typing `kSh`, a backspace leaves the context in the `kS` state instead of
+    /// clearing it.
+    ///
+    /// When the context is empty (nothing typed yet, or it was cleared or timed out) the
+    /// diff is empty with `context_length == 0` and the backspace should be left to the app.
+    pub fn take_backspace(&mut self) -> TypingDiff {
+        self.take_backspace_timed(now_ms())
+    }
+
+    /// Like [`Self::take_backspace`], with the time of the key in milliseconds from the same
+    /// clock as [`Self::take_key_input_char_at`].
+    pub fn take_backspace_at(&mut self, now_ms: u64) -> TypingDiff {
+        self.take_backspace_timed(Some(now_ms))
+    }
+
+    fn take_backspace_timed(&mut self, now_ms: Option<u64>) -> TypingDiff {
+        self.clear_if_idle(now_ms);
+        self.escape_tap = None;
+        self.pop_key(now_ms)
+    }
+
+    /// Removes the last key from `curr_input` and re-transliterates the rest.
+    fn pop_key(&mut self, now_ms: Option<u64>) -> TypingDiff {
+        if self.curr_input.pop().is_none() {
+            return TypingDiff {
+                to_delete_chars_count: 0,
+                diff_add_text: String::new(),
+                context_length: 0,
+                latin_passthrough: self.latin_passthrough,
+            };
+        }
+        if self.curr_input.is_empty() {
+            let to_delete_chars_count = self.curr_output.chars().count();
             self.clear_context();
+            return TypingDiff {
+                to_delete_chars_count,
+                diff_add_text: String::new(),
+                context_length: 0,
+                latin_passthrough: self.latin_passthrough,
+            };
         }
 
-        self.curr_input.push(ch);
-        let prev_output = self.curr_output.as_str();
+        let diff = self.retransliterate_input();
+
+        self.last_time_ms = now_ms;
+
+        diff
+    }
+
+    /// Transliterates `curr_input` and returns the diff against the previous output.
+    fn retransliterate_input(&mut self) -> TypingDiff {
+        let (output, context_length) = self.transliterate_keys(&self.curr_input);
+
+        // Calculate the diff between previous and current output, by common prefix length.
+        let (to_delete_chars_count, diff_add_text) = compute_diff(&self.curr_output, &output);
 
+        if context_length > 0 {
+            self.curr_output = output;
+            self.curr_context_length = context_length;
+        } else {
+            self.clear_context();
+        }
+
+        TypingDiff {
+            to_delete_chars_count,
+            diff_add_text,
+            context_length,
+            latin_passthrough: self.latin_passthrough,
+        }
+    }
+
+    /// Transliterates a run of typed keys in typing mode, returning the output and its
+    /// context length. Keymap overrides are applied first.
+    pub(crate) fn transliterate_keys(&self, keys: &str) -> (String, usize) {
+        if self.layout != TypingLayout::Phonetic {
+            return self.type_layout_keys(keys);
+        }
+        let Some(keymap) = &self.keymap else {
+            return self.transliterate_builtin_keys(keys);
+        };
+        let mut output = String::new();
+        let mut context_length = 0;
+        for piece in keymap.rewrite(keys) {
+            match piece {
+                Piece::Keys { keys, units } => {
+                    let (text, length) = self.transliterate_builtin_keys(&keys);
+                    output.push_str(&text);
+                    context_length = raw_context_length(&units, length);
+                }
+                Piece::Literal(text) => {
+                    output.push_str(text);
+                    context_length = 0;
+                }
+            }
+        }
+        (output, context_length.max(keymap.pending_chars(keys)))
+    }
+
+    /// Types keys of a native keyboard layout, converting from the layout's script.
+    fn type_layout_keys(&self, keys: &str) -> (String, usize) {
+        let (output, context_length) = self.layout.type_keys(keys, self.use_native_numerals);
+        let native_script = self.layout.native_script();
+        if native_script == self.typing_script {
+            return (output, context_length);
+        }
+        let output = transliterate_text(&output, native_script, self.typing_script, None, None);
+        (output.output, context_length)
+    }
+
+    /// Transliterates built-in keys only, without the keymap overrides.
+    fn transliterate_builtin_keys(&self, keys: &str) -> (String, usize) {
         let result = transliterate_text_core(
-            &self.curr_input,
+            keys,
             &ScriptListEnum::Normal,
             &self.typing_script,
             self.from_script_data,
@@ -185,29 +658,307 @@ impl TypingContext {
             &self.custom_rules,
             Some(self.build_translit_options()),
         );
+        (result.output, result.context_length)
+    }
 
-        let context_length = result.context_length;
-        let output = result.output;
+    /// Returns the preedit as Normal keys, the canonical form used by
+    /// [`crate::completion::Lexicon`].
+    pub(crate) fn canonical_preedit(&self) -> String {
+        let mut keys = transliterate_text(
+            &self.curr_output,
+            self.typing_script,
+            ScriptListEnum::Normal,
+            None,
+            None,
+        )
+        .output;
+        // With inherent vowels `k` shows `क`, which reads back as `ka`.
+        if self.include_inherent_vowel && keys.ends_with('a') && !self.curr_input.ends_with('a') {
+            keys.pop();
+        }
+        keys
+    }
 
-        // Calculate the diff between previous and current output, by common prefix length.
-        let (to_delete_chars_count, diff_add_text) = compute_diff(prev_output, &output);
+    /// Returns the text currently being composed (the preedit of an input method).
+    pub fn preedit(&self) -> &str {
+        &self.curr_output
+    }
 
-        if context_length > 0 {
-            self.curr_output = output;
-        } else {
+    /// Returns the keys typed in the current composition, eg. `kSh` while `क्ष्` is shown.
+    pub fn raw_input(&self) -> &str {
+        &self.curr_input
+    }
+
+    /// Returns the prefix of [`Self::preedit`] that no further key can change and which
+    /// may therefore be committed early (see [`Self::commit_committable`]).
+    ///
+    /// Eg: while typing `namast` in Devanagari, `न` is committable and `मस्त्` is not.
+    pub fn committable(&self) -> &str {
+        let (_, output_len) = self.committable_split();
+        &self.curr_output[..output_len]
+    }
+
+    /// Commits [`Self::committable`], keeping only the still-changing tail as the
+    /// composition, and returns the committed text.
+    pub fn commit_committable(&mut self) -> String {
+        let (input_len, output_len) = self.committable_split();
+        self.curr_input.drain(..input_len);
+        self.curr_output.drain(..output_len).collect()
+    }
+
+    /// Finishes the composition: returns the preedit as text to commit and clears the context.
+    pub fn commit(&mut self) -> String {
+        let text = core::mem::take(&mut self.curr_output);
+        self.clear_context();
+        text
+    }
+
+    /// Abandons the composition and returns the raw keys that were typed for it, so an
+    /// input method can choose to commit them untransliterated.
+    pub fn cancel(&mut self) -> String {
+        let keys = core::mem::take(&mut self.curr_input);
+        self.clear_context();
+        keys
+    }
+
+    /// Like [`Self::take_key_input_char`], but reports the result as commit/preedit text.
+    ///
+    /// Text is committed when the key finishes the context (eg. a space or a visarga) or
+    /// when the previous composition was dropped by the idle auto-clear.
+    pub fn take_key_input_ime(&mut self, ch: char) -> ImeUpdate {
+        self.take_key_input_ime_timed(ch, now_ms())
+    }
+
+    /// Like [`Self::take_key_input_ime`], with the time of the key in milliseconds from the
+    /// same clock as [`Self::take_key_input_char_at`].
+    pub fn take_key_input_ime_at(&mut self, ch: char, now_ms: u64) -> ImeUpdate {
+        self.take_key_input_ime_timed(ch, Some(now_ms))
+    }
+
+    fn take_key_input_ime_timed(&mut self, ch: char, now_ms: Option<u64>) -> ImeUpdate {
+        let mut commit_text = String::new();
+        if self.is_idle_at(now_ms) {
+            commit_text = self.commit();
+        }
+
+        let mut output = self.curr_output.clone();
+        let diff = self.take_key_input_char_timed(ch, now_ms);
+        truncate_last_chars(&mut output, diff.to_delete_chars_count);
+        output.push_str(&diff.diff_add_text);
+
+        if diff.context_length == 0 {
+            commit_text.push_str(&output);
+            output.clear();
+        }
+        ImeUpdate {
+            commit_text,
+            preedit: output,
+        }
+    }
+
+    /// Like [`Self::take_backspace`], but reports the remaining preedit. Unlike
+    /// [`Self::take_backspace`] the idle auto-clear does not apply.
+    ///
+    /// Returns `None` when there is no composition, in which case the backspace should be
+    /// left to the application.
+    pub fn take_backspace_ime(&mut self) -> Option<ImeUpdate> {
+        if self.curr_input.is_empty() {
+            return None;
+        }
+        // The preedit is still on screen, so it is edited even after the idle timeout.
+        self.pop_key(now_ms());
+        Some(ImeUpdate {
+            commit_text: String::new(),
+            preedit: self.curr_output.clone(),
+        })
+    }
+
+    /// Sets the word frequencies used to rank [`Self::candidates`]; `None` ranks by
+    /// closeness to the typed keys only.
+    pub fn set_frequency_model(&mut self, model: Option<FrequencyModel>) {
+        self.frequency_model = model;
+    }
+
+    /// Returns up to `limit` alternative outputs for the current composition, best first.
+    ///
+    /// Alternatives swap typed keys for the neighbouring entries of the script's typing map:
+    /// the keys an entry continues into (`s` → `sh` → `shh`, `n` → `nz`, `a` → `aa`) and
+    /// the key it continues from. Candidates are ranked by
+    /// the frequency model (see [`Self::set_frequency_model`]) and then by how few keys were
+    /// swapped, so without a model the current preedit comes first.
+    ///
+    /// Keys of native layouts are not ambiguous, so there are no candidates for them.
+    pub fn candidates(&self, limit: usize) -> Vec<Candidate> {
+        if self.curr_input.is_empty() || limit == 0 || self.layout != TypingLayout::Phonetic {
+            return Vec::new();
+        }
+
+        let keys = self.typing_keys();
+        let mut partials: Vec<(String, usize)> = alloc::vec![(String::new(), 0)];
+        let mut rest = self.curr_input.as_str();
+        while !rest.is_empty() {
+            let token = keys
+                .iter()
+                .filter(|k| rest.starts_with(**k))
+                .max_by_key(|k| k.len())
+                .copied()
+                .unwrap_or_else(|| {
+                    let len = rest.chars().next().map_or(0, char::len_utf8);
+                    &rest[..len]
+                });
+            rest = &rest[token.len()..];
+
+            let mut alternatives = alloc::vec![token];
+            alternatives.extend(self.key_alternatives(token, &keys));
+
+            let mut next = Vec::with_capacity(partials.len() * alternatives.len());
+            for (input, swaps) in &partials {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let mut input = input.clone();
+                    input.push_str(alternative);
+                    next.push((input, swaps + usize::from(i > 0)));
+                }
+            }
+            next.sort_by_key(|(_, swaps)| *swaps);
+            next.truncate(MAX_CANDIDATE_INPUTS);
+            partials = next;
+        }
+
+        // Swapped keys which type nothing in the script are left as Latin letters.
+        let has_latin = |text: &str| text.chars().any(|c| c.is_ascii_alphabetic());
+        let preedit_has_latin = has_latin(&self.curr_output);
+        let mut seen = HashSet::new();
+        let mut ranked: Vec<(Candidate, u64, usize)> = Vec::new();
+        for (input, swaps) in partials {
+            let (text, _) = self.transliterate_keys(&input);
+            if text.is_empty() || (has_latin(&text) && !preedit_has_latin) {
+                continue;
+            }
+            if !seen.insert(text.clone()) {
+                continue;
+            }
+            let frequency = self
+                .frequency_model
+                .as_ref()
+                .map_or(0, |model| model.frequency(&text));
+            ranked.push((Candidate { text, input }, frequency, swaps));
+        }
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(candidate, _, _)| candidate)
+            .collect()
+    }
+
+    /// Replaces the composition with the keys of a [`Candidate`] and returns the diff
+    /// against the previous output.
+    pub fn select_candidate(&mut self, input: &str) -> TypingDiff {
+        self.curr_input.clear();
+        self.curr_input.push_str(input);
+        if input.is_empty() {
+            let to_delete_chars_count = self.curr_output.chars().count();
             self.clear_context();
+            return TypingDiff {
+                to_delete_chars_count,
+                diff_add_text: String::new(),
+                context_length: 0,
+                latin_passthrough: self.latin_passthrough,
+            };
         }
+        self.retransliterate_input()
+    }
 
-        #[cfg(feature = "std")]
-        {
-            self.last_time = Some(now);
+    /// Keys of [`Self::typing_keys`] next to `token` in the typing map: the continuations
+    /// listed in its entry's `next` and the key whose entry lists `token` as one.
+    fn key_alternatives<'k>(&self, token: &str, keys: &[&'k str]) -> Vec<&'k str> {
+        let map = &self.to_script_data.typing_text_to_krama_map;
+        let entry_next = |key: &str| {
+            self.to_script_data
+                .text_to_krama_map_index(key, true)
+                .and_then(|i| map[i].1.next.as_deref())
+                .unwrap_or_default()
+        };
+        let mut candidates: Vec<String> = entry_next(token)
+            .iter()
+            .map(|next| format!("{token}{next}"))
+            .collect();
+        if let Some((last, _)) = token.char_indices().last().filter(|(i, _)| *i > 0) {
+            let (prev, tail) = token.split_at(last);
+            if entry_next(prev).iter().any(|next| next == tail) {
+                candidates.push(prev.to_string());
+            }
         }
+        candidates
+            .iter()
+            .filter_map(|key| keys.binary_search(&key.as_str()).ok().map(|i| keys[i]))
+            .collect()
+    }
 
-        TypingDiff {
-            to_delete_chars_count,
-            diff_add_text,
-            context_length,
+    /// Key sequences that type a character in this context: the built-in keys plus the
+    /// keymap overrides, sorted.
+    fn typing_keys(&self) -> Vec<&str> {
+        let mut keys = builtin_typing_keys(self.from_script_data, self.to_script_data);
+        if let Some(keymap) = &self.keymap {
+            keys.extend(keymap.inputs());
+            keys.sort_unstable();
+            keys.dedup();
+        }
+        keys
+    }
+
+    /// Finds the longest split of the composition whose head is settled, as byte lengths
+    /// of `(curr_input, curr_output)`.
+    ///
+    /// The last `context_length` keys can still change the output, and a key before them
+    /// may still shape its neighbours (`nam` gives `नम्` but `nama` gives `नम`), so a split
+    /// only counts when both halves transliterate independently to the current output.
+    fn committable_split(&self) -> (usize, usize) {
+        let keys = self.curr_input.chars().count();
+        let settled_keys = keys.saturating_sub(self.curr_context_length);
+        let splits: Vec<usize> = self
+            .curr_input
+            .char_indices()
+            .map(|(i, _)| i)
+            .take(settled_keys + 1)
+            .skip(1)
+            .collect();
+        for input_len in splits.into_iter().rev() {
+            let (head, _) = self.transliterate_keys(&self.curr_input[..input_len]);
+            let Some(rest) = self.curr_output.strip_prefix(head.as_str()) else {
+                continue;
+            };
+            if self.transliterate_keys(&self.curr_input[input_len..]).0 == rest {
+                return (input_len, head.len());
+            }
         }
+        (0, 0)
+    }
+
+    /// Applies user key overrides on top of the script's typing map (see
+    /// [`crate::keymap`]); `None` removes them. The context is cleared.
+    ///
+    /// Overrides apply to phonetic typing; native layouts (see [`crate::layout`]) ignore them.
+    ///
+    /// Returns the overrides that change built-in keys. On error the previous keymap stays.
+    pub fn set_keymap(
+        &mut self,
+        keymap: Option<&Keymap>,
+    ) -> Result<Vec<KeymapConflict>, KeymapError> {
+        let (resolved, conflicts) = match keymap {
+            Some(keymap) => {
+                let builtin_keys = builtin_typing_keys(self.from_script_data, self.to_script_data);
+                let (resolved, conflicts) =
+                    ResolvedKeymap::new(keymap, self.to_script_data, builtin_keys, |keys| {
+                        self.transliterate_builtin_keys(keys).0
+                    })?;
+                (Some(resolved), conflicts)
+            }
+            None => (None, Vec::new()),
+        };
+        self.clear_context();
+        self.keymap = resolved;
+        Ok(conflicts)
     }
 
     /// Updates whether native numerals should be used for subsequent typing.
@@ -228,10 +979,62 @@ impl TypingContext {
         self.include_inherent_vowel
     }
 
+    /// Returns the keyboard layout in use, [`TypingLayout::Phonetic`] if the requested
+    /// layout cannot type this script.
+    pub fn get_layout(&self) -> TypingLayout {
+        self.layout
+    }
+
+    /// Returns the Latin escape key, if any.
+    pub fn get_latin_escape(&self) -> Option<LatinEscape> {
+        self.latin_escape
+    }
+
     /// Returns the normalized script name for this typing context.
     pub fn get_normalized_script(&self) -> &str {
         self.to_script_data.script_name.as_str()
     }
+
+    /// Returns the keys that type `text` with the options and keymap of this context, see
+    /// [`typing_sequence_for`]. The composition is left as it is.
+    pub fn typing_sequence_for(&self, text: impl AsRef<str>) -> Option<String> {
+        let text = text.as_ref();
+        let mut snapshot = self.snapshot();
+        snapshot.options.layout = TypingLayout::Phonetic;
+        snapshot.input.clear();
+        snapshot.output.clear();
+        snapshot.context_length = 0;
+        let mut ctx = Self::restore(&snapshot).ok()?;
+        let keys = ReverseTypingMap::new(&ctx).keys_for(&ctx, text)?;
+        (type_keys(&mut ctx, &keys) == text).then_some(keys)
+    }
+}
+
+/// Built-in key sequences of a typing script: the Normal script keys plus the
+/// typing-specific keys of the target script.
+fn builtin_typing_keys(
+    from_script_data: &'static ScriptData,
+    to_script_data: &'static ScriptData,
+) -> Vec<&'static str> {
+    let krama_keys = from_script_data.krama_text_arr.iter().map(|(key, _)| key);
+    let normal_keys = from_script_data
+        .text_to_krama_map
+        .iter()
+        .filter(|(_, item)| item.krama.is_some())
+        .map(|(key, _)| key);
+    let typing_keys = to_script_data
+        .typing_text_to_krama_map
+        .iter()
+        .filter(|(_, item)| item.krama.is_some() || item.custom_back_ref.is_some());
+    let mut keys: Vec<&'static str> = krama_keys
+        .chain(normal_keys)
+        .chain(typing_keys.map(|(key, _)| key))
+        .map(String::as_str)
+        .filter(|key| !key.is_empty())
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys
 }
 
 /// Compute the character-wise diff between previous and current outputs.
@@ -259,11 +1062,14 @@ pub fn emulate_typing(
     typing_lang: Script,
     options: Option<TypingContextOptions>,
 ) -> String {
-    let text = text.as_ref();
-    let mut ctx = TypingContext::new(typing_lang, options);
+    type_keys(&mut TypingContext::new(typing_lang, options), text.as_ref())
+}
+
+/// Types `keys` one by one into `ctx`, returning the accumulated output.
+fn type_keys(ctx: &mut TypingContext, keys: &str) -> String {
     let mut result = String::new();
 
-    for ch in text.chars() {
+    for ch in keys.chars() {
         let diff = ctx.take_key_input_char(ch);
 
         if diff.to_delete_chars_count > 0 {
@@ -276,6 +1082,278 @@ pub fn emulate_typing(
     result
 }
 
+/// Returns the Normal keys that type `text` in `typing_lang`, so that
+/// `emulate_typing(keys, typing_lang, options)` gives back `text`, or `None` if some part
+/// of `text` cannot be typed.
+///
+/// Built on the inverse of the script's typing map: runs of `text` are matched against what
+/// single keys (and consonant keys followed by vowel keys) type, preferring fewer keys and
+/// adding a separator key where neighbouring keys would otherwise combine. The result is
+/// verified by typing it. Keys are for phonetic typing whatever the options' layout.
+///
+/// ```
+/// use lipilekhika::Script;
+/// use lipilekhika::typing::typing_sequence_for;
+///
+/// let keys = typing_sequence_for("भक्ति", Script::Devanagari, None);
+/// assert_eq!(keys.as_deref(), Some("bhakti"));
+/// ```
+pub fn typing_sequence_for(
+    text: impl AsRef<str>,
+    typing_lang: Script,
+    options: Option<TypingContextOptions>,
+) -> Option<String> {
+    TypingContext::new(typing_lang, options).typing_sequence_for(text)
+}
+
+/// Key sequences kept per output, and search states kept per text position.
+const REVERSE_KEYS_PER_TEXT: usize = 3;
+const REVERSE_STATES_PER_POSITION: usize = 8;
+
+/// What each key sequence types on its own, indexed by the output.
+struct ReverseTypingMap {
+    keys_by_text: HashMap<String, Vec<String>>,
+    max_text_chars: usize,
+    /// Shortest key that types nothing, used to keep neighbouring keys apart.
+    separator: Option<String>,
+}
+
+/// A partial key sequence in [`ReverseTypingMap::keys_for`], typing a prefix of the text.
+struct ReverseTypingState {
+    parent: Option<usize>,
+    keys: String,
+    key_count: usize,
+    /// Keys of the open typing context.
+    raw: String,
+    /// Bytes of the text already committed.
+    committed: usize,
+}
+
+impl ReverseTypingMap {
+    fn new(ctx: &TypingContext) -> Self {
+        let typed = |keys: &str| ctx.transliterate_keys(keys).0;
+        let script_data = ctx.to_script_data;
+        let halant = match script_data {
+            ScriptData::Brahmic { halant, .. } => Some(halant.as_str()),
+            ScriptData::Other { .. } => None,
+        };
+        let svaras: HashSet<&str> = script_data
+            .list
+            .iter()
+            .filter_map(|list| match list {
+                List::Svara { krama_ref, .. } => Some(krama_ref),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|&idx| script_data.krama_text_arr.get(usize::try_from(idx).ok()?))
+            .map(|(text, _)| text.as_str())
+            .collect();
+
+        let mut keys_by_text: HashMap<String, Vec<String>> = HashMap::new();
+        let mut separator: Option<String> = None;
+        for key in ctx.typing_keys() {
+            let output = typed(key);
+            if output.is_empty() {
+                if separator.as_ref().is_none_or(|sep| key.len() < sep.len()) {
+                    separator = Some(String::from(key));
+                }
+                continue;
+            }
+            keys_by_text
+                .entry(output)
+                .or_default()
+                .push(String::from(key));
+        }
+        // Fewest keys first, then the fewest shifted ones (`e` before `E`).
+        let key_order = |keys: &String| {
+            let shifted = keys.chars().filter(char::is_ascii_uppercase).count();
+            (keys.chars().count(), shifted, keys.clone())
+        };
+        for keys in keys_by_text.values_mut() {
+            keys.sort_unstable_by_key(key_order);
+            keys.truncate(REVERSE_KEYS_PER_TEXT);
+        }
+
+        // A consonant key types a halant that a following vowel key replaces with its sign.
+        let keys_of = |is_kind: &dyn Fn(&str) -> bool| -> Vec<String> {
+            keys_by_text
+                .iter()
+                .filter(|(text, _)| is_kind(text))
+                .flat_map(|(_, keys)| keys.iter().cloned())
+                .collect()
+        };
+        let consonant_keys = keys_of(&|text| halant.is_some_and(|h| text.ends_with(h)));
+        let vowel_keys = keys_of(&|text| svaras.contains(text));
+        for consonant in &consonant_keys {
+            for vowel in &vowel_keys {
+                let mut keys = consonant.clone();
+                keys.push_str(vowel);
+                keys_by_text.entry(typed(&keys)).or_default().push(keys);
+            }
+        }
+        for keys in keys_by_text.values_mut() {
+            keys.sort_unstable_by_key(key_order);
+            keys.dedup();
+            keys.truncate(REVERSE_KEYS_PER_TEXT);
+        }
+
+        let max_text_chars = keys_by_text
+            .keys()
+            .map(|text| text.chars().count())
+            .max()
+            .unwrap_or(0);
+        Self {
+            keys_by_text,
+            max_text_chars,
+            separator,
+        }
+    }
+
+    /// Searches for the fewest keys typing `text`, keeping the best few states at each
+    /// position of the text. States are checked as they are extended, the same way
+    /// [`TypingContext::take_key_input_char`] commits a context once it closes.
+    fn keys_for(&self, ctx: &TypingContext, text: &str) -> Option<String> {
+        let mut states = alloc::vec![ReverseTypingState {
+            parent: None,
+            keys: String::new(),
+            key_count: 0,
+            raw: String::new(),
+            committed: 0,
+        }];
+        let mut at_position: Vec<Vec<usize>> = alloc::vec![Vec::new(); text.len() + 1];
+        at_position[0].push(0);
+
+        for pos in 0..text.len() {
+            if !text.is_char_boundary(pos) {
+                continue;
+            }
+            let mut current = core::mem::take(&mut at_position[pos]);
+            current.sort_unstable_by_key(|&idx| states[idx].key_count);
+            current.truncate(REVERSE_STATES_PER_POSITION);
+            for state_idx in current {
+                for (end, candidates) in self.candidates_at(text, pos) {
+                    for keys in candidates {
+                        let with_separator = self.separator.as_ref().map(|sep| {
+                            let mut separated = sep.clone();
+                            separated.push_str(&keys);
+                            separated
+                        });
+                        let next = core::iter::once(keys)
+                            .chain(with_separator)
+                            .find_map(|keys| {
+                                extend_reverse_state(ctx, text, &states, state_idx, keys, end)
+                            });
+                        let Some(next) = next else {
+                            continue;
+                        };
+                        let existing = at_position[end].iter().copied().find(|&idx| {
+                            states[idx].raw == next.raw && states[idx].committed == next.committed
+                        });
+                        match existing {
+                            Some(idx) if states[idx].key_count <= next.key_count => {}
+                            Some(idx) => states[idx] = next,
+                            None => {
+                                at_position[end].push(states.len());
+                                states.push(next);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut idx = at_position[text.len()]
+            .iter()
+            .copied()
+            .min_by_key(|&idx| states[idx].key_count)?;
+        let mut parts = Vec::new();
+        while let Some(parent) = states[idx].parent {
+            parts.push(states[idx].keys.as_str());
+            idx = parent;
+        }
+        parts.reverse();
+        Some(parts.concat())
+    }
+
+    /// Key sequences that may type the text from `pos`, with the end of the text they type.
+    fn candidates_at(&self, text: &str, pos: usize) -> Vec<(usize, Vec<String>)> {
+        let rest = &text[pos..];
+        let mut out = Vec::new();
+        for (count, (offset, ch)) in rest
+            .char_indices()
+            .take(self.max_text_chars.max(1))
+            .enumerate()
+        {
+            let end = offset + ch.len_utf8();
+            let mut candidates = self
+                .keys_by_text
+                .get(&rest[..end])
+                .cloned()
+                .unwrap_or_default();
+            // Characters outside the typing map may still type as themselves.
+            if count == 0 && candidates.is_empty() {
+                candidates.push(ch.to_string());
+            }
+            if !candidates.is_empty() {
+                out.push((pos + end, candidates));
+            }
+        }
+        out
+    }
+}
+
+/// Types `keys` after the state at `parent`, returning the new state if the output is
+/// still `text[..end]`.
+fn extend_reverse_state(
+    ctx: &TypingContext,
+    text: &str,
+    states: &[ReverseTypingState],
+    parent: usize,
+    keys: String,
+    end: usize,
+) -> Option<ReverseTypingState> {
+    let state = &states[parent];
+    let mut raw = state.raw.clone();
+    let mut committed = state.committed;
+    let mut pending = String::new();
+    for ch in keys.chars() {
+        raw.push(ch);
+        let (output, context_length) = ctx.transliterate_keys(&raw);
+        if context_length > 0 {
+            pending = output;
+            continue;
+        }
+        if !text[committed..].starts_with(output.as_str()) {
+            return None;
+        }
+        committed += output.len();
+        raw.clear();
+        pending.clear();
+    }
+    if committed + pending.len() != end || !text[committed..].starts_with(pending.as_str()) {
+        return None;
+    }
+    Some(ReverseTypingState {
+        parent: Some(parent),
+        key_count: state.key_count + keys.chars().count(),
+        keys,
+        raw,
+        committed,
+    })
+}
+
+/// Milliseconds on the `std` monotonic clock for the idle auto-clear; `None` without `std`.
+fn now_ms() -> Option<u64> {
+    #[cfg(feature = "std")]
+    {
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        let elapsed = EPOCH.get_or_init(Instant::now).elapsed();
+        Some(u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+    }
+    #[cfg(not(feature = "std"))]
+    None
+}
+
 /// Truncate the last `n` characters from a UTF-8 string (character-wise, not bytes).
 /// Iterates from the end for O(n) instead of O(string_length).
 fn truncate_last_chars(s: &mut String, n: usize) {
@@ -440,6 +1518,60 @@ pub fn get_script_typing_data_map(typing_script: Script) -> ScriptTypingDataMap
     }
 }
 
+/// Returns the typing data map for a script with the overrides of `keymap` applied, so a
+/// typing helper shows the user's custom keys.
+///
+/// Overridden built-in keys are removed from the characters they typed, override inputs
+/// are listed with the character they type, and literal outputs or characters without an
+/// entry are added to `script_specific_krama_map`.
+pub fn get_script_typing_data_map_with_keymap(
+    typing_script: Script,
+    keymap: &Keymap,
+) -> Result<ScriptTypingDataMap, KeymapError> {
+    let mut ctx = TypingContext::new(typing_script, None);
+    ctx.set_keymap(Some(keymap))?;
+    let mut data = get_script_typing_data_map(typing_script);
+    let Some(resolved) = &ctx.keymap else {
+        return Ok(data);
+    };
+
+    for (input, target) in &resolved.entries {
+        for (_, _, mappings) in data
+            .common_krama_map
+            .iter_mut()
+            .chain(data.script_specific_krama_map.iter_mut())
+        {
+            mappings.retain(|m| m != input);
+        }
+
+        let (text, keys) = match target {
+            ResolvedTarget::Keys { keys, text } => (text, Some(keys)),
+            ResolvedTarget::Literal(text) => (text, None),
+        };
+        let item = data
+            .common_krama_map
+            .iter_mut()
+            .chain(data.script_specific_krama_map.iter_mut())
+            .find(|(item_text, _, mappings)| {
+                item_text == text || keys.is_some_and(|keys| mappings.contains(keys))
+            });
+        match item {
+            Some((_, _, mappings)) => mappings.push(input.clone()),
+            None => data.script_specific_krama_map.push((
+                text.clone(),
+                ListType::Anya,
+                alloc::vec![input.clone()],
+            )),
+        }
+    }
+
+    data.common_krama_map
+        .retain(|(_, _, mappings)| !mappings.is_empty());
+    data.script_specific_krama_map
+        .retain(|(_, _, mappings)| !mappings.is_empty());
+    Ok(data)
+}
+
 /// Type alias for krama data items used in script comparison.
 /// Each item is (character_text, list_type).
 pub type KramaDataItem = (String, ListType);
@@ -475,6 +1607,7 @@ pub fn get_script_krama_data(script: Script) -> Vec<KramaDataItem> {
 mod tests {
     use super::*;
 
+    use crate::keymap::KeymapTarget;
     use crate::scripts::Script;
     use crate::transliterate::helpers::VEDIC_SVARAS;
     use alloc::format;
@@ -494,7 +1627,9 @@ mod tests {
         assert_send_sync::<TypingContext>();
         assert_send_sync::<TypingContextOptions>();
         assert_send_sync::<TypingDiff>();
+        assert_send_sync::<FrequencyModel>();
         assert_send_sync::<ScriptTypingDataMap>();
+        assert_send_sync::<TypingSnapshot>();
     }
 
     /// For transliteration auto tests, `index` can be string or number in YAML.
@@ -842,6 +1977,379 @@ mod tests {
         }
     }
 
+    #[test]
+    fn take_backspace_steps_back_one_key() {
+        fn apply(output: &mut String, diff: &TypingDiff) {
+            truncate_last_chars(output, diff.to_delete_chars_count);
+            output.push_str(&diff.diff_add_text);
+        }
+
+        for (typed, script) in [("kSh", Script::Devanagari), ("shr", Script::Telugu)] {
+            let mut ctx = TypingContext::new(script, None);
+            let mut output = String::new();
+            for ch in typed.chars() {
+                apply(&mut output, &ctx.take_key_input_char(ch));
+            }
+
+            let keys: Vec<char> = typed.chars().collect();
+            for remaining in (0..keys.len()).rev() {
+                let diff = ctx.take_backspace();
+                apply(&mut output, &diff);
+                let expected: String = keys[..remaining].iter().collect();
+                assert_eq!(output, emulate_typing(&expected, script, None), "{typed:?}");
+                assert_eq!(diff.context_length == 0, remaining == 0);
+            }
+
+            let diff = ctx.take_backspace();
+            assert_eq!(diff.to_delete_chars_count, 0);
+            assert!(diff.diff_add_text.is_empty());
+            assert_eq!(diff.context_length, 0);
+        }
+    }
+
+    #[test]
+    fn take_text_input_returns_the_net_diff_of_its_keys() {
+        for (before, text) in [
+            ("", "namaste"),
+            ("k", "Sh"),
+            ("rAm", " sItA"),
+            ("kS", "h. bhakti "),
+            ("na", ""),
+        ] {
+            let mut ctx = TypingContext::new(Script::Devanagari, None);
+            let mut keyed = TypingContext::new(Script::Devanagari, None);
+            for ch in before.chars() {
+                ctx.take_key_input_char(ch);
+                keyed.take_key_input_char(ch);
+            }
+
+            let mut screen = emulate_typing(before, Script::Devanagari, None);
+            let diff = ctx.take_text_input(text);
+            truncate_last_chars(&mut screen, diff.to_delete_chars_count);
+            screen.push_str(&diff.diff_add_text);
+            let expected =
+                emulate_typing(alloc::format!("{before}{text}"), Script::Devanagari, None);
+            assert_eq!(screen, expected, "{before:?} + {text:?}");
+
+            if let Some(last) = text.chars().map(|ch| keyed.take_key_input_char(ch)).last() {
+                assert_eq!(diff.context_length, last.context_length);
+            }
+            assert_eq!(ctx.raw_input(), keyed.raw_input());
+        }
+    }
+
+    #[test]
+    fn latin_escape_passes_text_through() {
+        fn typed(escape: Option<LatinEscape>, keys: &str) -> (String, bool) {
+            let mut ctx = TypingContext::new(
+                Script::Devanagari,
+                Some(TypingContextOptions {
+                    latin_escape: escape,
+                    ..Default::default()
+                }),
+            );
+            let mut output = String::new();
+            let mut latin = false;
+            for ch in keys.chars() {
+                let diff = ctx.take_key_input_char(ch);
+                truncate_last_chars(&mut output, diff.to_delete_chars_count);
+                output.push_str(&diff.diff_add_text);
+                latin = diff.latin_passthrough;
+                assert_eq!(latin, ctx.is_latin_passthrough());
+            }
+            (output, latin)
+        }
+        fn typed_as_usual(keys: &str) -> (String, bool) {
+            (emulate_typing(keys, Script::Devanagari, None), false)
+        }
+        let backslash = LatinEscape {
+            key: '\\',
+            double_tap: false,
+            scope: LatinEscapeScope::NextWord,
+        };
+
+        // the next word only, the composition before the escape is kept
+        assert_eq!(
+            typed(Some(backslash), "rAm\\a@b.com rAm"),
+            ("राम्a@b.com राम्".to_string(), false)
+        );
+        assert_eq!(typed(Some(backslash), "\\ok"), ("ok".to_string(), true));
+        assert_eq!(
+            typed(Some(backslash), "\\o\\kA"),
+            ("oका".to_string(), false)
+        );
+        // without an escape the key types as usual
+        assert_eq!(typed(None, "a\\"), typed_as_usual("a\\"));
+
+        let until_escape = LatinEscape {
+            scope: LatinEscapeScope::UntilEscape,
+            ..backslash
+        };
+        assert_eq!(
+            typed(Some(until_escape), "\\hi there\\rAm"),
+            ("hi thereराम्".to_string(), false)
+        );
+
+        // a single `;` types as usual, a second one right after takes it back and escapes
+        let semicolons = LatinEscape {
+            key: ';',
+            double_tap: true,
+            scope: LatinEscapeScope::NextWord,
+        };
+        assert_eq!(typed(Some(semicolons), "kA;"), typed_as_usual("kA;"));
+        assert_eq!(
+            typed(Some(semicolons), "kA;;ok;;kA"),
+            ("काokका".to_string(), false)
+        );
+        assert_eq!(typed(Some(semicolons), "k;;"), ("क्".to_string(), true));
+
+        let mut ctx = TypingContext::new(Script::Devanagari, None);
+        ctx.set_latin_passthrough(true);
+        assert_eq!(ctx.take_key_input_char('k').diff_add_text, "k");
+        ctx.set_latin_passthrough(false);
+        assert_eq!(ctx.take_key_input_char('k').diff_add_text, "क्");
+    }
+
+    #[test]
+    fn caller_clock_drives_idle_auto_clear() {
+        let options = TypingContextOptions {
+            auto_context_clear_time_ms: 1000,
+            ..Default::default()
+        };
+        let mut ctx = TypingContext::new(Script::Devanagari, Some(options));
+        ctx.take_key_input_char_at('k', 0);
+        assert_eq!(ctx.take_key_input_char_at('S', 1000).context_length, 2);
+        assert_eq!(ctx.raw_input(), "kS");
+
+        // an idle backspace only finds the cleared context
+        let diff = ctx.take_backspace_at(2001);
+        assert_eq!(diff.to_delete_chars_count, 0);
+        assert_eq!(diff.context_length, 0);
+        assert!(ctx.raw_input().is_empty());
+
+        ctx.take_key_input_char_at('k', 3000);
+        ctx.take_key_input_char_at('A', 3500);
+        assert_eq!(ctx.preedit(), "का");
+        let update = ctx.take_key_input_ime_at('i', 5000);
+        assert_eq!(update.commit_text, "का");
+        assert_eq!(update.preedit, "इ");
+
+        // a clock going backwards is not idle time
+        ctx.take_key_input_char_at('k', 100);
+        assert_eq!(ctx.raw_input(), "ik");
+    }
+
+    #[test]
+    fn restored_snapshot_continues_the_composition() {
+        let options = TypingContextOptions {
+            include_inherent_vowel: true,
+            ..Default::default()
+        };
+        let mut keymap = Keymap::new();
+        keymap.insert("w", KeymapTarget::Char("व".to_string()));
+        let mut ctx = TypingContext::new(Script::Devanagari, Some(options.clone()));
+        ctx.set_keymap(Some(&keymap)).unwrap();
+        for ch in "kS".chars() {
+            ctx.take_key_input_char(ch);
+        }
+
+        let yaml = serde_yaml_ng::to_string(&ctx.snapshot()).unwrap();
+        let snapshot: TypingSnapshot = serde_yaml_ng::from_str(&yaml).unwrap();
+        assert_eq!(snapshot, ctx.snapshot());
+        assert_eq!(snapshot.script, "Devanagari");
+        assert_eq!(snapshot.options, options);
+        assert_eq!(snapshot.input, "kS");
+
+        let mut restored = TypingContext::restore(&snapshot).unwrap();
+        for ch in "hw".chars() {
+            assert_eq!(
+                restored.take_key_input_char(ch),
+                ctx.take_key_input_char(ch)
+            );
+        }
+        assert_eq!(restored.preedit(), ctx.preedit());
+
+        let unknown = TypingSnapshot {
+            script: "Klingon".to_string(),
+            ..snapshot
+        };
+        assert!(matches!(
+            TypingContext::restore(&unknown),
+            Err(TypingSnapshotError::UnknownScript(_))
+        ));
+    }
+
+    #[test]
+    fn ime_commits_settled_text_and_keeps_the_rest_in_preedit() {
+        let mut ctx = TypingContext::new(Script::Devanagari, None);
+        let mut committed = String::new();
+        for ch in "namast".chars() {
+            let update = ctx.take_key_input_ime(ch);
+            committed.push_str(&update.commit_text);
+            assert_eq!(update.preedit, ctx.preedit());
+        }
+        assert!(committed.is_empty());
+        assert_eq!(ctx.raw_input(), "namast");
+
+        let preedit = ctx.preedit().to_string();
+        let head = ctx.committable().to_string();
+        assert!(!head.is_empty());
+        assert_eq!(ctx.commit_committable(), head);
+        assert_eq!(format!("{head}{}", ctx.preedit()), preedit);
+
+        let update = ctx.take_key_input_ime('e');
+        assert_eq!(
+            format!("{head}{}", update.preedit),
+            emulate_typing("namaste", Script::Devanagari, None)
+        );
+
+        let update = ctx.take_key_input_ime(' ');
+        assert_eq!(
+            format!("{head}{}", update.commit_text),
+            emulate_typing("namaste ", Script::Devanagari, None)
+        );
+        assert!(update.preedit.is_empty());
+        assert!(ctx.take_backspace_ime().is_none());
+
+        for ch in "kSh".chars() {
+            ctx.take_key_input_ime(ch);
+        }
+        let update = ctx.take_backspace_ime().unwrap();
+        assert_eq!(
+            update.preedit,
+            emulate_typing("kS", Script::Devanagari, None)
+        );
+        assert_eq!(ctx.commit(), update.preedit);
+        assert_eq!(ctx.preedit(), "");
+
+        for ch in "rAma".chars() {
+            ctx.take_key_input_ime(ch);
+        }
+        assert_eq!(ctx.cancel(), "rAma");
+        assert_eq!(ctx.raw_input(), "");
+        assert_eq!(ctx.preedit(), "");
+    }
+
+    #[test]
+    fn typing_sequence_for_round_trips_through_typing() {
+        let cases = [
+            (Script::Devanagari, "भक्ति", Some("bhakti")),
+            (Script::Devanagari, "रामः", Some("rAmaH")),
+            (Script::Devanagari, "क्ह", Some("kqha")),
+            (Script::Devanagari, "नमस्ते दुनिया।", Some("namaste duniyA.")),
+            (Script::Devanagari, "ॐ १२", None),
+            (Script::Telugu, "తెలుగు", None),
+            (Script::Tamil, "தமிழ்", None),
+        ];
+        for (script, text, expected) in cases {
+            let keys = typing_sequence_for(text, script, None)
+                .unwrap_or_else(|| panic!("no keys for {text:?} in {script:?}"));
+            if let Some(expected) = expected {
+                assert_eq!(keys, expected, "keys for {text:?}");
+            }
+            assert_eq!(emulate_typing(&keys, script, None), text, "keys {keys:?}");
+        }
+
+        let inherent = TypingContextOptions {
+            include_inherent_vowel: true,
+            ..Default::default()
+        };
+        let keys = typing_sequence_for("कमल", Script::Devanagari, Some(inherent.clone())).unwrap();
+        assert_eq!(
+            emulate_typing(&keys, Script::Devanagari, Some(inherent)),
+            "कमल"
+        );
+
+        assert_eq!(
+            typing_sequence_for("", Script::Devanagari, None).as_deref(),
+            Some("")
+        );
+        assert_eq!(typing_sequence_for("abc", Script::Devanagari, None), None);
+
+        let mut keymap = Keymap::new();
+        keymap.insert("q", KeymapTarget::Char("ष".to_string()));
+        let mut ctx = TypingContext::new(Script::Devanagari, None);
+        ctx.set_keymap(Some(&keymap)).unwrap();
+        ctx.take_key_input_char('k');
+        assert_eq!(ctx.typing_sequence_for("षा").as_deref(), Some("qA"));
+        assert_eq!(ctx.raw_input(), "k");
+    }
+
+    #[test]
+    fn candidates_offer_ambiguous_letters() {
+        let mut ctx = TypingContext::new(Script::Devanagari, None);
+        for ch in "shiva".chars() {
+            ctx.take_key_input_char(ch);
+        }
+
+        let candidates = ctx.candidates(8);
+        assert_eq!(candidates[0].text, ctx.preedit());
+        assert_eq!(candidates[0].input, "shiva");
+        let texts: Vec<&str> = candidates.iter().map(|c| c.text.as_str()).collect();
+        assert!(texts.contains(&"षिव"), "{texts:?}");
+        assert_eq!(ctx.candidates(2).len(), 2);
+
+        ctx.set_frequency_model(Some(FrequencyModel::from_iter([("षिव", 3)])));
+        let best = ctx.candidates(1).remove(0);
+        assert_eq!(best.text, "षिव");
+
+        let mut output = ctx.preedit().to_string();
+        let diff = ctx.select_candidate(&best.input);
+        truncate_last_chars(&mut output, diff.to_delete_chars_count);
+        output.push_str(&diff.diff_add_text);
+        assert_eq!(output, "षिव");
+        assert_eq!(ctx.preedit(), "षिव");
+
+        assert!(
+            TypingContext::new(Script::Devanagari, None)
+                .candidates(5)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn candidates_follow_typing_map_entries() {
+        let script_data = ScriptData::get_script_data(&ScriptListEnum::Devanagari);
+        let type_keys = |keys: &str| {
+            let mut ctx = TypingContext::new(Script::Devanagari, None);
+            for ch in keys.chars() {
+                ctx.take_key_input_char(ch);
+            }
+            let output = ctx.transliterate_keys(keys).0;
+            let texts: Vec<String> = ctx.candidates(64).into_iter().map(|c| c.text).collect();
+            (output, texts)
+        };
+        let mut checked = 0;
+        for (key, item) in &script_data.typing_text_to_krama_map {
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+                continue;
+            }
+            let (output, texts) = type_keys(key);
+            for next in item.next.iter().flatten() {
+                let continued = format!("{key}{next}");
+                let (continued_output, continued_texts) = type_keys(&continued);
+                if continued_output == output
+                    || [&output, &continued_output]
+                        .iter()
+                        .any(|text| text.chars().any(|c| c.is_ascii_alphabetic()))
+                {
+                    continue;
+                }
+                assert!(
+                    texts.contains(&continued_output),
+                    "{key} -> {continued}: {texts:?}"
+                );
+                // Keys which end the composition (`AUM`) leave nothing to swap.
+                assert!(
+                    continued_texts.is_empty() || continued_texts.contains(&output),
+                    "{continued} -> {key}: {continued_texts:?}"
+                );
+                checked += 1;
+            }
+        }
+        assert!(checked > 10, "{checked}");
+    }
+
     #[test]
     fn test_get_script_typing_data_map_valid_script() {
         let data = get_script_typing_data_map(Script::Devanagari);
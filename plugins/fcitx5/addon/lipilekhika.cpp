@@ -3,22 +3,28 @@
  *
  * Notes:
  * - This is intentionally "v1 minimal": ASCII roman key -> preedit diff -> preedit update.
- * - Backspace is implemented by replaying `raw_ascii_` (no Rust-side pop API yet).
+ * - Preedit/commit decisions come from the Rust IME API (`lipi_typing_context_ime_*`,
+ *   `lipi_typing_context_commit`), so this file only mirrors them into Fcitx.
  */
 
 #include "lipilekhika.h"
 
 #include <fcitx-config/iniparser.h>
+#include <fcitx/candidatelist.h>
 #include <fcitx-config/rawconfig.h>
 #include <fcitx/inputcontext.h>
 #include <fcitx/inputmethodentry.h>
 #include <fcitx/inputpanel.h>
 #include <fcitx/userinterface.h>
+#include <fcitx-utils/fs.h>
 #include <fcitx-utils/key.h>
 #include <fcitx-utils/keysym.h>
+#include <fcitx-utils/standardpath.h>
 
 #include <algorithm>
 #include <cctype>
+#include <memory>
+#include <vector>
 #include <string_view>
 #include <unordered_map>
 
@@ -71,16 +77,22 @@ static std::string scriptFromEntry(const fcitx::InputMethodEntry &entry) {
   return key;
 }
 
-// Remove last Unicode codepoint from a UTF-8 string.
-static void truncateLastCodepoints(std::string &s, size_t n) {
-  while (n-- > 0 && !s.empty()) {
-    // Walk back to the beginning of the last UTF-8 codepoint.
-    size_t i = s.size();
-    do {
-      --i;
-    } while (i > 0 && ((static_cast<unsigned char>(s[i]) & 0xC0) == 0x80));
-    s.erase(i);
+static std::string takeLipiString(LipiString s) {
+  std::string out;
+  if (s.ptr && s.len) {
+    out.assign(s.ptr, s.len);
   }
+  lipi_string_free(s);
+  return out;
+}
+
+// Learned word frequencies live next to other per-user Fcitx data.
+static std::string frequenciesPath() {
+  const std::string dir =
+      fcitx::StandardPath::global().userDirectory(fcitx::StandardPath::Type::PkgData) +
+      "/lipilekhika";
+  fcitx::fs::makePath(dir);
+  return dir + "/word_frequencies.tsv";
 }
 
 static bool isPrintableAscii(uint32_t sym) { return sym >= 0x20 && sym <= 0x7E; }
@@ -98,6 +110,86 @@ static void updatePreeditUI(fcitx::InputContext *ic, const std::string &preedit)
   ic->updatePreedit();
 }
 
+constexpr size_t kMaxCandidates = 9;
+constexpr size_t kLearnedWordsPerSave = 20;
+
+static void updateUI(fcitx::InputContext *ic, LipilekhikaState *state);
+
+// Picking a candidate commits its text and ends the composition.
+class LipilekhikaCandidateWord : public fcitx::CandidateWord {
+public:
+  LipilekhikaCandidateWord(LipilekhikaState *state, std::string text)
+      : fcitx::CandidateWord(fcitx::Text(text)), state_(state), text_(std::move(text)) {}
+
+  void select(fcitx::InputContext *ic) const override {
+    state_->engine_->commit(ic, text_);
+    state_->clear();
+    updateUI(ic, state_);
+  }
+
+private:
+  LipilekhikaState *state_;
+  std::string text_;
+};
+
+// Appends the texts of a Rust candidate list (skipping duplicates) and frees it.
+static void appendCandidateTexts(LipiCandidateList list, std::vector<std::string> &texts) {
+  for (size_t i = 0; i < list.len; ++i) {
+    std::string text(list.ptr[i].text.ptr, list.ptr[i].text.len);
+    if (std::find(texts.begin(), texts.end(), text) == texts.end()) {
+      texts.push_back(std::move(text));
+    }
+  }
+  lipi_candidate_list_free(list);
+}
+
+static void updateCandidatesUI(fcitx::InputContext *ic, LipilekhikaState *state) {
+  auto &panel = ic->inputPanel();
+  panel.setCandidateList(nullptr);
+
+  if (!state->preedit_utf8_.empty() && state->ctx_ && *state->engine_->config().showCandidates) {
+    // Alternatives for ambiguous keys first (the preedit itself leads), then completions.
+    std::vector<std::string> texts;
+    LipiCandidateList list = {};
+    LipiString err = {};
+    if (lipi_typing_context_candidates(state->ctx_, kMaxCandidates, &list, &err) == Ok) {
+      appendCandidateTexts(list, texts);
+    }
+    takeLipiString(err);
+    if (auto *lexicon = state->engine_->lexicon(); lexicon && texts.size() < kMaxCandidates) {
+      list = {};
+      err = {};
+      if (lipi_lexicon_complete(lexicon, state->ctx_, kMaxCandidates - texts.size(), &list, &err) ==
+          Ok) {
+        appendCandidateTexts(list, texts);
+      }
+      takeLipiString(err);
+    }
+
+    // A single candidate is just the preedit itself.
+    if (texts.size() > 1) {
+      auto candidates = std::make_unique<fcitx::CommonCandidateList>();
+      fcitx::KeyList selectionKeys;
+      for (size_t i = 0; i < kMaxCandidates; ++i) {
+        selectionKeys.emplace_back(static_cast<fcitx::KeySym>(FcitxKey_1 + i));
+      }
+      candidates->setSelectionKey(selectionKeys);
+      candidates->setPageSize(kMaxCandidates);
+      for (auto &text : texts) {
+        candidates->append<LipilekhikaCandidateWord>(state, std::move(text));
+      }
+      panel.setCandidateList(std::move(candidates));
+    }
+  }
+
+  ic->updateUserInterface(fcitx::UserInterfaceComponent::InputPanel);
+}
+
+static void updateUI(fcitx::InputContext *ic, LipilekhikaState *state) {
+  updatePreeditUI(ic, state->preedit_utf8_);
+  updateCandidatesUI(ic, state);
+}
+
 } // namespace
 
 bool LipilekhikaState::ensureContext(const std::string &script) {
@@ -106,6 +198,7 @@ bool LipilekhikaState::ensureContext(const std::string &script) {
   const uint64_t autoMs = static_cast<uint64_t>(std::max(0, *cfg.autoContextClearTimeMs));
   const bool useNative = *cfg.useNativeNumerals;
   const bool includeInherent = *cfg.includeInherentVowel;
+  const auto layout = static_cast<LipiTypingLayout>(*cfg.layout);
 
   if (ctx_ && script_ == script) {
     // Apply mutable options to existing context.
@@ -117,8 +210,8 @@ bool LipilekhikaState::ensureContext(const std::string &script) {
       lipi_typing_context_set_include_inherent_vowel(ctx_, includeInherent);
       include_inherent_vowel_ = includeInherent;
     }
-    // No setter for auto context clear time: recreate context if it changed.
-    if (auto_context_clear_time_ms_ != autoMs) {
+    // No setter for auto context clear time or layout: recreate context if they changed.
+    if (auto_context_clear_time_ms_ != autoMs || layout_ != layout) {
       resetContext();
       // fallthrough to recreate.
     } else {
@@ -132,10 +225,9 @@ bool LipilekhikaState::ensureContext(const std::string &script) {
   }
 
   // clear composition buffers
-  if (script_ != script) {  
-    raw_ascii_.clear();  
-    preedit_utf8_.clear();  
-  }  
+  if (script_ != script) {
+    preedit_utf8_.clear();
+  }
 
   if (ctx_) {
     lipi_typing_context_free(ctx_);
@@ -148,9 +240,11 @@ bool LipilekhikaState::ensureContext(const std::string &script) {
   opts.auto_context_clear_time_ms = autoMs;
   opts.use_native_numerals = useNative;
   opts.include_inherent_vowel = includeInherent;
+  opts.layout = layout;
   auto_context_clear_time_ms_ = autoMs;
   use_native_numerals_ = useNative;
   include_inherent_vowel_ = includeInherent;
+  layout_ = layout;
 
   LipiString err = {};
   auto status =
@@ -169,7 +263,6 @@ bool LipilekhikaState::ensureContext(const std::string &script) {
 }
 
 void LipilekhikaState::clear() {
-  raw_ascii_.clear();
   preedit_utf8_.clear();
   if (ctx_) {
     lipi_typing_context_clear(ctx_);
@@ -177,7 +270,6 @@ void LipilekhikaState::clear() {
 }
 
 void LipilekhikaState::resetContext() {
-  raw_ascii_.clear();
   preedit_utf8_.clear();
   script_.clear();
   if (ctx_) {
@@ -186,35 +278,44 @@ void LipilekhikaState::resetContext() {
   }
 }
 
-void LipilekhikaState::rebuildFromRaw() {
-  preedit_utf8_.clear();
-  if (ctx_) {
-    lipi_typing_context_clear(ctx_);
+bool LipilekhikaState::takeKey(const char *keyUtf8, std::string &commitText) {
+  LipiImeUpdate update = {};
+  LipiString err = {};
+  auto status = lipi_typing_context_ime_key_input(ctx_, keyUtf8, &update, &err);
+  takeLipiString(err);
+  if (status != Ok) {
+    clear();
+    return false;
   }
-  for (char ch : raw_ascii_) {
-    const char keyStr[2] = {ch, 0};
-    LipiTypingDiff diff = {};
-    LipiString err = {};
-    auto status = lipi_typing_context_take_key_input(ctx_, keyStr, &diff, &err);
-    if (status != Ok) {
-      if (err.ptr) {
-        lipi_string_free(err);
-      }
-      clear();
-      return;
-    }
-    if (err.ptr) {
-      lipi_string_free(err);
-    }
-    if (diff.to_delete_chars_count) {
-      truncateLastCodepoints(preedit_utf8_, diff.to_delete_chars_count);
-    }
-    if (diff.diff_add_text.ptr && diff.diff_add_text.len) {
-      preedit_utf8_.append(diff.diff_add_text.ptr,
-                           diff.diff_add_text.len);
-    }
-    lipi_string_free(diff.diff_add_text);
+  commitText = takeLipiString(update.commit_text);
+  preedit_utf8_ = takeLipiString(update.preedit);
+  return true;
+}
+
+bool LipilekhikaState::takeBackspace() {
+  LipiImeUpdate update = {};
+  LipiString err = {};
+  auto status = lipi_typing_context_ime_backspace(ctx_, &update, &err);
+  takeLipiString(err);
+  if (status != Ok || !update.handled) {
+    clear();
+    return false;
   }
+  takeLipiString(update.commit_text);
+  preedit_utf8_ = takeLipiString(update.preedit);
+  return true;
+}
+
+std::string LipilekhikaState::commit() {
+  LipiString text = {};
+  auto status = lipi_typing_context_commit(ctx_, &text);
+  std::string out = takeLipiString(text);
+  if (status != Ok) {
+    // Keep what the user saw rather than dropping it.
+    out = preedit_utf8_;
+  }
+  clear();
+  return out;
 }
 
 LipilekhikaEngine::LipilekhikaEngine(fcitx::Instance *instance)
@@ -224,12 +325,69 @@ LipilekhikaEngine::LipilekhikaEngine(fcitx::Instance *instance)
   instance_->inputContextManager().registerProperty("lipilekhikaState", &factory_);
 }
 
+LipilekhikaEngine::~LipilekhikaEngine() {
+  saveLexicon();
+  if (lexicon_) {
+    lipi_lexicon_free(lexicon_);
+    lexicon_ = nullptr;
+  }
+}
+
 void LipilekhikaEngine::reloadConfig() {
   // User config: ~/.config/fcitx5/conf/lipilekhika.conf
   fcitx::readAsIni(config_, "conf/lipilekhika.conf");
+  reloadLexicon();
   refreshAllContexts();
 }
 
+void LipilekhikaEngine::reloadLexicon() {
+  // Keep what was learned so far before rebuilding from the (possibly new) word list.
+  saveLexicon();
+  if (lexicon_) {
+    lipi_lexicon_free(lexicon_);
+    lexicon_ = nullptr;
+  }
+  if (lipi_lexicon_new(&lexicon_) != Ok) {
+    lexicon_ = nullptr;
+    return;
+  }
+
+  LipiString err = {};
+  if (!config_.wordListPath->empty()) {
+    lipi_lexicon_load_word_list_file(lexicon_, config_.wordListPath->c_str(), &err);
+    takeLipiString(err);
+  }
+  // Missing on first use; the error is expected then.
+  lipi_lexicon_load_frequencies_file(lexicon_, frequenciesPath().c_str(), &err);
+  takeLipiString(err);
+}
+
+void LipilekhikaEngine::saveLexicon() {
+  if (!lexicon_ || learnedSinceSave_ == 0) {
+    return;
+  }
+  LipiString err = {};
+  lipi_lexicon_save_frequencies_file(lexicon_, frequenciesPath().c_str(), &err);
+  takeLipiString(err);
+  learnedSinceSave_ = 0;
+}
+
+void LipilekhikaEngine::commit(fcitx::InputContext *ic, const std::string &text) {
+  ic->commitString(text);
+  learn(text);
+}
+
+void LipilekhikaEngine::learn(const std::string &text) {
+  if (!lexicon_ || text.empty()) {
+    return;
+  }
+  lipi_lexicon_learn(lexicon_, text.c_str());
+  // Save in batches rather than on every word.
+  if (++learnedSinceSave_ >= kLearnedWordsPerSave) {
+    saveLexicon();
+  }
+}
+
 const fcitx::Configuration *LipilekhikaEngine::getConfig() const { return &config_; }
 
 void LipilekhikaEngine::saveConfig() const { fcitx::safeSaveAsIni(config_, "conf/lipilekhika.conf"); }
@@ -245,7 +403,7 @@ void LipilekhikaEngine::refreshAllContexts() {
     }
     // Reset to make option changes deterministic and avoid desync.
     state->resetContext();
-    updatePreeditUI(ic, state->preedit_utf8_);
+    updateUI(ic, state);
     return true;
   });
 }
@@ -253,6 +411,7 @@ void LipilekhikaEngine::refreshAllContexts() {
 void LipilekhikaEngine::setConfig(const fcitx::RawConfig &config) {
   config_.load(config, true);
   saveConfig();
+  reloadLexicon();
   refreshAllContexts();
 }
 
@@ -288,9 +447,9 @@ void LipilekhikaEngine::keyEvent(const fcitx::InputMethodEntry &entry,
   // Cancel composition.
   if (sym == FcitxKey_Escape) {
     // Only consume Escape if we actually had an active composition.
-    if (!state->preedit_utf8_.empty() || !state->raw_ascii_.empty()) {
+    if (!state->preedit_utf8_.empty()) {
       state->clear();
-      updatePreeditUI(ic, state->preedit_utf8_);
+      updateUI(ic, state);
       keyEvent.filterAndAccept();
     }
     return;
@@ -299,59 +458,69 @@ void LipilekhikaEngine::keyEvent(const fcitx::InputMethodEntry &entry,
   // Commit preedit on Return.
   if (sym == FcitxKey_Return || sym == FcitxKey_KP_Enter) {
     if (!state->preedit_utf8_.empty()) {
-      ic->commitString(state->preedit_utf8_);
-      state->clear();
-      updatePreeditUI(ic, state->preedit_utf8_);
+      commit(ic, state->commit());
+      updateUI(ic, state);
       keyEvent.filterAndAccept();
       return;
     }
     return;
   }
 
-  // Commit preedit on Shift (without adding any character).
+  // Commit preedit on Shift (without adding any character). Native layouts use Shift
+  // for the upper row of each key, so it stays part of the composition there.
   if (sym == FcitxKey_Shift_L || sym == FcitxKey_Shift_R) {
-    if (!state->preedit_utf8_.empty()) {
-      ic->commitString(state->preedit_utf8_);
-      state->clear();
-      updatePreeditUI(ic, state->preedit_utf8_);
+    if (!state->preedit_utf8_.empty() && *config_.layout == TypingLayoutMode::Phonetic) {
+      commit(ic, state->commit());
+      updateUI(ic, state);
       keyEvent.filterAndAccept();
       return;
     }
     return;
   }
 
-  // Backspace inside composition: replay raw buffer.
+  // Backspace inside composition: step the context back one key.
   if (sym == FcitxKey_BackSpace) {
-    if (state->raw_ascii_.empty()) {
+    if (state->preedit_utf8_.empty()) {
       return;
     }
-    state->raw_ascii_.pop_back();
-    state->rebuildFromRaw();
-    updatePreeditUI(ic, state->preedit_utf8_);
-    keyEvent.filterAndAccept();
+    const bool handled = state->takeBackspace();
+    updateUI(ic, state);
+    if (handled) {
+      keyEvent.filterAndAccept();
+    }
     return;
   }
 
   // Space: commit preedit + a space (m17n-like feel).
   if (sym == FcitxKey_space) {
     if (!state->preedit_utf8_.empty()) {
-      ic->commitString(state->preedit_utf8_);
+      commit(ic, state->commit());
       ic->commitString(" ");
-      state->clear();
-      updatePreeditUI(ic, state->preedit_utf8_);
+      updateUI(ic, state);
       keyEvent.filterAndAccept();
       return;
     }
     return;
   }
 
+  // Number keys pick from the candidate list while it is shown.
+  if (auto *candidates = ic->inputPanel().candidateList().get();
+      candidates && sym >= FcitxKey_1 && sym <= FcitxKey_9) {
+    const int index = static_cast<int>(sym - FcitxKey_1);
+    if (index < candidates->size()) {
+      candidates->candidate(index).select(ic);
+      keyEvent.filterAndAccept();
+      return;
+    }
+  }
+
   // Only accept printable ASCII for v1 roman typing.
   if (!isPrintableAscii(sym)) {
     // Non-text input: if we are composing, clear so we don't desync,
     // but do NOT consume the key (let it pass through to the app).
-    if (!state->preedit_utf8_.empty() || !state->raw_ascii_.empty()) {
+    if (!state->preedit_utf8_.empty()) {
       state->clear();
-      updatePreeditUI(ic, state->preedit_utf8_);
+      updateUI(ic, state);
     }
     return;
   }
@@ -359,45 +528,14 @@ void LipilekhikaEngine::keyEvent(const fcitx::InputMethodEntry &entry,
   char ch = static_cast<char>(sym);
   const char keyStr[2] = {ch, 0};
 
-  LipiTypingDiff diff = {};
-  LipiString err = {};
-  auto status = lipi_typing_context_take_key_input(state->ctx_, keyStr, &diff, &err);
-  if (status != Ok) {
-    if (err.ptr) {
-      lipi_string_free(err);
-    }
-    state->clear();
-    updatePreeditUI(ic, state->preedit_utf8_);
-    keyEvent.filterAndAccept();
-    return;
-  }
-  if (err.ptr) {
-    lipi_string_free(err);
-  }
-
-  state->raw_ascii_.push_back(ch);
-
-  if (diff.to_delete_chars_count) {
-    truncateLastCodepoints(state->preedit_utf8_, diff.to_delete_chars_count);
-  }
-  if (diff.diff_add_text.ptr && diff.diff_add_text.len) {
-    state->preedit_utf8_.append(diff.diff_add_text.ptr, diff.diff_add_text.len);
-  }
-  lipi_string_free(diff.diff_add_text);
-
-  // If Rust indicates the context has been cleared, commit immediately.
-  // This keeps the preedit tooltip short and matches lipilekhika's internal context behavior.
-  if (diff.context_length == 0) {
-    if (!state->preedit_utf8_.empty()) {
-      ic->commitString(state->preedit_utf8_);
-    }
-    state->clear();
-    updatePreeditUI(ic, state->preedit_utf8_);
-    keyEvent.filterAndAccept();
-    return;
+  // Rust decides what is finished (e.g. a visarga or digit ends the context) and
+  // what stays in the preedit.
+  std::string commitText;
+  state->takeKey(keyStr, commitText);
+  if (!commitText.empty()) {
+    commit(ic, commitText);
   }
-
-  updatePreeditUI(ic, state->preedit_utf8_);
+  updateUI(ic, state);
   keyEvent.filterAndAccept();
 }
 
@@ -12,6 +12,7 @@
 #include <fcitx/text.h>
 
 #include <fcitx-config/configuration.h>
+#include <fcitx-config/enum.h>
 #include <fcitx-config/option.h>
 
 #include <string>
@@ -20,6 +21,10 @@
 
 class LipilekhikaEngine;
 
+// Same order as `LipiTypingLayout`.
+enum class TypingLayoutMode { Phonetic, InScript, Tamil99 };
+FCITX_CONFIG_ENUM_NAME(TypingLayoutMode, "Phonetic", "InScript", "Tamil99");
+
 class LipilekhikaConfig final : public fcitx::Configuration {
 public:
   fcitx::Option<int> autoContextClearTimeMs{
@@ -28,6 +33,13 @@ public:
       this, "UseNativeNumerals", "Use native numerals", true};
   fcitx::Option<bool> includeInherentVowel{
       this, "IncludeInherentVowel", "Include inherent vowel (schwa)", false};
+  fcitx::Option<TypingLayoutMode> layout{
+      this, "Layout", "Keyboard layout (InScript: Indic scripts, Tamil99: Tamil)",
+      TypingLayoutMode::Phonetic};
+  fcitx::Option<bool> showCandidates{
+      this, "ShowCandidates", "Show candidate list", true};
+  fcitx::Option<std::string> wordListPath{
+      this, "WordListPath", "Word list for completions (one word per line)", ""};
 
   FCITX_NODISCARD const char *typeName() const override { return "LipilekhikaConfig"; }
 };
@@ -46,30 +58,38 @@ public:
   bool ensureContext(const std::string &script);
   void clear();
   void resetContext();
-  void rebuildFromRaw();
+  bool takeKey(const char *keyUtf8, std::string &commitText);
+  bool takeBackspace();
+  std::string commit();
 
   LipilekhikaEngine *engine_ = nullptr;
   fcitx::InputContext *ic_ = nullptr;
   LipiTypingContext *ctx_ = nullptr;
   std::string script_;
-  std::string raw_ascii_;
   std::string preedit_utf8_;
 
   // Last applied engine options (for detecting changes).
   uint64_t auto_context_clear_time_ms_ = 0;
   bool use_native_numerals_ = true;
   bool include_inherent_vowel_ = false;
+  LipiTypingLayout layout_ = Phonetic;
 };
 
 class LipilekhikaEngine : public fcitx::InputMethodEngineV2 {
 public:
   explicit LipilekhikaEngine(fcitx::Instance *instance);
+  ~LipilekhikaEngine() override;
 
   void reloadConfig() override;
   const fcitx::Configuration *getConfig() const override;
   void setConfig(const fcitx::RawConfig &config) override;
 
   const LipilekhikaConfig &config() const { return config_; }
+  LipiLexicon *lexicon() const { return lexicon_; }
+
+  // Commits text to the client and learns its words for completions.
+  void commit(fcitx::InputContext *ic, const std::string &text);
+  void learn(const std::string &text);
 
   void keyEvent(const fcitx::InputMethodEntry &entry,
                 fcitx::KeyEvent &keyEvent) override;
@@ -77,10 +97,14 @@ public:
 private:
   void saveConfig() const;
   void refreshAllContexts();
+  void reloadLexicon();
+  void saveLexicon();
 
   fcitx::Instance *instance_;
   fcitx::FactoryFor<LipilekhikaState> factory_;
   LipilekhikaConfig config_;
+  LipiLexicon *lexicon_ = nullptr;
+  size_t learnedSinceSave_ = 0;
 };
 
 class LipilekhikaEngineFactory : public fcitx::AddonFactory {
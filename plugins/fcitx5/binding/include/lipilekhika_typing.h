@@ -25,6 +25,29 @@ typedef enum LipiStatus {
   Error = 4,
 } LipiStatus;
 
+/**
+ * Keyboard layout used for typing.
+ */
+typedef enum LipiTypingLayout {
+  /**
+   * Phonetic Roman input.
+   */
+  Phonetic = 0,
+  /**
+   * InScript, for any Brahmic script.
+   */
+  InScript = 1,
+  /**
+   * Tamil99, for Tamil.
+   */
+  Tamil99 = 2,
+} LipiTypingLayout;
+
+/**
+ * Opaque word lexicon handle used for completions.
+ */
+typedef struct LipiLexicon LipiLexicon;
+
 /**
  * Opaque handle type used by the C ABI.
  *
@@ -46,6 +69,29 @@ typedef struct LipiString {
   size_t cap;
 } LipiString;
 
+/**
+ * An alternative output for the current composition.
+ */
+typedef struct LipiCandidate {
+  /**
+   * UTF-8 text the composition would produce.
+   */
+  struct LipiString text;
+  /**
+   * UTF-8 keys that produce `text`.
+   */
+  struct LipiString input;
+} LipiCandidate;
+
+/**
+ * Owned array of candidates allocated by Rust; free with `lipi_candidate_list_free`.
+ */
+typedef struct LipiCandidateList {
+  struct LipiCandidate *ptr;
+  size_t len;
+  size_t cap;
+} LipiCandidateList;
+
 /**
  * Options for configuring a typing context.
  */
@@ -53,6 +99,10 @@ typedef struct LipiTypingContextOptions {
   uint64_t auto_context_clear_time_ms;
   bool use_native_numerals;
   bool include_inherent_vowel;
+  /**
+   * Layouts that cannot type the script fall back to `Phonetic`.
+   */
+  enum LipiTypingLayout layout;
 } LipiTypingContextOptions;
 
 /**
@@ -73,6 +123,24 @@ typedef struct LipiTypingDiff {
   size_t context_length;
 } LipiTypingDiff;
 
+/**
+ * Result of processing a key in preedit/commit terms.
+ */
+typedef struct LipiImeUpdate {
+  /**
+   * `false` when there was nothing to compose and the key should pass through.
+   */
+  bool handled;
+  /**
+   * UTF-8 text to commit to the client now (may be empty).
+   */
+  struct LipiString commit_text;
+  /**
+   * UTF-8 text that replaces the current preedit.
+   */
+  struct LipiString preedit;
+} LipiImeUpdate;
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -82,6 +150,11 @@ extern "C" {
  */
 void lipi_string_free(struct LipiString s);
 
+/**
+ * Frees a `LipiCandidateList` and all of its strings.
+ */
+void lipi_candidate_list_free(struct LipiCandidateList list);
+
 /**
  * Writes default typing options to `out_opts`.
  */
@@ -120,6 +193,77 @@ enum LipiStatus lipi_typing_context_take_key_input(struct LipiTypingContext *ctx
                                                    struct LipiTypingDiff *out_diff,
                                                    struct LipiString *out_err);
 
+/**
+ * Processes every key of a UTF-8 string (eg. pasted or composed text) and writes the
+ * single net diff against the output before the call.
+ *
+ * On success writes `out_diff` and returns `LipiStatus::Ok`. `context_length` is that
+ * of the last key.
+ */
+enum LipiStatus lipi_typing_context_take_text_input(struct LipiTypingContext *ctx,
+                                                    const char *text_utf8,
+                                                    struct LipiTypingDiff *out_diff,
+                                                    struct LipiString *out_err);
+
+/**
+ * Removes the last typed key from the context (Backspace inside a composition).
+ *
+ * On success writes `out_diff` and returns `LipiStatus::Ok`. An empty diff with
+ * `context_length == 0` means the context was empty and the key should pass through.
+ */
+enum LipiStatus lipi_typing_context_take_backspace(struct LipiTypingContext *ctx,
+                                                   struct LipiTypingDiff *out_diff,
+                                                   struct LipiString *out_err);
+
+/**
+ * Processes a single key and reports the text to commit and the new preedit.
+ *
+ * On success writes `out_update` (always `handled`) and returns `LipiStatus::Ok`.
+ * Free both strings of `out_update` with `lipi_string_free`.
+ */
+enum LipiStatus lipi_typing_context_ime_key_input(struct LipiTypingContext *ctx,
+                                                  const char *key_utf8,
+                                                  struct LipiImeUpdate *out_update,
+                                                  struct LipiString *out_err);
+
+/**
+ * Removes the last typed key from the composition and reports the new preedit.
+ *
+ * `out_update->handled` is `false` when there is no composition; the key should then
+ * pass through to the client.
+ */
+enum LipiStatus lipi_typing_context_ime_backspace(struct LipiTypingContext *ctx,
+                                                  struct LipiImeUpdate *out_update,
+                                                  struct LipiString *out_err);
+
+/**
+ * Writes the prefix of the preedit that no further key can change to `out_text`,
+ * removing it from the composition.
+ */
+enum LipiStatus lipi_typing_context_commit_committable(struct LipiTypingContext *ctx,
+                                                       struct LipiString *out_text);
+
+/**
+ * Finishes the composition: writes the whole preedit to `out_text` and clears the context.
+ */
+enum LipiStatus lipi_typing_context_commit(struct LipiTypingContext *ctx,
+                                           struct LipiString *out_text);
+
+/**
+ * Abandons the composition: writes the raw typed keys to `out_text` and clears the context.
+ */
+enum LipiStatus lipi_typing_context_cancel(struct LipiTypingContext *ctx,
+                                           struct LipiString *out_text);
+
+/**
+ * Writes up to `limit` alternative outputs for the current composition to `out_list`,
+ * best first. The list is empty when nothing is being composed.
+ */
+enum LipiStatus lipi_typing_context_candidates(struct LipiTypingContext *ctx,
+                                               size_t limit,
+                                               struct LipiCandidateList *out_list,
+                                               struct LipiString *out_err);
+
 /**
  * Updates whether native numerals should be used for subsequent typing.
  */
@@ -132,6 +276,52 @@ enum LipiStatus lipi_typing_context_set_use_native_numerals(struct LipiTypingCon
 enum LipiStatus lipi_typing_context_set_include_inherent_vowel(struct LipiTypingContext *ctx,
                                                                bool include_inherent_vowel);
 
+/**
+ * Creates an empty word lexicon.
+ */
+enum LipiStatus lipi_lexicon_new(struct LipiLexicon **out_lex);
+
+/**
+ * Destroys a lexicon created by `lipi_lexicon_new`.
+ */
+void lipi_lexicon_free(struct LipiLexicon *lex);
+
+/**
+ * Adds the words of a word list file (UTF-8, one word per line, any script).
+ */
+enum LipiStatus lipi_lexicon_load_word_list_file(struct LipiLexicon *lex,
+                                                 const char *path_utf8,
+                                                 struct LipiString *out_err);
+
+/**
+ * Loads learned word frequencies saved by `lipi_lexicon_save_frequencies_file`.
+ */
+enum LipiStatus lipi_lexicon_load_frequencies_file(struct LipiLexicon *lex,
+                                                   const char *path_utf8,
+                                                   struct LipiString *out_err);
+
+/**
+ * Saves learned word frequencies to a file.
+ */
+enum LipiStatus lipi_lexicon_save_frequencies_file(struct LipiLexicon *lex,
+                                                   const char *path_utf8,
+                                                   struct LipiString *out_err);
+
+/**
+ * Counts one more use of every word in committed text (UTF-8, any script).
+ */
+enum LipiStatus lipi_lexicon_learn(struct LipiLexicon *lex, const char *text_utf8);
+
+/**
+ * Writes up to `limit` completions of the word being typed in `ctx` to `out_list`, most
+ * used first. Free the list with `lipi_candidate_list_free`.
+ */
+enum LipiStatus lipi_lexicon_complete(struct LipiLexicon *lex,
+                                      struct LipiTypingContext *ctx,
+                                      size_t limit,
+                                      struct LipiCandidateList *out_list,
+                                      struct LipiString *out_err);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
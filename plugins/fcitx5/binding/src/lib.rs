@@ -9,10 +9,13 @@ use std::ffi::CStr;
 use std::os::raw::c_char;
 use std::str::FromStr;
 
+use lipilekhika::layout::TypingLayout;
 use lipilekhika::scripts::Script;
 
 type RustTypingContext = lipilekhika::typing::TypingContext;
 type RustTypingContextOptions = lipilekhika::typing::TypingContextOptions;
+type RustImeUpdate = lipilekhika::typing::ImeUpdate;
+type RustLexicon = lipilekhika::completion::Lexicon;
 
 /// Status codes returned by the C ABI.
 #[repr(C)]
@@ -61,6 +64,69 @@ pub struct LipiTypingDiff {
     pub context_length: usize,
 }
 
+/// Result of processing a key in preedit/commit terms.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiImeUpdate {
+    /// `false` when there was nothing to compose and the key should pass through.
+    pub handled: bool,
+    /// UTF-8 text to commit to the client now (may be empty).
+    pub commit_text: LipiString,
+    /// UTF-8 text that replaces the current preedit.
+    pub preedit: LipiString,
+}
+
+/// An alternative output for the current composition.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiCandidate {
+    /// UTF-8 text the composition would produce.
+    pub text: LipiString,
+    /// UTF-8 keys that produce `text`.
+    pub input: LipiString,
+}
+
+/// Owned array of candidates allocated by Rust; free with `lipi_candidate_list_free`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LipiCandidateList {
+    pub ptr: *mut LipiCandidate,
+    pub len: usize,
+    pub cap: usize,
+}
+
+/// Keyboard layout used for typing.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LipiTypingLayout {
+    /// Phonetic Roman input.
+    Phonetic = 0,
+    /// InScript, for any Brahmic script.
+    InScript = 1,
+    /// Tamil99, for Tamil.
+    Tamil99 = 2,
+}
+
+impl From<LipiTypingLayout> for TypingLayout {
+    fn from(layout: LipiTypingLayout) -> Self {
+        match layout {
+            LipiTypingLayout::Phonetic => TypingLayout::Phonetic,
+            LipiTypingLayout::InScript => TypingLayout::InScript,
+            LipiTypingLayout::Tamil99 => TypingLayout::Tamil99,
+        }
+    }
+}
+
+impl From<TypingLayout> for LipiTypingLayout {
+    fn from(layout: TypingLayout) -> Self {
+        match layout {
+            TypingLayout::Phonetic => LipiTypingLayout::Phonetic,
+            TypingLayout::InScript => LipiTypingLayout::InScript,
+            TypingLayout::Tamil99 => LipiTypingLayout::Tamil99,
+        }
+    }
+}
+
 /// Options for configuring a typing context.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -68,6 +134,8 @@ pub struct LipiTypingContextOptions {
     pub auto_context_clear_time_ms: u64,
     pub use_native_numerals: bool,
     pub include_inherent_vowel: bool,
+    /// Layouts that cannot type the script fall back to `Phonetic`.
+    pub layout: LipiTypingLayout,
 }
 
 /// Opaque handle type used by the C ABI.
@@ -77,6 +145,11 @@ pub struct LipiTypingContext {
     _private: *mut c_void,
 }
 
+/// Opaque word lexicon handle used for completions.
+pub struct LipiLexicon {
+    _private: *mut c_void,
+}
+
 fn alloc_lipi_string(s: String) -> LipiString {
     let mut bytes = s.into_bytes();
     bytes.push(0);
@@ -116,12 +189,76 @@ unsafe fn ctx_from_ptr<'a>(
     Ok(&mut *(ctx as *mut RustTypingContext))
 }
 
+unsafe fn lexicon_from_ptr<'a>(lex: *mut LipiLexicon) -> Result<&'a mut RustLexicon, LipiStatus> {
+    if lex.is_null() {
+        return Err(LipiStatus::NullPtr);
+    }
+    Ok(&mut *(lex as *mut RustLexicon))
+}
+
+fn alloc_candidate_list(candidates: Vec<lipilekhika::typing::Candidate>) -> LipiCandidateList {
+    let mut items: Vec<LipiCandidate> = candidates
+        .into_iter()
+        .map(|c| LipiCandidate {
+            text: alloc_lipi_string(c.text),
+            input: alloc_lipi_string(c.input),
+        })
+        .collect();
+    let list = LipiCandidateList {
+        ptr: items.as_mut_ptr(),
+        len: items.len(),
+        cap: items.capacity(),
+    };
+    std::mem::forget(items);
+    list
+}
+
+fn write_ime_update(out: *mut LipiImeUpdate, update: Option<RustImeUpdate>) {
+    let update = match update {
+        Some(u) => LipiImeUpdate {
+            handled: true,
+            commit_text: alloc_lipi_string(u.commit_text),
+            preedit: alloc_lipi_string(u.preedit),
+        },
+        None => LipiImeUpdate {
+            handled: false,
+            commit_text: LipiString::null(),
+            preedit: LipiString::null(),
+        },
+    };
+    unsafe { *out = update };
+}
+
+/// Runs a string-producing context operation and writes its result to `out_text`.
+unsafe fn take_context_string(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+    op: impl FnOnce(&mut RustTypingContext) -> String + std::panic::UnwindSafe,
+) -> LipiStatus {
+    set_out_string(out_text, None);
+    if out_text.is_null() {
+        return LipiStatus::NullPtr;
+    }
+
+    let result = std::panic::catch_unwind(|| ctx_from_ptr(ctx).map(op));
+    match result {
+        Err(_) => LipiStatus::Panic,
+        Ok(Err(status)) => status,
+        Ok(Ok(text)) => {
+            set_out_string(out_text, Some(text));
+            LipiStatus::Ok
+        }
+    }
+}
+
 fn map_options(opts: Option<LipiTypingContextOptions>) -> RustTypingContextOptions {
     match opts {
         Some(o) => RustTypingContextOptions {
             auto_context_clear_time_ms: o.auto_context_clear_time_ms,
             use_native_numerals: o.use_native_numerals,
             include_inherent_vowel: o.include_inherent_vowel,
+            layout: o.layout.into(),
+            latin_escape: None,
         },
         None => RustTypingContextOptions::default(),
     }
@@ -138,6 +275,19 @@ pub unsafe extern "C" fn lipi_string_free(s: LipiString) {
     let _ = Vec::<u8>::from_raw_parts(s.ptr as *mut u8, total_len, s.cap);
 }
 
+/// Frees a `LipiCandidateList` and all of its strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_candidate_list_free(list: LipiCandidateList) {
+    if list.ptr.is_null() {
+        return;
+    }
+    let items = Vec::from_raw_parts(list.ptr, list.len, list.cap);
+    for item in items {
+        lipi_string_free(item.text);
+        lipi_string_free(item.input);
+    }
+}
+
 /// Writes default typing options to `out_opts`.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn lipi_typing_default_options(
@@ -151,6 +301,7 @@ pub unsafe extern "C" fn lipi_typing_default_options(
         auto_context_clear_time_ms: defaults.auto_context_clear_time_ms,
         use_native_numerals: defaults.use_native_numerals,
         include_inherent_vowel: defaults.include_inherent_vowel,
+        layout: defaults.layout.into(),
     };
     LipiStatus::Ok
 }
@@ -289,6 +440,249 @@ pub unsafe extern "C" fn lipi_typing_context_take_key_input(
     }
 }
 
+/// Processes every key of a UTF-8 string (eg. pasted or composed text) and writes the
+/// single net diff against the output before the call.
+///
+/// On success writes `out_diff` and returns `LipiStatus::Ok`. `context_length` is that
+/// of the last key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_take_text_input(
+    ctx: *mut LipiTypingContext,
+    text_utf8: *const c_char,
+    out_diff: *mut LipiTypingDiff,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+
+    if out_diff.is_null() {
+        return LipiStatus::NullPtr;
+    }
+
+    let text = match cstr_to_string(text_utf8) {
+        Ok(s) => s,
+        Err(status) => {
+            set_out_string(out_err, Some(format!("{status:?}")));
+            return status;
+        }
+    };
+
+    let result = std::panic::catch_unwind(|| match ctx_from_ptr(ctx) {
+        Err(e) => Err(format!("{e:?}")),
+        Ok(ctx) => Ok(ctx.take_text_input(&text)),
+    });
+
+    match result {
+        Err(_) => {
+            set_out_string(out_err, Some("panic across FFI boundary".to_string()));
+            LipiStatus::Panic
+        }
+        Ok(Err(err_msg)) => {
+            set_out_string(out_err, Some(err_msg));
+            LipiStatus::Error
+        }
+        Ok(Ok(diff)) => {
+            *out_diff = LipiTypingDiff {
+                to_delete_chars_count: diff.to_delete_chars_count,
+                diff_add_text: alloc_lipi_string(diff.diff_add_text),
+                context_length: diff.context_length,
+            };
+            LipiStatus::Ok
+        }
+    }
+}
+
+/// Removes the last typed key from the context (Backspace inside a composition).
+///
+/// On success writes `out_diff` and returns `LipiStatus::Ok`. An empty diff with
+/// `context_length == 0` means the context was empty and the key should pass through.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_take_backspace(
+    ctx: *mut LipiTypingContext,
+    out_diff: *mut LipiTypingDiff,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+
+    if out_diff.is_null() {
+        return LipiStatus::NullPtr;
+    }
+
+    let result = std::panic::catch_unwind(|| match ctx_from_ptr(ctx) {
+        Err(e) => Err(format!("{e:?}")),
+        Ok(ctx) => Ok(ctx.take_backspace()),
+    });
+
+    match result {
+        Err(_) => {
+            set_out_string(out_err, Some("panic across FFI boundary".to_string()));
+            LipiStatus::Panic
+        }
+        Ok(Err(err_msg)) => {
+            set_out_string(out_err, Some(err_msg));
+            LipiStatus::Error
+        }
+        Ok(Ok(diff)) => {
+            *out_diff = LipiTypingDiff {
+                to_delete_chars_count: diff.to_delete_chars_count,
+                diff_add_text: alloc_lipi_string(diff.diff_add_text),
+                context_length: diff.context_length,
+            };
+            LipiStatus::Ok
+        }
+    }
+}
+
+/// Processes a single key and reports the text to commit and the new preedit.
+///
+/// On success writes `out_update` (always `handled`) and returns `LipiStatus::Ok`.
+/// Free both strings of `out_update` with `lipi_string_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_ime_key_input(
+    ctx: *mut LipiTypingContext,
+    key_utf8: *const c_char,
+    out_update: *mut LipiImeUpdate,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+
+    if out_update.is_null() {
+        return LipiStatus::NullPtr;
+    }
+
+    let key = match cstr_to_string(key_utf8) {
+        Ok(s) => s,
+        Err(status) => {
+            set_out_string(out_err, Some(format!("{status:?}")));
+            return status;
+        }
+    };
+    let Some(ch) = key.chars().next() else {
+        write_ime_update(out_update, None);
+        return LipiStatus::Ok;
+    };
+
+    let result = std::panic::catch_unwind(|| match ctx_from_ptr(ctx) {
+        Err(e) => Err(format!("{e:?}")),
+        Ok(ctx) => Ok(ctx.take_key_input_ime(ch)),
+    });
+
+    match result {
+        Err(_) => {
+            set_out_string(out_err, Some("panic across FFI boundary".to_string()));
+            LipiStatus::Panic
+        }
+        Ok(Err(err_msg)) => {
+            set_out_string(out_err, Some(err_msg));
+            LipiStatus::Error
+        }
+        Ok(Ok(update)) => {
+            write_ime_update(out_update, Some(update));
+            LipiStatus::Ok
+        }
+    }
+}
+
+/// Removes the last typed key from the composition and reports the new preedit.
+///
+/// `out_update->handled` is `false` when there is no composition; the key should then
+/// pass through to the client.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_ime_backspace(
+    ctx: *mut LipiTypingContext,
+    out_update: *mut LipiImeUpdate,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+
+    if out_update.is_null() {
+        return LipiStatus::NullPtr;
+    }
+
+    let result = std::panic::catch_unwind(|| match ctx_from_ptr(ctx) {
+        Err(e) => Err(format!("{e:?}")),
+        Ok(ctx) => Ok(ctx.take_backspace_ime()),
+    });
+
+    match result {
+        Err(_) => {
+            set_out_string(out_err, Some("panic across FFI boundary".to_string()));
+            LipiStatus::Panic
+        }
+        Ok(Err(err_msg)) => {
+            set_out_string(out_err, Some(err_msg));
+            LipiStatus::Error
+        }
+        Ok(Ok(update)) => {
+            write_ime_update(out_update, update);
+            LipiStatus::Ok
+        }
+    }
+}
+
+/// Writes the prefix of the preedit that no further key can change to `out_text`,
+/// removing it from the composition.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_commit_committable(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    take_context_string(ctx, out_text, |ctx| ctx.commit_committable())
+}
+
+/// Finishes the composition: writes the whole preedit to `out_text` and clears the context.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_commit(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    take_context_string(ctx, out_text, |ctx| ctx.commit())
+}
+
+/// Abandons the composition: writes the raw typed keys to `out_text` and clears the context.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_cancel(
+    ctx: *mut LipiTypingContext,
+    out_text: *mut LipiString,
+) -> LipiStatus {
+    take_context_string(ctx, out_text, |ctx| ctx.cancel())
+}
+
+/// Writes up to `limit` alternative outputs for the current composition to `out_list`,
+/// best first. The list is empty when nothing is being composed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_typing_context_candidates(
+    ctx: *mut LipiTypingContext,
+    limit: usize,
+    out_list: *mut LipiCandidateList,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+
+    if out_list.is_null() {
+        return LipiStatus::NullPtr;
+    }
+
+    let result = std::panic::catch_unwind(|| match ctx_from_ptr(ctx) {
+        Err(e) => Err(format!("{e:?}")),
+        Ok(ctx) => Ok(ctx.candidates(limit)),
+    });
+
+    match result {
+        Err(_) => {
+            set_out_string(out_err, Some("panic across FFI boundary".to_string()));
+            LipiStatus::Panic
+        }
+        Ok(Err(err_msg)) => {
+            set_out_string(out_err, Some(err_msg));
+            LipiStatus::Error
+        }
+        Ok(Ok(candidates)) => {
+            *out_list = alloc_candidate_list(candidates);
+            LipiStatus::Ok
+        }
+    }
+}
+
 /// Updates whether native numerals should be used for subsequent typing.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn lipi_typing_context_set_use_native_numerals(
@@ -324,3 +718,153 @@ pub unsafe extern "C" fn lipi_typing_context_set_include_inherent_vowel(
         Ok(Ok(())) => LipiStatus::Ok,
     }
 }
+
+/// Creates an empty word lexicon.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_lexicon_new(out_lex: *mut *mut LipiLexicon) -> LipiStatus {
+    if out_lex.is_null() {
+        return LipiStatus::NullPtr;
+    }
+    *out_lex = Box::into_raw(Box::new(RustLexicon::new())) as *mut LipiLexicon;
+    LipiStatus::Ok
+}
+
+/// Destroys a lexicon created by `lipi_lexicon_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_lexicon_free(lex: *mut LipiLexicon) {
+    if lex.is_null() {
+        return;
+    }
+    drop(Box::from_raw(lex as *mut RustLexicon));
+}
+
+/// Runs a file operation on a lexicon, reporting I/O errors through `out_err`.
+unsafe fn lexicon_file_op(
+    lex: *mut LipiLexicon,
+    path_utf8: *const c_char,
+    out_err: *mut LipiString,
+    op: impl FnOnce(&mut RustLexicon, &str) -> std::io::Result<()> + std::panic::UnwindSafe,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+
+    let path = match cstr_to_string(path_utf8) {
+        Ok(s) => s,
+        Err(status) => {
+            set_out_string(out_err, Some(format!("{status:?}")));
+            return status;
+        }
+    };
+
+    let result = std::panic::catch_unwind(|| match lexicon_from_ptr(lex) {
+        Err(e) => Err(format!("{e:?}")),
+        Ok(lex) => op(lex, &path).map_err(|e| format!("{path}: {e}")),
+    });
+
+    match result {
+        Err(_) => {
+            set_out_string(out_err, Some("panic across FFI boundary".to_string()));
+            LipiStatus::Panic
+        }
+        Ok(Err(err_msg)) => {
+            set_out_string(out_err, Some(err_msg));
+            LipiStatus::Error
+        }
+        Ok(Ok(())) => LipiStatus::Ok,
+    }
+}
+
+/// Adds the words of a word list file (UTF-8, one word per line, any script).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_lexicon_load_word_list_file(
+    lex: *mut LipiLexicon,
+    path_utf8: *const c_char,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    lexicon_file_op(lex, path_utf8, out_err, |lex, path| {
+        lex.load_word_list_file(path).map(|_| ())
+    })
+}
+
+/// Loads learned word frequencies saved by `lipi_lexicon_save_frequencies_file`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_lexicon_load_frequencies_file(
+    lex: *mut LipiLexicon,
+    path_utf8: *const c_char,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    lexicon_file_op(lex, path_utf8, out_err, |lex, path| {
+        lex.load_learned_frequencies_file(path)
+    })
+}
+
+/// Saves learned word frequencies to a file.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_lexicon_save_frequencies_file(
+    lex: *mut LipiLexicon,
+    path_utf8: *const c_char,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    lexicon_file_op(lex, path_utf8, out_err, |lex, path| {
+        lex.save_learned_frequencies_file(path)
+    })
+}
+
+/// Counts one more use of every word in committed text (UTF-8, any script).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_lexicon_learn(
+    lex: *mut LipiLexicon,
+    text_utf8: *const c_char,
+) -> LipiStatus {
+    let text = match cstr_to_string(text_utf8) {
+        Ok(s) => s,
+        Err(status) => return status,
+    };
+    let result = std::panic::catch_unwind(|| {
+        let lex = lexicon_from_ptr(lex)?;
+        lex.learn(&text);
+        Ok::<(), LipiStatus>(())
+    });
+    match result {
+        Err(_) => LipiStatus::Panic,
+        Ok(Err(status)) => status,
+        Ok(Ok(())) => LipiStatus::Ok,
+    }
+}
+
+/// Writes up to `limit` completions of the word being typed in `ctx` to `out_list`, most
+/// used first. Free the list with `lipi_candidate_list_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lipi_lexicon_complete(
+    lex: *mut LipiLexicon,
+    ctx: *mut LipiTypingContext,
+    limit: usize,
+    out_list: *mut LipiCandidateList,
+    out_err: *mut LipiString,
+) -> LipiStatus {
+    set_out_string(out_err, None);
+
+    if out_list.is_null() {
+        return LipiStatus::NullPtr;
+    }
+
+    let result = std::panic::catch_unwind(|| {
+        let lex = lexicon_from_ptr(lex).map_err(|e| format!("{e:?}"))?;
+        let ctx = ctx_from_ptr(ctx).map_err(|e| format!("{e:?}"))?;
+        Ok::<_, String>(lex.complete(ctx, limit))
+    });
+
+    match result {
+        Err(_) => {
+            set_out_string(out_err, Some("panic across FFI boundary".to_string()));
+            LipiStatus::Panic
+        }
+        Ok(Err(err_msg)) => {
+            set_out_string(out_err, Some(err_msg));
+            LipiStatus::Error
+        }
+        Ok(Ok(candidates)) => {
+            *out_list = alloc_candidate_list(candidates);
+            LipiStatus::Ok
+        }
+    }
+}
@@ -0,0 +1,50 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+SCRIPT_DIR="$(cd -- "$(dirname -- "${BASH_SOURCE[0]}")" && pwd)"
+REPO_ROOT="$(cd -- "${SCRIPT_DIR}/../.." && pwd)"
+
+# Parse arguments
+for arg in "$@"; do
+  case $arg in
+    --help|-h)
+      echo "Usage: $0 [OPTIONS]"
+      echo ""
+      echo "Builds and installs the IBus Lipilekhika engine system-wide to /usr"
+      echo ""
+      echo "Options:"
+      echo "  --help      Show this help message"
+      echo ""
+      echo "Requires sudo for installation."
+      exit 0
+      ;;
+  esac
+done
+
+PREFIX="/usr"
+ENGINE_EXEC="${PREFIX}/libexec/ibus-engine-lipilekhika"
+COMPONENT_DIR="${PREFIX}/share/ibus/component"
+TARGET_DIR="${CARGO_TARGET_DIR:-$REPO_ROOT/target}"
+
+echo "==> Building IBus Lipilekhika engine"
+echo "    prefix:     ${PREFIX}"
+echo "    target dir: ${TARGET_DIR}"
+
+cargo build --release --manifest-path "${SCRIPT_DIR}/Cargo.toml"
+
+sudo install -Dm755 "${TARGET_DIR}/release/ibus-engine-lipilekhika" "${ENGINE_EXEC}"
+sed "s|@ENGINE_EXEC@|${ENGINE_EXEC}|g" "${SCRIPT_DIR}/lipilekhika.xml.in" \
+  | sudo install -Dm644 /dev/stdin "${COMPONENT_DIR}/lipilekhika.xml"
+
+cat <<EOT
+
+==> Installed ${ENGINE_EXEC}
+    and ${COMPONENT_DIR}/lipilekhika.xml
+
+Next steps:
+  - Restart IBus:     ibus restart
+  - Add input method: GNOME Settings → Keyboard → Input Sources → Add,
+                      pick the language and then "<Script> - Lipilekhika"
+                      (or search "lipilekhika" in ibus-setup)
+
+EOT
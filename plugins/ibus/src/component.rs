@@ -0,0 +1,100 @@
+//! IBus engine descriptions: one engine per typing script of [`get_script_list_data`].
+//!
+//! IBus runs `ibus-engine-lipilekhika --xml` (see `lipilekhika.xml.in`) to list them.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use lipilekhika::{Script, get_script_list_data};
+
+/// Engine names are this prefix followed by the lowercase script name.
+pub const ENGINE_PREFIX: &str = "lipilekhika-";
+
+/// Scripts that can be typed, in the order of the script list (`Normal` is plain Latin).
+pub fn engine_scripts() -> impl Iterator<Item = &'static str> {
+    get_script_list_data()
+        .scripts
+        .iter()
+        .map(String::as_str)
+        .filter(|script| *script != "Normal")
+}
+
+pub fn engine_name(script: &str) -> String {
+    format!("{ENGINE_PREFIX}{}", script.to_lowercase())
+}
+
+/// Script typed by the engine `name`, if it is one of ours.
+pub fn script_for_engine(name: &str) -> Option<Script> {
+    let script = name.strip_prefix(ENGINE_PREFIX)?;
+    engine_scripts()
+        .find(|s| s.eq_ignore_ascii_case(script))
+        .and_then(|s| Script::from_str(s).ok())
+}
+
+/// Language of the engine in the IBus engine list (same as the Fcitx5 addon).
+fn lang_code(script: &str) -> &'static str {
+    match script {
+        "Telugu" => "te",
+        "Tamil" | "Tamil-Extended" | "Granth" => "ta",
+        "Bengali" => "bn",
+        "Kannada" => "kn",
+        "Gujarati" => "gu",
+        "Malayalam" => "ml",
+        "Odia" => "or",
+        "Sinhala" => "si",
+        "Romanized" => "en",
+        "Gurumukhi" => "pa",
+        "Assamese" => "as",
+        "Modi" => "mr",
+        _ => "sa",
+    }
+}
+
+/// The `<engines>` element of the IBus component.
+pub fn engines_xml() -> String {
+    let mut xml = String::from("<engines>\n");
+    for script in engine_scripts() {
+        let _ = write!(
+            xml,
+            "  <engine>\n\
+             \x20   <name>{name}</name>\n\
+             \x20   <longname>{script} - Lipilekhika</longname>\n\
+             \x20   <description>Type {script} with Roman keys</description>\n\
+             \x20   <language>{lang}</language>\n\
+             \x20   <license>MIT</license>\n\
+             \x20   <author>Lipilekhika</author>\n\
+             \x20   <icon>ibus-keyboard</icon>\n\
+             \x20   <layout>us</layout>\n\
+             \x20   <symbol>{symbol}</symbol>\n\
+             \x20   <rank>0</rank>\n\
+             \x20 </engine>\n",
+            name = engine_name(script),
+            lang = lang_code(script),
+            symbol = script.chars().next().unwrap_or('L'),
+        );
+    }
+    xml.push_str("</engines>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_typing_script_has_an_engine() {
+        assert_eq!(engine_scripts().count(), 19);
+        assert_eq!(engine_name("Tamil-Extended"), "lipilekhika-tamil-extended");
+        assert_eq!(
+            script_for_engine("lipilekhika-tamil-extended"),
+            Some(Script::TamilExtended)
+        );
+        assert_eq!(script_for_engine("lipilekhika-normal"), None);
+        assert_eq!(script_for_engine("m17n-hi-inscript"), None);
+
+        let xml = engines_xml();
+        assert_eq!(xml.matches("<engine>").count(), 19);
+        assert!(xml.contains("<name>lipilekhika-devanagari</name>"));
+        assert!(xml.contains("<language>sa</language>"));
+    }
+}
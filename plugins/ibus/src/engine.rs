@@ -0,0 +1,218 @@
+//! Key handling of an engine in preedit/commit terms, kept apart from D-Bus so that it can
+//! be tested without `ibus-daemon`. Follows the key handling of the Fcitx5 addon.
+
+use lipilekhika::Script;
+use lipilekhika::typing::{ImeUpdate, TypingContext, TypingContextOptions};
+
+use crate::settings::Settings;
+
+// ---- Keysyms ----
+pub const KEY_SPACE: u32 = 0x0020;
+pub const KEY_BACKSPACE: u32 = 0xff08;
+pub const KEY_RETURN: u32 = 0xff0d;
+pub const KEY_ESCAPE: u32 = 0xff1b;
+pub const KEY_KP_ENTER: u32 = 0xff8d;
+
+// ---- Modifier masks of the key event state ----
+pub const CONTROL_MASK: u32 = 1 << 2;
+/// Alt
+pub const MOD1_MASK: u32 = 1 << 3;
+/// Super on most keymaps
+pub const MOD4_MASK: u32 = 1 << 6;
+pub const SUPER_MASK: u32 = 1 << 26;
+pub const HYPER_MASK: u32 = 1 << 27;
+pub const META_MASK: u32 = 1 << 28;
+pub const RELEASE_MASK: u32 = 1 << 30;
+
+const SHORTCUT_MASK: u32 =
+    CONTROL_MASK | MOD1_MASK | MOD4_MASK | SUPER_MASK | HYPER_MASK | META_MASK;
+
+/// Shift, Ctrl, Caps Lock, ... and the ISO level/group shifts.
+fn is_modifier_keysym(keyval: u32) -> bool {
+    matches!(keyval, 0xffe1..=0xffee | 0xfe01..=0xfe0f)
+}
+
+/// Result of a key event.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct KeyResult {
+    /// `false` when the key is left to the application.
+    pub handled: bool,
+    /// Text to commit and the new preedit; `None` when the composition did not change.
+    pub update: Option<ImeUpdate>,
+}
+
+impl KeyResult {
+    fn handled(update: ImeUpdate) -> Self {
+        Self {
+            handled: true,
+            update: Some(update),
+        }
+    }
+}
+
+pub struct EngineState {
+    ctx: TypingContext,
+}
+
+impl EngineState {
+    pub fn new(script: Script, settings: &Settings) -> Self {
+        let options = TypingContextOptions {
+            use_native_numerals: settings.use_native_numerals,
+            include_inherent_vowel: settings.include_inherent_vowel,
+            ..Default::default()
+        };
+        Self {
+            ctx: TypingContext::new(script, Some(options)),
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.ctx
+            .update_use_native_numerals(settings.use_native_numerals);
+        self.ctx
+            .update_include_inherent_vowel(settings.include_inherent_vowel);
+    }
+
+    pub fn is_composing(&self) -> bool {
+        !self.ctx.raw_input().is_empty()
+    }
+
+    /// Finishes the composition (eg. on focus out), returning the text to commit.
+    pub fn commit(&mut self) -> String {
+        self.ctx.commit()
+    }
+
+    fn commit_and_pass(&mut self) -> KeyResult {
+        KeyResult {
+            handled: false,
+            update: self.is_composing().then(|| ImeUpdate {
+                commit_text: self.ctx.commit(),
+                preedit: String::new(),
+            }),
+        }
+    }
+
+    /// Handles a key event of IBus (`keyval` is an X keysym, `state` the modifier mask).
+    pub fn process_key(&mut self, keyval: u32, state: u32) -> KeyResult {
+        if state & RELEASE_MASK != 0 || is_modifier_keysym(keyval) {
+            return KeyResult::default();
+        }
+        // Shortcuts (Ctrl+C, Alt+Tab, ...) go to the application
+        if state & SHORTCUT_MASK != 0 {
+            return self.commit_and_pass();
+        }
+
+        let composing = self.is_composing();
+        match keyval {
+            // Cancel composition
+            KEY_ESCAPE if composing => {
+                self.ctx.cancel();
+                KeyResult::handled(ImeUpdate::default())
+            }
+            KEY_RETURN | KEY_KP_ENTER if composing => KeyResult::handled(ImeUpdate {
+                commit_text: self.ctx.commit(),
+                preedit: String::new(),
+            }),
+            // Backspace inside composition: step the context back one key
+            KEY_BACKSPACE => match self.ctx.take_backspace_ime() {
+                Some(update) => KeyResult::handled(update),
+                None => KeyResult::default(),
+            },
+            // Space: commit preedit + a space (m17n-like feel)
+            KEY_SPACE if composing => {
+                let mut commit_text = self.ctx.commit();
+                commit_text.push(' ');
+                KeyResult::handled(ImeUpdate {
+                    commit_text,
+                    preedit: String::new(),
+                })
+            }
+            // Printable ASCII is typed; the context decides what is finished
+            0x21..=0x7e => KeyResult::handled(self.ctx.take_key_input_ime(keyval as u8 as char)),
+            // Other keys (arrows, Tab, ...) end the composition and pass through
+            _ => self.commit_and_pass(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text the application gets for `keys`: the commits, and the keys left to it.
+    fn type_keys(engine: &mut EngineState, keys: &str) -> String {
+        let mut committed = String::new();
+        for ch in keys.chars() {
+            let result = engine.process_key(ch as u32, 0);
+            if let Some(update) = result.update {
+                committed.push_str(&update.commit_text);
+            }
+            if !result.handled {
+                committed.push(ch);
+            }
+        }
+        committed
+    }
+
+    #[test]
+    fn keys_update_the_preedit_and_space_commits() {
+        let mut engine = EngineState::new(Script::Devanagari, &Settings::default());
+        let result = engine.process_key('k' as u32, 0);
+        assert_eq!(result.update.unwrap().preedit, "क्");
+
+        assert_eq!(type_keys(&mut engine, "a"), "");
+        assert_eq!(type_keys(&mut engine, "ma "), "कम ");
+        assert!(!engine.is_composing());
+
+        // Without a composition, space and backspace go to the application
+        assert_eq!(engine.process_key(KEY_SPACE, 0), KeyResult::default());
+        assert_eq!(engine.process_key(KEY_BACKSPACE, 0), KeyResult::default());
+    }
+
+    #[test]
+    fn editing_keys_change_the_composition() {
+        let mut engine = EngineState::new(Script::Devanagari, &Settings::default());
+        type_keys(&mut engine, "kA");
+        let result = engine.process_key(KEY_BACKSPACE, 0);
+        assert_eq!(result.update.unwrap().preedit, "क्");
+
+        let result = engine.process_key(KEY_ESCAPE, 0);
+        assert!(result.handled);
+        assert!(!engine.is_composing());
+
+        type_keys(&mut engine, "ga");
+        let result = engine.process_key(KEY_RETURN, 0);
+        assert_eq!(result.update.unwrap().commit_text, "ग");
+    }
+
+    #[test]
+    fn shortcuts_and_other_keys_commit_and_pass_through() {
+        let mut engine = EngineState::new(Script::Devanagari, &Settings::default());
+        type_keys(&mut engine, "ka");
+        let result = engine.process_key('c' as u32, CONTROL_MASK);
+        assert!(!result.handled);
+        assert_eq!(result.update.unwrap().commit_text, "क");
+
+        // Shift alone keeps the composition
+        type_keys(&mut engine, "k");
+        assert_eq!(engine.process_key(0xffe1, 0), KeyResult::default());
+        assert!(engine.is_composing());
+
+        // Left arrow
+        let result = engine.process_key(0xff51, 0);
+        assert!(!result.handled);
+        assert_eq!(result.update.unwrap().commit_text, "क्");
+    }
+
+    #[test]
+    fn settings_apply_to_the_next_keys() {
+        let mut engine = EngineState::new(Script::Devanagari, &Settings::default());
+        assert_eq!(type_keys(&mut engine, "1 "), "१ ");
+
+        engine.apply_settings(&Settings {
+            use_native_numerals: false,
+            include_inherent_vowel: true,
+        });
+        assert_eq!(type_keys(&mut engine, "1 k "), "1 क ");
+    }
+}
@@ -0,0 +1,481 @@
+//! IBus D-Bus protocol: the engine factory and the engines served to `ibus-daemon`.
+//!
+//! IBus values (texts, properties) are sent as variants of `(name, attachments, fields...)`
+//! structures, see `ibus_serializable_serialize` in libibus.
+
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedObjectPath, StructureBuilder, Value};
+use zbus::{ObjectServer, fdo, interface};
+
+use crate::component;
+use crate::engine::EngineState;
+use crate::settings::{Settings, config_dir};
+
+/// Bus name of the component, as in `lipilekhika.xml.in`.
+pub const COMPONENT_NAME: &str = "org.freedesktop.IBus.Lipilekhika";
+pub const FACTORY_PATH: &str = "/org/freedesktop/IBus/Factory";
+const ENGINE_PATH_PREFIX: &str = "/org/freedesktop/IBus/Engine/";
+
+// ---- Properties shown in the IBus panel ----
+pub const PROP_NATIVE_NUMERALS: &str = "native-numerals";
+pub const PROP_INHERENT_VOWEL: &str = "inherent-vowel";
+const PROP_TYPE_TOGGLE: u32 = 1;
+const PROP_STATE_UNCHECKED: u32 = 0;
+const PROP_STATE_CHECKED: u32 = 1;
+
+const ATTR_TYPE_UNDERLINE: u32 = 1;
+const ATTR_UNDERLINE_SINGLE: u32 = 1;
+/// The client drops the preedit on focus out; the engine commits it itself.
+const PREEDIT_MODE_CLEAR: u32 = 0;
+
+/// Starts the structure of an `IBusSerializable` named `name`, without attachments.
+fn serializable(name: &str) -> StructureBuilder<'static> {
+    StructureBuilder::new()
+        .add_field(name.to_string())
+        .add_field(HashMap::<String, Value<'static>>::new())
+}
+
+/// Finishes a serialized IBus value. As a field of another one it is added with
+/// `add_field`, which makes it a variant.
+fn ibus_value(builder: StructureBuilder<'static>) -> Value<'static> {
+    Value::Structure(builder.build().expect("IBus values have fields"))
+}
+
+/// `IBusText`, underlined as a whole when `underline` is set (for the preedit).
+pub fn ibus_text(text: &str, underline: bool) -> Value<'static> {
+    let mut attributes = Vec::new();
+    if underline && !text.is_empty() {
+        attributes.push(ibus_value(
+            serializable("IBusAttribute")
+                .add_field(ATTR_TYPE_UNDERLINE)
+                .add_field(ATTR_UNDERLINE_SINGLE)
+                .add_field(0u32)
+                .add_field(text.chars().count() as u32),
+        ));
+    }
+    let attr_list = ibus_value(serializable("IBusAttrList").add_field(attributes));
+    ibus_value(
+        serializable("IBusText")
+            .add_field(text.to_string())
+            .add_field(attr_list),
+    )
+}
+
+fn prop_list(props: Vec<Value<'static>>) -> Value<'static> {
+    ibus_value(serializable("IBusPropList").add_field(props))
+}
+
+/// `IBusProperty` of a toggle.
+fn toggle_property(key: &str, label: &str, checked: bool) -> Value<'static> {
+    let state = if checked {
+        PROP_STATE_CHECKED
+    } else {
+        PROP_STATE_UNCHECKED
+    };
+    ibus_value(
+        serializable("IBusProperty")
+            .add_field(key.to_string())
+            .add_field(PROP_TYPE_TOGGLE)
+            .add_field(ibus_text(label, false))
+            .add_field(String::new())
+            .add_field(ibus_text(label, false))
+            .add_field(true)
+            .add_field(true)
+            .add_field(state)
+            .add_field(prop_list(Vec::new()))
+            .add_field(ibus_text("", false)),
+    )
+}
+
+fn properties(settings: &Settings) -> [Value<'static>; 2] {
+    [
+        toggle_property(
+            PROP_NATIVE_NUMERALS,
+            "Native numerals",
+            settings.use_native_numerals,
+        ),
+        toggle_property(
+            PROP_INHERENT_VOWEL,
+            "Inherent vowel",
+            settings.include_inherent_vowel,
+        ),
+    ]
+}
+
+/// Creates an engine for each input context that selects one of ours.
+pub struct Factory {
+    settings: Arc<Mutex<Settings>>,
+    next_id: u32,
+}
+
+impl Factory {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(settings)),
+            next_id: 0,
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.IBus.Factory")]
+impl Factory {
+    async fn create_engine(
+        &mut self,
+        name: &str,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> fdo::Result<OwnedObjectPath> {
+        let script = component::script_for_engine(name)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("Unknown engine `{name}`")))?;
+        self.next_id += 1;
+        let path = OwnedObjectPath::try_from(format!("{ENGINE_PATH_PREFIX}{}", self.next_id))
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+
+        let settings = Arc::clone(&self.settings);
+        let state = match settings.lock() {
+            Ok(settings) => EngineState::new(script, &settings),
+            Err(_) => return Err(fdo::Error::Failed("settings lock poisoned".into())),
+        };
+        server.at(&path, Engine { state, settings }).await?;
+        server.at(&path, Service).await?;
+        Ok(path)
+    }
+}
+
+/// Lets `ibus-daemon` release an engine.
+struct Service;
+
+#[interface(name = "org.freedesktop.IBus.Service")]
+impl Service {
+    async fn destroy(
+        &self,
+        #[zbus(object_server)] server: &ObjectServer,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let path = emitter.path().to_owned();
+        server.remove::<Engine, _>(&path).await?;
+        server.remove::<Service, _>(&path).await?;
+        Ok(())
+    }
+}
+
+pub struct Engine {
+    state: EngineState,
+    settings: Arc<Mutex<Settings>>,
+}
+
+impl Engine {
+    async fn show(
+        emitter: &SignalEmitter<'_>,
+        commit_text: &str,
+        preedit: &str,
+    ) -> zbus::Result<()> {
+        if !commit_text.is_empty() {
+            Self::commit_text(emitter, ibus_text(commit_text, false)).await?;
+        }
+        Self::update_preedit_text(
+            emitter,
+            ibus_text(preedit, true),
+            preedit.chars().count() as u32,
+            !preedit.is_empty(),
+            PREEDIT_MODE_CLEAR,
+        )
+        .await
+    }
+
+    /// Commits the composition, if any (focus out, reset, ...).
+    async fn finish(&mut self, emitter: &SignalEmitter<'_>) -> zbus::Result<()> {
+        if !self.state.is_composing() {
+            return Ok(());
+        }
+        let text = self.state.commit();
+        Self::show(emitter, &text, "").await
+    }
+}
+
+// Keys are handled one at a time, in the order they were sent
+#[interface(name = "org.freedesktop.IBus.Engine", spawn = false)]
+impl Engine {
+    async fn process_key_event(
+        &mut self,
+        keyval: u32,
+        _keycode: u32,
+        state: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> bool {
+        let result = self.state.process_key(keyval, state);
+        if let Some(update) = result.update {
+            let _ = Self::show(&emitter, &update.commit_text, &update.preedit).await;
+        }
+        result.handled
+    }
+
+    async fn focus_in(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        // Another engine may have changed the settings
+        let settings = match self.settings.lock() {
+            Ok(settings) => settings.clone(),
+            Err(_) => return,
+        };
+        self.state.apply_settings(&settings);
+        let props = prop_list(properties(&settings).to_vec());
+        let _ = Self::register_properties(&emitter, props).await;
+    }
+
+    async fn focus_out(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        let _ = self.finish(&emitter).await;
+    }
+
+    async fn reset(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        let _ = self.finish(&emitter).await;
+    }
+
+    async fn enable(&mut self) {}
+
+    async fn disable(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        let _ = self.finish(&emitter).await;
+    }
+
+    async fn property_activate(
+        &mut self,
+        name: &str,
+        state: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) {
+        let checked = state == PROP_STATE_CHECKED;
+        let settings = {
+            let Ok(mut settings) = self.settings.lock() else {
+                return;
+            };
+            match name {
+                PROP_NATIVE_NUMERALS => settings.use_native_numerals = checked,
+                PROP_INHERENT_VOWEL => settings.include_inherent_vowel = checked,
+                _ => return,
+            }
+            let _ = settings.save();
+            settings.clone()
+        };
+        self.state.apply_settings(&settings);
+        for prop in properties(&settings) {
+            let _ = Self::update_property(&emitter, prop).await;
+        }
+    }
+
+    async fn set_capabilities(&mut self, _caps: u32) {}
+
+    async fn set_cursor_location(&mut self, _x: i32, _y: i32, _w: i32, _h: i32) {}
+
+    async fn set_surrounding_text(&mut self, _text: Value<'_>, _cursor_pos: u32, _anchor_pos: u32) {
+    }
+
+    async fn property_show(&mut self, _name: &str) {}
+
+    async fn property_hide(&mut self, _name: &str) {}
+
+    async fn candidate_clicked(&mut self, _index: u32, _button: u32, _state: u32) {}
+
+    async fn page_up(&mut self) {}
+
+    async fn page_down(&mut self) {}
+
+    async fn cursor_up(&mut self) {}
+
+    async fn cursor_down(&mut self) {}
+
+    #[zbus(property)]
+    async fn content_type(&self) -> (u32, u32) {
+        (0, 0)
+    }
+
+    #[zbus(property)]
+    async fn set_content_type(&mut self, _value: (u32, u32)) {}
+
+    #[zbus(signal)]
+    async fn commit_text(emitter: &SignalEmitter<'_>, text: Value<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn update_preedit_text(
+        emitter: &SignalEmitter<'_>,
+        text: Value<'_>,
+        cursor_pos: u32,
+        visible: bool,
+        mode: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn register_properties(emitter: &SignalEmitter<'_>, props: Value<'_>)
+    -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn update_property(emitter: &SignalEmitter<'_>, prop: Value<'_>) -> zbus::Result<()>;
+}
+
+/// Address of the IBus bus: `$IBUS_ADDRESS`, or from the address file that `ibus-daemon`
+/// writes for the display (see `ibus_get_socket_path` in libibus).
+fn bus_address() -> Result<String, Box<dyn Error>> {
+    if let Ok(address) = env::var("IBUS_ADDRESS")
+        && !address.is_empty()
+    {
+        return Ok(address);
+    }
+    let path = address_file_path().ok_or("Cannot find the IBus address file")?;
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("IBUS_ADDRESS="))
+        .map(str::to_string)
+        .ok_or_else(|| format!("No IBUS_ADDRESS in {}", path.display()).into())
+}
+
+fn address_file_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("IBUS_ADDRESS_FILE") {
+        return Some(PathBuf::from(path));
+    }
+    let machine_id = fs::read_to_string("/etc/machine-id")
+        .or_else(|_| fs::read_to_string("/var/lib/dbus/machine-id"))
+        .ok()?;
+    let (host, display) = match env::var("WAYLAND_DISPLAY") {
+        Ok(display) if !display.is_empty() => (String::new(), display),
+        _ => {
+            let display = env::var("DISPLAY").ok()?;
+            let (host, rest) = display.split_once(':')?;
+            let number = rest.split('.').next()?;
+            (host.to_string(), number.to_string())
+        }
+    };
+    let host = if host.is_empty() { "unix" } else { &host };
+    let file_name = format!("{}-{host}-{display}", machine_id.trim());
+    Some(config_dir()?.join("ibus").join("bus").join(file_name))
+}
+
+/// Serves the factory on the IBus bus until `ibus-daemon` exits.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let address = bus_address()?;
+    let connection = zbus::blocking::connection::Builder::address(address.as_str())?
+        .serve_at(FACTORY_PATH, Factory::new(Settings::load()))?
+        .name(COMPONENT_NAME)?
+        .build()?;
+    // The messages are handled by the object server; this only waits for the disconnect.
+    for _ in zbus::blocking::MessageIterator::from(&connection) {}
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use zbus::blocking::connection::Builder;
+    use zbus::blocking::{Connection, MessageIterator};
+    use zbus::message::Type as MessageType;
+
+    /// The engine side and the `ibus-daemon` side of a peer-to-peer connection.
+    fn connect() -> (Connection, Connection) {
+        let (engine_stream, daemon_stream) = UnixStream::pair().unwrap();
+        let guid = zbus::Guid::generate();
+        let engine = std::thread::spawn(move || {
+            Builder::unix_stream(engine_stream)
+                .server(guid)
+                .unwrap()
+                .p2p()
+                .serve_at(FACTORY_PATH, Factory::new(Settings::default()))
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        let daemon = Builder::unix_stream(daemon_stream).p2p().build().unwrap();
+        (engine.join().unwrap(), daemon)
+    }
+
+    /// Text of a serialized `IBusText`.
+    fn text_of(value: &Value<'_>) -> String {
+        match value {
+            Value::Value(inner) => text_of(inner),
+            Value::Structure(text) => match &text.fields()[2] {
+                Value::Str(text) => text.to_string(),
+                other => panic!("not a string: {other:?}"),
+            },
+            other => panic!("not an IBusText: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn engines_commit_typed_text_over_dbus() {
+        let (_engine, daemon) = connect();
+        let path: OwnedObjectPath = daemon
+            .call_method(
+                None::<&str>,
+                FACTORY_PATH,
+                Some("org.freedesktop.IBus.Factory"),
+                "CreateEngine",
+                &("lipilekhika-devanagari",),
+            )
+            .unwrap()
+            .body()
+            .deserialize()
+            .unwrap();
+
+        let keys = "namaste ";
+        let mut messages = MessageIterator::from(&daemon);
+        for key in keys.chars() {
+            let handled: bool = daemon
+                .call_method(
+                    None::<&str>,
+                    &path,
+                    Some("org.freedesktop.IBus.Engine"),
+                    "ProcessKeyEvent",
+                    &(key as u32, 0u32, 0u32),
+                )
+                .unwrap()
+                .body()
+                .deserialize()
+                .unwrap();
+            assert!(handled);
+        }
+
+        // Each key updates the preedit, after committing any finished text
+        let mut commits = String::new();
+        let mut preedit_updates = 0;
+        while preedit_updates < keys.len() {
+            let msg = messages.next().unwrap().unwrap();
+            let header = msg.header();
+            if header.message_type() != MessageType::Signal {
+                continue;
+            }
+            match header.member().map(|m| m.as_str()) {
+                Some("UpdatePreeditText") => preedit_updates += 1,
+                Some("CommitText") => {
+                    let body = msg.body();
+                    let text: Value<'_> = body.deserialize().unwrap();
+                    commits.push_str(&text_of(&text));
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(commits, "नमस्ते ");
+
+        let unknown = daemon.call_method(
+            None::<&str>,
+            FACTORY_PATH,
+            Some("org.freedesktop.IBus.Factory"),
+            "CreateEngine",
+            &("lipilekhika-normal",),
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn properties_are_ibus_toggles() {
+        let [native_numerals, _] = properties(&Settings::default());
+        assert_eq!(
+            native_numerals.value_signature().to_string(),
+            "(sa{sv}suvsvbbuvv)"
+        );
+        assert_eq!(text_of(&ibus_text("क", true)), "क");
+    }
+}
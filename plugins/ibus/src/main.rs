@@ -0,0 +1,32 @@
+//! IBus engine for typing Indian scripts with the Lipilekhika typing engine.
+//!
+//! `ibus-daemon` starts it with `--ibus` (see `lipilekhika.xml.in`) and lists its engines,
+//! one per script, with `--xml`.
+
+mod component;
+mod engine;
+mod ibus;
+mod settings;
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let arg = std::env::args().nth(1);
+    match arg.as_deref() {
+        Some("--xml") => {
+            print!("{}", component::engines_xml());
+            ExitCode::SUCCESS
+        }
+        Some("--ibus") | None => match ibus::run() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("ibus-engine-lipilekhika: {err}");
+                ExitCode::FAILURE
+            }
+        },
+        Some(arg) => {
+            eprintln!("Usage: ibus-engine-lipilekhika [--ibus | --xml], unknown argument `{arg}`");
+            ExitCode::FAILURE
+        }
+    }
+}
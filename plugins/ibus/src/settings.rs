@@ -0,0 +1,57 @@
+//! Options shared by all engines, toggled from the IBus panel and kept in
+//! `$XDG_CONFIG_HOME/lipilekhika/ibus.toml`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use lipilekhika::typing::{DEFAULT_INCLUDE_INHERENT_VOWEL, DEFAULT_USE_NATIVE_NUMERALS};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub use_native_numerals: bool,
+    pub include_inherent_vowel: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            use_native_numerals: DEFAULT_USE_NATIVE_NUMERALS,
+            include_inherent_vowel: DEFAULT_INCLUDE_INHERENT_VOWEL,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME`, or `~/.config`.
+pub fn config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("lipilekhika").join("ibus.toml"))
+}
+
+impl Settings {
+    /// Reads the saved settings; missing or invalid files give the defaults.
+    pub fn load() -> Self {
+        settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = settings_path().ok_or("no config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {dir:?}: {e}"))?;
+        }
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {e}"))?;
+        fs::write(&path, content).map_err(|e| format!("Failed to write {path:?}: {e}"))
+    }
+}
@@ -0,0 +1,166 @@
+//! Types through a private `ibus-daemon`, which starts the engine from its component file
+//! as it does after installation. Needs `ibus-daemon` and `dbus-run-session`, so it only
+//! runs with `cargo test -p lipilekhika-ibus -- --ignored`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use zbus::blocking::{Connection, MessageIterator, Proxy, connection};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{OwnedObjectPath, Structure, Value};
+use zbus::{MatchRule, Message};
+
+const ENGINE_EXEC: &str = env!("CARGO_BIN_EXE_ibus-engine-lipilekhika");
+const IBUS: &str = "org.freedesktop.IBus";
+const INPUT_CONTEXT: &str = "org.freedesktop.IBus.InputContext";
+
+const CAP_PREEDIT_TEXT: u32 = 1 << 0;
+const CAP_FOCUS: u32 = 1 << 3;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Daemon {
+    child: Child,
+    dir: PathBuf,
+    address: String,
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn start_daemon(dir: &Path) -> Daemon {
+    let component_dir = dir.join("component");
+    fs::create_dir_all(&component_dir).unwrap();
+    let component = include_str!("../lipilekhika.xml.in").replace("@ENGINE_EXEC@", ENGINE_EXEC);
+    fs::write(component_dir.join("lipilekhika.xml"), component).unwrap();
+
+    let socket = dir.join("ibus");
+    let address = format!("unix:path={}", socket.display());
+    let child = Command::new("dbus-run-session")
+        .args([
+            "--",
+            "ibus-daemon",
+            "--panel=disable",
+            "--emoji-extension=disable",
+            "--cache=none",
+            &format!("--address={address}"),
+        ])
+        .env("IBUS_ADDRESS", &address)
+        .env("IBUS_COMPONENT_PATH", &component_dir)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .env("GSETTINGS_BACKEND", "memory")
+        .spawn()
+        .expect("dbus-run-session and ibus-daemon must be installed");
+    let daemon = Daemon {
+        child,
+        dir: dir.to_path_buf(),
+        address,
+    };
+
+    let started = Instant::now();
+    while !socket.exists() {
+        assert!(started.elapsed() < TIMEOUT, "ibus-daemon did not start");
+        thread::sleep(Duration::from_millis(100));
+    }
+    daemon
+}
+
+/// Forwards the text of the `CommitText` signals of the input contexts.
+fn commits(conn: &Connection) -> Receiver<String> {
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface(INPUT_CONTEXT)
+        .unwrap()
+        .member("CommitText")
+        .unwrap()
+        .build();
+    let signals = MessageIterator::for_match_rule(rule, conn, None).unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for msg in signals.flatten() {
+            if tx.send(commit_text(&msg)).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+/// The text of an `IBusText` (`(sa{sv}sv)`) sent as a variant.
+fn commit_text(msg: &Message) -> String {
+    let body = msg.body();
+    let text: Value = body.deserialize().unwrap();
+    let text = match text {
+        Value::Value(inner) => *inner,
+        text => text,
+    };
+    let fields = Structure::try_from(text).unwrap().into_fields();
+    String::try_from(fields[2].try_clone().unwrap()).unwrap()
+}
+
+fn type_keys(context: &Proxy, keys: &str) {
+    for ch in keys.chars() {
+        for state in [0u32, 1 << 30] {
+            let _: bool = context
+                .call("ProcessKeyEvent", &(ch as u32, 0u32, state))
+                .unwrap();
+        }
+    }
+}
+
+/// Collects the commits until they add up to `expected` (or time out).
+fn committed(rx: &Receiver<String>, expected: &str) -> String {
+    let mut text = String::new();
+    let started = Instant::now();
+    while text.len() < expected.len() {
+        match rx.recv_timeout(TIMEOUT.saturating_sub(started.elapsed())) {
+            Ok(commit) => text.push_str(&commit),
+            Err(_) => break,
+        }
+    }
+    text
+}
+
+#[test]
+#[ignore]
+fn types_through_ibus_daemon() {
+    let dir = std::env::temp_dir().join(format!("lipilekhika-ibus-{}", std::process::id()));
+    let daemon = start_daemon(&dir);
+    let conn = connection::Builder::address(daemon.address.as_str())
+        .unwrap()
+        .build()
+        .unwrap();
+    let rx = commits(&conn);
+
+    let ibus = Proxy::new(&conn, IBUS, "/org/freedesktop/IBus", IBUS).unwrap();
+    let path: OwnedObjectPath = ibus.call("CreateInputContext", &("test",)).unwrap();
+    let context = Proxy::new(&conn, IBUS, path.as_str(), INPUT_CONTEXT).unwrap();
+    context
+        .call::<_, _, ()>("SetCapabilities", &(CAP_PREEDIT_TEXT | CAP_FOCUS,))
+        .unwrap();
+    context.call::<_, _, ()>("FocusIn", &()).unwrap();
+    context
+        .call::<_, _, ()>("SetEngine", &("lipilekhika-devanagari",))
+        .unwrap();
+
+    type_keys(&context, "namaste ");
+    assert_eq!(committed(&rx, "नमस्ते "), "नमस्ते ");
+
+    // The panel toggles apply to the next keys
+    context
+        .call::<_, _, ()>("PropertyActivate", &("inherent-vowel", 1u32))
+        .unwrap();
+    type_keys(&context, "ka ");
+    assert_eq!(committed(&rx, "क "), "क ");
+
+    let _ = ibus.call::<_, _, ()>("Exit", &(false,));
+}